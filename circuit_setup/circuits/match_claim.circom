@@ -4,6 +4,8 @@ include "./circomlib/circuits/comparators.circom";
 include "indicator.circom";
 include "./circomlib/circuits/mimc.circom";
 include "./circomlib/circuits/bitify.circom";
+include "./utils/date.circom";
+include "./utils/json_string.circom";
 
 // Converts an array of ascii digits (base-10) and converts them to a field element.
 // The input is big endian and may contain trailing zeros. 
@@ -58,15 +60,19 @@ template AsciiDigitsToField(n) {
 
 // Match the claim name in json_bytes with slice.
 // The input just assume the l is valid.
-template MatchClaimName(json_byte_len, name_byte_len){
+// name is "name" (with the quotes), l is the index of its opening quote and value_l the index of the first byte of
+// the value. The bytes in between are JSON whitespace and a single colon, e.g., "name" : value.
+// level is the nesting level of the object that has the claim: 1 for the outermost object, and for a nested claim,
+// the number of segments of its path (see also MatchParentObject).
+template MatchClaimName(json_byte_len, name_byte_len, level){
     var MAX_JSON_BITLEN = 16;
     signal input json_bytes[json_byte_len];
     signal input name[name_byte_len];
     signal input l;
+    signal input value_l;
     signal input r;
     signal input object_nested_level[json_byte_len + 1];
 
-    signal output value_l;
     signal output value_r;
 
     component start = PointIndicator(json_byte_len);
@@ -86,6 +92,8 @@ template MatchClaimName(json_byte_len, name_byte_len){
     l_bits.in <== l;
     component r_bits = Num2Bits(MAX_JSON_BITLEN);
     r_bits.in <== r;
+    component value_l_bits = Num2Bits(MAX_JSON_BITLEN);
+    value_l_bits.in <== value_l;
 
     // Check the validity of l and r.
     signal interval_valid <== LessThan(MAX_JSON_BITLEN)([l, r]);
@@ -93,17 +101,163 @@ template MatchClaimName(json_byte_len, name_byte_len){
     signal bound_valid <== LessThan(MAX_JSON_BITLEN)([r, json_byte_len + 1]);
     bound_valid === 1;
 
-    // Check the claim name is located at the outermost object.
+    // Check the claim name is located at the expected nesting level.
     for (var i = 0; i < json_byte_len; i++) {
-        start.indicator[i] * (object_nested_level[i + 1] - 1) === 0;
+        start.indicator[i] * (object_nested_level[i + 1] - level) === 0;
+    }
+
+    // The name is preceded by {, a comma or whitespace, so its opening quote is not an escaped quote inside another
+    // name, e.g., the name "exp" in {"a\"exp": 1}.
+    var preceding_byte = 0;
+    for (var i = 0; i < json_byte_len - 1; i++) {
+        preceding_byte += start.indicator[i + 1] * json_bytes[i];
+    }
+    signal preceding_ws <== JSONWhitespace(1)([preceding_byte]);
+    signal preceding_brace <== preceding_ws * (preceding_byte - 123);     // 123 is the ASCII code of {
+    preceding_brace * (preceding_byte - 44) === 0;                      // 44 is the ASCII code of ,
+
+    // The value starts after the colon, and is not empty: l + name_byte_len < value_l < r
+    signal separator_valid <== LessThan(MAX_JSON_BITLEN)([l + name_byte_len, value_l]);
+    separator_valid === 1;
+    signal value_valid <== LessThan(MAX_JSON_BITLEN)([value_l, r]);
+    value_valid === 1;
+
+    // The separator is whitespace and a single colon: ws is zero on whitespace, and COLON_WS on a colon
+    var COLON_WS = (58 - 32) * (58 - 9) * (58 - 10) * (58 - 13);   // 58 is the ASCII code of :
+    component ws = JSONWhitespace(json_byte_len);
+    ws.in <== json_bytes;
+    component separator = IntervalIndicator(json_byte_len);
+    separator.l <== l + name_byte_len;
+    separator.r <== value_l;
+    signal separator_ws[json_byte_len];
+    var num_colons = 0;
+    for (var i = 0; i < json_byte_len; i++) {
+        separator_ws[i] <== separator.indicator[i] * ws.out[i];
+        separator_ws[i] * (json_bytes[i] - 58) === 0;
+        num_colons += separator_ws[i];
+    }
+    num_colons === COLON_WS;
+
+    // The value starts with neither whitespace nor a colon, so value_l is unique
+    signal value_start_ws[json_byte_len - 1];
+    signal value_prod[json_byte_len - 1];
+    var value_ws = 0;
+    for (var i = 0; i < json_byte_len - 1; i++) {
+        value_start_ws[i] <== separator.last_indicator[i] * ws.out[i + 1];
+        value_prod[i] <== value_start_ws[i] * (json_bytes[i + 1] - 58);
+        value_ws += value_prod[i];
     }
+    signal value_not_ws <== IsZero()(value_ws);
+    value_not_ws === 0;
 
-    value_l <== l + name_byte_len;
     value_r <== r;
 }
 
+// Check that the claim name at l is in the object value of its parent claim, which starts at parent_value_l.
+// The claim is at the nesting level `level` (checked by MatchClaimName), so it is a member of the parent object if
+// the object starts with { and is not closed between parent_value_l and l, i.e., the nesting level never drops
+// to level - 1.
+template MatchParentObject(json_byte_len, level) {
+    var MAX_JSON_BITLEN = 16;
+    signal input json_bytes[json_byte_len];
+    signal input object_nested_level[json_byte_len + 1];
+    signal input parent_value_l;
+    signal input l;
+
+    // parent_value_l < l, both are range checked by MatchClaimName
+    signal order_valid <== LessThan(MAX_JSON_BITLEN)([parent_value_l, l]);
+    order_valid === 1;
+
+    component interval = IntervalIndicator(json_byte_len);
+    interval.l <== parent_value_l;
+    interval.r <== l + 1;
+
+    // The parent value is an object
+    signal prod[json_byte_len];
+    var c = 0;
+    for (var j = 0; j < json_byte_len; j++) {
+        prod[j] <== interval.start_indicator[j] * json_bytes[j];
+        c += prod[j];
+    }
+    c === 123;      // 123 is the ASCII code of {
+
+    component is_closed[json_byte_len];
+    for (var j = 0; j < json_byte_len; j++) {
+        is_closed[j] = IsZero();
+        is_closed[j].in <== object_nested_level[j + 1] - (level - 1);
+        interval.indicator[j] * is_closed[j].out === 0;
+    }
+}
+
+// Check that the claim name does not occur in json_bytes at nesting level `level`, i.e., that the claim is absent,
+// unless present is 1. name is "name" (with the quotes), so a string value equal to the name also counts as an
+// occurrence, and the claim cannot be proven absent. Bytes are compared in chunks packed into field elements, the packing is injective since each
+// byte is less than 256.
+template MatchNoClaimName(json_byte_len, name_byte_len, level) {
+    var CHUNK_BYTE_LEN = 31;
+    var num_chunks = (name_byte_len + CHUNK_BYTE_LEN - 1) \ CHUNK_BYTE_LEN;
+    var num_starts = json_byte_len - name_byte_len + 1;
+    signal input json_bytes[json_byte_len];
+    signal input name[name_byte_len];
+    signal input object_nested_level[json_byte_len + 1];
+    signal input present;
+
+    component chunk_eq[num_starts][num_chunks];
+    signal name_eq[num_starts][num_chunks];
+    component level_eq[num_starts];
+    signal found[num_starts];
+    var num_found = 0;
+    for (var j = 0; j < num_starts; j++) {
+        for (var k = 0; k < num_chunks; k++) {
+            var diff = 0;
+            var pow256 = 1;
+            for (var i = k * CHUNK_BYTE_LEN; i < name_byte_len && i < (k + 1) * CHUNK_BYTE_LEN; i++) {
+                diff += (json_bytes[j + i] - name[i]) * pow256;
+                pow256 = pow256 * 256;
+            }
+            chunk_eq[j][k] = IsZero();
+            chunk_eq[j][k].in <== diff;
+            if (k == 0) {
+                name_eq[j][k] <== chunk_eq[j][k].out;
+            } else {
+                name_eq[j][k] <== name_eq[j][k - 1] * chunk_eq[j][k].out;
+            }
+        }
+        level_eq[j] = IsZero();
+        level_eq[j].in <== object_nested_level[j + 1] - level;
+        found[j] <== name_eq[j][num_chunks - 1] * level_eq[j].out;
+        num_found += found[j];
+    }
+
+    (1 - present) * num_found === 0;
+}
+
+// Select the JSON bytes an optional claim is matched in: json_bytes if the claim is present, and otherwise
+// default_json, a constant holding the claim name and a default value (see write_optional_claim in prepare_setup.py).
+// The nesting level of default_json is `level` throughout.
+template OptionalClaim(json_byte_len, name_byte_len, default_byte_len, level) {
+    signal input json_bytes[json_byte_len];
+    signal input object_nested_level[json_byte_len + 1];
+    signal input name[name_byte_len];
+    signal input default_json[default_byte_len];
+    signal input present;
+    signal output claim_json_bytes[json_byte_len];
+    signal output claim_nested_level[json_byte_len + 1];
+
+    present * (present - 1) === 0;
+    MatchNoClaimName(json_byte_len, name_byte_len, level)(json_bytes, name, object_nested_level, present);
+
+    for (var i = 0; i < json_byte_len; i++) {
+        var d = i < default_byte_len ? default_json[i] : 0;
+        claim_json_bytes[i] <== present * (json_bytes[i] - d) + d;
+    }
+    for (var i = 0; i < json_byte_len + 1; i++) {
+        claim_nested_level[i] <== present * (object_nested_level[i] - level) + level;
+    }
+}
+
 // Validate the claim value without reveal.
-// typ : 0 for string, 1 for number, 2 for bool, 3 for null, 4 for array, 5 for object.
+// typ : 0 for string (and date), 1 for number, 2 for boolean, 3 for null, 4 for array, 5 for object.
 template ValidateClaimValue(msg_json_len, typ) {
     signal input json_bytes[msg_json_len];
     signal input l;
@@ -130,20 +284,31 @@ template ValidateClaimValue(msg_json_len, typ) {
         ExcludeSpecial(msg_json_len, 93)(range_indicator, json_bytes);
         // Exclude `}`
         ExcludeSpecial(msg_json_len, 125)(range_indicator, json_bytes);
+        // Exclude whitespace, that may follow the number
+        ExcludeWhitespace(msg_json_len)(range_indicator, json_bytes);
         AssertEndNumber(msg_json_len)(value_range.last_indicator, json_bytes);
     } else if (typ == 0) {
         signal inside_indicator[msg_json_len];
         for (var i = 0; i < msg_json_len; i++) {
             inside_indicator[i] <== range_indicator[i] - start_indicator[i] - last_indicator[i];
         }
-        // Exclude `"`
-        ExcludeSpecial(msg_json_len, 34)(inside_indicator, json_bytes);
-        // The last character must be '"'
+        // Exclude unescaped `"`, and the last character must be an unescaped '"'
+        component esc = JSONEscaped(msg_json_len);
+        esc.in <== json_bytes;
+        component is_quote[msg_json_len];
+        signal unescaped_quote[msg_json_len];
         for (var i = 0; i < msg_json_len; i++) {
-            last_indicator[i] * (json_bytes[i] - 34) === 0;
+            is_quote[i] = IsEqual();
+            is_quote[i].in[0] <== json_bytes[i];
+            is_quote[i].in[1] <== 34;
+            unescaped_quote[i] <== is_quote[i].out * (1 - esc.escaped[i]);
+            inside_indicator[i] * unescaped_quote[i] === 0;
+            last_indicator[i] * (1 - unescaped_quote[i]) === 0;
         }
-    } else if (typ == 2 || typ  == 3) {
-        1 === 0; // assert(false, "Support for types bool and null is not implemented");
+    } else if (typ == 2) {
+        signal is_true <== RevealClaimBoolean(msg_json_len)(json_bytes, l, r);
+    } else if (typ == 3) {
+        1 === 0; // assert(false, "Support for type null is not implemented");
     } else {
         // We don't do any operations except revealing it directly for list or object.
         // TODO: we check whether the pairs of `[]` (num(`[`) - num(']') > 0 and = 0 at the right end))
@@ -153,6 +318,8 @@ template ValidateClaimValue(msg_json_len, typ) {
 }
 
 // Reveal the claim value with claim_byte_len as the maximum length.
+// Strings are decoded (see DecodeJSONString): value holds the UTF-8 bytes of the string, with the quotes, and
+// value_len their number.
 template RevealClaimValueBytes(msg_json_len, claim_byte_len, field_byte_len, is_number) {
     signal input json_bytes[msg_json_len];
     signal input l;
@@ -164,13 +331,14 @@ template RevealClaimValueBytes(msg_json_len, claim_byte_len, field_byte_len, is_
     value_range.l <== l;
     value_range.r <== r;
 
-    value_len <== r - l;
-
     // Number is special because there's no symbol denoting the end of a number.
+    // The whitespace that may follow it is not part of the value.
     if (is_number) {
+        ExcludeWhitespace(msg_json_len)(value_range.indicator, json_bytes);
         AssertEndNumber(msg_json_len)(value_range.last_indicator, json_bytes);
     }
 
+    signal raw_value[claim_byte_len];
     var tmp_prod1[claim_byte_len][msg_json_len];
     var tmp_prod2[claim_byte_len][msg_json_len];
     for (var i = 0; i < claim_byte_len; i++) {
@@ -179,12 +347,12 @@ template RevealClaimValueBytes(msg_json_len, claim_byte_len, field_byte_len, is_
             tmp_prod1[i][j] = value_range.start_indicator[j - i] * value_range.indicator[j];
             c +=  tmp_prod1[i][j] * json_bytes[j];
         }
-        value[i] <-- c;
+        raw_value[i] <-- c;
     }
 
     component match_substring = MatchSubstring(msg_json_len, claim_byte_len, field_byte_len);
     for (var i = 0; i < claim_byte_len; i++) {
-        match_substring.substr[i] <== value[i];
+        match_substring.substr[i] <== raw_value[i];
     }
     for (var i = 0; i < msg_json_len; i++) {
         match_substring.msg[i] <== json_bytes[i];
@@ -192,9 +360,21 @@ template RevealClaimValueBytes(msg_json_len, claim_byte_len, field_byte_len, is_
     }
     match_substring.l <== l;
     match_substring.r <== r;
+
+    if (is_number) {
+        value <== raw_value;
+        value_len <== r - l;
+    } else {
+        component decode = DecodeJSONString(claim_byte_len);
+        decode.in <== raw_value;
+        decode.len <== r - l;
+        value <== decode.out;
+        value_len <== decode.out_len;
+    }
 }
 
-// Reveal the claim value as a single field element. 
+// Reveal the claim value as a single field element. Strings are packed without their quotes, as in mDLs, so that
+// a string can be linked to the same string in a credential of another format.
 // The claim_byte_len must be less than 32 so that it fits in a 254-bit field element
 template RevealClaimValue(msg_json_len, claim_byte_len, field_byte_len, is_number) {
     signal input json_bytes[msg_json_len];
@@ -215,18 +395,139 @@ template RevealClaimValue(msg_json_len, claim_byte_len, field_byte_len, is_numbe
         value <== convert.field_elt;
     }
     else {
-        signal intermediate_value[claim_byte_len];
+        component unquote = UnquoteString(claim_byte_len);
+        unquote.in <== reveal_claim.value;
+        unquote.len <== reveal_claim.value_len;
+
+        signal intermediate_value[claim_byte_len - 1];
         
-        intermediate_value[0] <== reveal_claim.value[0];
+        intermediate_value[0] <== unquote.out[0];
         var  pow256 = 256;
-        for(var i = 1; i < claim_byte_len; i++) {
-            intermediate_value[i] <== intermediate_value[i-1] + reveal_claim.value[i] * pow256;
+        for(var i = 1; i < claim_byte_len - 1; i++) {
+            intermediate_value[i] <== intermediate_value[i-1] + unquote.out[i] * pow256;
             pow256 = pow256*256;
         }
-        value <== intermediate_value[claim_byte_len-1];        
+        value <== intermediate_value[claim_byte_len-2];        
     }
 }
 
+// Reveal a string claim value longer than a field element, as claim_byte_len / field_byte_len field elements.
+// Each field element packs field_byte_len bytes of the value, as in RevealClaimValue, and the value must fit in
+// claim_byte_len bytes.
+template RevealClaimValueFields(msg_json_len, claim_byte_len, field_byte_len) {
+    var MAX_JSON_BITLEN = 16;
+    var num_fields = claim_byte_len \ field_byte_len;
+    signal input json_bytes[msg_json_len];
+    signal input l;
+    signal input r;
+    signal output value[num_fields];
+
+    assert(claim_byte_len % field_byte_len == 0);
+
+    component reveal_claim = RevealClaimValueBytes(msg_json_len, claim_byte_len, field_byte_len, 0);
+    reveal_claim.json_bytes <== json_bytes;
+    reveal_claim.l <== l;
+    reveal_claim.r <== r;
+
+    // The value is not truncated. l < r is checked by MatchClaimName.
+    signal len_valid <== LessThan(MAX_JSON_BITLEN)([reveal_claim.value_len, claim_byte_len + 1]);
+    len_valid === 1;
+
+    signal intermediate_value[claim_byte_len];
+    for (var k = 0; k < num_fields; k++) {
+        var base = k * field_byte_len;
+        intermediate_value[base] <== reveal_claim.value[base];
+        var pow256 = 256;
+        for (var i = 1; i < field_byte_len; i++) {
+            intermediate_value[base + i] <== intermediate_value[base + i - 1] + reveal_claim.value[base + i] * pow256;
+            pow256 = pow256 * 256;
+        }
+        value[k] <== intermediate_value[base + field_byte_len - 1];
+    }
+}
+
+// The n bytes of json_bytes starting at l. Bytes past the end of json_bytes are unconstrained, callers
+// must check that l + n <= json_byte_len.
+template ExtractBytesAt(json_byte_len, n) {
+    signal input json_bytes[json_byte_len];
+    signal input l;
+    signal output out[n];
+
+    component start = PointIndicator(json_byte_len);
+    start.l <== l;
+
+    for (var i = 0; i < n; i++) {
+        var c = 0;
+        for (var j = 0; j < json_byte_len - i; j++) {
+            c += start.indicator[j] * json_bytes[j + i];
+        }
+        out[i] <-- c;
+        for (var j = 0; j < json_byte_len - i; j++) {
+            start.indicator[j] * (out[i] - json_bytes[j + i]) === 0;
+        }
+    }
+}
+
+// Reveal a boolean claim value, true or false, as 1 or 0.
+template RevealClaimBoolean(msg_json_len) {
+    signal input json_bytes[msg_json_len];
+    signal input l;
+    signal input r;
+    signal output value;
+
+    component bytes = ExtractBytesAt(msg_json_len, 5);
+    bytes.json_bytes <== json_bytes;
+    bytes.l <== l;
+
+    var true_bytes[4] = [116, 114, 117, 101];       // true
+    var false_bytes[5] = [102, 97, 108, 115, 101];  // false
+
+    // The first byte is t or f
+    value <== IsEqual()([bytes.out[0], true_bytes[0]]);
+    signal not_value <== 1 - value;
+    not_value * (bytes.out[0] - false_bytes[0]) === 0;
+
+    for (var i = 1; i < 4; i++) {
+        value * (bytes.out[i] - true_bytes[i]) === 0;
+    }
+    for (var i = 1; i < 5; i++) {
+        not_value * (bytes.out[i] - false_bytes[i]) === 0;
+    }
+
+    // r <= msg_json_len is checked by MatchClaimName, so the bytes compared are in json_bytes
+    r - l === 5 - value;
+}
+
+// Reveal a date claim value, an ISO 8601 calendar date string "YYYY-MM-DD", as a daystamp (see Daystamp).
+// The year must be between 1900 and 2030, other dates are rejected (see DaysBeforeYear).
+template RevealClaimDate(msg_json_len) {
+    signal input json_bytes[msg_json_len];
+    signal input l;
+    signal input r;
+    signal output value;
+
+    component bytes = ExtractBytesAt(msg_json_len, 12);
+    bytes.json_bytes <== json_bytes;
+    bytes.l <== l;
+
+    // "YYYY-MM-DD", with the quotes. r <= msg_json_len is checked by MatchClaimName.
+    r - l === 12;
+    bytes.out[0] === 34;    // 34 is the ASCII code of "
+    bytes.out[5] === 45;    // 45 is the ASCII code of -
+    bytes.out[8] === 45;
+    bytes.out[11] === 34;
+
+    signal year <== (bytes.out[1] - 48) * 1000 + (bytes.out[2] - 48) * 100 + (bytes.out[3] - 48) * 10 + (bytes.out[4] - 48);
+    signal month <== (bytes.out[6] - 48) * 10 + (bytes.out[7] - 48);
+    signal day <== (bytes.out[9] - 48) * 10 + (bytes.out[10] - 48);
+
+    component ds = Daystamp();
+    ds.year <== year;
+    ds.month <== month;
+    ds.day <== day;
+    value <== ds.out;
+}
+
 // Replace double quote characters with zero
 template StripQuotes(input_len) {
     signal input in[input_len];
@@ -240,6 +541,22 @@ template StripQuotes(input_len) {
     }
 }
 
+// Drop the quotes of a decoded string literal: in holds the len bytes of the literal, with the quotes, followed by
+// zeros (see DecodeJSONString), and out holds the string followed by zeros.
+template UnquoteString(input_len) {
+    signal input in[input_len];
+    signal input len;
+    signal output out[input_len - 1];
+    component is_closing[input_len - 1];
+    for (var i = 0; i < input_len - 1; i++) {
+        // The closing quote is in[len - 1]
+        is_closing[i] = IsEqual();
+        is_closing[i].in[0] <== i + 2;
+        is_closing[i].in[1] <== len;
+        out[i] <== (1 - is_closing[i].out) * in[i + 1];
+    }
+}
+
 // Reveal part of the claim value, following the @ symbol
 // E.g., reveals 'example.com' on input 'alice@example.com'
 // The claim_byte_len must be less than 32 so that it fits in a 254-bit field element
@@ -359,6 +676,7 @@ template CalculatePadding(){
 
 // Hash and Reveal the claim value with claim_byte_len as the maximum length.
 // We do not assume that the claim length is public (only the max)
+// Strings are hashed decoded, with their quotes, see RevealClaimValueBytes
 template HashRevealClaimValue(msg_json_len, max_claim_byte_len, field_byte_len, is_number) {
     signal input json_bytes[msg_json_len];
     signal input l;
@@ -378,7 +696,7 @@ template HashRevealClaimValue(msg_json_len, max_claim_byte_len, field_byte_len,
     var max_bytes_padded = max_bits_padded\8;
     component sha256 = Sha256General(max_bits_padded);
 
-    signal data_len_bytes <== (r - l);
+    signal data_len_bytes <== reveal_claim.value_len;
     component calculate_padding = CalculatePadding();
     calculate_padding.data_len_bytes <== data_len_bytes;
     signal padding_zero_bytes <== calculate_padding.padding_zero_bytes;
@@ -533,15 +851,51 @@ template ExcludeSpecial(byte_len, special_char) {
     }
 }
 
+// Exclude JSON whitespace from the interval
+template ExcludeWhitespace(byte_len) {
+    signal input interval[byte_len];
+    signal input msg[byte_len];
+
+    component ws = JSONWhitespace(byte_len);
+    ws.in <== msg;
+    component is_zero[byte_len];
+    for (var i = 0; i < byte_len; i++) {
+        is_zero[i] = IsZero();
+        is_zero[i].in <== ws.out[i];
+        interval[i] * is_zero[i].out === 0;
+    }
+}
+
+// out[i] = (in[i] - 32)(in[i] - 9)(in[i] - 10)(in[i] - 13) is zero if and only if in[i] is JSON whitespace:
+// space, tab, line feed or carriage return
+template JSONWhitespace(byte_len) {
+    signal input in[byte_len];
+    signal output out[byte_len];
+
+    signal tmp[byte_len];
+    signal tmp2[byte_len];
+    for (var i = 0; i < byte_len; i++) {
+        tmp[i] <== (in[i] - 32) * (in[i] - 9);
+        tmp2[i] <== tmp[i] * (in[i] - 10);
+        out[i] <== tmp2[i] * (in[i] - 13);
+    }
+}
+
 template AssertEndNumber(msg_byte_len) {
     signal input last_indicator[msg_byte_len];
     signal input msg[msg_byte_len];
 
     // The last character must be a non-number, to ensure the entire number is being used,
-    // the possibilities are ',' (44) or '}' (125)
+    // the possibilities are ',' (44), '}' (125), at the end of an SD-JWT disclosure, ']' (93), or whitespace
+    component ws = JSONWhitespace(msg_byte_len);
+    ws.in <== msg;
     signal tmp[msg_byte_len];
+    signal tmp2[msg_byte_len];
+    signal tmp3[msg_byte_len];
     for (var j = 1; j < msg_byte_len; j++) {
         tmp[j] <== last_indicator[j - 1] * (msg[j] - 44); // 44 is the ASCII code of ','
-        tmp[j] * (msg[j] - 125) === 0; // 125 is the ASCII code of '}'
+        tmp2[j] <== tmp[j] * (msg[j] - 125); // 125 is the ASCII code of '}'
+        tmp3[j] <== tmp2[j] * (msg[j] - 93); // 93 is the ASCII code of ']'
+        tmp3[j] * ws.out[j] === 0;
     }
 }
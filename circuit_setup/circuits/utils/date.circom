@@ -0,0 +1,113 @@
+pragma circom 2.1.6;
+
+include "../circomlib/circuits/comparators.circom";
+include "../indicator.circom";
+
+// Calendar date templates, shared with the mDL circuits (see circuits-mdl/strings.circom)
+
+template DotProd(n) {
+    signal input v1[n];
+    signal input v2[n];
+    signal output out;
+    
+    signal dp_int[n];
+    dp_int[0] <== v1[0] * v2[0];
+    for (var i = 1; i < n; i++){
+        dp_int[i] <== dp_int[i-1] + v1[i] * v2[i];
+    }
+
+    out <== dp_int[n-1];    
+}
+
+
+template Lookup(n) {
+    signal input table[n];
+    signal input idx;
+
+    component indicator = PointIndicator(n);
+    indicator.l <== idx;
+
+    component dp = DotProd(n);
+    dp.v1 <== indicator.indicator;
+    dp.v2 <== table;
+
+    signal output out <== dp.out;
+}
+
+template DaysBeforeMonth() {
+    signal input month;
+    signal input year;
+
+    signal days_before_month[13] <== [-1, 0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+    component lookup_dbm = Lookup(13);
+    lookup_dbm.table <== days_before_month;
+    lookup_dbm.idx <== month;
+    signal dbm <== lookup_dbm.out;
+
+    // Add one to dbm if month > 2 and IsLeap(year)
+    component is_leap = IsLeap();
+    is_leap.year <== year;
+    
+    component gt = GreaterThan(16);
+    gt.in[0] <== month;
+    gt.in[1] <== 2;
+
+    signal output out <== dbm + is_leap.out * gt.out;
+}
+
+
+template IsLeap() {
+    signal input year;
+    
+    // For years between 1900 and 2030, is_leap[y] = 1 if y is a leap year
+    signal is_leap[131] <== [0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0];
+
+    component lookup = Lookup(131);
+    lookup.table <== is_leap;
+    lookup.idx <== year - 1900;
+
+    signal output out <== lookup.out;
+}
+
+template DaysBeforeYear() {
+    signal input year;
+
+    // Start with _days_before_year
+    // For the years 1900 to 2030 we precompute the days that have happened before this year
+    assert(year >= 1900);
+    assert(year <= 2030);
+
+    // Lookup outputs 0 for an index outside of the table, so the range must also be constrained
+    component year_bits = Num2Bits(8);
+    year_bits.in <== year - 1900;
+    component year_in_range = LessThan(8);
+    year_in_range.in[0] <== year - 1900;
+    year_in_range.in[1] <== 131;
+    year_in_range.out === 1;
+    signal days_before_year[131] <== [693595, 693960, 694325, 694690, 695055, 695421, 695786, 696151, 696516, 696882, 697247, 697612, 697977, 698343, 698708, 699073, 699438, 699804, 700169, 700534, 700899, 701265, 701630, 701995, 702360, 702726, 703091, 703456, 703821, 704187, 704552, 704917, 705282, 705648, 706013, 706378, 706743, 707109, 707474, 707839, 708204, 708570, 708935, 709300, 709665, 710031, 710396, 710761, 711126, 711492, 711857, 712222, 712587, 712953, 713318, 713683, 714048, 714414, 714779, 715144, 715509, 715875, 716240, 716605, 716970, 717336, 717701, 718066, 718431, 718797, 719162, 719527, 719892, 720258, 720623, 720988, 721353, 721719, 722084, 722449, 722814, 723180, 723545, 723910, 724275, 724641, 725006, 725371, 725736, 726102, 726467, 726832, 727197, 727563, 727928, 728293, 728658, 729024, 729389, 729754, 730119, 730485, 730850, 731215, 731580, 731946, 732311, 732676, 733041, 733407, 733772, 734137, 734502, 734868, 735233, 735598, 735963, 736329, 736694, 737059, 737424, 737790, 738155, 738520, 738885, 739251, 739616, 739981, 740346, 740712, 741077];
+
+    component lookup_dby = Lookup(131);
+    lookup_dby.table <== days_before_year;
+    lookup_dby.idx <== year - 1900;
+
+    signal output out <== lookup_dby.out;
+}
+
+// Similar to a unix timestamp, but it counts the number of days since January 1, year 0000
+template Daystamp() {
+    signal input year;
+    signal input month;
+    signal input day;
+
+    // See https://github.com/python/cpython/blob/54b5e4da8a4c6ae527ab238fcd6b9ba0a3ed0fc7/Lib/datetime.py#L63
+    
+    component dby = DaysBeforeYear();
+    dby.year <== year;
+
+    component dbm = DaysBeforeMonth();
+    dbm.year <== year;
+    dbm.month <== month;
+
+
+    signal output out <== dby.out + dbm.out + day;
+}
@@ -0,0 +1,242 @@
+pragma circom 2.1.6;
+
+include "../circomlib/circuits/comparators.circom";
+include "../circomlib/circuits/bitify.circom";
+include "../indicator.circom";
+
+// JSON string templates (RFC 8259, section 7)
+
+// escaped[i] is 1 if in[i] follows a backslash that starts an escape sequence, e.g., the n of \n or the second
+// backslash of \\, and escaping[i] is 1 if in[i] is such a backslash. JSON has no backslashes outside of strings,
+// so in can be a whole JSON document.
+template JSONEscaped(n) {
+    signal input in[n];
+    signal output escaped[n];
+    signal output escaping[n];
+
+    component is_backslash[n];
+    for (var i = 0; i < n; i++) {
+        is_backslash[i] = IsEqual();
+        is_backslash[i].in[0] <== in[i];
+        is_backslash[i].in[1] <== 92;   // 92 is the ASCII code of \
+        if (i == 0) {
+            escaped[i] <== 0;
+        } else {
+            escaped[i] <== escaping[i - 1];
+        }
+        escaping[i] <== is_backslash[i].out * (1 - escaped[i]);
+    }
+}
+
+// Decode a JSON string literal: in holds the len bytes of the literal, with the quotes, followed by zeros, and out
+// holds the decoded UTF-8 bytes, with the quotes, followed by zeros. out_len is the length of the decoded literal.
+// Checks that the literal fits in n bytes, starts with a quote and ends at the first unescaped quote.
+// The escape sequences \uXXXX must encode a character of the Basic Multilingual Plane, surrogate pairs are not
+// supported. The literal is assumed to be valid JSON, e.g., the 4 bytes following \u are hex digits.
+template DecodeJSONString(n) {
+    signal input in[n];
+    signal input len;
+    signal output out[n];
+    signal output out_len;
+
+    // The bytes are range checked, so that the packing of in is injective (see MatchSubstring)
+    component bits[n];
+    for (var i = 0; i < n; i++) {
+        bits[i] = Num2Bits(8);
+        bits[i].in <== in[i];
+    }
+
+    // The literal is not truncated, so its closing quote is checked below. len is at most 16 bits, as the difference
+    // of the range-checked bounds of a claim (see MatchClaimName).
+    signal len_valid <== LessThan(16)([len, n + 1]);
+    len_valid === 1;
+
+    component range = IntervalIndicator(n);
+    range.l <== 0;
+    range.r <== len;
+    component esc = JSONEscaped(n);
+    esc.in <== in;
+
+    // The literal starts with a quote, the quotes inside are escaped, and the last one is not
+    in[0] === 34;   // 34 is the ASCII code of "
+    component is_quote[n];
+    signal unescaped_quote[n];
+    for (var i = 0; i < n; i++) {
+        is_quote[i] = IsEqual();
+        is_quote[i].in[0] <== in[i];
+        is_quote[i].in[1] <== 34;
+        unescaped_quote[i] <== is_quote[i].out * (1 - esc.escaped[i]);
+        (range.indicator[i] - range.start_indicator[i] - range.last_indicator[i]) * unescaped_quote[i] === 0;
+        range.last_indicator[i] * (1 - unescaped_quote[i]) === 0;
+    }
+
+    // The escape sequences \b \f \n \r \t, the others (\" \\ \/) decode to the escaped byte
+    var SIMPLE_ESCAPES[5][2] = [[98, 8], [102, 12], [110, 10], [114, 13], [116, 9]];
+    component is_simple[n][5];
+
+    // The escape sequences \uXXXX, decoded to 1 to 3 UTF-8 bytes. With u_start[i] = 1 at the u, the hex digits
+    // i + 2, i + 3 and i + 4 are replaced by the bytes e2[i], e3[i] and e4[i], the first two being dropped for
+    // shorter encodings.
+    component is_u[n];
+    signal u_start[n];
+    signal code_point[n];
+    component cp_bits[n];
+    component lt80[n];
+    component lt800[n];
+    signal surrogate1[n];
+    signal surrogate2[n];
+    signal surrogate3[n];
+    signal e2[n];
+    signal e3a[n];
+    signal e3[n];
+    signal e4a[n];
+    signal e4[n];
+    for (var i = 0; i < n; i++) {
+        is_u[i] = IsEqual();
+        is_u[i].in[0] <== in[i];
+        is_u[i].in[1] <== 117;  // 117 is the ASCII code of u
+        u_start[i] <== esc.escaped[i] * is_u[i].out;
+
+        // The value of a hex digit is its low nibble, plus 9 for letters (bit 6 is set for A-F and a-f)
+        var cp = 0;
+        if (i + 4 < n) {
+            for (var k = 1; k <= 4; k++) {
+                var digit = 9 * bits[i + k].out[6];
+                for (var t = 0; t < 4; t++) {
+                    digit += bits[i + k].out[t] * (1 << t);
+                }
+                cp = cp * 16 + digit;
+            }
+        } else {
+            u_start[i] === 0;
+        }
+        code_point[i] <== u_start[i] * cp;
+        cp_bits[i] = Num2Bits(16);
+        cp_bits[i].in <== code_point[i];
+        var b[16];
+        for (var k = 0; k < 16; k++) {
+            b[k] = cp_bits[i].out[k];
+        }
+
+        var hi9 = 0;
+        for (var k = 7; k < 16; k++) {
+            hi9 += b[k];
+        }
+        var hi5 = 0;
+        for (var k = 11; k < 16; k++) {
+            hi5 += b[k];
+        }
+        lt80[i] = IsZero();
+        lt80[i].in <== hi9;
+        lt800[i] = IsZero();
+        lt800[i].in <== hi5;
+
+        // Surrogates, from D800 to DFFF, have the top bits 11011
+        surrogate1[i] <== b[15] * b[14];
+        surrogate2[i] <== surrogate1[i] * b[12];
+        surrogate3[i] <== surrogate2[i] * b[11];
+        surrogate3[i] * (1 - b[13]) === 0;
+
+        var low6 = 0;
+        for (var k = 0; k < 6; k++) {
+            low6 += b[k] * (1 << k);
+        }
+        var mid5 = 0;
+        for (var k = 6; k < 11; k++) {
+            mid5 += b[k] * (1 << (k - 6));
+        }
+        var top4 = 0;
+        for (var k = 12; k < 16; k++) {
+            top4 += b[k] * (1 << (k - 12));
+        }
+        // 3 bytes 1110xxxx 10xxxxxx 10xxxxxx, 2 bytes 110xxxxx 10xxxxxx, or 1 byte 0xxxxxxx
+        e2[i] <== (1 - lt800[i].out) * (224 + top4);
+        e3a[i] <== (lt800[i].out - lt80[i].out) * (192 + mid5);
+        e3[i] <== e3a[i] + (1 - lt800[i].out) * (128 + mid5 + 32 * b[11]);
+        e4a[i] <== lt80[i].out * code_point[i];
+        e4[i] <== e4a[i] + (1 - lt80[i].out) * (128 + low6);
+    }
+
+    // The decoded byte of each input byte, and whether it is kept
+    signal keep2[n];
+    signal keep3[n];
+    signal keep[n];
+    signal plain_byte[n];
+    signal simple_byte[n];
+    signal hex2_byte[n];
+    signal hex3_byte[n];
+    signal hex4_byte[n];
+    signal dec[n];
+    signal kept_in_range[n];
+    var decoded_len = 0;
+    for (var j = 0; j < n; j++) {
+        // hex[k] is 1 at the k-th hex digit of a \uXXXX sequence
+        var hex[5] = [0, 0, 0, 0, 0];
+        for (var k = 1; k <= 4; k++) {
+            if (j >= k) {
+                hex[k] = u_start[j - k];
+            }
+        }
+        var plain = 1 - esc.escaping[j] - esc.escaped[j] - hex[1] - hex[2] - hex[3] - hex[4];
+        var simple = esc.escaped[j] - u_start[j];
+
+        var mapped = in[j];
+        for (var c = 0; c < 5; c++) {
+            is_simple[j][c] = IsEqual();
+            is_simple[j][c].in[0] <== in[j];
+            is_simple[j][c].in[1] <== SIMPLE_ESCAPES[c][0];
+            mapped += is_simple[j][c].out * (SIMPLE_ESCAPES[c][1] - SIMPLE_ESCAPES[c][0]);
+        }
+
+        if (j >= 2) {
+            keep2[j] <== hex[2] * (1 - lt800[j - 2].out);
+            hex2_byte[j] <== hex[2] * e2[j - 2];
+        } else {
+            keep2[j] <== 0;
+            hex2_byte[j] <== 0;
+        }
+        if (j >= 3) {
+            keep3[j] <== hex[3] * (1 - lt80[j - 3].out);
+            hex3_byte[j] <== hex[3] * e3[j - 3];
+        } else {
+            keep3[j] <== 0;
+            hex3_byte[j] <== 0;
+        }
+        if (j >= 4) {
+            hex4_byte[j] <== hex[4] * e4[j - 4];
+        } else {
+            hex4_byte[j] <== 0;
+        }
+        keep[j] <== plain + simple + keep2[j] + keep3[j] + hex[4];
+
+        plain_byte[j] <== plain * in[j];
+        simple_byte[j] <== simple * mapped;
+        dec[j] <== range.indicator[j] * (plain_byte[j] + simple_byte[j] + hex2_byte[j] + hex3_byte[j] + hex4_byte[j]);
+
+        kept_in_range[j] <== range.indicator[j] * keep[j];
+        decoded_len += kept_in_range[j];
+    }
+    out_len <== decoded_len;
+
+    // The decoded byte j goes to out[pos], pos the number of bytes kept before it. The bytes that are not kept
+    // decode to 0.
+    component at[n];
+    signal placed[n][n];
+    var acc[n];
+    for (var m = 0; m < n; m++) {
+        acc[m] = 0;
+    }
+    var pos = 0;
+    for (var j = 0; j < n; j++) {
+        at[j] = PointIndicator(j + 1);
+        at[j].l <== pos;
+        for (var m = 0; m <= j; m++) {
+            placed[j][m] <== at[j].indicator[m] * dec[j];
+            acc[m] += placed[j][m];
+        }
+        pos += keep[j];
+    }
+    for (var m = 0; m < n; m++) {
+        out[m] <== acc[m];
+    }
+}
@@ -6,16 +6,17 @@ include "./fp.circom";
 /// @title RSAVerifier65537 
 /// @notice Verifies an RSA signature with exponent 65537.
 /// @param n Number of bits per chunk the modulus is split into. Recommended to be 121.
-/// @param k Number of chunks the modulus is split into. Recommended to be 17.
+/// @param k Number of chunks the modulus is split into. Recommended to be 17 for 2048-bit keys, 26 for 3072-bit keys and 34 for 4096-bit keys.
+/// @param hash_bits Output length of the hash function (256, 384 or 512 for SHA-256, SHA-384 or SHA-512).
 /// @input message[k] The message that was signed; assumes to consist of `k` chunks that fit in `n` bits (also constrained implicitly).
 /// @input signature[k] The signature to verify; assumes to consist of `k` chunks that fit in `n` bits (also constrained implicitly).
 /// @input modulus[k] The modulus of the RSA key (pubkey); assumes to consist of `k` chunks that fit in `n` bits (also constrained implicitly).
-template RSAVerifier65537(n, k) {
+template RSAVerifier65537(n, k, hash_bits) {
     signal input message[k];
     signal input signature[k];
     signal input modulus[k];
 
-    component padder = RSAPad(n, k);
+    component padder = RSAPad(n, k, hash_bits);
     for (var i = 0; i < k; i++) {
         padder.modulus[i] <== modulus[i];
         padder.message[i] <== message[i];
@@ -91,22 +92,35 @@ template FpPow65537Mod(n, k) {
     }
 }
 
+// The DigestInfo prefix of the RSASSA-PKCS1-v1_5 encoding (RFC 8017, Section 9.2) for SHA-256, SHA-384 and SHA-512
+function rsa_digest_info(hash_bits) {
+    if (hash_bits == 384) {
+        return 0x3041300d060960864801650304020205000430;
+    } else if (hash_bits == 512) {
+        return 0x3051300d060960864801650304020305000440;
+    }
+    return 0x3031300d060960864801650304020105000420;
+}
+
 /// @title RSAPad
 /// @notice Pads a message for RSA signing.
 /// @param n Number of bits per chunk the modulus is split into.
 /// @param k Number of chunks the modulus is split into.
+/// @param hash_bits Output length of the hash function (256, 384 or 512).
 /// @input modulus The modulus of the RSA key (pubkey).
 /// @input message The message to pad.
 /// @output out The padded message.
-template RSAPad(n, k) {
+template RSAPad(n, k, hash_bits) {
     signal input modulus[k];
     signal input message[k];
     signal output out[k];
 
-    // The extra 152 bits comes from 0x3031300d060960864801650304020105000420
-    // This is due to padding from the RSASSA-PKCS1-v1_5 standard
-    var baseLen = 408;
-    var msgLen = 256;
+    // The extra 152 bits comes from the DigestInfo prefix of the hash function, e.g., 0x3031300d060960864801650304020105000420
+    // for SHA-256. This is due to padding from the RSASSA-PKCS1-v1_5 standard
+    assert(hash_bits == 256 || hash_bits == 384 || hash_bits == 512);
+    var digestInfo = rsa_digest_info(hash_bits);
+    var msgLen = hash_bits;
+    var baseLen = msgLen + 152;
 
     signal paddedMessageBits[n*k];
 
@@ -140,7 +154,7 @@ template RSAPad(n, k) {
     }
 
     for (var i = msgLen; i < baseLen; i++) {
-        paddedMessageBits[i] <== (0x3031300d060960864801650304020105000420 >> (i - msgLen)) & 1;
+        paddedMessageBits[i] <== (digestInfo >> (i - msgLen)) & 1;
     }
 
     component modulusZero[(n*k + 7 - (baseLen + 8))\8];
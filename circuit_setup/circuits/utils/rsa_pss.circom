@@ -0,0 +1,120 @@
+pragma circom 2.1.6;
+
+include "../circomlib/circuits/bitify.circom";
+include "../circomlib/circuits/sha256/sha256.circom";
+include "./rsa.circom";
+
+
+/// @title RSAPSSVerifier65537
+/// @notice Verifies an RSASSA-PSS signature (RFC 8017, Section 8.1) with exponent 65537, SHA-256 as hash and mask
+///         generation function, and a salt as long as the hash, as required for PS256 by RFC 7518.
+/// @param n Number of bits per chunk the modulus is split into. Recommended to be 121.
+/// @param k Number of chunks the modulus is split into.
+/// @param key_bits Size of the modulus in bits, must be a multiple of 8.
+/// @input message_hash The SHA-256 hash of the message that was signed, as a big-endian bit string.
+/// @input signature[k] The signature to verify; assumes to consist of `k` chunks that fit in `n` bits.
+/// @input modulus[k] The modulus of the RSA key (pubkey); assumes to consist of `k` chunks that fit in `n` bits.
+template RSAPSSVerifier65537(n, k, key_bits) {
+    signal input message_hash[256];
+    signal input signature[k];
+    signal input modulus[k];
+
+    assert(key_bits % 8 == 0);
+    assert(n * k >= key_bits);
+    var hLen = 32;                      // Length of the hash and of the salt, in bytes
+    var emLen = key_bits \ 8;           // Length of the encoded message EM = maskedDB || H || 0xbc
+    var dbLen = emLen - hLen - 1;       // Length of DB = PS || 0x01 || salt
+    var psLen = dbLen - hLen - 1;
+
+    // The modulus has key_bits bits
+    component modulusTopLimb = Num2Bits(n);
+    modulusTopLimb.in <== modulus[(key_bits - 1) \ n];
+    modulusTopLimb.out[(key_bits - 1) % n] === 1;
+
+    // Check that the signature is in proper form and reduced mod modulus.
+    component signatureRangeCheck[k];
+    component bigLessThan = BigLessThan(n, k);
+    for (var i = 0; i < k; i++) {
+        signatureRangeCheck[i] = Num2Bits(n);
+        signatureRangeCheck[i].in <== signature[i];
+        bigLessThan.a[i] <== signature[i];
+        bigLessThan.b[i] <== modulus[i];
+    }
+    bigLessThan.out === 1;
+
+    // EM = signature^65537 mod modulus, which FpMul reduces fully
+    component bigPow = FpPow65537Mod(n, k);
+    for (var i = 0; i < k; i++) {
+        bigPow.base[i] <== signature[i];
+        bigPow.modulus[i] <== modulus[i];
+    }
+
+    component emN2B[k];
+    signal emBits[n*k];     // Least significant bit first
+    for (var i = 0; i < k; i++) {
+        emN2B[i] = Num2Bits(n);
+        emN2B[i].in <== bigPow.out[i];
+        for (var j = 0; j < n; j++) {
+            emBits[i*n + j] <== emN2B[i].out[j];
+        }
+    }
+
+    // EM has emBits = key_bits - 1 bits, so its leftmost bit is zero
+    for (var i = key_bits - 1; i < n*k; i++) {
+        emBits[i] === 0;
+    }
+
+    // EM as a big-endian bit string
+    signal em[8*emLen];
+    for (var i = 0; i < 8*emLen; i++) {
+        em[i] <== emBits[8*emLen - 1 - i];
+    }
+
+    // The trailer field is 0xbc
+    for (var i = 0; i < 8; i++) {
+        em[8*(emLen - 1) + i] === (0xbc >> (7 - i)) & 1;
+    }
+
+    // dbMask = MGF1(H, dbLen), the concatenation of SHA-256(H || counter) for 4-byte big-endian counters
+    var numMaskBlocks = (dbLen + hLen - 1) \ hLen;
+    component mgf[numMaskBlocks];
+    for (var c = 0; c < numMaskBlocks; c++) {
+        mgf[c] = Sha256(8 * (hLen + 4));
+        for (var i = 0; i < 8*hLen; i++) {
+            mgf[c].in[i] <== em[8*dbLen + i];
+        }
+        for (var i = 0; i < 32; i++) {
+            mgf[c].in[8*hLen + i] <== (c >> (31 - i)) & 1;
+        }
+    }
+
+    // DB = maskedDB xor dbMask, with its leftmost bit cleared
+    signal db[8*dbLen];
+    db[0] <== 0;
+    for (var i = 1; i < 8*dbLen; i++) {
+        var mask = mgf[i \ 256].out[i % 256];
+        db[i] <== em[i] + mask - 2 * em[i] * mask;
+    }
+
+    // DB = PS || 0x01 || salt, where PS is all zero
+    for (var i = 1; i < 8*psLen; i++) {
+        db[i] === 0;
+    }
+    for (var i = 0; i < 7; i++) {
+        db[8*psLen + i] === 0;
+    }
+    db[8*psLen + 7] === 1;
+
+    // H = SHA-256(0x00 00 00 00 00 00 00 00 || message_hash || salt)
+    component hash = Sha256(8 * (8 + 2*hLen));
+    for (var i = 0; i < 64; i++) {
+        hash.in[i] <== 0;
+    }
+    for (var i = 0; i < 8*hLen; i++) {
+        hash.in[64 + i] <== message_hash[i];
+        hash.in[64 + 8*hLen + i] <== db[8*(psLen + 1) + i];
+    }
+    for (var i = 0; i < 8*hLen; i++) {
+        hash.out[i] === em[8*dbLen + i];
+    }
+}
@@ -0,0 +1,207 @@
+pragma circom 2.1.6;
+
+include "./base64.circom";
+include "../indicator.circom";
+include "../circomlib/circuits/bitify.circom";
+include "../circomlib/circuits/comparators.circom";
+
+// Selective disclosure for SD-JWTs (RFC 9901). A claim is disclosed as base64url(JSON array [salt, name, value]) and the
+// signed JWT payload holds the base64url-encoded SHA-256 digest of the disclosure in its "_sd" array.
+// The templates here expect Sha256Bytes to be included by the main circuit (from utils/sha.circom or ecdsa-p256/sha256.circom),
+// and MatchClaimName and JSONWhitespace (from match_claim.circom).
+
+// Match the top-level "_sd" array of the JWT payload. l is the index of the opening quote of "_sd", value_l the index of
+// the [ that opens the array and r the index after the ] that closes it. The elements of the array are digests, so
+// the array is closed by the first ] after value_l, and r is unique.
+template SDArray(max_json_bytes) {
+    signal input json_bytes[max_json_bytes];
+    signal input object_nested_level[max_json_bytes + 1];
+    signal input l;
+    signal input value_l;
+    signal input r;
+
+    var SD_NAME[5] = [34, 95, 115, 100, 34];    // "_sd"
+    component match_name = MatchClaimName(max_json_bytes, 5, 1);
+    match_name.name <== SD_NAME;
+    match_name.json_bytes <== json_bytes;
+    match_name.l <== l;
+    match_name.value_l <== value_l;
+    match_name.r <== r;
+    match_name.object_nested_level <== object_nested_level;
+
+    // The value is [...], with no ] before its end
+    component array = IntervalIndicator(max_json_bytes);
+    array.l <== value_l;
+    array.r <== r;
+    component is_close[max_json_bytes];
+    signal first_prod[max_json_bytes];
+    signal last_prod[max_json_bytes];
+    var first_char = 0;
+    var last_char = 0;
+    for (var j = 0; j < max_json_bytes; j++) {
+        first_prod[j] <== array.start_indicator[j] * json_bytes[j];
+        first_char += first_prod[j];
+        last_prod[j] <== array.last_indicator[j] * json_bytes[j];
+        last_char += last_prod[j];
+        is_close[j] = IsZero();
+        is_close[j].in <== json_bytes[j] - 93;
+        (array.indicator[j] - array.last_indicator[j]) * is_close[j].out === 0;
+    }
+    first_char === 91;      // 91 is the ASCII code of [
+    last_char === 93;       // 93 is the ASCII code of ]
+}
+
+// Checks that the SHA-256 digest of the disclosure is one of the strings in the top-level "_sd" array of the JWT
+// payload, and decodes the disclosure.
+// disclosure is the base64url disclosure with SHA-256 padding (as the JWT in Main), digest_l is the index of the
+// opening quote of the digest string in json_bytes, and sd_value_l and sd_r are the interval of the "_sd" array, as
+// matched by SDArray.
+template SDJWTDisclosure(max_json_bytes, max_disclosure_bytes) {
+    var MAX_JSON_BITLEN = 16;
+    var DIGEST_B64_LEN = 43;    // base64url encoding of 32 bytes, without padding
+    var max_disclosure_json_bytes = max_disclosure_bytes * 3 \ 4;
+    signal input json_bytes[max_json_bytes];
+    signal input disclosure[max_disclosure_bytes];
+    signal input disclosure_padded_bytes;
+    signal input digest_l;
+    signal input sd_value_l;
+    signal input sd_r;
+
+    signal output disclosure_json[max_disclosure_json_bytes];
+
+    assert(max_disclosure_bytes % 4 == 0);
+
+    // *********** hash the padded disclosure ***********
+    component sha = Sha256Bytes(max_disclosure_bytes);
+    sha.in_padded <== disclosure;
+    sha.in_len_padded_bytes <== disclosure_padded_bytes;
+
+    // *********** extract the quoted digest from the payload ***********
+    component start = PointIndicator(max_json_bytes);
+    start.l <== digest_l;
+    for (var j = max_json_bytes - DIGEST_B64_LEN - 1; j < max_json_bytes; j++) {
+        start.indicator[j] === 0;
+    }
+
+    // The digest is an element of the "_sd" array: the quoted digest is between the brackets of the array, and its
+    // opening quote follows [, a comma or whitespace. A string with the digest as the value of another claim is not
+    // in the array, and a quote inside a string is escaped, so it follows a backslash.
+    signal after_open <== LessThan(MAX_JSON_BITLEN)([sd_value_l, digest_l]);
+    after_open === 1;
+    signal before_close <== LessThan(MAX_JSON_BITLEN)([digest_l + DIGEST_B64_LEN + 1, sd_r - 1]);
+    before_close === 1;
+    signal preceding_prod[max_json_bytes - 1];
+    var preceding_byte = 0;
+    for (var j = 0; j < max_json_bytes - 1; j++) {
+        preceding_prod[j] <== start.indicator[j + 1] * json_bytes[j];
+        preceding_byte += preceding_prod[j];
+    }
+    component preceding_ws = JSONWhitespace(1);
+    preceding_ws.in[0] <== preceding_byte;
+    signal preceding_open <== preceding_ws.out[0] * (preceding_byte - 91);     // 91 is the ASCII code of [
+    preceding_open * (preceding_byte - 44) === 0;                             // 44 is the ASCII code of ,
+
+    signal prod[DIGEST_B64_LEN + 2][max_json_bytes];
+    signal digest_chars[DIGEST_B64_LEN + 2];
+    for (var i = 0; i < DIGEST_B64_LEN + 2; i++) {
+        var c = 0;
+        for (var j = 0; j < max_json_bytes - i; j++) {
+            prod[i][j] <== start.indicator[j] * json_bytes[j + i];
+            c += prod[i][j];
+        }
+        digest_chars[i] <== c;
+    }
+    digest_chars[0] === 34;                     // 34 is the ASCII code of "
+    digest_chars[DIGEST_B64_LEN + 1] === 34;
+
+    // *********** compare with the hash ***********
+    component digest_b64 = Base64Decode(32);
+    for (var i = 0; i < DIGEST_B64_LEN; i++) {
+        digest_b64.in[i] <== digest_chars[i + 1];
+    }
+    digest_b64.in[DIGEST_B64_LEN] <== 0;
+
+    component digest_bytes[32];
+    for (var i = 0; i < 32; i++) {
+        digest_bytes[i] = Bits2Num(8);
+        for (var j = 0; j < 8; j++) {
+            digest_bytes[i].in[j] <== sha.out[8*i + 7 - j];
+        }
+        digest_bytes[i].out === digest_b64.out[i];
+    }
+
+    // *********** decode the disclosure ***********
+    // The SHA-256 padding decodes to bytes following the disclosure's JSON, they are never part of a claim value
+    component decoder = Base64Decode(max_disclosure_json_bytes);
+    decoder.in <== disclosure;
+    disclosure_json <== decoder.out;
+}
+
+// Match the claim name in a disclosure ["salt", "name", value], similar to MatchClaimName for JWT payloads.
+// name is "name", (with the quotes and the comma), and l is the index of its opening quote.
+// The name must be the second element of the array: it is preceded by exactly two quotes (those of the salt).
+// A single space may separate the comma and the value.
+template MatchDisclosureName(json_byte_len, name_byte_len) {
+    var MAX_JSON_BITLEN = 16;
+    signal input json_bytes[json_byte_len];
+    signal input name[name_byte_len];
+    signal input l;
+    signal input r;
+
+    signal output value_l;
+    signal output value_r;
+
+    component start = PointIndicator(json_byte_len);
+    start.l <== l;
+
+    // Match the claim name.
+    for (var i = 0; i < name_byte_len; i++) {
+        for (var j = i; j < json_byte_len; j++) {
+            start.indicator[j - i] * (name[i] - json_bytes[j]) === 0;
+        }
+    }
+
+    // Enforcing bit-size constraints on `l` and `r`, see MatchClaimName
+    component l_bits = Num2Bits(MAX_JSON_BITLEN);
+    l_bits.in <== l;
+    component r_bits = Num2Bits(MAX_JSON_BITLEN);
+    r_bits.in <== r;
+
+    signal interval_valid <== LessThan(MAX_JSON_BITLEN)([l, r]);
+    interval_valid === 1;
+    signal bound_valid <== LessThan(MAX_JSON_BITLEN)([r, json_byte_len + 1]);
+    bound_valid === 1;
+
+    // The disclosure is an array, and the name follows the salt
+    json_bytes[0] === 91;   // 91 is the ASCII code of [
+    component is_quote[json_byte_len];
+    signal before[json_byte_len];
+    signal quotes_before[json_byte_len];
+    var num_quotes = 0;
+    var seen = 0;
+    for (var j = 0; j < json_byte_len; j++) {
+        seen += start.indicator[j];
+        before[j] <== 1 - seen;
+        is_quote[j] = IsZero();
+        is_quote[j].in <== json_bytes[j] - 34;
+        quotes_before[j] <== before[j] * is_quote[j].out;
+        num_quotes += quotes_before[j];
+    }
+    num_quotes === 2;
+
+    // Skip a space between the comma and the value
+    signal space_prod[json_byte_len];
+    var next_char = 0;
+    for (var j = 0; j < json_byte_len - name_byte_len; j++) {
+        space_prod[j] <== start.indicator[j] * json_bytes[j + name_byte_len];
+        next_char += space_prod[j];
+    }
+    for (var j = json_byte_len - name_byte_len; j < json_byte_len; j++) {
+        space_prod[j] <== 0;
+    }
+    component is_space = IsZero();
+    is_space.in <== next_char - 32;     // 32 is the ASCII code of space
+
+    value_l <== l + name_byte_len + is_space.out;
+    value_r <== r;
+}
@@ -0,0 +1,273 @@
+pragma circom 2.1.6;
+
+include "../circomlib/circuits/bitify.circom";
+include "../circomlib/circuits/comparators.circom";
+include "../circomlib/circuits/sha256/xor3.circom";
+include "../circomlib/circuits/sha256/ch.circom";
+include "../circomlib/circuits/sha256/maj.circom";
+include "./utils.circom";
+
+// SHA-512 and SHA-384 (FIPS 180-4), for the RS384 and RS512 signature algorithms.
+// Words are represented as 64 bits, least significant bit first, except for the message blocks and the
+// digest, which are big-endian bit strings (as for SHA-256 in sha256general.circom).
+
+function sha512_k(t) {
+    var k[80] = [
+        0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+        0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+        0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+        0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+        0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+        0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+        0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+        0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+        0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+        0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+        0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+        0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+        0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+        0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+        0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+        0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+        0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+        0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+        0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+        0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817
+    ];
+    return k[t];
+}
+
+// Initial hash value of SHA-512, or of SHA-384 if hash_bits is 384
+function sha512_iv(hash_bits, i) {
+    var iv512[8] = [
+        0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+        0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179
+    ];
+    var iv384[8] = [
+        0xcbbb9d5dc1059ed8, 0x629a292a367cd507, 0x9159015a3070dd17, 0x152fecd8f70e5939,
+        0x67332667ffc00b31, 0x8eb44a8768581511, 0xdb0c2e0d64f98fa7, 0x47b5481dbefa4fa4
+    ];
+    if (hash_bits == 384) {
+        return iv384[i];
+    }
+    return iv512[i];
+}
+
+// Sum of ops 64-bit words, mod 2^64
+template Sum64(ops) {
+    signal input in[ops][64];
+    signal output out[64];
+
+    var sum = 0;
+    for (var i = 0; i < ops; i++) {
+        var pow2 = 1;
+        for (var k = 0; k < 64; k++) {
+            sum += in[i][k] * pow2;
+            pow2 = pow2 + pow2;
+        }
+    }
+    component bits = Num2Bits(64 + log2_ceil(ops));
+    bits.in <== sum;
+    for (var k = 0; k < 64; k++) {
+        out[k] <== bits.out[k];
+    }
+}
+
+// rotr(x, r1) ^ rotr(x, r2) ^ rotr(x, r3), or rotr(x, r1) ^ rotr(x, r2) ^ shr(x, r3) if shift is 1
+template Sigma512(r1, r2, r3, shift) {
+    signal input in[64];
+    signal output out[64];
+
+    component xor3 = Xor3(64);
+    for (var k = 0; k < 64; k++) {
+        xor3.a[k] <== in[(k + r1) % 64];
+        xor3.b[k] <== in[(k + r2) % 64];
+        if (shift == 0) {
+            xor3.c[k] <== in[(k + r3) % 64];
+        } else if (k + r3 < 64) {
+            xor3.c[k] <== in[k + r3];
+        } else {
+            xor3.c[k] <== 0;
+        }
+    }
+    out <== xor3.out;
+}
+
+// The SHA-512 compression function. hin and out are the 8 words of the hash state, inp is a 1024-bit message block.
+template Sha512compression() {
+    signal input hin[512];
+    signal input inp[1024];
+    signal output out[512];
+
+    // Message schedule
+    signal w[80][64];
+    component s0[64];
+    component s1[64];
+    component w_sum[64];
+    for (var t = 0; t < 16; t++) {
+        for (var k = 0; k < 64; k++) {
+            w[t][k] <== inp[t*64 + 63 - k];
+        }
+    }
+    for (var t = 16; t < 80; t++) {
+        s0[t-16] = Sigma512(1, 8, 7, 1);
+        s0[t-16].in <== w[t-15];
+        s1[t-16] = Sigma512(19, 61, 6, 1);
+        s1[t-16].in <== w[t-2];
+        w_sum[t-16] = Sum64(4);
+        for (var k = 0; k < 64; k++) {
+            w_sum[t-16].in[0][k] <== w[t-16][k];
+            w_sum[t-16].in[1][k] <== s0[t-16].out[k];
+            w_sum[t-16].in[2][k] <== w[t-7][k];
+            w_sum[t-16].in[3][k] <== s1[t-16].out[k];
+        }
+        w[t] <== w_sum[t-16].out;
+    }
+
+    // In round t, the working variables a, b, c, d are a[t+3], a[t+2], a[t+1], a[t] and e, f, g, h are e[t+3], e[t+2], e[t+1], e[t]
+    signal a[84][64];
+    signal e[84][64];
+    for (var i = 0; i < 4; i++) {
+        for (var k = 0; k < 64; k++) {
+            a[3-i][k] <== hin[i*64 + k];
+            e[3-i][k] <== hin[(4+i)*64 + k];
+        }
+    }
+
+    component big_sigma0[80];
+    component big_sigma1[80];
+    component ch[80];
+    component maj[80];
+    component a_sum[80];
+    component e_sum[80];
+    for (var t = 0; t < 80; t++) {
+        big_sigma1[t] = Sigma512(14, 18, 41, 0);
+        big_sigma1[t].in <== e[t+3];
+        ch[t] = Ch_t(64);
+        ch[t].a <== e[t+3];
+        ch[t].b <== e[t+2];
+        ch[t].c <== e[t+1];
+
+        big_sigma0[t] = Sigma512(28, 34, 39, 0);
+        big_sigma0[t].in <== a[t+3];
+        maj[t] = Maj_t(64);
+        maj[t].a <== a[t+3];
+        maj[t].b <== a[t+2];
+        maj[t].c <== a[t+1];
+
+        // T1 = h + Sigma1(e) + Ch(e, f, g) + K_t + W_t, T2 = Sigma0(a) + Maj(a, b, c)
+        // The new a is T1 + T2 and the new e is d + T1
+        var k_t = sha512_k(t);
+        a_sum[t] = Sum64(7);
+        e_sum[t] = Sum64(6);
+        for (var k = 0; k < 64; k++) {
+            a_sum[t].in[0][k] <== e[t][k];
+            a_sum[t].in[1][k] <== big_sigma1[t].out[k];
+            a_sum[t].in[2][k] <== ch[t].out[k];
+            a_sum[t].in[3][k] <== (k_t >> k) & 1;
+            a_sum[t].in[4][k] <== w[t][k];
+            a_sum[t].in[5][k] <== big_sigma0[t].out[k];
+            a_sum[t].in[6][k] <== maj[t].out[k];
+
+            e_sum[t].in[0][k] <== e[t][k];
+            e_sum[t].in[1][k] <== big_sigma1[t].out[k];
+            e_sum[t].in[2][k] <== ch[t].out[k];
+            e_sum[t].in[3][k] <== (k_t >> k) & 1;
+            e_sum[t].in[4][k] <== w[t][k];
+            e_sum[t].in[5][k] <== a[t][k];
+        }
+        a[t+4] <== a_sum[t].out;
+        e[t+4] <== e_sum[t].out;
+    }
+
+    component h_sum[8];
+    for (var i = 0; i < 8; i++) {
+        h_sum[i] = Sum64(2);
+        for (var k = 0; k < 64; k++) {
+            h_sum[i].in[0][k] <== hin[i*64 + k];
+            if (i < 4) {
+                h_sum[i].in[1][k] <== a[83-i][k];
+            } else {
+                h_sum[i].in[1][k] <== e[87-i][k];
+            }
+        }
+        for (var k = 0; k < 64; k++) {
+            out[i*64 + k] <== h_sum[i].out[k];
+        }
+    }
+}
+
+// SHA-512 (or SHA-384 if hash_bits is 384) of a padded message of variable length, up to maxBitsPadded bits.
+// As in Sha256General, the padding is not checked by the circuit.
+template Sha512General(maxBitsPadded, hash_bits) {
+    assert(hash_bits == 384 || hash_bits == 512);
+    assert(maxBitsPadded % 1024 == 0);
+    var maxBitsPaddedBits = log2_ceil(maxBitsPadded);
+    assert(2 ** maxBitsPaddedBits > maxBitsPadded);
+
+    signal input paddedIn[maxBitsPadded];
+    signal input in_len_padded_bits; // This is the padded length of the message pre-hash.
+    signal output out[hash_bits];
+
+    var maxBlocks = maxBitsPadded \ 1024;
+    var maxBlocksBits = log2_ceil(maxBlocks);
+    assert(2 ** maxBlocksBits > maxBlocks);
+
+    signal inBlockIndex;
+    inBlockIndex <-- (in_len_padded_bits >> 10);
+    in_len_padded_bits === inBlockIndex * 1024;
+
+    component bitLengthVerifier = LessEqThan(maxBitsPaddedBits);
+    bitLengthVerifier.in[0] <== in_len_padded_bits;
+    bitLengthVerifier.in[1] <== maxBitsPadded;
+    bitLengthVerifier.out === 1;
+
+    component sha512compression[maxBlocks];
+    for (var i = 0; i < maxBlocks; i++) {
+        sha512compression[i] = Sha512compression();
+        for (var k = 0; k < 512; k++) {
+            if (i == 0) {
+                sha512compression[i].hin[k] <== (sha512_iv(hash_bits, k \ 64) >> (k % 64)) & 1;
+            } else {
+                sha512compression[i].hin[k] <== sha512compression[i-1].out[k];
+            }
+        }
+        for (var k = 0; k < 1024; k++) {
+            sha512compression[i].inp[k] <== paddedIn[i*1024 + k];
+        }
+    }
+
+    // Select the compression output for the given length, and output the digest as a big-endian bit string
+    component arraySelectors[hash_bits];
+    for (var k = 0; k < hash_bits; k++) {
+        var word = k \ 64;
+        var bit = 63 - (k % 64);
+        arraySelectors[k] = QuinSelector(maxBlocks, maxBlocksBits);
+        for (var j = 0; j < maxBlocks; j++) {
+            arraySelectors[k].in[j] <== sha512compression[j].out[word*64 + bit];
+        }
+        arraySelectors[k].index <== inBlockIndex - 1; // The index is 0 indexed and the block numbers are 1 indexed.
+        out[k] <== arraySelectors[k].out;
+    }
+}
+
+template Sha512Bytes(max_num_bytes, hash_bits) {
+    signal input in_padded[max_num_bytes];
+    signal input in_len_padded_bytes;
+    signal output out[hash_bits];
+
+    var num_bits = max_num_bytes * 8;
+    component sha = Sha512General(num_bits, hash_bits);
+
+    component bytes[max_num_bytes];
+    for (var i = 0; i < max_num_bytes; i++) {
+        bytes[i] = Num2Bits(8);
+        bytes[i].in <== in_padded[i];
+        for (var j = 0; j < 8; j++) {
+            sha.paddedIn[i*8+j] <== bytes[i].out[7-j];
+        }
+    }
+    sha.in_len_padded_bits <== in_len_padded_bytes * 8;
+
+    out <== sha.out;
+}
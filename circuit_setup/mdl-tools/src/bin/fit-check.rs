@@ -0,0 +1,139 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// This program checks sample CBOR-encoded mDLs against a config: it estimates the size of the circuit, reports the
+// mDLs prepare-prover-input would reject, and recommends config values they all fit in (see crescent_config::estimate,
+// which does the same for JWTs with `crescent estimate`).
+//
+// Usage:
+//    fit-check --config <config> <mdl>...
+// where
+//    <config> is a JSON file containing the configuration
+//    <mdl> is a CBOR-encoded mDL
+//
+// Notes:
+//    - To test: cargo run --bin fit-check -- --config ../inputs/mdl1/config.json ../inputs/mdl1/mdl.cbor
+
+use clap::Parser;
+use coset::cbor::Value;
+use crescent_config::config::{load_config, ClaimType, CredentialConfig, DEVICE_KEY_CLAIMS};
+use crescent_config::estimate::{estimate_constraints, CredentialSizes, FitReport};
+use isomdl::cbor;
+use isomdl::issuance::mdoc::Mdoc;
+use std::path::PathBuf;
+
+static MDL_DOCTYPE: &str = "org.iso.18013.5.1.mDL";
+static ISO_MDL_NAMESPACE: &str = "org.iso.18013.5.1";
+static AAMVA_MDL_NAMESPACE: &str = "org.iso.18013.5.1.aamva";
+static SUPPORTED_NAMESPACES: [&str; 2] = [ISO_MDL_NAMESPACE, AAMVA_MDL_NAMESPACE];
+// The length of the padded claim preimages, hardcoded in the circuit (see prepare_mdl_setup.py)
+const CLAIM_PREIMAGE_LEN: usize = 128;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+struct Args {
+    /// JSON file containing the config
+    #[arg(short = 'c', long = "config")]
+    config: String,
+
+    /// CBOR files containing the sample mDLs
+    mdls: Vec<String>,
+}
+
+// The length of a message after SHA-256 padding
+fn sha256_padded_len(len: usize) -> usize {
+    (len + 9).div_ceil(64) * 64
+}
+
+/// Extract the text from a Value, which can be a Tag, Text, or Integer.
+fn extract_text(value: Value) -> Option<String> {
+    match value {
+        Value::Tag(_, boxed) => extract_text(*boxed),
+        Value::Text(s) => Some(s),
+        Value::Integer(i) => Some(i128::from(i).to_string()),
+        _ => None,
+    }
+}
+
+// Measure an mDL, recording the checks of prepare-prover-input it fails
+fn measure_mdl(config: &CredentialConfig, name: &str, mdl_cbor: &[u8]) -> CredentialSizes {
+    let mut sizes = CredentialSizes { name: name.to_string(), ..Default::default() };
+    if let Err(e) = measure_mdl_internal(config, mdl_cbor, &mut sizes) {
+        sizes.error = Some(e);
+    }
+    sizes
+}
+
+fn measure_mdl_internal(config: &CredentialConfig, mdl_cbor: &[u8], sizes: &mut CredentialSizes) -> Result<(), String> {
+    let mdoc = cbor::from_slice::<Mdoc>(mdl_cbor).map_err(|_| "Failed to parse mDL")?;
+    if mdoc.doc_type != MDL_DOCTYPE {
+        return Err(format!("Invalid mDL doc type: {}", mdoc.doc_type));
+    }
+
+    // per mDL spec, aad is empty
+    let empty_aad = Vec::<u8>::new();
+    let tbs_data = mdoc.issuer_auth.inner.tbs_data(&empty_aad);
+    let cred_len = sha256_padded_len(tbs_data.len());
+    sizes.cred_len = Some(cred_len);
+
+    let mut errors = vec![];
+    if cred_len > config.max_cred_len {
+        errors.push(format!("mDL too large ({} bytes after SHA256 padding), but maximum length supported is {} bytes", cred_len, config.max_cred_len));
+    }
+    for (claim_name, entry) in &config.claims {
+        // the device key is not a claim of the namespaces
+        if DEVICE_KEY_CLAIMS.contains(&claim_name.as_str()) {
+            continue;
+        }
+        let item = SUPPORTED_NAMESPACES.iter()
+            .filter_map(|ns| mdoc.namespaces.get(*ns))
+            .flat_map(|items| items.iter())
+            .find(|item| item.as_ref().element_identifier == *claim_name);
+        let Some(item) = item else {
+            errors.push(format!("Claim not found: {}", claim_name));
+            continue;
+        };
+
+        let preimage = cbor::to_vec(item).map_err(|_| "unable to encode IssuerSigned as cbor bytes")?;
+        if sha256_padded_len(preimage.len()) != CLAIM_PREIMAGE_LEN {
+            errors.push(format!("Invalid {}_preimage length: {}; expected {} (hardcoded in circom circuit)", claim_name, sha256_padded_len(preimage.len()), CLAIM_PREIMAGE_LEN));
+        }
+        if entry.claim_type == ClaimType::String {
+            let value = extract_text(item.as_ref().element_value.clone()).ok_or(format!("Claim {} is not a string", claim_name))?;
+            sizes.claim_lens.insert(claim_name.clone(), value.len());
+            if value.len() > entry.max_claim_byte_len() {
+                errors.push(format!("Claim {} too large ({} bytes), largest allowed by configuration is {} bytes", claim_name, value.len(), entry.max_claim_byte_len()));
+            }
+        }
+    }
+    if !errors.is_empty() {
+        sizes.error = Some(errors.join("; "));
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+
+    // read and check the config file, as for JWTs (see crescent_config::config)
+    let config = load_config(PathBuf::from(&args.config)).unwrap_or_else(|e| panic!("Invalid config file: {}", e));
+    if !config.is_mdl() {
+        panic!("Invalid credtype: {}, JWTs are checked with `crescent estimate`", config.credtype);
+    }
+
+    println!("Estimated circuit size (constraints):");
+    println!("{}", estimate_constraints(&config));
+    if args.mdls.is_empty() {
+        return;
+    }
+
+    let sizes = args.mdls.iter().map(|path| {
+        let mdl_cbor = std::fs::read(path).unwrap_or_else(|e| panic!("Unable to read mDL {}: {}", path, e));
+        measure_mdl(&config, path, &mdl_cbor)
+    }).collect::<Vec<_>>();
+    let report = FitReport::new(&config, sizes);
+    println!("{}", report);
+    if report.failures().next().is_some() {
+        std::process::exit(1);
+    }
+}
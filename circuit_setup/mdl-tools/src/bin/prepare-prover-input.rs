@@ -23,7 +23,6 @@ use clap::Parser;
 use coset::cbor::Value;
 use coset::iana::Algorithm;
 use coset::Label;
-use lazy_static::lazy_static;
 use isomdl::cbor;
 use isomdl::definitions::helpers::{ByteStr, NonEmptyVec};
 use isomdl::definitions::issuer_signed::IssuerSignedItemBytes;
@@ -40,7 +39,9 @@ use num_bigint::BigUint;
 use num_traits::{Zero, One, ToPrimitive};
 use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
 use std::str;
-use std::collections::{BTreeMap,HashSet};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use crescent_config::config::{load_config, ClaimType, DEVICE_KEY_CLAIMS};
 
 static MDL_DOCTYPE: &str = "org.iso.18013.5.1.mDL";
 static ISO_MDL_NAMESPACE: &str = "org.iso.18013.5.1";
@@ -48,16 +49,6 @@ static AAMVA_MDL_NAMESPACE: &str = "org.iso.18013.5.1.aamva";
 static SUPPORTED_NAMESPACES: [&str; 2] = [ISO_MDL_NAMESPACE, AAMVA_MDL_NAMESPACE];
 const CIRCOM_ES256_LIMB_BITS: usize = 43;
 
-lazy_static! {
-    static ref CRESCENT_CONFIG_KEYS: HashSet<&'static str> = {
-        let mut set = HashSet::new();
-        set.insert("alg");
-        set.insert("credtype");
-        set.insert("max_cred_len");
-        set.insert("device_bound");
-        set
-    };
-}
 
 // Define the issuer's timezone as PST (UTC-8). Change this if needed (TODO: move to config)
 const ISSUER_TIMEZONE_OFFSET_HOURS: i32 = -8; // US West Coast / PST (UTC-8)
@@ -330,27 +321,6 @@ pub(crate) fn find_value_digest_info(
     None
 }
 
-// TODO: see the check_config() function in the python script; more stuff to implement
-fn check_config(config: &serde_json::Map<String, serde_json::Value>) {
-    // check the credtype
-    let credtype = config["credtype"].as_str().unwrap();
-    if credtype != "mdl" {
-        panic!("Invalid credtype: {}", credtype);
-    }
-
-    // check the signature alg
-    let alg = config["alg"].as_str().unwrap();
-    if alg != "ES256" {
-        panic!("Unsupported alg: {}", alg);
-    }
-
-    // make sure max_cred_len exists
-    let max_cred_len = config["max_cred_len"].as_u64().unwrap();
-    if max_cred_len == 0 {
-        panic!("Invalid max_cred_len, needs to be > 0: {}", max_cred_len);
-    }
-}
-
 fn bytes_to_circom_limbs(bytes: &[u8], limb_size: usize) -> Vec<u128> {
     let n = BigUint::from_bytes_be(bytes);
     let mut limbs = Vec::new();
@@ -396,11 +366,11 @@ fn pack_string_to_int_unquoted(s: &str, n_bytes: usize) -> Result<String, Box<st
 fn main() {
     let args = Args::parse();
 
-    // read and parse the config file
-    let config_file = std::fs::read_to_string(&args.config).unwrap();
-    let config_value: serde_json::Value = serde_json::from_str(&config_file).unwrap();
-    let config = config_value.as_object().expect("Invalid config file");
-    check_config(config);
+    // read and check the config file, as for JWTs (see crescent_config::config)
+    let config = load_config(PathBuf::from(&args.config)).unwrap_or_else(|e| panic!("Invalid config file: {}", e));
+    if !config.is_mdl() {
+        panic!("Invalid credtype: {}", config.credtype);
+    }
 
     let mdl_cbor = std::fs::read(&args.mdl).unwrap();
     let mdoc = cbor::from_slice::<Mdoc>(
@@ -462,7 +432,7 @@ fn main() {
     let msg_len_after_sha2_padding = padded_m.len();
     println!("msg_len_after_SHA2_padding: {:?}\n", msg_len_after_sha2_padding);
 
-    let config_max_cred_len = config["max_cred_len"].as_u64().unwrap() as usize;
+    let config_max_cred_len = config.max_cred_len;
     if msg_len_after_sha2_padding > config_max_cred_len {
         println!(
             "Error: mDL too large. Current mDL header + payload is {} bytes ({} bytes after SHA256 padding), but maximum length supported is {} bytes.",
@@ -501,17 +471,16 @@ fn main() {
     prover_inputs.insert("valid_until_prefix_r".to_string(), valid_until_prefix_r.into());
     
     // process claims listed in the config
-    let keys = config.keys();
-    for key in keys {
-        if CRESCENT_CONFIG_KEYS.contains(key.as_str()) {
+    for (claim_name, entry) in &config.claims {
+        // the device key is processed below
+        if DEVICE_KEY_CLAIMS.contains(&claim_name.as_str()) {
             continue;
         }
-        let claim_name = key.as_str();
-        let entry = config[claim_name].as_object().ok_or(format!("Config file entry for claim {}, does not have object type", claim_name)).unwrap();
-        let claim_type = entry["type"].as_str().ok_or(format!("Config file entry for claim {}, is missing 'type'", claim_name)).unwrap();
-        let reveal = entry.get("reveal").and_then(|v| v.as_bool()).unwrap_or(false);
-        let reveal_digest = entry.get("reveal_digest").and_then(|v| v.as_bool()).unwrap_or(false);
-        let max_claim_byte_len = entry["max_claim_byte_len"].as_u64().ok_or(format!("Config file entry for claim {} does not have a valid u64 'max_claim_byte_len'", claim_name)).map(|v| v as usize).unwrap();
+        let claim_name = claim_name.as_str();
+        let claim_type = entry.claim_type.as_str();
+        let reveal = entry.reveal;
+        let reveal_digest = entry.reveal_digest;
+        let max_claim_byte_len = entry.max_claim_byte_len();
 
         if !reveal && !reveal_digest {
             println!("Claim {} is not revealed or reveal_digest: skipping", claim_name);
@@ -535,8 +504,8 @@ fn main() {
         prover_inputs.insert(format!("{}_identifier_l", claim_name).to_string(), serde_json::json!(claim_info.identifier_l));
 
         if reveal {
-            match claim_type {
-                "string" => {
+            match entry.claim_type {
+                ClaimType::String => {
                     // for string values, we skip the first CBOR byte (0x60) which indicates the string length, to only compare the claim value
                     // FIXME: not true in general, only for short strings!
                     let value_l = claim_info.value_l + 1;
@@ -545,28 +514,28 @@ fn main() {
                     let claim_value = pack_string_to_int_unquoted(claim_value_str, max_claim_byte_len).unwrap();
                     prover_inputs.insert(format!("{}_value", claim_name).to_string(), serde_json::json!(claim_value));
                 },
-                "date" => {
+                ClaimType::Date => {
                     // we don't need the value_l and value_r for date
                     let claim_value = ymd_to_daystamp(claim_value_str).unwrap();
                     prover_inputs.insert(format!("{}_value", claim_name).to_string(), serde_json::json!(claim_value));
                 },
-                "integer" => {
+                ClaimType::Integer => {
                     // encode integer directly
                     prover_inputs.insert(format!("{}_value", claim_name).to_string(), serde_json::json!(claim_value_str));
                 },
                 // TODO: add support for other claim types
-                &_ => {
+                _ => {
                     panic!("Unsupported claim type: {}", claim_type);
                 }
             };
         } else if reveal_digest {
             // Add the preimage to the prover auxiliary data
-            match claim_type {
-                    "integer" => {
+            match entry.claim_type {
+                    ClaimType::Integer => {
                         prover_aux.insert(claim_name.to_string(), serde_json::json!(claim_value_str));
                     }
-                    "string" => {
-                        if claim_value_str.len() > max_claim_byte_len as usize {
+                    ClaimType::String => {
+                        if claim_value_str.len() > max_claim_byte_len {
                             panic!("Claim too large ({} bytes), largest allowed by configuration is {} bytes", claim_value_str.len(), max_claim_byte_len);
                         }
                         // for string values, we skip the first CBOR byte (0x60) which indicates the string length, to only compare the claim value
@@ -628,7 +597,7 @@ fn main() {
     println!("Number of SHA blocks to hash: {}\n", msg_len_after_sha2_padding);
 
     // If device bound, include the device public key in the prover inputs
-    if config["device_bound"].as_bool().is_some_and(|x| x) {
+    if config.device_bound {
         let device_key = mso.device_key_info.device_key;
         assert!(device_key.signature_algorithm() == Some(Algorithm::ES256), "Only device keys with the ES256 algorithm are supported");
 
@@ -17,8 +17,13 @@ CIRCOM_ES256K_LIMB_BITS = 64
 CIRCOM_ES256_LIMB_BITS = 43     # Required by the ecdsa-p256 circuit we use
 CIRCOM_P256_LIMB_BITS = 43   # the limb size you already use for ES‑256
 CIRCOM_P256_N_LIMBS    = 6   # number of limbs in the Main() template
-CRESCENT_CONFIG_KEYS = ['alg', 'credtype', 'reveal_all_claims', 'defer_sig_ver', 'max_cred_len', 'device_bound']     # fields in config.json that are for crescent configuration and do not refer to claims in the token
-CRESCENT_SUPPORTED_ALGS = ['RS256', 'ES256', 'ES256K']     # Signature algorithms used to sign JWT/mDL
+CRESCENT_CONFIG_KEYS = ['alg', 'credtype', 'reveal_all_claims', 'defer_sig_ver', 'max_cred_len', 'device_bound', 'timezone', 'rsa_key_bits']     # fields in config.json that are for crescent configuration and do not refer to claims in the token
+CRESCENT_SUPPORTED_ALGS = ['RS256', 'RS384', 'RS512', 'PS256', 'ES256', 'ES256K']     # Signature algorithms used to sign JWT/mDL
+RSA_ALGS = {'RS256': (256, 0), 'RS384': (384, 0), 'RS512': (512, 0), 'PS256': (256, 1)}  # Hash output length and PSS flag of the RSA algorithms
+RSA_KEY_SIZES = [2048, 3072, 4096]      # Supported sizes of the RSA modulus, set with 'rsa_key_bits' in config.json
+DEFAULT_RSA_KEY_BITS = 2048
+DEFAULT_MAX_DISCLOSURE_LEN = 128    # Maximum length of an SD-JWT disclosure (base64url encoded, with SHA-256 padding)
+FIXED_LEN_CLAIM_TYPES = ['boolean', 'date']     # Claim types revealed as integers (0/1, or a daystamp), without 'max_claim_byte_len'
 
 
 ##### Module functions ######
@@ -47,13 +52,14 @@ def camel_to_snake(str):
      
     return ''.join(res)
 
-# typ: 0 for string, 1 for number, 2 for bool, 3 for null, 4 for array, 5 for object
+# typ: 0 for string, 1 for number, 2 for boolean, 3 for null, 4 for array, 5 for object
+# Dates are strings in the JSON payload
 def claim_type_as_int(type):
-     if type == "string":
+     if type == "string" or type == "date":
          return 0
      elif type == "number":
          return 1
-     elif type == "bool":
+     elif type == "boolean":
          return 2
      elif type == "null":
          return 3
@@ -83,18 +89,12 @@ def digest_to_limbs(digest_hex):
     return [n,b]
 
 def pack_string_to_int(s, n_bytes):
-    # must match function "RevealClaimValue" in match_claim.circom
-    # so we add quotes to the string TODO: maybe reveal the unquoted claim value instead?
-    #First convert s to bytes and pad with zeros
-    s_bytes = bytearray("\"" + s + "\"", 'utf-8')
-    if len(s_bytes) > n_bytes:
+    # must match function "RevealClaimValue" in match_claim.circom, which reveals the string without its quotes,
+    # but the quoted string must fit in n_bytes
+    if len(bytearray(s, 'utf-8')) + 2 > n_bytes:
         print("String to large to convert to integer of n_bytes = {}".format(n_bytes))
         sys.exit(-1)
-    s_bytes.extend([0x00]*(n_bytes - len(s_bytes)))
-    n = 0
-    for i in range(0, len(s_bytes)):
-        n = n + s_bytes[i] * pow(256, i)
-    return n
+    return pack_string_to_int_unquoted(s, n_bytes)
 
 def pack_string_to_int_unquoted(s, n_bytes):
     s_bytes = bytearray(s, 'utf-8')
@@ -115,8 +115,6 @@ def is_printable(byte_array):
 
 def unpack_int_to_string(s_int, n_bytes):
     # must match function "RevealClaimValue" in match_claim.circom
-    # so we add quotes to the string TODO: maybe reveal the unquoted claim value instead?
-    #First convert s to bytes and pad with zeros
     s_bytes = bytearray("", 'utf-8')
     l = math.ceil(math.log(s_int)/math.log(256))
     for i in range(0, l):
@@ -167,6 +165,40 @@ def claim_reveal_hashed(claim):
          return True
      return False
 
+# String claims revealed as several field elements
+def claim_reveal_bytes(claim):
+     if claim.get("reveal_bytes") is not None and claim["reveal_bytes"] == True:
+         return True
+     return False
+
+# Claims of SD-JWTs that are in a disclosure rather than in the issuer-signed JWT
+def claim_is_disclosed(claim):
+     if claim.get("sd") is not None and claim["sd"] == True:
+         return True
+     return False
+
+# Claims that may be absent from the token
+def claim_is_optional(claim):
+     if claim.get("optional") is not None and claim["optional"] == True:
+         return True
+     return False
+
+# The JSON value an optional claim takes in the circuit when it is absent from the token
+def claim_default_value(typ_string):
+    return {"string": '""', "number": '0', "boolean": 'false', "date": '"1900-01-01"'}[typ_string]
+
+# Claims are top-level claim names, or JSON pointers (RFC 6901) to nested claims, e.g., "/address/country"
+def claim_path(name):
+    if not name.startswith('/'):
+        return [name]
+    return [segment.replace('~1', '/').replace('~0', '~') for segment in name[1:].split('/')]
+
+# The prefix of a circom signal name for the claim, e.g., "address_country" for "/address/country"
+def claim_signal_name(name):
+    if name.startswith('/'):
+        name = name[1:]
+    return ''.join(c if (c.isascii() and c.isalnum()) or c == '_' else '_' for c in name)
+
 def check_config(config):
     # Check that the config file has required fields
     if 'alg' not in config:
@@ -200,15 +232,27 @@ def check_config(config):
     if 'max_cred_len' not in config:
         config['max_cred_len'] = 2048  # Maximum length of JWT, excluding the
                                       # signature part.  The length in bytes of the header 
-                                      # and payload, base64url encoded. Must be a multiple of 64.
+                                      # and payload, base64url encoded. Must be a multiple of the
+                                      # hash block size (64, or 128 for RS384 and RS512).
     else:
         if type(config['max_cred_len']) != int:
             print_debug("Error: config field 'max_cred_len' must be an integer")
             return False
         max_cred_len = config['max_cred_len']
-        if max_cred_len % 64 != 0:
-            print_debug("Error: 'max_cred_len' must be a multiple of 64. Found {}, try {}".format(max_cred_len, (64 - (max_cred_len % 64)) + max_cred_len ))
+        block_len = hash_block_len(config['alg'])
+        if max_cred_len % block_len != 0:
+            print_debug("Error: 'max_cred_len' must be a multiple of {}. Found {}, try {}".format(block_len, max_cred_len, (block_len - (max_cred_len % block_len)) + max_cred_len ))
             return False
+
+    if config['alg'] in RSA_ALGS:
+        if 'rsa_key_bits' not in config:
+            config['rsa_key_bits'] = DEFAULT_RSA_KEY_BITS
+        elif config['rsa_key_bits'] not in RSA_KEY_SIZES:
+            print_debug("Error: 'rsa_key_bits' must be one of {}".format(RSA_KEY_SIZES))
+            return False
+    elif 'rsa_key_bits' in config:
+        print_debug("Error: 'rsa_key_bits' is only valid with RSA algorithms")
+        return False
         
     # Additional checks
     if config['defer_sig_ver']:
@@ -218,7 +262,7 @@ def check_config(config):
         
     # If a JWT is device bound, assume it has the claims "device_key_0" and "device_key_1", and ensure
     # they will be revealed (mDL handles this differently)
-    if config['device_bound'] and config['credtype'] == 'jwt':
+    if config['device_bound'] and config['credtype'] in ['jwt', 'sd-jwt']:
         config['device_key_0'] = {
             "type": "number",
             "reveal": True,
@@ -234,20 +278,60 @@ def check_config(config):
     # For all the config entries about claims (e.g, "email", "exp", etc.) make sure that if the claim 
     # is to be revealed, that max_claim_byte_len is set.  Further, if reveal_digest is not set, the 
     # claim must fit in a field element
+    signal_names = {}
     for key in config.keys():
         if key in CRESCENT_CONFIG_KEYS:
             continue
-        if claim_reveal_hashed(config[key]) or claim_reveal_unhashed(config[key]):            
+        if '' in claim_path(key):
+            print_debug("Error: claim '{}' has an empty path segment".format(key))
+            return False
+        signal_name = claim_signal_name(key)
+        if signal_name in signal_names:
+            print_debug("Error: claims '{}' and '{}' have the same circuit signal name '{}'".format(signal_names[signal_name], key, signal_name))
+            return False
+        signal_names[signal_name] = key
+        if config[key].get("type") in FIXED_LEN_CLAIM_TYPES:
+            if claim_reveal_hashed(config[key]) or claim_reveal_bytes(config[key]) or config[key].get("reveal_domain_only"):
+                print_debug("Error: claim '{}' has type {}, which can only be revealed with the reveal flag".format(key, config[key]["type"]))
+                return False
+        elif claim_reveal_hashed(config[key]) or claim_reveal_unhashed(config[key]) or claim_reveal_bytes(config[key]):
             if config[key].get("max_claim_byte_len") is None:
                 print_debug("Error: claim '", key, "' is going to be revealed but is missing 'max_claim_byte_len'")
                 return False
             if config[key].get("max_claim_byte_len") % MAX_FIELD_BYTE_LEN != 0:
                 print_debug("Error: claim '{}' must have max_claim_byte_len be a multiple of the field element size ({})".format(key, MAX_FIELD_BYTE_LEN))
                 return False        
-        if claim_reveal_unhashed(config[key]):
+        if 'sd' in config[key]:
+            if type(config[key]['sd']) != bool:
+                print_debug("Error: field 'sd' of claim '{}' must be of type bool".format(key))
+                return False
+            if config[key]['sd'] and config['credtype'] != 'sd-jwt':
+                print_debug("Error: claim '{}' has the 'sd' flag set, but the credential type is not 'sd-jwt'".format(key))
+                return False
+            if config[key]['sd'] and len(claim_path(key)) > 1:
+                print_debug("Error: claim '{}' has the 'sd' flag set, only top-level claims can be in disclosures".format(key))
+                return False
+        if claim_is_disclosed(config[key]):
+            if 'max_disclosure_len' not in config[key]:
+                config[key]['max_disclosure_len'] = DEFAULT_MAX_DISCLOSURE_LEN
+            max_disclosure_len = config[key]['max_disclosure_len']
+            if type(max_disclosure_len) != int or max_disclosure_len <= 0 or max_disclosure_len % 64 != 0:
+                print_debug("Error: claim '{}' must have max_disclosure_len be a multiple of 64".format(key))
+                return False
+        if claim_is_optional(config[key]):
+            if len(claim_path(key)) > 1 or claim_is_disclosed(config[key]) or key in ['exp', 'device_key_0', 'device_key_1']:
+                print_debug("Error: claim '{}' cannot be optional, only top-level claims that are not in disclosures can be".format(key))
+                return False
+            if claim_reveal_hashed(config[key]) or config[key].get("type") not in ["string", "number", "boolean", "date"]:
+                print_debug("Error: optional claim '{}' must have type string, number, boolean or date, and cannot be revealed with reveal_digest".format(key))
+                return False
+        if claim_reveal_bytes(config[key]) and config[key].get("type") != "string":
+            print_debug("Error: claim '{}' has reveal_bytes flag set, but only strings can be revealed as bytes".format(key))
+            return False
+        if claim_reveal_unhashed(config[key]) and not claim_reveal_bytes(config[key]) and config[key].get("type") not in FIXED_LEN_CLAIM_TYPES:
             max_claim_byte_len = config[key].get("max_claim_byte_len")
             if max_claim_byte_len > MAX_FIELD_BYTE_LEN and config[key].get("type") != "number":
-                print_debug("Error: claim '{}' has reveal flag set but max_claim_byte_len={} exceeds MAX_FIELD_BYTE_LEN={}. To reveal larger claims use reveal_digest".format(key, max_claim_byte_len, MAX_FIELD_BYTE_LEN))
+                print_debug("Error: claim '{}' has reveal flag set but max_claim_byte_len={} exceeds MAX_FIELD_BYTE_LEN={}. To reveal larger claims use reveal_bytes or reveal_digest".format(key, max_claim_byte_len, MAX_FIELD_BYTE_LEN))
                 return False
             # For number types, the number of bytes is the number of base-10 digits, which can
             #  exceed the number bytes to represent the field (base-256 digits)            
@@ -258,6 +342,16 @@ def check_config(config):
     return True
 
 
+# Number of limbs of an RSA modulus in the circuit
+def rsa_num_limbs(key_bits):
+    return (key_bits + CIRCOM_RS256_LIMB_BITS - 1) // CIRCOM_RS256_LIMB_BITS
+
+# Block size in bytes of the hash function of the signature algorithm (SHA-384 and SHA-512 have 128-byte blocks)
+def hash_block_len(alg):
+    if alg in ['RS384', 'RS512']:
+        return 128
+    return 64
+
 def base64_decoded_size(encoded_len):
     return ((encoded_len + 3) // 4) * 3
 
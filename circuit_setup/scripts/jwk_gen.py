@@ -28,13 +28,13 @@ import sys, os
 def usage():
     print("Python3 script to generate a JWK (JSON web key)")
     print("Usage:")
-    print("\t./" + os.path.basename(sys.argv[0]) + " <alg: ES256, ES256K, RS256> <private key file> <public key file>")
+    print("\t./" + os.path.basename(sys.argv[0]) + " <alg: ES256, ES256K, RS256, RS384, RS512, PS256> <private key file> <public key file> <optional RSA key size>")
     print("Example:")
     print("\tpython3 " + os.path.basename(sys.argv[0]) + "RS256 key.prv key.pub")
     print("creates an RSA key pair output to files key.prv and key.pub (overwriting these files if they already exist)")
     print("Keys are stored in PEM format")
     print("Algorithms:")
-    print("RS256 - RSA signing key, 2048-bit")
+    print("RS256, RS384, RS512, PS256 - RSA signing key, 2048-bit unless a key size (3072 or 4096) is given")
     print("ES256 - ECDSA key with curve NIST P256 (secp256r1)")
     print("ES256K - ECDSA key with curve secp256k1 (bitcoin)")
 
@@ -42,11 +42,11 @@ def usage():
 
 ### Main ###
 
-if len(sys.argv) != 4 : 
+if len(sys.argv) != 4 and len(sys.argv) != 5 : 
     usage()
     sys.exit(-1)
 
-algs = ('RS256', 'ES256K', 'ES256')
+algs = ('RS256', 'RS384', 'RS512', 'PS256', 'ES256K', 'ES256')
 alg = sys.argv[1]
 if alg not in algs:
     print("Algorithm '{}' is not supported\n".format(alg))
@@ -56,8 +56,14 @@ if alg not in algs:
 
 # Create key pair
 key = None
-if alg == "RS256":
-    key = jwk.JWK.generate(kty='RSA', size=2048)
+if alg in ("RS256", "RS384", "RS512", "PS256"):
+    key_size = 2048
+    if len(sys.argv) == 5:
+        key_size = int(sys.argv[4])
+    if key_size not in (2048, 3072, 4096):
+        print("RSA key size must be 2048, 3072 or 4096")
+        sys.exit(-1)
+    key = jwk.JWK.generate(kty='RSA', size=key_size)
 elif alg == "ES256":
     key = jwk.JWK.generate(kty='EC', crv="P-256")
 elif alg == "ES256K":
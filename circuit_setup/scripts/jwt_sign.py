@@ -17,25 +17,60 @@
 import python_jwt as jwt, jwcrypto.jwk as jwk
 from jwcrypto.common import base64url_decode, base64url_encode
 from jwcrypto.jws import JWS
-import sys, os, json, datetime
+import sys, os, json, datetime, hashlib
 
 def usage():
     print("Python3 script to create a JWT")
     print("Usage:")
-    print("\t./" + os.path.basename(sys.argv[0]) + " <claims.file.json> <issuer private key> <output JWT> <optional device public key>")
+    print("\t./" + os.path.basename(sys.argv[0]) + " <claims.file.json> <issuer private key> <output JWT> <optional device public key> [--alg <RS256, RS384, RS512, PS256>] [--config <config.json>]")
     print("Example:")
     print("\tpython3 " + os.path.basename(sys.argv[0]) + "claims.json issuer.prv token.jwt")
     print("will sign the json in claims.json with the issuer private key in issuer.prv and output the JWT in token.jwt")
     print("If a device public key is provided, it will be added to the claims.")
+    print("For RSA issuer keys, --alg selects the signature algorithm (RS256 by default). It is ignored for EC keys.")
+    print("If the config (from --config) has credential type sd-jwt, the claims with the 'sd' flag set are issued as SD-JWT disclosures.")
+
+def take_option(args, option):
+    if option not in args:
+        return None
+    i = args.index(option)
+    if i + 1 >= len(args):
+        usage()
+        sys.exit(-1)
+    value = args[i + 1]
+    del args[i:i + 2]
+    return value
+
+# Replace the claims with the 'sd' flag in the config by their digests, see RFC 9901, Section 4.2.
+# Returns the disclosures, in the order of the digests in the '_sd' array
+def make_disclosures(claims, config):
+    disclosures = []
+    for name in config:
+        if type(config[name]) != dict or config[name].get("sd") != True:
+            continue
+        if name not in claims:
+            raise ValueError("Claim {} is not in the claims file".format(name))
+        salt = base64url_encode(os.urandom(16))
+        disclosures.append(base64url_encode(json.dumps([salt, name, claims.pop(name)])))
+    digests = [base64url_encode(hashlib.sha256(d.encode('ascii')).digest()) for d in disclosures]
+    # The digests are sorted, so that their order does not reveal the order of the claims
+    order = sorted(range(len(digests)), key=lambda i: digests[i])
+    claims['_sd'] = [digests[i] for i in order]
+    claims['_sd_alg'] = 'sha-256'
+    return [disclosures[i] for i in order]
 
 ### Main ###
 
-if len(sys.argv) != 4 and len(sys.argv) != 5 : 
+args = sys.argv[1:]
+rsa_alg = take_option(args, "--alg") or "RS256"
+config_file = take_option(args, "--config")
+
+if len(args) != 3 and len(args) != 4 : 
     usage()
     sys.exit(-1)
 
 # Load issuer key
-with open(sys.argv[2], "rb") as f:
+with open(args[1], "rb") as f:
     issuer_key_bytes = f.read()
 
 issuer_key = jwk.JWK.from_pem(issuer_key_bytes, password=None)
@@ -43,7 +78,9 @@ issuer_key = jwk.JWK.from_pem(issuer_key_bytes, password=None)
 new_alg = None
 if issuer_key.get('kty') == "RSA" :
     print("Read issuer key type: RSA")
-    new_alg = "RS256"
+    if rsa_alg not in ("RS256", "RS384", "RS512", "PS256"):
+        raise ValueError("Unsupported algorithm {} for an RSA key".format(rsa_alg))
+    new_alg = rsa_alg
 elif issuer_key.get('kty') == "EC":
     print("Read issuer key type: EC", end='')
     if issuer_key.get('crv') == "P-256":
@@ -60,15 +97,15 @@ else:
 print("Using signature algorithm {} for new token\n".format(new_alg))
 
 # load the claims from a file
-with open(sys.argv[1], 'r') as file:
+with open(args[0], 'r') as file:
     claims = json.load(file)
 
 # If a device public key was provided, add it to the claims, in the format expected by 
 # Crescent.  This is currently a custom format, but ideally would be a 'cnf' claim
 # https://datatracker.ietf.org/doc/html/rfc7800#section-3.2
-if sys.argv[4] is not None: 
+if len(args) == 4: 
     print("Adding device public key to claims")
-    with open(sys.argv[4], "rb") as f:
+    with open(args[3], "rb") as f:
         device_key_bytes = f.read()
 
     device_key = jwk.JWK.from_pem(device_key_bytes, password=None)    
@@ -86,6 +123,14 @@ if sys.argv[4] is not None:
     claims['device_key_0'] = device_key_0
     claims['device_key_1'] = device_key_1
 
+disclosures = None
+if config_file is not None:
+    with open(config_file, 'r') as file:
+        config = json.load(file)
+    if config.get('credtype') == 'sd-jwt':
+        disclosures = make_disclosures(claims, config)
+        print("Created {} SD-JWT disclosures".format(len(disclosures)))
+
 # Create the new token with the claims, and one year lifetime
 short_kid = issuer_key.get('kid')
 new_jwt = jwt.generate_jwt(claims, issuer_key, new_alg, datetime.timedelta(weeks=52), other_headers={'kid': short_kid})
@@ -97,7 +142,7 @@ new_token_header, new_token_claims = jwt.process_jwt(new_jwt)
 
 print("Verifying... ", end="")
 try:
-    jwt.verify_jwt(new_jwt, issuer_key.public(), allowed_algs=['RS256', 'ES256', 'ES256K'])
+    jwt.verify_jwt(new_jwt, issuer_key.public(), allowed_algs=['RS256', 'RS384', 'RS512', 'PS256', 'ES256', 'ES256K'])
     print("  success")
 except jwt._JWTError as e:
     if str(e) == 'expired':
@@ -107,6 +152,10 @@ except jwt._JWTError as e:
         # We don't fail here since some reasons for verify_jwt to fail don't apply to W3C VCs, 
         # E.g., failing because the "nbf" claim is not present
 
-with open(sys.argv[3], "w") as f:
+# An SD-JWT is the issuer-signed JWT followed by the disclosures, each terminated with ~
+if disclosures is not None:
+    new_jwt = new_jwt + "~" + "".join(d + "~" for d in disclosures)
+
+with open(args[2], "w") as f:
     token = f.write(new_jwt)
-print("New token written to {}".format(sys.argv[3]))
+print("New token written to {}".format(args[2]))
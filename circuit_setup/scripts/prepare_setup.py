@@ -18,8 +18,10 @@ def usage():
 def main_circom_header(config):
 
     template_filename = ''
-    if config['alg'] == 'RS256':
-        template_filename = "circuits/main_header_rs256.circom.template"
+    if config['alg'] in RSA_ALGS:
+        template_filename = "circuits/main_header_rsa.circom.template"
+    elif config['alg'] == 'ES256':
+        template_filename = "circuits/main_header_es256.circom.template"
 # TODO: add support for ES256K
 #    elif config['alg'] == 'ES256K':
 #       template_filename = "circuits/main_header_es256k.circom.template"
@@ -32,6 +34,79 @@ def main_circom_header(config):
         res = f.read()
     return res
 
+# Check that the claim (or object) with signal name prefix `name`, at nesting level `level`, is a member of the object
+# value of its parent
+def write_parent_check(f, name, parent, level):
+    f.write('''
+    component {name}_in_parent = MatchParentObject(max_json_bytes, {level});
+    {name}_in_parent.json_bytes <== jwt_bytes;
+    {name}_in_parent.object_nested_level <== object_nested_level;
+    {name}_in_parent.parent_value_l <== {parent}_value_l;
+    {name}_in_parent.l <== {name}_l;
+'''.format(name = name, parent = parent, level = level))
+
+# Match the objects that contain a nested claim, e.g., "address" for "/address/country", and return the signal name
+# prefix of the innermost one. Each object is matched once, even if it contains several claims of the config.
+def write_parent_objects(f, name, matched_objects):
+    segments = name.split('/')
+    parent = None
+    for k in range(1, len(segments) - 1):
+        prefix = '/'.join(segments[:k + 1])
+        obj = claim_signal_name(prefix) + "_object"
+        if obj not in matched_objects:
+            matched_objects.add(obj)
+            obj_template = list(('"' + claim_path(prefix)[-1] + '"').encode('utf-8'))
+            f.write('''
+    log("=== {obj} ===");
+    var {obj}[{obj_template_len}] = {obj_template};
+    signal input {obj}_l;
+    signal input {obj}_value_l;
+    signal input {obj}_r;
+    component match_{obj}_name = MatchClaimName(max_json_bytes, {obj_template_len}, {level});
+    match_{obj}_name.name <== {obj};
+    match_{obj}_name.json_bytes <== jwt_bytes;
+    match_{obj}_name.l <== {obj}_l;
+    match_{obj}_name.value_l <== {obj}_value_l;
+    match_{obj}_name.r <== {obj}_r;
+    match_{obj}_name.object_nested_level <== object_nested_level;
+'''.format(obj = obj, obj_template_len = len(obj_template), obj_template = str(obj_template), level = k))
+            if parent is not None:
+                write_parent_check(f, obj, parent, k)
+        parent = obj
+    return parent
+
+# Match the "_sd" array of an SD-JWT payload, that has the digests of the disclosures (see SDArray in sd_jwt.circom).
+# The array is matched once, for all the disclosed claims.
+def write_sd_array(f):
+    f.write('''
+    log("=== sd_array ===");
+    signal input sd_array_l;
+    signal input sd_array_value_l;
+    signal input sd_array_r;
+    component match_sd_array = SDArray(max_json_bytes);
+    match_sd_array.json_bytes <== jwt_bytes;
+    match_sd_array.object_nested_level <== object_nested_level;
+    match_sd_array.l <== sd_array_l;
+    match_sd_array.value_l <== sd_array_value_l;
+    match_sd_array.r <== sd_array_r;
+''')
+
+# Select the bytes an optional claim is matched in: the token if the claim is present, and otherwise a constant
+# default JSON '{"name":<default value>}' (see OptionalClaim in match_claim.circom)
+def write_optional_claim(f, name, claim, typ_string):
+    default_json = list(('{' + claim + ':' + claim_default_value(typ_string) + '}').encode('utf-8'))
+    f.write('''    signal input {name}_present;
+    var {name}_default_json[{default_json_len}] = {default_json};
+    component {name}_optional = OptionalClaim(max_json_bytes, {claim_template_len}, {default_json_len}, 1);
+    {name}_optional.json_bytes <== jwt_bytes;
+    {name}_optional.object_nested_level <== object_nested_level;
+    {name}_optional.name <== {name};
+    {name}_optional.default_json <== {name}_default_json;
+    {name}_optional.present <== {name}_present;
+    signal {name}_json_bytes[max_json_bytes];
+    {name}_json_bytes <== {name}_optional.claim_json_bytes;
+'''.format(name = name, claim_template_len = len(claim.encode('utf-8')), default_json_len = len(default_json), default_json = str(default_json)))
+
 def prepare_circom(config, circom_output_file):
 
     print_debug("== Prepare circom circuit ==")
@@ -39,91 +114,159 @@ def prepare_circom(config, circom_output_file):
     keys = list(config.keys())
     claims = []
     public_inputs = []
-    if config['alg'] == 'RS256':
+    if config['alg'] in RSA_ALGS:
         public_inputs.append("modulus")
+    if config['alg'] == 'ES256':
+        public_inputs += ["pubkey_x", "pubkey_y"]
     if config['alg'] == 'ES256K':
         #public_inputs += ["pubkey_x, pubkey_y"]
         print("skipping adding ECC pub key to public inputs")
     if config['defer_sig_ver']:
         public_inputs.append("digest_248")
 
+    # The presence bits of optional claims follow the other public inputs
+    present_inputs = []
+    matched_objects = set()
+    sd_array_matched = False
     with open(circom_output_file, "w") as f:
         f.write(main_circom_header(config))
         for i in range(0, len(keys)):
-            name = keys[i]
-            if name in CRESCENT_CONFIG_KEYS:
+            key = keys[i]
+            if key in CRESCENT_CONFIG_KEYS:
                 continue
+            # Nested claims are configured with their JSON pointer, e.g., "/address/country"
+            path = claim_path(key)
+            name = claim_signal_name(key)
 
-            typ_string = config[name].get("type")
+            typ_string = config[key].get("type")
             if typ_string is None:
-                print("Missing 'type' field in config file for claim '{}'".format(name))
+                print("Missing 'type' field in config file for claim '{}'".format(key))
                 sys.exit(-1)
 
             typ = claim_type_as_int(typ_string)
-            claim = '"' + keys[i] + '":'
-            claims.append(claim)
-            claim_template = list(claim.encode('utf-8'))
-            f.write('''
+            if claim_is_disclosed(config[key]):
+                # The claim is in an SD-JWT disclosure ["salt", "name", value], matched in the decoded disclosure
+                json_bytes = name + "_disclosure_json"
+                json_len = name + "_max_json_bytes"
+                claim = '"' + path[-1] + '",'
+                value_l = "match_" + name + "_name.value_l"
+                claims.append(claim)
+                claim_template = list(claim.encode('utf-8'))
+                if not sd_array_matched:
+                    write_sd_array(f)
+                    sd_array_matched = True
+                f.write('''
     log("=== {name} ===");
+    var {name}_max_disclosure_bytes = {max_disclosure_len};
+    var {json_len} = {name}_max_disclosure_bytes * 3 \\ 4;
+    signal input {name}_disclosure[{name}_max_disclosure_bytes];
+    signal input {name}_disclosure_padded_bytes;
+    signal input {name}_digest_l;
+    component {name}_sd = SDJWTDisclosure(max_json_bytes, {name}_max_disclosure_bytes);
+    {name}_sd.json_bytes <== jwt_bytes;
+    {name}_sd.disclosure <== {name}_disclosure;
+    {name}_sd.disclosure_padded_bytes <== {name}_disclosure_padded_bytes;
+    {name}_sd.digest_l <== {name}_digest_l;
+    {name}_sd.sd_value_l <== sd_array_value_l;
+    {name}_sd.sd_r <== sd_array_r;
+    signal {json_bytes}[{json_len}];
+    {json_bytes} <== {name}_sd.disclosure_json;
+
     var {name}[{claim_template_len}] = {claim_template};
     signal input {name}_l;
     signal input {name}_r;
-    component match_{name}_name = MatchClaimName(max_json_bytes, {claim_template_len});
+    component match_{name}_name = MatchDisclosureName({json_len}, {claim_template_len});
     match_{name}_name.name <== {name};
-    match_{name}_name.json_bytes <== jwt_bytes;
+    match_{name}_name.json_bytes <== {json_bytes};
     match_{name}_name.l <== {name}_l;
     match_{name}_name.r <== {name}_r;
-    match_{name}_name.object_nested_level <== object_nested_level;
+'''.format(name = name, json_bytes = json_bytes, json_len = json_len, max_disclosure_len = config[key]["max_disclosure_len"],
+             claim_template_len = len(claim_template), claim_template = str(claim_template)))
+            else:
+                json_bytes = "jwt_bytes"
+                json_len = "max_json_bytes"
+                object_nested_level = "object_nested_level"
+                parent = write_parent_objects(f, key, matched_objects)
+                # The name is followed by whitespace and a colon, checked by MatchClaimName
+                claim = '"' + path[-1] + '"'
+                value_l = name + "_value_l"
+                claims.append(claim)
+                claim_template = list(claim.encode('utf-8'))
+                f.write('''
+    log("=== {name} ===");
+    var {name}[{claim_template_len}] = {claim_template};
 '''.format(name = name, claim_template_len = len(claim_template), claim_template = str(claim_template)))
+                if claim_is_optional(config[key]):
+                    write_optional_claim(f, name, claim, typ_string)
+                    present_inputs.append(name + "_present")
+                    json_bytes = name + "_json_bytes"
+                    object_nested_level = name + "_optional.claim_nested_level"
+                f.write('''    signal input {name}_l;
+    signal input {name}_value_l;
+    signal input {name}_r;
+    component match_{name}_name = MatchClaimName(max_json_bytes, {claim_template_len}, {level});
+    match_{name}_name.name <== {name};
+    match_{name}_name.json_bytes <== {json_bytes};
+    match_{name}_name.l <== {name}_l;
+    match_{name}_name.value_l <== {name}_value_l;
+    match_{name}_name.r <== {name}_r;
+    match_{name}_name.object_nested_level <== {object_nested_level};
+'''.format(name = name, claim_template_len = len(claim_template), json_bytes = json_bytes, object_nested_level = object_nested_level, level = len(path)))
+                if parent is not None:
+                    write_parent_check(f, name, parent, len(path))
 
-### begin reveal bytes (currently unused)            
-            if config[name].get("reveal_bytes") is not None and config[name]["reveal_bytes"] == True:
-                if config[name].get("max_claim_byte_len") is not None:
-                    if (config[name]["max_claim_byte_len"] % MAX_FIELD_BYTE_LEN) != 0:
-                        print("max_claim_byte_len must be a multiple of MAX_FIELD_BYTE_LEN")
-                        sys.exit(-1)
-
-                    f.write('''
-    var {}_max_claim_byte_len = {};
-                            '''.format(name, config[name]["max_claim_byte_len"]))
-                else:
-                    f.write('''
-    var {}_max_claim_byte_len = max_json_bytes;
-                            '''.format(name))
-                
-                is_number = 0
-                if typ == 1:
-                    is_number = 1
+### begin reveal bytes
+            if claim_reveal_bytes(config[key]):
+                # Strings longer than a field element are revealed as several field elements
+                public_inputs.append(name + "_value")
+                max_claim_byte_len = config[key]["max_claim_byte_len"]
                 f.write('''
-    component reveal_bytes_{name} = RevealClaimValueBytes(max_json_bytes, {name}_max_claim_byte_len, field_byte_len, {is_number});
-    reveal_bytes_{name}.json_bytes <== jwt_bytes;
-    reveal_bytes_{name}.l <== match_{name}_name.value_l;
+    var {name}_max_claim_byte_len = {max_claim_byte_len};
+    component reveal_bytes_{name} = RevealClaimValueFields({json_len}, {name}_max_claim_byte_len, field_byte_len);
+    reveal_bytes_{name}.json_bytes <== {json_bytes};
+    reveal_bytes_{name}.l <== {value_l};
     reveal_bytes_{name}.r <== match_{name}_name.value_r;
-                        
-    signal output {name}_value[{name}_max_claim_byte_len];
-    signal output {name}_value_len;
-    for (var i = 0; i < {name}_max_claim_byte_len; i++) 
-        {name}_value[i] <== reveal_bytes_{name}.value[i];
-    {name}_value_len <== reveal_bytes_{name}.value_len;
-'''.format(name = name, is_number = is_number))
+
+    signal input {name}_value[{num_fields}];
+    for (var i = 0; i < {num_fields}; i++) {{
+        {name}_value[i] === reveal_bytes_{name}.value[i];
+    }}
+'''.format(name = name, max_claim_byte_len = max_claim_byte_len, num_fields = max_claim_byte_len // MAX_FIELD_BYTE_LEN, json_bytes = json_bytes, json_len = json_len, value_l = value_l))
 ### end reveal bytes
 
+### begin reveal boolean and date
+            elif claim_reveal_unhashed(config[key]) and typ_string in FIXED_LEN_CLAIM_TYPES:
+                # Booleans are revealed as 0 or 1, and dates as daystamps, so that they can be used in range proofs
+                public_inputs.append(name + "_value")
+                reveal_function = "RevealClaimBoolean" if typ_string == "boolean" else "RevealClaimDate"
+                f.write('''
+    component reveal_{name} = {reveal_function}({json_len});
+    reveal_{name}.json_bytes <== {json_bytes};
+    reveal_{name}.l <== {value_l};
+    reveal_{name}.r <== match_{name}_name.value_r;
+
+    signal input {name}_value;
+    log("{name}_value = ", {name}_value);
+    {name}_value === reveal_{name}.value;
+'''.format(name = name, reveal_function = reveal_function, json_bytes = json_bytes, json_len = json_len, value_l = value_l))
+### end reveal boolean and date
+
 ### begin reveal unhashed                 
-            elif claim_reveal_unhashed(config[name]): 
-                if config[name].get("max_claim_byte_len") is not None:
-                    if (config[name]["max_claim_byte_len"] % MAX_FIELD_BYTE_LEN) != 0:
+            elif claim_reveal_unhashed(config[key]): 
+                if config[key].get("max_claim_byte_len") is not None:
+                    if (config[key]["max_claim_byte_len"] % MAX_FIELD_BYTE_LEN) != 0:
                         print("max_claim_byte_len must be a multiple of MAX_FIELD_BYTE_LEN")
                         sys.exit(-1)
                     public_inputs.append(name + "_value")
                     f.write('''
     var {}_max_claim_byte_len = {};
-                            '''.format(name, config[name]["max_claim_byte_len"]))
+                            '''.format(name, config[key]["max_claim_byte_len"]))
                 else:
                     print("max_claim_byte_len must be set")
                     sys.exit(-1)
                 
                 reveal_function = "RevealClaimValue"
-                dom_only = config[name].get("reveal_domain_only");
+                dom_only = config[key].get("reveal_domain_only");
                 if  dom_only is not None and dom_only == True:
                     reveal_function = "RevealDomainOnly"
 
@@ -131,9 +274,9 @@ def prepare_circom(config, circom_output_file):
                 if typ == 1:
                     is_number = 1
                 f.write('''
-    component reveal_{name} = {reveal_function}(max_json_bytes, {name}_max_claim_byte_len, field_byte_len, {is_number});
-    reveal_{name}.json_bytes <== jwt_bytes;
-    reveal_{name}.l <== match_{name}_name.value_l;
+    component reveal_{name} = {reveal_function}({json_len}, {name}_max_claim_byte_len, field_byte_len, {is_number});
+    reveal_{name}.json_bytes <== {json_bytes};
+    reveal_{name}.l <== {value_l};
     reveal_{name}.r <== match_{name}_name.value_r;
                         
     signal input {name}_value;
@@ -141,39 +284,39 @@ def prepare_circom(config, circom_output_file):
     log("reveal_{name}.value = ", reveal_{name}.value);                        
     {name}_value === reveal_{name}.value;
 
-'''.format(name = name, reveal_function = reveal_function, is_number = is_number))                
+'''.format(name = name, reveal_function = reveal_function, is_number = is_number, json_bytes = json_bytes, json_len = json_len, value_l = value_l))                
 ###  end reveal unhashed
 
 ###  begin reveal hashed          
-            elif claim_reveal_hashed(config[name]):
-                f.write('''    var {}_max_claim_byte_len = {};'''.format(name, config[name]["max_claim_byte_len"]))
+            elif claim_reveal_hashed(config[key]):
+                f.write('''    var {}_max_claim_byte_len = {};'''.format(name, config[key]["max_claim_byte_len"]))
                 
                 is_number = 0
                 if typ == 1:
                     is_number = 1
                 
                 f.write('''
-    component hash_reveal_{name} = HashRevealClaimValue(max_json_bytes, {name}_max_claim_byte_len, field_byte_len, {is_number});
-    hash_reveal_{name}.json_bytes <== jwt_bytes;
-    hash_reveal_{name}.l <== match_{name}_name.value_l;
+    component hash_reveal_{name} = HashRevealClaimValue({json_len}, {name}_max_claim_byte_len, field_byte_len, {is_number});
+    hash_reveal_{name}.json_bytes <== {json_bytes};
+    hash_reveal_{name}.l <== {value_l};
     hash_reveal_{name}.r <== match_{name}_name.value_r;
                         
     signal output {name}_digest;
     {name}_digest <== hash_reveal_{name}.digest;
-'''.format(name = name, is_number = is_number))
+'''.format(name = name, is_number = is_number, json_bytes = json_bytes, json_len = json_len, value_l = value_l))
 ### end reveal hashed
 
             else:
                 f.write('''
-    component validate_{name} = ValidateClaimValue(max_json_bytes, {typ});
-    validate_{name}.json_bytes <== jwt_bytes;
-    validate_{name}.l <== match_{name}_name.value_l;
+    component validate_{name} = ValidateClaimValue({json_len}, {typ});
+    validate_{name}.json_bytes <== {json_bytes};
+    validate_{name}.l <== {value_l};
     validate_{name}.r <== match_{name}_name.value_r;
-'''.format(name = name, typ = typ))
+'''.format(name = name, typ = typ, json_bytes = json_bytes, json_len = json_len, value_l = value_l))
                 
-            if config[name].get("predicates") is not None:
+            if config[key].get("predicates") is not None:
 
-                for predicate in config[name]["predicates"]:
+                for predicate in config[key]["predicates"]:
                     predicate_name = predicate["name"]
                     pred_var_name = camel_to_snake(predicate_name)
 
@@ -194,10 +337,10 @@ def prepare_circom(config, circom_output_file):
 '''.format(input = k_name+k_dim))
                     
                     f.write('''
-    component {name}_{pred_var_name} = {predicate_name}(max_json_bytes);
-    {name}_{pred_var_name}.json_bytes <== jwt_bytes;
+    component {name}_{pred_var_name} = {predicate_name}({json_len});
+    {name}_{pred_var_name}.json_bytes <== {json_bytes};
     {name}_{pred_var_name}.range_indicator <== validate_{name}.range_indicator;
-'''.format(name = name, pred_var_name = pred_var_name, predicate_name = predicate_name))
+'''.format(name = name, pred_var_name = pred_var_name, predicate_name = predicate_name, json_bytes = json_bytes, json_len = json_len))
                     
                     for input in circom_special_inputs:
                         f.write('''
@@ -206,18 +349,19 @@ def prepare_circom(config, circom_output_file):
 
         f.write("}\n")
         # Print the main statement in circom.
-        limb_size = 0
-        n_limbs = 0
+        sig_params = []
         if config['alg'] == "ES256K":
-            limb_size = CIRCOM_ES256K_LIMB_BITS
-            n_limbs = 4
-        elif config['alg'] == "RS256":
-            limb_size = CIRCOM_RS256_LIMB_BITS
-            n_limbs = 17
-        main_input = "{ public [" + ", ".join(public_inputs) + " ] }"
+            sig_params = [CIRCOM_ES256K_LIMB_BITS, 4]
+        elif config['alg'] in RSA_ALGS:
+            (hash_bits, pss) = RSA_ALGS[config['alg']]
+            key_bits = config['rsa_key_bits']
+            sig_params = [CIRCOM_RS256_LIMB_BITS, rsa_num_limbs(key_bits), key_bits, hash_bits, pss]
+        elif config['alg'] == "ES256":
+            sig_params = [CIRCOM_ES256_LIMB_BITS, CIRCOM_P256_N_LIMBS]
+        main_input = "{ public [" + ", ".join(public_inputs + present_inputs) + " ] }"
         f.write('''
-component main {main_input} = Main({max_msg_len}, {max_json_len}, {max_field_byte_len}, {limb_size}, {n_limbs});
-'''.format(main_input = main_input, max_msg_len = config['max_cred_len'], max_json_len = base64_decoded_size(config['max_cred_len']), max_field_byte_len = MAX_FIELD_BYTE_LEN, limb_size=limb_size, n_limbs=n_limbs))
+component main {main_input} = Main({max_msg_len}, {max_json_len}, {max_field_byte_len}, {sig_params});
+'''.format(main_input = main_input, max_msg_len = config['max_cred_len'], max_json_len = base64_decoded_size(config['max_cred_len']), max_field_byte_len = MAX_FIELD_BYTE_LEN, sig_params = ", ".join(str(p) for p in sig_params)))
 
 
     print_debug("Claims:", claims)
@@ -33,8 +33,16 @@ if [ ! -f ${INPUTS_DIR}/config.json ]; then
     exit -1 
 fi
 
-# Determine the credential type, JWT or mDL
-CREDTYPE_REGEX="\"credtype\": \"([a-z]+)\""
+# Validate the config before doing any expensive work
+cd ${ROOT_DIR}/../creds
+if ! cargo run --release --bin crescent -- check-config --config ${INPUTS_DIR}/config.json; then
+    echo "Invalid config file ${INPUTS_DIR}/config.json, aborting"
+    exit 1
+fi
+cd ${ROOT_DIR}
+
+# Determine the credential type, JWT, SD-JWT or mDL
+CREDTYPE_REGEX="\"credtype\": \"([a-z-]+)\""
 if [[ `cat ${INPUTS_DIR}/config.json` =~ $CREDTYPE_REGEX ]]; then
     CREDTYPE="${BASH_REMATCH[1]}"
     echo "Credential type read from config.json: $CREDTYPE"
@@ -88,9 +96,9 @@ if [ -f ${LOG_FILE} ]; then
 fi
 touch ${LOG_FILE}
 
-# For JWTs, we create sample issuer keys and a token
+# For JWTs and SD-JWTs, we create sample issuer keys and a token
 ALG_REGEX="\"alg\": \"([A-Z0-9]+)\""
-if [ ${CREDTYPE} == 'jwt' ] && ([ ! -f ${INPUTS_DIR}/issuer.pub ] || [ ! -f ${INPUTS_DIR}/issuer.prv ] || [ ! -f ${INPUTS_DIR}/token.jwt ]); then
+if ([ ${CREDTYPE} == 'jwt' ] || [ ${CREDTYPE} == 'sd-jwt' ]) && ([ ! -f ${INPUTS_DIR}/issuer.pub ] || [ ! -f ${INPUTS_DIR}/issuer.prv ] || [ ! -f ${INPUTS_DIR}/token.jwt ]); then
     rm ${INPUTS_DIR}/issuer.pub ${INPUTS_DIR}/issuer.prv ${INPUTS_DIR}/token.jwt 2>/dev/null && true 
 
     if [[ `cat ${INPUTS_DIR}/config.json` =~ $ALG_REGEX ]]; then
@@ -100,13 +108,20 @@ if [ ${CREDTYPE} == 'jwt' ] && ([ ! -f ${INPUTS_DIR}/issuer.pub ] || [ ! -f ${IN
         echo "Error: algorithm not found in config.json"
         exit 1
     fi
-    python3 scripts/jwk_gen.py ${ALG} ${INPUTS_DIR}/issuer.prv ${INPUTS_DIR}/issuer.pub
+    # For RSA algorithms, the key size may be set in config.json (2048 bits if not)
+    RSA_KEY_BITS_REGEX="\"rsa_key_bits\": ([0-9]+)"
+    if [[ `cat ${INPUTS_DIR}/config.json` =~ $RSA_KEY_BITS_REGEX ]]; then
+        KEY_BITS="${BASH_REMATCH[1]}"
+    else
+        KEY_BITS=""
+    fi
+    python3 scripts/jwk_gen.py ${ALG} ${INPUTS_DIR}/issuer.prv ${INPUTS_DIR}/issuer.pub ${KEY_BITS}
     if [ $DEVICE_BOUND ]; then
         echo "Creating device public key"
         python3 scripts/jwk_gen.py ES256 ${INPUTS_DIR}/device.prv ${INPUTS_DIR}/device.pub
-        python3 scripts/jwt_sign.py ${INPUTS_DIR}/claims.json ${INPUTS_DIR}/issuer.prv  ${INPUTS_DIR}/token.jwt ${INPUTS_DIR}/device.pub
+        python3 scripts/jwt_sign.py ${INPUTS_DIR}/claims.json ${INPUTS_DIR}/issuer.prv  ${INPUTS_DIR}/token.jwt ${INPUTS_DIR}/device.pub --alg ${ALG} --config ${INPUTS_DIR}/config.json
     else
-        python3 scripts/jwt_sign.py ${INPUTS_DIR}/claims.json ${INPUTS_DIR}/issuer.prv  ${INPUTS_DIR}/token.jwt
+        python3 scripts/jwt_sign.py ${INPUTS_DIR}/claims.json ${INPUTS_DIR}/issuer.prv  ${INPUTS_DIR}/token.jwt --alg ${ALG} --config ${INPUTS_DIR}/config.json
     fi
 elif [ ${CREDTYPE} == 'mdl' ] && ([ ! -f ${INPUTS_DIR}/device.prv ] || [ ! -f ${INPUTS_DIR}/issuer.prv ] || [ ! -f ${INPUTS_DIR}/issuer.pub ] || [ ! -f ${INPUTS_DIR}/issuer_certs.pem ] || [ ! -f ${INPUTS_DIR}/mdl.cbor ]); then
     echo "Creating sample issuer keys and mDL"
@@ -146,6 +161,10 @@ echo -e "\n=== circom output start ===" >> ${LOG_FILE}
 
 # Copy the circom files we need to the instance's circom folder.
 cp -r -L ${CIRCOM_SRC_DIR}/* ${CIRCOM_DIR}/
+# The ES256 JWT circuit uses the ECDSA-P256 circuit of the mDL circuits (see main_header_es256.circom.template)
+if [ ${CREDTYPE} != 'mdl' ]; then
+    cp -r ${ROOT_DIR}/circuits-mdl/ecdsa-p256 ${CIRCOM_DIR}/
+fi
 
 # Compile the circom circuit.  First check if the hash of the circom files has changed, only re-compile if so. To force a re-build remove circom_files.sha256
 cd $CIRCOM_DIR
@@ -219,7 +238,7 @@ ISSUER_KEY_FILE=${INPUTS_DIR}/issuer.pub
 PROOF_SPEC_FILE=${INPUTS_DIR}/proof_spec.json
 DEVICE_PUB_FILE=${INPUTS_DIR}/device.pub
 DEVICE_PRV_FILE=${INPUTS_DIR}/device.prv
-if [ ${CREDTYPE} == 'jwt' ]; then
+if [ ${CREDTYPE} == 'jwt' ] || [ ${CREDTYPE} == 'sd-jwt' ]; then
     CRED_FILE=${INPUTS_DIR}/token.jwt
 elif [ ${CREDTYPE} == 'mdl' ]; then 
     CRED_FILE=${INPUTS_DIR}/mdl.cbor
@@ -9,9 +9,10 @@ use std::error::Error;
 use std::fs::OpenOptions;
 use std::fs;
 use ark_std::{path::PathBuf, io::BufWriter};
-use crescent::prep_inputs::load_config;
+use crescent::config::load_config;
 use crescent::return_error;
-use crescent::prep_inputs::prepare_prover_inputs;
+use crescent::prep_inputs::{prepare_prover_inputs, verify_jwt};
+use crescent::sd_jwt::SdJwtToken;
 
 #[derive(Debug, StructOpt)]
 struct Opts {
@@ -46,17 +47,22 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // Load issuer's public key
     let issuer_pem = fs::read_to_string(opts.jwk)?;
-    let issuer_pub = RS256PublicKey::from_pem(&issuer_pem)?;
-
     let token_str = fs::read_to_string(opts.jwt)?;
-    let claims_limited_set = issuer_pub.verify_token::<NoCustomClaims>(&token_str, None);
-    if claims_limited_set.is_ok() {
+
+    // For SD-JWTs, the claims are those of the issuer-signed JWT and of the disclosures
+    let sd_jwt = if config.credtype == "sd-jwt" {
+        Some(SdJwtToken::parse(&token_str)?)
+    } else {
+        None
+    };
+    let issuer_jwt = sd_jwt.as_ref().map(|t| t.issuer_jwt.as_str()).unwrap_or(&token_str);
+    if verify_jwt(&config.alg, issuer_jwt, &issuer_pem).is_ok() {
         println!("Token verifies");
     } else {
         println!("Token failed to verify");
     }
 
-    let mut parts = token_str.split('.');
+    let mut parts = issuer_jwt.split('.');
     let jwt_header_b64 = parts.next().ok_or("Missing JWT header")?;
     let claims_b64 = parts.next().ok_or("Missing JWT claims")?;
     let _signature_b64 = parts.next().ok_or("Missing JWT signature")?;
@@ -64,8 +70,10 @@ fn main() -> Result<(), Box<dyn Error>> {
     let _jwt_header_decoded = String::from_utf8(base64_url::decode(jwt_header_b64)?)?;
     let _claims_decoded = String::from_utf8(base64_url::decode(claims_b64)?)?;
 
-    let claims: Value =
-        serde_json::from_slice(&Base64UrlSafeNoPadding::decode_to_vec(claims_b64, None)?)?;
+    let claims: Value = match &sd_jwt {
+        Some(t) => t.claims()?,
+        None => serde_json::from_slice(&Base64UrlSafeNoPadding::decode_to_vec(claims_b64, None)?)?,
+    };
 
     println!("Claims:");
     if let Value::Object(map) = claims.clone() {
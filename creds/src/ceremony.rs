@@ -0,0 +1,692 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// Multi-party generation of the Groth16 parameters (phase 2 of the setup ceremony), so that no single party knows
+// the trapdoor that would let it forge proofs. The initial parameters are derived from the R1CS instance and from
+// the powers of tau of a phase-1 ceremony, read from a .ptau file of snarkjs (e.g., from the Perpetual Powers of Tau
+// ceremony). Each participant then multiplies delta by a secret random factor, divides the L and H queries by it,
+// and publishes a proof of knowledge of the factor bound to the previous contributions, as in the Zcash Sapling
+// ceremony. The parameters are sound if one participant of each phase deleted their secret.
+// The KZG parameters of the range proofs are also taken from the powers of tau, so that their trapdoor is not known
+// either; they do not change during phase 2.
+
+use ark_bn254::{Bn254 as ECPairing, Fq, Fq2, Fr, G1Affine, G1Projective as G1, G2Affine, G2Projective as G2};
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ff::{BigInteger, BigInteger256, Field, One, PrimeField, UniformRand};
+use ark_groth16::{ProvingKey, VerifyingKey};
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, SynthesisMode};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{end_timer, rand::{thread_rng, Rng}, start_timer};
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::rangeproof::{RangeProofPK, RangeProofVK};
+use crate::return_error;
+use crate::utils::hash_to_curve_vartime;
+
+// Sections of a .ptau file (see powersoftau_new.js in snarkjs)
+const PTAU_HEADER: u32 = 1;
+const PTAU_TAU_G1: u32 = 2;
+const PTAU_TAU_G2: u32 = 3;
+const PTAU_ALPHA_TAU_G1: u32 = 4;
+const PTAU_BETA_TAU_G1: u32 = 5;
+const PTAU_BETA_G2: u32 = 6;
+
+// Size of a base field element, and of uncompressed points
+const FQ_SIZE: usize = 32;
+const G1_SIZE: usize = 2 * FQ_SIZE;
+const G2_SIZE: usize = 4 * FQ_SIZE;
+
+/// The powers of tau of a phase-1 ceremony that the parameters of a circuit with an evaluation domain of size n are
+/// derived from: tau^i G1 for i < 2n - 1, and tau^i G2, alpha tau^i G1 and beta tau^i G1 for i < n
+pub struct PowersOfTau {
+    pub tau_g1: Vec<G1Affine>,
+    pub tau_g2: Vec<G2Affine>,
+    pub alpha_tau_g1: Vec<G1Affine>,
+    pub beta_tau_g1: Vec<G1Affine>,
+    pub beta_g2: G2Affine,
+}
+
+impl PowersOfTau {
+    /// Read the powers of tau for an evaluation domain of size `domain_size` from a .ptau file, and check that they
+    /// are consistent. The contributions of the phase-1 ceremony are not verified (use `snarkjs powersoftau verify`).
+    pub fn read_ptau<R: Read + Seek>(reader: &mut R, domain_size: usize) -> Result<Self, Box<dyn Error>> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != b"ptau" {
+            return_error!("Invalid .ptau file");
+        }
+        let version = read_u32(reader)?;
+        if version != 1 {
+            return_error!(format!("Unsupported .ptau file version: {}", version));
+        }
+
+        // Find the first section of each type
+        let num_sections = read_u32(reader)?;
+        let mut sections = BTreeMap::new();
+        for _ in 0..num_sections {
+            let section_type = read_u32(reader)?;
+            let size = read_u64(reader)?;
+            let pos = reader.stream_position()?;
+            sections.entry(section_type).or_insert((pos, size));
+            reader.seek(SeekFrom::Start(pos + size))?;
+        }
+
+        seek_section(reader, &sections, PTAU_HEADER, 4 + FQ_SIZE + 4)?;
+        let n8 = read_u32(reader)?;
+        let mut q = [0u8; FQ_SIZE];
+        reader.read_exact(&mut q)?;
+        if n8 as usize != FQ_SIZE || q.as_slice() != Fq::MODULUS.to_bytes_le() {
+            return_error!("The .ptau file is not for the BN254 curve");
+        }
+        let power = read_u32(reader)?;
+        let domain_size = domain_size.max(2);
+        if power >= usize::BITS || (1usize << power) < domain_size {
+            return_error!(format!("The circuit needs powers of tau of power at least {}, the .ptau file has power {}", domain_size.trailing_zeros(), power));
+        }
+
+        let powers = PowersOfTau {
+            tau_g1: read_points(reader, &sections, PTAU_TAU_G1, 2 * domain_size - 1, G1_SIZE, parse_g1)?,
+            tau_g2: read_points(reader, &sections, PTAU_TAU_G2, domain_size, G2_SIZE, parse_g2)?,
+            alpha_tau_g1: read_points(reader, &sections, PTAU_ALPHA_TAU_G1, domain_size, G1_SIZE, parse_g1)?,
+            beta_tau_g1: read_points(reader, &sections, PTAU_BETA_TAU_G1, domain_size, G1_SIZE, parse_g1)?,
+            beta_g2: read_points(reader, &sections, PTAU_BETA_G2, 1, G2_SIZE, parse_g2)?[0],
+        };
+        powers.check()?;
+        Ok(powers)
+    }
+
+    // Check that the points are powers of the same tau, and that the alpha and beta points use the same alpha and beta
+    fn check(&self) -> Result<(), Box<dyn Error>> {
+        let (g1, g2) = (G1Affine::generator(), G2Affine::generator());
+        if self.tau_g1[0] != g1 || self.tau_g2[0] != g2 {
+            return_error!("Invalid powers of tau: the first powers are not the generators");
+        }
+        let (tau_g1, tau_g2) = (self.tau_g1[1], self.tau_g2[1]);
+        let successive = |powers: &[G1Affine]| {
+            same_ratio(merge_pairs::<G1>(&powers[..powers.len() - 1], &powers[1..]), (g2, tau_g2))
+        };
+        if !successive(&self.tau_g1)
+            || !same_ratio((g1, tau_g1), merge_pairs::<G2>(&self.tau_g2[..self.tau_g2.len() - 1], &self.tau_g2[1..]))
+            || !successive(&self.alpha_tau_g1)
+            || !successive(&self.beta_tau_g1)
+            || !same_ratio((g1, self.beta_tau_g1[0]), (g2, self.beta_g2))
+        {
+            return_error!("Invalid powers of tau: the points are not consistent");
+        }
+        Ok(())
+    }
+}
+
+/// The public key of a contribution: the new delta (in G1), and a proof of knowledge of the factor `d` it multiplied
+/// delta by: `s_delta = d s` for a random `s`, and `r_delta = d r` where `r` is hashed from the `transcript` of
+/// the ceremony, the hash of the initial parameters, of the previous contributions, and of `s` and `s_delta`
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Contribution {
+    pub delta_after: G1Affine,
+    pub s: G1Affine,
+    pub s_delta: G1Affine,
+    pub r_delta: G2Affine,
+    pub transcript: [u8; 32],
+}
+
+impl Contribution {
+    /// The hash of the contribution, that its participant publishes to check it is part of the final parameters
+    pub fn hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        self.serialize_uncompressed(&mut hasher).unwrap();
+        hasher.finalize().into()
+    }
+}
+
+/// The KZG parameters of the range proofs, from the powers of tau: tau^i G1, and alpha tau^i G1 as the powers of the
+/// hiding generator of KZG10 (alpha G1 is a generator of unknown discrete log, as alpha is a secret of the phase-1
+/// ceremony), as generated by `RangeProofPK::setup_with_degree` from the secret tau
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct RangeProofPowers {
+    /// The largest bit width of the range proofs
+    pub max_bits: usize,
+    pub powers_of_g: Vec<G1Affine>,
+    pub powers_of_gamma_g: Vec<G1Affine>,
+    pub beta_h: G2Affine,
+}
+
+impl RangeProofPowers {
+    fn new(powers: &PowersOfTau, max_bits: usize, max_degree: usize) -> Self {
+        RangeProofPowers {
+            max_bits,
+            powers_of_g: powers.tau_g1[..num_range_proof_powers(max_bits, max_degree)].to_vec(),
+            powers_of_gamma_g: powers.alpha_tau_g1[..4 * max_bits + 1].to_vec(),
+            beta_h: powers.tau_g2[1],
+        }
+    }
+
+    /// The range proof parameters
+    pub fn keys(&self) -> (RangeProofPK<'static, ECPairing>, RangeProofVK<ECPairing>) {
+        RangeProofPK::from_powers(self.max_bits, self.powers_of_g.clone(), self.powers_of_gamma_g.clone(),
+            G2Affine::generator(), self.beta_h)
+    }
+}
+
+// The number of powers of g of the range proof parameters, see `RangeProofPK::setup_with_degree`
+fn num_range_proof_powers(max_bits: usize, max_degree: usize) -> usize {
+    max_degree.max(4 * max_bits) + 1
+}
+
+/// The Groth16 parameters of a setup ceremony, with the contributions made to them so far
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CeremonyParams {
+    pub params: ProvingKey<ECPairing>,
+    pub range_powers: RangeProofPowers,
+    /// The hash of the initial parameters, that identifies the circuit and the phase-1 powers of tau
+    pub cs_hash: [u8; 32],
+    pub contributions: Vec<Contribution>,
+}
+
+impl CeremonyParams {
+    /// Derive the initial parameters for `circuit` from the powers of tau in the .ptau file `ptau`, with delta = 1,
+    /// and the parameters of range proofs of up to `range_bits` bits with `range_degree` + 1 powers of g (as
+    /// `RangeProofPK::setup_with_degree` does)
+    pub fn new<C, R>(circuit: C, ptau: &mut R, range_bits: usize, range_degree: usize) -> Result<Self, Box<dyn Error>>
+    where
+        C: ConstraintSynthesizer<Fr>,
+        R: Read + Seek,
+    {
+        if !range_bits.is_power_of_two() || range_bits > Fr::MODULUS_BIT_SIZE as usize {
+            return_error!("The bit width of range proofs must be a power of two");
+        }
+        let (params, range_powers) = initial_params(circuit, ptau, range_bits, range_degree)?;
+        let mut hasher = Sha256::new();
+        params.serialize_uncompressed(&mut hasher)?;
+        range_powers.serialize_uncompressed(&mut hasher)?;
+        Ok(Self { params, range_powers, cs_hash: hasher.finalize().into(), contributions: vec![] })
+    }
+
+    /// Multiply delta by a secret random factor, and divide the L and H queries by it. Returns the hash of the new
+    /// contribution.
+    pub fn contribute<R: Rng>(&mut self, rng: &mut R) -> [u8; 32] {
+        let timer = start_timer!(|| "Contributing to the setup ceremony");
+        let delta = Fr::rand(rng);
+        let delta_inverse = delta.inverse().unwrap();
+
+        let s = G1::rand(rng).into_affine();
+        let s_delta = (s * delta).into_affine();
+        let transcript = transcript_hash(&self.cs_hash, &self.contributions, &s, &s_delta);
+        let r_delta = (hash_to_g2(&transcript) * delta).into_affine();
+
+        let params = &mut self.params;
+        params.delta_g1 = (params.delta_g1 * delta).into_affine();
+        params.vk.delta_g1 = params.delta_g1;
+        params.vk.delta_g2 = (params.vk.delta_g2 * delta).into_affine();
+        params.h_query = scale(&params.h_query, delta_inverse);
+        params.l_query = scale(&params.l_query, delta_inverse);
+
+        let contribution = Contribution { delta_after: params.delta_g1, s, s_delta, r_delta, transcript };
+        let hash = contribution.hash();
+        self.contributions.push(contribution);
+        end_timer!(timer);
+        hash
+    }
+
+    /// Verify that the parameters were derived from `circuit` and the powers of tau in `ptau`, and changed only by the
+    /// contributions, each with a valid proof of knowledge, and that the range proof parameters are those of the powers
+    /// of tau. Returns the hashes of the contributions.
+    pub fn verify<C, R>(&self, circuit: C, ptau: &mut R) -> Result<Vec<[u8; 32]>, Box<dyn Error>>
+    where
+        C: ConstraintSynthesizer<Fr>,
+        R: Read + Seek,
+    {
+        let range_degree = self.range_powers.powers_of_g.len().saturating_sub(1);
+        let initial = Self::new(circuit, ptau, self.range_powers.max_bits, range_degree)?;
+        if self.cs_hash != initial.cs_hash {
+            return_error!("The ceremony is not for this circuit and powers of tau");
+        }
+        if self.range_powers != initial.range_powers {
+            return_error!("The range proof parameters were not taken from the powers of tau");
+        }
+        let (pk, pk0) = (&self.params, &initial.params);
+        if pk.vk.alpha_g1 != pk0.vk.alpha_g1 || pk.vk.beta_g2 != pk0.vk.beta_g2 || pk.vk.gamma_g2 != pk0.vk.gamma_g2
+            || pk.vk.gamma_abc_g1 != pk0.vk.gamma_abc_g1 || pk.beta_g1 != pk0.beta_g1 || pk.a_query != pk0.a_query
+            || pk.b_g1_query != pk0.b_g1_query || pk.b_g2_query != pk0.b_g2_query
+            || pk.h_query.len() != pk0.h_query.len() || pk.l_query.len() != pk0.l_query.len()
+        {
+            return_error!("The parameters were changed in more than delta and the L and H queries");
+        }
+        if self.contributions.is_empty() {
+            return_error!("The ceremony has no contributions");
+        }
+
+        let mut delta = G1Affine::generator();
+        let mut hashes = vec![];
+        for (i, c) in self.contributions.iter().enumerate() {
+            let transcript = transcript_hash(&self.cs_hash, &self.contributions[..i], &c.s, &c.s_delta);
+            if c.transcript != transcript {
+                return_error!(format!("Contribution {} does not match the transcript of the ceremony", i + 1));
+            }
+            let r = hash_to_g2(&transcript);
+            if c.s.is_zero() || c.s_delta.is_zero() || c.r_delta.is_zero() || r.is_zero() {
+                return_error!(format!("Contribution {} has an invalid proof of knowledge", i + 1));
+            }
+            if !same_ratio((c.s, c.s_delta), (r, c.r_delta)) {
+                return_error!(format!("Contribution {} has an invalid proof of knowledge", i + 1));
+            }
+            if !same_ratio((delta, c.delta_after), (r, c.r_delta)) {
+                return_error!(format!("Contribution {} does not match the previous delta", i + 1));
+            }
+            delta = c.delta_after;
+            hashes.push(c.hash());
+        }
+
+        let g2 = G2Affine::generator();
+        if pk.delta_g1 != delta || pk.vk.delta_g1 != delta || !same_ratio((G1Affine::generator(), delta), (g2, pk.vk.delta_g2)) {
+            return_error!("The delta of the parameters does not match the last contribution");
+        }
+        // The L and H queries were divided by delta
+        if !same_ratio(merge_pairs::<G1>(&pk.h_query, &pk0.h_query), (g2, pk.vk.delta_g2))
+            || !same_ratio(merge_pairs::<G1>(&pk.l_query, &pk0.l_query), (g2, pk.vk.delta_g2))
+        {
+            return_error!("The L and H queries do not match delta");
+        }
+        Ok(hashes)
+    }
+}
+
+// Derive the Groth16 parameters for `circuit`, with the libsnark reduction used by `Groth16::prove`, from the powers
+// of tau, as `Groth16::generate_parameters_with_qap` does from the secrets: gamma and delta are 1, and the
+// polynomials are evaluated at tau in the Lagrange basis of the evaluation domain, computed with an FFT in the group.
+// Also returns the range proof parameters, which may need more powers than the circuit.
+fn initial_params<C, R>(circuit: C, ptau: &mut R, range_bits: usize, range_degree: usize)
+    -> Result<(ProvingKey<ECPairing>, RangeProofPowers), Box<dyn Error>>
+where
+    C: ConstraintSynthesizer<Fr>,
+    R: Read + Seek,
+{
+    let synthesis_timer = start_timer!(|| "Constraint synthesis");
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(SynthesisMode::Setup);
+    circuit.generate_constraints(cs.clone())?;
+    cs.finalize();
+    let matrices = match cs.to_matrices() {
+        Some(matrices) => matrices,
+        None => return_error!("Failed to get the constraint matrices of the circuit"),
+    };
+    end_timer!(synthesis_timer);
+
+    let num_instance_variables = matrices.num_instance_variables;
+    let num_variables = num_instance_variables + matrices.num_witness_variables;
+    let num_constraints = matrices.num_constraints;
+    let domain = match GeneralEvaluationDomain::<Fr>::new(num_constraints + num_instance_variables) {
+        Some(domain) => domain,
+        None => return_error!("The circuit is too large"),
+    };
+    let n = domain.size();
+
+    let ptau_timer = start_timer!(|| "Reading the powers of tau");
+    let powers = PowersOfTau::read_ptau(ptau, n.max(num_range_proof_powers(range_bits, range_degree).next_power_of_two()))?;
+    end_timer!(ptau_timer);
+
+    let lagrange_timer = start_timer!(|| "Computing the Lagrange basis");
+    let lagrange_g1 = lagrange_basis::<G1>(&domain, &powers.tau_g1[..n]);
+    let lagrange_g2 = lagrange_basis::<G2>(&domain, &powers.tau_g2[..n]);
+    let lagrange_alpha = lagrange_basis::<G1>(&domain, &powers.alpha_tau_g1[..n]);
+    let lagrange_beta = lagrange_basis::<G1>(&domain, &powers.beta_tau_g1[..n]);
+    end_timer!(lagrange_timer);
+
+    // The (row, coefficient) terms of each variable in A, B and C. Instance variable i is also in A at row
+    // num_constraints + i, to make the polynomials of the instance variables linearly independent.
+    let mut a = vec![vec![]; num_variables];
+    let mut b = vec![vec![]; num_variables];
+    let mut c = vec![vec![]; num_variables];
+    for (matrix, terms) in [(&matrices.a, &mut a), (&matrices.b, &mut b), (&matrices.c, &mut c)] {
+        for (row, constraint) in matrix.iter().enumerate() {
+            for &(coeff, var) in constraint {
+                terms[var].push((row, coeff));
+            }
+        }
+    }
+    for (i, terms) in a.iter_mut().enumerate().take(num_instance_variables) {
+        terms.push((num_constraints + i, Fr::one()));
+    }
+
+    let queries_timer = start_timer!(|| "Computing the queries");
+    let a_query: Vec<G1> = a.par_iter().map(|terms| lincomb::<G1>(&lagrange_g1, terms)).collect();
+    let b_g1_query: Vec<G1> = b.par_iter().map(|terms| lincomb::<G1>(&lagrange_g1, terms)).collect();
+    let b_g2_query: Vec<G2> = b.par_iter().map(|terms| lincomb::<G2>(&lagrange_g2, terms)).collect();
+    // beta u_i(tau) + alpha v_i(tau) + w_i(tau), divided by gamma for the instance variables and by delta for the others
+    let abc: Vec<G1> = (0..num_variables).into_par_iter().map(|i| {
+        lincomb::<G1>(&lagrange_beta, &a[i]) + lincomb::<G1>(&lagrange_alpha, &b[i]) + lincomb::<G1>(&lagrange_g1, &c[i])
+    }).collect();
+    // tau^i t(tau), where t(X) = X^n - 1 is the vanishing polynomial of the domain
+    let h_query: Vec<G1> = (0..n - 1).into_par_iter().map(|i| powers.tau_g1[n + i].into_group() - powers.tau_g1[i]).collect();
+    end_timer!(queries_timer);
+
+    let vk = VerifyingKey {
+        alpha_g1: powers.alpha_tau_g1[0],
+        beta_g2: powers.beta_g2,
+        gamma_g2: G2Affine::generator(),
+        delta_g1: G1Affine::generator(),
+        delta_g2: G2Affine::generator(),
+        gamma_abc_g1: G1::normalize_batch(&abc[..num_instance_variables]),
+    };
+    let params = ProvingKey {
+        vk,
+        beta_g1: powers.beta_tau_g1[0],
+        delta_g1: G1Affine::generator(),
+        a_query: G1::normalize_batch(&a_query),
+        b_g1_query: G1::normalize_batch(&b_g1_query),
+        b_g2_query: G2::normalize_batch(&b_g2_query),
+        h_query: G1::normalize_batch(&h_query),
+        l_query: G1::normalize_batch(&abc[num_instance_variables..]),
+    };
+    Ok((params, RangeProofPowers::new(&powers, range_bits, range_degree)))
+}
+
+// The Lagrange basis of `domain` evaluated at tau, from the powers of tau: the inverse FFT of the powers
+fn lagrange_basis<G: CurveGroup<ScalarField = Fr>>(domain: &GeneralEvaluationDomain<Fr>, powers: &[G::Affine]) -> Vec<G::Affine> {
+    let powers: Vec<G> = powers.iter().map(|p| p.into_group()).collect();
+    G::normalize_batch(&domain.ifft(&powers))
+}
+
+fn lincomb<G: CurveGroup<ScalarField = Fr>>(bases: &[G::Affine], terms: &[(usize, Fr)]) -> G {
+    terms.iter().map(|&(i, coeff)| if coeff.is_one() { bases[i].into_group() } else { bases[i] * coeff }).sum()
+}
+
+fn scale(points: &[G1Affine], factor: Fr) -> Vec<G1Affine> {
+    let scaled: Vec<G1> = points.par_iter().map(|p| *p * factor).collect();
+    G1::normalize_batch(&scaled)
+}
+
+fn transcript_hash(cs_hash: &[u8; 32], contributions: &[Contribution], s: &G1Affine, s_delta: &G1Affine) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(cs_hash);
+    for contribution in contributions {
+        contribution.serialize_uncompressed(&mut hasher).unwrap();
+    }
+    s.serialize_uncompressed(&mut hasher).unwrap();
+    s_delta.serialize_uncompressed(&mut hasher).unwrap();
+    hasher.finalize().into()
+}
+
+fn hash_to_g2(transcript: &[u8; 32]) -> G2Affine {
+    hash_to_curve_vartime::<G2>(&hex::encode(transcript)).clear_cofactor()
+}
+
+// Check that b = x a and d = x c for the same x
+fn same_ratio((a, b): (G1Affine, G1Affine), (c, d): (G2Affine, G2Affine)) -> bool {
+    ECPairing::pairing(a, d) == ECPairing::pairing(b, c)
+}
+
+// Combine pairs of points with random coefficients, so that same_ratio checks all pairs at once
+fn merge_pairs<G: VariableBaseMSM<ScalarField = Fr> + CurveGroup>(v1: &[G::MulBase], v2: &[G::MulBase]) -> (G::Affine, G::Affine) {
+    let mut rng = thread_rng();
+    let coeffs: Vec<Fr> = (0..v1.len()).map(|_| Fr::rand(&mut rng)).collect();
+    (G::msm_unchecked(v1, &coeffs).into_affine(), G::msm_unchecked(v2, &coeffs).into_affine())
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, std::io::Error> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, std::io::Error> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn seek_section<R: Seek>(reader: &mut R, sections: &BTreeMap<u32, (u64, u64)>, section_type: u32, len: usize) -> Result<(), Box<dyn Error>> {
+    match sections.get(&section_type) {
+        Some(&(pos, size)) if size >= len as u64 => {
+            reader.seek(SeekFrom::Start(pos))?;
+            Ok(())
+        }
+        Some(_) => return_error!(format!("Section {} of the .ptau file is too small", section_type)),
+        None => return_error!(format!("Section {} of the .ptau file is missing", section_type)),
+    }
+}
+
+fn read_points<R, T>(reader: &mut R, sections: &BTreeMap<u32, (u64, u64)>, section_type: u32, count: usize, point_size: usize,
+    parse: fn(&[u8]) -> Option<T>) -> Result<Vec<T>, Box<dyn Error>>
+where
+    R: Read + Seek,
+    T: Send,
+{
+    seek_section(reader, sections, section_type, count * point_size)?;
+    let mut buf = vec![0u8; count * point_size];
+    reader.read_exact(&mut buf)?;
+    match buf.par_chunks(point_size).map(parse).collect::<Option<Vec<T>>>() {
+        Some(points) => Ok(points),
+        None => return_error!(format!("Section {} of the .ptau file has an invalid point", section_type)),
+    }
+}
+
+// Base field elements are stored in little-endian Montgomery form, with R = 2^256
+fn parse_fq(bytes: &[u8]) -> Option<Fq> {
+    let limbs = core::array::from_fn(|i| u64::from_le_bytes(bytes[8 * i..8 * i + 8].try_into().unwrap()));
+    let r_inverse = Fq::from(2u64).pow([256]).inverse().unwrap();
+    Fq::from_bigint(BigInteger256::new(limbs)).map(|x| x * r_inverse)
+}
+
+// G1 has cofactor 1, so points on the curve are in the group
+fn parse_g1(bytes: &[u8]) -> Option<G1Affine> {
+    let p = G1Affine::new_unchecked(parse_fq(&bytes[..FQ_SIZE])?, parse_fq(&bytes[FQ_SIZE..])?);
+    p.is_on_curve().then_some(p)
+}
+
+fn parse_g2(bytes: &[u8]) -> Option<G2Affine> {
+    let x = Fq2::new(parse_fq(&bytes[..FQ_SIZE])?, parse_fq(&bytes[FQ_SIZE..2 * FQ_SIZE])?);
+    let y = Fq2::new(parse_fq(&bytes[2 * FQ_SIZE..3 * FQ_SIZE])?, parse_fq(&bytes[3 * FQ_SIZE..])?);
+    let p = G2Affine::new_unchecked(x, y);
+    (p.is_on_curve() && p.is_in_correct_subgroup_assuming_on_curve()).then_some(p)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dlog::PedersenOpening;
+    use crate::rangeproof::RangeProof;
+    use ark_crypto_primitives::snark::SNARK;
+    use ark_ec::{scalar_mul::fixed_base::FixedBase, Group};
+    use ark_groth16::Groth16;
+    use ark_relations::{lc, r1cs::{ConstraintSystemRef, SynthesisError}};
+    use ark_std::rand::{rngs::StdRng, SeedableRng};
+    use std::io::Cursor;
+
+    // Proves knowledge of a, b such that a * b = c and a + b = d, for public c and d
+    #[derive(Clone, Copy)]
+    struct TestCircuit {
+        a: Option<Fr>,
+        b: Option<Fr>,
+    }
+
+    impl ConstraintSynthesizer<Fr> for TestCircuit {
+        fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+            let a = cs.new_witness_variable(|| self.a.ok_or(SynthesisError::AssignmentMissing))?;
+            let b = cs.new_witness_variable(|| self.b.ok_or(SynthesisError::AssignmentMissing))?;
+            let c = cs.new_input_variable(|| Ok(self.a.ok_or(SynthesisError::AssignmentMissing)? * self.b.ok_or(SynthesisError::AssignmentMissing)?))?;
+            let d = cs.new_input_variable(|| Ok(self.a.ok_or(SynthesisError::AssignmentMissing)? + self.b.ok_or(SynthesisError::AssignmentMissing)?))?;
+            cs.enforce_constraint(lc!() + a, lc!() + b, lc!() + c)?;
+            cs.enforce_constraint(lc!() + a + b, lc!() + ConstraintSystem::<Fr>::one(), lc!() + d)?;
+            for _ in 0..3 {
+                cs.enforce_constraint(lc!() + b, lc!() + a, lc!() + c)?;
+            }
+            Ok(())
+        }
+    }
+
+    fn write_fq(buf: &mut Vec<u8>, x: Fq) {
+        buf.extend((x * Fq::from(2u64).pow([256])).into_bigint().to_bytes_le());
+    }
+
+    fn write_g1(buf: &mut Vec<u8>, p: G1Affine) {
+        write_fq(buf, p.x);
+        write_fq(buf, p.y);
+    }
+
+    fn write_g2(buf: &mut Vec<u8>, p: G2Affine) {
+        for x in [p.x.c0, p.x.c1, p.y.c0, p.y.c1] {
+            write_fq(buf, x);
+        }
+    }
+
+    // x G for each scalar x, with G the generator
+    fn fixed_base_mul<G: CurveGroup<ScalarField = Fr>>(scalars: &[Fr]) -> Vec<G::Affine> {
+        let window = FixedBase::get_mul_window_size(scalars.len());
+        let table = FixedBase::get_window_table(Fr::MODULUS_BIT_SIZE as usize, window, G::generator());
+        G::normalize_batch(&FixedBase::msm::<G>(Fr::MODULUS_BIT_SIZE as usize, window, &table, scalars))
+    }
+
+    // The sections of a .ptau file of power `power` with secrets tau, alpha and beta
+    fn ptau_sections(power: u32, tau: Fr, alpha: Fr, beta: Fr) -> Vec<(u32, Vec<u8>)> {
+        let n = 1usize << power;
+        let powers: Vec<Fr> = (0..2 * n - 1).map(|i| tau.pow([i as u64])).collect();
+        let mut header = vec![];
+        header.extend((FQ_SIZE as u32).to_le_bytes());
+        header.extend(Fq::MODULUS.to_bytes_le());
+        header.extend(power.to_le_bytes());
+        header.extend(power.to_le_bytes());
+        let (mut tau_g1, mut tau_g2, mut alpha_tau_g1, mut beta_tau_g1, mut beta_g2) = (vec![], vec![], vec![], vec![], vec![]);
+        let scaled = |factor: Fr| powers[..n].iter().map(|x| factor * x).collect::<Vec<_>>();
+        fixed_base_mul::<G1>(&powers).into_iter().for_each(|p| write_g1(&mut tau_g1, p));
+        fixed_base_mul::<G2>(&powers[..n]).into_iter().for_each(|p| write_g2(&mut tau_g2, p));
+        fixed_base_mul::<G1>(&scaled(alpha)).into_iter().for_each(|p| write_g1(&mut alpha_tau_g1, p));
+        fixed_base_mul::<G1>(&scaled(beta)).into_iter().for_each(|p| write_g1(&mut beta_tau_g1, p));
+        write_g2(&mut beta_g2, (G2::generator() * beta).into_affine());
+        // the contributions section is not read
+        vec![(PTAU_HEADER, header), (PTAU_TAU_G1, tau_g1), (PTAU_TAU_G2, tau_g2), (PTAU_ALPHA_TAU_G1, alpha_tau_g1),
+            (PTAU_BETA_TAU_G1, beta_tau_g1), (PTAU_BETA_G2, beta_g2), (7, vec![0u8; 4])]
+    }
+
+    fn ptau_file(sections: &[(u32, Vec<u8>)]) -> Cursor<Vec<u8>> {
+        let mut file = b"ptau".to_vec();
+        file.extend(1u32.to_le_bytes());
+        file.extend((sections.len() as u32).to_le_bytes());
+        for (section_type, data) in sections {
+            file.extend(section_type.to_le_bytes());
+            file.extend((data.len() as u64).to_le_bytes());
+            file.extend(data);
+        }
+        Cursor::new(file)
+    }
+
+    #[test]
+    fn test_read_ptau() {
+        let (tau, alpha, beta) = (Fr::from(5u64), Fr::from(7u64), Fr::from(11u64));
+        let sections = ptau_sections(3, tau, alpha, beta);
+        let powers = PowersOfTau::read_ptau(&mut ptau_file(&sections), 4).unwrap();
+        assert_eq!(powers.tau_g1.len(), 7);
+        assert_eq!(powers.tau_g1[6], (G1::generator() * tau.pow([6])).into_affine());
+        assert_eq!(powers.tau_g2[3], (G2::generator() * tau.pow([3])).into_affine());
+        assert_eq!(powers.alpha_tau_g1[0], (G1::generator() * alpha).into_affine());
+        assert_eq!(powers.beta_g2, (G2::generator() * beta).into_affine());
+
+        // The power must be large enough for the circuit
+        assert!(PowersOfTau::read_ptau(&mut ptau_file(&sections), 16).is_err());
+
+        // Inconsistent powers are rejected
+        let mut bad_sections = sections.clone();
+        let mut point = vec![];
+        write_g1(&mut point, (G1::generator() * Fr::from(3u64)).into_affine());
+        bad_sections[1].1[2 * G1_SIZE..3 * G1_SIZE].copy_from_slice(&point);
+        assert!(PowersOfTau::read_ptau(&mut ptau_file(&bad_sections), 4).is_err());
+        let mut bad_sections = sections.clone();
+        bad_sections[5] = ptau_sections(3, tau, alpha, alpha)[5].clone();
+        assert!(PowersOfTau::read_ptau(&mut ptau_file(&bad_sections), 4).is_err());
+
+        // Points that are not on the curve, and other curves
+        let mut bad_sections = sections.clone();
+        bad_sections[3].1[..FQ_SIZE].copy_from_slice(&[0u8; FQ_SIZE]);
+        assert!(PowersOfTau::read_ptau(&mut ptau_file(&bad_sections), 4).is_err());
+        let mut bad_sections = sections.clone();
+        bad_sections[0].1[4] ^= 1;
+        assert!(PowersOfTau::read_ptau(&mut ptau_file(&bad_sections), 4).is_err());
+    }
+
+    #[test]
+    fn test_ceremony() {
+        let rng = &mut StdRng::seed_from_u64(0u64);
+        let setup_circuit = TestCircuit { a: None, b: None };
+        // generate_parameters_with_qap evaluates the polynomials at the first random value of its rng
+        let tau = Fr::rand(&mut StdRng::seed_from_u64(1u64));
+        let (alpha, beta) = (Fr::rand(rng), Fr::rand(rng));
+        // The range proof parameters need more powers than the circuit
+        let (range_bits, range_degree) = (16, 100);
+        let sections = ptau_sections(7, tau, alpha, beta);
+
+        // The initial parameters are the parameters generated from the secrets, with delta = 1
+        let mut ceremony = CeremonyParams::new(setup_circuit, &mut ptau_file(&sections), range_bits, range_degree).unwrap();
+        let expected = Groth16::<ECPairing>::generate_parameters_with_qap(setup_circuit, alpha, beta, Fr::one(), Fr::one(),
+            G1::generator(), G2::generator(), &mut StdRng::seed_from_u64(1u64)).unwrap();
+        assert_eq!(ceremony.params, expected);
+        assert!(ceremony.verify(setup_circuit, &mut ptau_file(&sections)).is_err());
+
+        let hashes = vec![ceremony.contribute(rng), ceremony.contribute(rng)];
+        assert_eq!(ceremony.verify(setup_circuit, &mut ptau_file(&sections)).unwrap(), hashes);
+        let mut buf = vec![];
+        ceremony.serialize_uncompressed(&mut buf).unwrap();
+        assert_eq!(CeremonyParams::deserialize_uncompressed(buf.as_slice()).unwrap(), ceremony);
+
+        // The parameters create valid proofs
+        let circuit = TestCircuit { a: Some(Fr::from(3u64)), b: Some(Fr::from(4u64)) };
+        let proof = Groth16::<ECPairing>::prove(&ceremony.params, circuit, rng).unwrap();
+        let pvk = Groth16::<ECPairing>::process_vk(&ceremony.params.vk).unwrap();
+        assert!(Groth16::<ECPairing>::verify_with_processed_vk(&pvk, &[Fr::from(12u64), Fr::from(7u64)], &proof).unwrap());
+        assert!(!Groth16::<ECPairing>::verify_with_processed_vk(&pvk, &[Fr::from(12u64), Fr::from(8u64)], &proof).unwrap());
+
+        // The range proof parameters are the KZG parameters of tau, with alpha G1 as the hiding generator
+        let (range_pk, range_vk) = ceremony.range_powers.keys();
+        assert_eq!(range_vk.kzg_vk.beta_h, (G2::generator() * tau).into_affine());
+        assert_eq!(range_vk.kzg_vk.gamma_g, ceremony.params.vk.alpha_g1);
+        assert_eq!(range_vk.powers_of_g.len(), range_degree + 1);
+        assert_eq!(range_pk.powers.powers_of_gamma_g.len(), 4 * range_bits + 1);
+        let bases = [G1::rand(rng), G1::rand(rng)];
+        let (m, r) = (Fr::from(40_000u64), Fr::rand(rng));
+        let ped_open = PedersenOpening { bases: bases.map(|b| b.into_affine()).to_vec(), m, r, c: bases[0] * m + bases[1] * r };
+        let range_proof = RangeProof::prove_n_bits(&ped_open, 16, &range_pk.powers);
+        assert!(range_proof.verify_n_bits(&ped_open.c, &bases, 16, &range_vk));
+
+        // Range proof parameters that are not those of the powers of tau
+        let mut bad = ceremony.clone();
+        let (trapdoor_pk, _) = RangeProofPK::<ECPairing>::setup_with_degree(range_bits, range_degree);
+        bad.range_powers.powers_of_g = trapdoor_pk.powers.powers_of_g.to_vec();
+        assert!(bad.verify(setup_circuit, &mut ptau_file(&sections)).is_err());
+        let mut bad = ceremony.clone();
+        bad.range_powers.powers_of_gamma_g.swap(0, 1);
+        assert!(bad.verify(setup_circuit, &mut ptau_file(&sections)).is_err());
+
+        // A change of delta without a contribution
+        let mut bad = ceremony.clone();
+        let x = Fr::from(2u64);
+        bad.params.delta_g1 = (bad.params.delta_g1 * x).into_affine();
+        bad.params.vk.delta_g1 = bad.params.delta_g1;
+        bad.params.vk.delta_g2 = (bad.params.vk.delta_g2 * x).into_affine();
+        bad.params.h_query = scale(&bad.params.h_query, x.inverse().unwrap());
+        bad.params.l_query = scale(&bad.params.l_query, x.inverse().unwrap());
+        assert!(bad.verify(setup_circuit, &mut ptau_file(&sections)).is_err());
+        bad.contributions.last_mut().unwrap().delta_after = bad.params.delta_g1;
+        assert!(bad.verify(setup_circuit, &mut ptau_file(&sections)).is_err());
+
+        // A dropped contribution
+        let mut bad = ceremony.clone();
+        bad.contributions.remove(0);
+        assert!(bad.verify(setup_circuit, &mut ptau_file(&sections)).is_err());
+
+        // Changes of the other parameters
+        let mut bad = ceremony.clone();
+        bad.params.a_query.swap(0, 1);
+        assert!(bad.verify(setup_circuit, &mut ptau_file(&sections)).is_err());
+        let mut bad = ceremony.clone();
+        bad.params.l_query[0] = bad.params.h_query[0];
+        assert!(bad.verify(setup_circuit, &mut ptau_file(&sections)).is_err());
+
+        // Other powers of tau
+        let other_sections = ptau_sections(7, tau + Fr::one(), alpha, beta);
+        assert!(ceremony.verify(setup_circuit, &mut ptau_file(&other_sections)).is_err());
+    }
+}
@@ -1,7 +1,8 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
-use chrono::{DateTime, Datelike, Local};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate};
+use std::error::Error;
 
 // The function ymd_to_ordinal() and supporting functions are ported from 
 //    https://github.com/python/cpython/blob/54b5e4da8a4c6ae527ab238fcd6b9ba0a3ed0fc7/Lib/datetime.py#L63
@@ -16,7 +17,7 @@ fn is_leap(year: usize) -> bool {
 // Returns the number of days that came before the given year (from year 0)
 fn days_before_year(year: usize) -> usize {
     let y = year - 1;
-    y*365 + y/4 + y/100 + y/400
+    y*365 + y/4 - y/100 + y/400
 }
 // Returns the number of days in that month in that year.
 fn days_in_month(year: usize, month: usize) -> usize {
@@ -38,27 +39,163 @@ fn ymd_to_ordinal(year: usize, month: usize, day: usize) -> usize {
     let dim = days_in_month(year, month);
     assert!(1 <= day && day <= dim, "day must be in 1..{}", dim);
     days_before_year(year) + days_before_month(year, month) + day
-}  
+}
 
+// The circuit looks up the days before the year in a table, see DaysBeforeYear in date.circom
+const MIN_DATE_YEAR: usize = 1900;
+const MAX_DATE_YEAR: usize = 2030;
 
+/// The daystamp of an ISO 8601 calendar date "YYYY-MM-DD", as computed by the RevealClaimDate circuit template
+pub fn date_to_daystamp(date: &str) -> Result<usize, Box<dyn Error>> {
+    // The circuit expects exactly 10 characters, e.g., it does not accept "1990-5-17"
+    let parsed = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok().filter(|_| date.len() == 10)
+        .ok_or(format!("invalid date {}, expected YYYY-MM-DD", date))?;
+    let year = parsed.year() as usize;
+    if !(MIN_DATE_YEAR..=MAX_DATE_YEAR).contains(&year) {
+        return Err(format!("date {} is not supported, the year must be between {} and {}", date, MIN_DATE_YEAR, MAX_DATE_YEAR).into());
+    }
+    Ok(ymd_to_ordinal(year, parsed.month() as usize, parsed.day() as usize))
+}
 
+/// The ISO 8601 calendar date "YYYY-MM-DD" of a daystamp
+pub fn daystamp_to_date(daystamp: u64) -> Option<String> {
+    let date = NaiveDate::from_num_days_from_ce_opt(i32::try_from(daystamp).ok()?)?;
+    Some(date.format("%Y-%m-%d").to_string())
+}
 
-pub fn days_to_be_age(age: u64) -> usize {
+/// Parse the timezone used to evaluate age predicates: "UTC" (or "Z"), or a fixed offset from UTC such as "+09:00" or "-05:00"
+pub fn parse_timezone(tz: &str) -> Result<FixedOffset, Box<dyn Error>> {
+    if tz == "UTC" || tz == "Z" {
+        return Ok(FixedOffset::east_opt(0).unwrap());
+    }
+    let (sign, hhmm) = if let Some(rest) = tz.strip_prefix('+') {
+        (1, rest)
+    } else if let Some(rest) = tz.strip_prefix('-') {
+        (-1, rest)
+    } else {
+        return Err(format!("invalid timezone {}, expected UTC or +HH:MM/-HH:MM", tz).into());
+    };
+    let (hours, minutes) = hhmm.split_once(':').ok_or(format!("invalid timezone {}, expected UTC or +HH:MM/-HH:MM", tz))?;
+    let (hours, minutes) : (i32, i32) = (hours.parse()?, minutes.parse()?);
+    if hours > 23 || minutes > 59 {
+        return Err(format!("invalid timezone {}", tz).into());
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or(format!("invalid timezone {}", tz).into())
+}
+
+/// The daystamp (days since January 1, year 0000, as computed by the Daystamp circuit template) of the calendar date
+/// at `unix_seconds` in timezone `tz`
+pub fn daystamp_at(unix_seconds: u64, tz: &FixedOffset) -> usize {
+    let date = DateTime::from_timestamp(unix_seconds as i64, 0).expect("timestamp out of range").with_timezone(tz).date_naive();
+    ymd_to_ordinal(date.year() as usize, date.month() as usize, date.day() as usize)
+}
 
-    let local: DateTime<Local> = Local::now();
-    let today = local.date_naive();
-    let year = today.year() as usize;
+/// The daystamp of the latest birth date of someone who is at least `age` years old at `unix_seconds`, in timezone `tz`.
+/// Prover and verifier both use the time of the show proof, so they compute the same threshold.
+/// Someone born on February 29 turns a year older on March 1 in non-leap years.
+pub fn age_threshold_daystamp(age: u64, unix_seconds: u64, tz: &FixedOffset) -> usize {
+    let today = DateTime::from_timestamp(unix_seconds as i64, 0).expect("timestamp out of range").with_timezone(tz).date_naive();
+    let year = today.year() as usize - age as usize;
     let month = today.month() as usize;
     let mut day = today.day() as usize;
-
-    let today_stamp = ymd_to_ordinal(year, month, day);
-    if month == 2 && day == 29 {
+    if month == 2 && day == 29 && !is_leap(year) {
         day = 28;
     }
-    let past_stamp = ymd_to_ordinal(year - age as usize, month, day);
+    ymd_to_ordinal(year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    fn ds(y: i32, m: u32, d: u32) -> usize {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap().num_days_from_ce() as usize
+    }
+
+    fn unix(y: i32, m: u32, d: u32, h: u32, min: u32) -> u64 {
+        Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap().timestamp() as u64
+    }
+
+    fn is_of_age(birth: usize, age: u64, unix_seconds: u64, tz: &FixedOffset) -> bool {
+        birth <= age_threshold_daystamp(age, unix_seconds, tz)
+    }
+
+    #[test]
+    fn test_daystamp_matches_circuit() {
+        // The circuit precomputes days_before_year(1900) = 693595
+        assert_eq!(ymd_to_ordinal(1900, 1, 1), 693596);
+        assert_eq!(ds(1900, 1, 1), 693596);
+        let utc = parse_timezone("UTC").unwrap();
+        assert_eq!(daystamp_at(0, &utc), ds(1970, 1, 1));
+    }
+
+    #[test]
+    fn test_age_threshold() {
+        let utc = parse_timezone("UTC").unwrap();
+        let now = unix(2026, 10, 17, 12, 0);
+        assert_eq!(age_threshold_daystamp(18, now, &utc), ds(2008, 10, 17));
+        assert!(is_of_age(ds(2008, 10, 17), 18, now, &utc));
+        assert!(!is_of_age(ds(2008, 10, 18), 18, now, &utc));
+    }
+
+    #[test]
+    fn test_leap_day() {
+        let utc = parse_timezone("UTC").unwrap();
+        let born = ds(2008, 2, 29);
+
+        // In a non-leap year, someone born on Feb 29 is of age from March 1
+        assert!(!is_of_age(born, 18, unix(2026, 2, 28, 12, 0), &utc));
+        assert!(is_of_age(born, 18, unix(2026, 3, 1, 0, 0), &utc));
+
+        // On Feb 29, the threshold is Feb 29 if it exists in the birth year, Feb 28 otherwise
+        let feb29 = unix(2028, 2, 29, 12, 0);
+        assert_eq!(age_threshold_daystamp(20, feb29, &utc), ds(2008, 2, 29));
+        assert!(is_of_age(born, 20, feb29, &utc));
+        assert_eq!(age_threshold_daystamp(18, feb29, &utc), ds(2010, 2, 28));
+        assert!(is_of_age(ds(2010, 2, 28), 18, feb29, &utc));
+        assert!(!is_of_age(ds(2010, 3, 1), 18, feb29, &utc));
+    }
 
-    assert!(today_stamp > past_stamp);
-    println!("To be {} years old, you must be {} days old", age, today_stamp - past_stamp);
+    #[test]
+    fn test_timezone_boundary() {
+        let utc = parse_timezone("UTC").unwrap();
+        let tokyo = parse_timezone("+09:00").unwrap();
+        let new_york = parse_timezone("-05:00").unwrap();
+        let born = ds(2008, 10, 17);
 
-    today_stamp - past_stamp
-}
\ No newline at end of file
+        // 2026-10-16 20:00 UTC is already 2026-10-17 in Tokyo, but still 2026-10-16 in UTC and New York
+        let t = unix(2026, 10, 16, 20, 0);
+        assert!(is_of_age(born, 18, t, &tokyo));
+        assert!(!is_of_age(born, 18, t, &utc));
+        assert!(!is_of_age(born, 18, t, &new_york));
+
+        // 2026-10-17 02:00 UTC is still 2026-10-16 in New York
+        let t = unix(2026, 10, 17, 2, 0);
+        assert!(is_of_age(born, 18, t, &utc));
+        assert!(!is_of_age(born, 18, t, &new_york));
+
+        // The threshold only depends on the proof time and the timezone, not on the local clock of either party
+        assert_eq!(age_threshold_daystamp(18, t, &utc), age_threshold_daystamp(18, t, &parse_timezone("Z").unwrap()));
+    }
+
+    #[test]
+    fn test_date_to_daystamp() {
+        assert_eq!(date_to_daystamp("1990-05-17").unwrap(), ds(1990, 5, 17));
+        assert_eq!(daystamp_to_date(ds(1990, 5, 17) as u64).unwrap(), "1990-05-17");
+        assert_eq!(date_to_daystamp("2000-02-29").unwrap(), ds(2000, 2, 29));
+        assert!(date_to_daystamp("1990-5-17").is_err());
+        assert!(date_to_daystamp("1899-12-31").is_err());
+        assert!(date_to_daystamp("2031-01-01").is_err());
+        assert!(date_to_daystamp("17/05/1990").is_err());
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(parse_timezone("+05:30").unwrap().local_minus_utc(), 5 * 3600 + 30 * 60);
+        assert_eq!(parse_timezone("-08:00").unwrap().local_minus_utc(), -8 * 3600);
+        assert!(parse_timezone("PST").is_err());
+        assert!(parse_timezone("+25:00").is_err());
+        assert!(parse_timezone("+0500").is_err());
+    }
+}
@@ -13,6 +13,9 @@ use merlin::Transcript;
 
 use crate::utils::add_to_transcript;
 
+/// A link ((a, i), (b, j)) between two statements of a proof: scalar i of statement a equals scalar j of statement b
+pub type Link = ((usize, usize), (usize, usize));
+
 #[derive(Clone, Debug, Default, CanonicalSerialize, CanonicalDeserialize)]
 pub struct DLogPoK<G: Group> {
     pub c: G::ScalarField,
@@ -43,6 +46,24 @@ impl<G: Group> DLogPoK<G> {
         scalars: &[Vec<G::ScalarField>],
         eq_pos: Option<Vec<(usize, usize)>>,
     ) -> Self
+    where
+        G: CurveGroup + VariableBaseMSM,
+    {
+        if eq_pos.is_some() {
+            assert!(y.len() == 2);
+        }
+        Self::prove_linked(context, y, bases, scalars, &eq_pos_links(eq_pos))
+    }
+
+    /// Like `prove`, for any number of statements: for each ((a,i), (b,j)) in `links`, the proof ensures
+    /// that scalars[a][i] == scalars[b][j]
+    pub fn prove_linked(
+        context: Option<&[u8]>,
+        y: &[G],
+        bases: &[Vec<G>],
+        scalars: &[Vec<G::ScalarField>],
+        links: &[Link],
+    ) -> Self
     where
         G: CurveGroup + VariableBaseMSM,
     {
@@ -66,12 +87,8 @@ impl<G: Group> DLogPoK<G> {
             r.push(ri);
         }
 
-        if eq_pos.is_some() {
-            assert!(y.len() == 2);
-
-            for (i,j) in eq_pos.unwrap().iter() {
-                r[1][*j] = r[0][*i];
-            }
+        for ((a, i), (b, j)) in links {
+            r[*b][*j] = r[*a][*i];
         }
 
         for i in 0..y.len() {
@@ -123,6 +140,23 @@ impl<G: Group> DLogPoK<G> {
     ) -> bool
     where
         G: CurveGroup + VariableBaseMSM,    
+    {
+        if eq_pos.is_some() {
+            assert!(y.len() == 2);
+        }
+        self.verify_linked(context, bases, y, &eq_pos_links(eq_pos))
+    }
+
+    /// Verify a proof created with `prove_linked`
+    pub fn verify_linked(
+        &self,
+        context: Option<&[u8]>,
+        bases: &[Vec<G>],
+        y: &[G],
+        links: &[Link],
+    ) -> bool
+    where
+        G: CurveGroup + VariableBaseMSM,
     {
         // compute the challenge
         // serialize and hash the bases, k and y
@@ -152,16 +186,12 @@ impl<G: Group> DLogPoK<G> {
             add_to_transcript(&mut ts, b"y", &y[i]);
         }
 
-        if eq_pos.is_some() {
-            assert!(y.len() == 2);
-
-            for (i,j) in eq_pos.unwrap().iter() {
-                if self.s[0][*i] != self.s[1][*j] {
-                    println!("DLogPoK verification failed: eq_pos mismatch");
-                    return false;
-                }
+        for ((a, i), (b, j)) in links {
+            if self.s[*a][*i] != self.s[*b][*j] {
+                println!("DLogPoK verification failed: eq_pos mismatch");
+                return false;
             }
-        }        
+        }
 
         // get the challenge
         let mut c_bytes = [0u8; 31];
@@ -210,6 +240,11 @@ impl<G: Group> DLogPoK<G> {
     }
 }
 
+// The eq_pos pairs of a two-statement proof, as links between statements 0 and 1
+fn eq_pos_links(eq_pos: Option<Vec<(usize, usize)>>) -> Vec<Link> {
+    eq_pos.unwrap_or_default().into_iter().map(|(i, j)| ((0, i), (1, j))).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -314,6 +349,28 @@ mod tests {
         
     }    
 
+    #[test]
+    fn test_dleq_linked() {
+        let rng = &mut test_rng();
+        let bases : Vec<Vec<G1>> = (0..3).map(|_| (0..3).map(|_| G1::rand(rng)).collect()).collect();
+        let x = F::rand(rng);
+        let t = F::rand(rng);
+        let scalars = vec![vec![x, F::rand(rng), F::rand(rng)], vec![F::rand(rng), x, t], vec![t, F::rand(rng), F::rand(rng)]];
+        let y : Vec<G1> = (0..3).map(|i| (0..3).map(|j| bases[i][j] * scalars[i][j]).sum()).collect();
+        let links = [((0, 0), (1, 1)), ((1, 2), (2, 0))];
+
+        let pok = DLogPoK::<G1>::prove_linked(None, &y, &bases, &scalars, &links);
+        assert!(pok.verify_linked(None, &bases, &y, &links));
+        assert!(!pok.verify_linked(None, &bases, &y, &[((0, 0), (2, 0))]));
+
+        // Proofs that the linked scalars are equal fail when they are not
+        let mut scalars2 = scalars.clone();
+        scalars2[2][0] = F::rand(rng);
+        let y2 : Vec<G1> = (0..3).map(|i| (0..3).map(|j| bases[i][j] * scalars2[i][j]).sum()).collect();
+        let pok = DLogPoK::<G1>::prove_linked(None, &y2, &bases, &scalars2, &links);
+        assert!(!pok.verify_linked(None, &bases, &y2, &links));
+    }
+
     fn run_dleq_test(bases1 : &Vec<G1A>, bases2 : &Vec<G1A>, scalars1: &Vec<F>, scalars2:  &Vec<F>, eq_pos: &[(usize, usize)]) -> bool
     {
         let y1 = msm_select(bases1, scalars1);
@@ -0,0 +1,133 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// Measuring sample JWTs and SD-JWTs against a config, for `crescent estimate`. The circuit size estimates and the
+// fit report are in the crescent-config crate, shared with mdl-tools, which measures mDLs.
+
+use std::error::Error;
+
+pub use crescent_config::estimate::*;
+
+use crate::config::{hash_block_len, CredentialConfig};
+use crate::prep_inputs::{base_64_decoded_header_padding, check_prover_inputs, claim_value, find_disclosure_value_interval,
+    find_path_intervals, sha2_padding, to_utf8_integers};
+use crate::sd_jwt::SdJwtToken;
+
+/// Measure a JWT or SD-JWT, and check it as prepare_prover_inputs does, without verifying its signature
+pub fn measure_jwt(config: &CredentialConfig, name: &str, token_str: &str) -> CredentialSizes {
+    let mut sizes = CredentialSizes { name: name.to_string(), ..Default::default() };
+    if let Err(e) = measure_jwt_internal(config, token_str.trim(), &mut sizes) {
+        sizes.error = Some(e.to_string());
+        return sizes;
+    }
+    sizes.error = check_prover_inputs(config, token_str.trim()).err().map(|e| e.to_string());
+    sizes
+}
+
+fn measure_jwt_internal(config: &CredentialConfig, token_str: &str, sizes: &mut CredentialSizes) -> Result<(), Box<dyn Error>> {
+    let sd_jwt = if config.credtype == "sd-jwt" { Some(SdJwtToken::parse(token_str)?) } else { None };
+    let token_str = sd_jwt.as_ref().map(|t| t.issuer_jwt.as_str()).unwrap_or(token_str);
+    let mut parts = token_str.split('.');
+    let header_b64 = parts.next().ok_or("Missing JWT header")?;
+    let claims_b64 = parts.next().ok_or("Missing JWT claims")?;
+    let signed_data = format!("{}.{}", header_b64, claims_b64);
+    sizes.cred_len = Some(sha2_padding(&to_utf8_integers(&signed_data), hash_block_len(&config.alg)).len());
+
+    let header_and_payload = format!("{}{}{}", String::from_utf8(base64_url::decode(header_b64)?)?,
+        base_64_decoded_header_padding(header_b64.len())?, String::from_utf8(base64_url::decode(claims_b64)?)?);
+    let claims: serde_json::Value = match &sd_jwt {
+        Some(t) => t.claims()?,
+        None => serde_json::from_slice(&base64_url::decode(claims_b64)?)?,
+    };
+    for (name, entry) in &config.claims {
+        if claim_value(&claims, name).is_null() {
+            continue;
+        }
+        if entry.sd {
+            let Some(disclosure) = sd_jwt.as_ref().and_then(|t| t.disclosure(name)) else { continue };
+            sizes.disclosure_lens.insert(name.clone(), sha2_padding(&to_utf8_integers(&disclosure.encoded), 64).len());
+            if entry.max_claim_byte_len.is_some() {
+                let disclosure_json = disclosure.json()?;
+                let (_, value_l, value_r) = find_disclosure_value_interval(&disclosure_json, &format!("\"{}\"", name), entry.claim_type)?;
+                sizes.claim_lens.insert(name.clone(), value_r - value_l);
+            }
+        } else if entry.max_claim_byte_len.is_some() {
+            let (_, value_l, value_r) = find_path_intervals(&header_and_payload, name, entry.claim_type)?.pop().ok_or("Invalid claim path")?;
+            sizes.claim_lens.insert(name.clone(), value_r - value_l);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+    use jwt_simple::prelude::*;
+    use serde_json::{json, Value};
+
+    const CONFIG: &str = r#"{"alg": "ES256", "max_cred_len": 1024,
+        "exp": {"type": "number"},
+        "email": {"type": "string", "reveal_digest": true, "max_claim_byte_len": 64},
+        "iss": {"type": "string", "reveal_bytes": true, "max_claim_byte_len": 62}}"#;
+
+    fn sign(key_pair: &ES256KeyPair, claims: Value) -> String {
+        key_pair.sign(Claims::with_custom_claims(claims, Duration::from_hours(1))).unwrap()
+    }
+
+    #[test]
+    fn test_fit_report() {
+        let config = parse_config(CONFIG).unwrap();
+        let key_pair = ES256KeyPair::generate();
+        let small = sign(&key_pair, json!({"email": "alice@example.com", "iss": "https://example.com"}));
+        let escaped = sign(&key_pair, json!({"email": "bob\"@example.com", "iss": "https://login.example.com/tenant"}));
+        let large = sign(&key_pair, json!({"email": "carol@example.com", "iss": "https://example.com", "groups": "x".repeat(1000)}));
+        let long_iss = sign(&key_pair, json!({"email": "dave@example.com", "iss": format!("https://{}.example.com", "x".repeat(60))}));
+
+        let sizes = measure_jwt(&config, "small.jwt", &small);
+        assert_eq!(sizes.error, None);
+        assert_eq!(sizes.claim_lens["email"], "\"alice@example.com\"".len());
+        assert_eq!(sizes.claim_lens["iss"], "\"https://example.com\"".len());
+        assert!(sizes.cred_len.unwrap() <= config.max_cred_len && sizes.cred_len.unwrap().is_multiple_of(64));
+        // Strings are measured as written in the payload, with their escape sequences
+        assert_eq!(measure_jwt(&config, "escaped.jwt", &escaped).claim_lens["email"], "\"bob\\\"@example.com\"".len());
+
+        let large_sizes = measure_jwt(&config, "large.jwt", &large);
+        assert!(large_sizes.cred_len.unwrap() > config.max_cred_len);
+        assert!(large_sizes.error.as_ref().unwrap().contains("JWT too large"));
+        let long_iss_sizes = measure_jwt(&config, "long_iss.jwt", &long_iss);
+        assert!(long_iss_sizes.error.as_ref().unwrap().contains("Claim iss too large"));
+        assert!(measure_jwt(&config, "invalid.jwt", "not a token").error.is_some());
+
+        let report = FitReport::new(&config, vec![sizes, large_sizes.clone(), long_iss_sizes.clone()]);
+        assert_eq!(report.failures().map(|(name, _)| name).collect::<Vec<_>>(), vec!["large.jwt", "long_iss.jwt"]);
+        let recommended = report.recommended_config();
+        assert_eq!(recommended.max_cred_len, large_sizes.cred_len.unwrap());
+        assert_eq!(recommended.claim("iss").unwrap().max_claim_byte_len, Some(93));
+        assert_eq!(recommended.claim("email").unwrap().max_claim_byte_len, Some("\"carol@example.com\"".len()));
+        assert!(recommended.claim("exp").unwrap().max_claim_byte_len.is_none());
+        // All the measured tokens fit the recommended config
+        for token in [&small, &large, &long_iss] {
+            assert_eq!(measure_jwt(&recommended, "token.jwt", token).error, None);
+        }
+        let report = report.to_string();
+        assert!(report.contains("large.jwt") && report.contains("Recommended config values"));
+    }
+
+    #[test]
+    fn test_measure_sd_jwt() {
+        let config = parse_config(r#"{"alg": "ES256", "credtype": "sd-jwt", "max_cred_len": 1024,
+            "given_name": {"type": "string", "sd": true, "reveal_digest": true, "max_claim_byte_len": 31}}"#).unwrap();
+        let key_pair = ES256KeyPair::generate();
+        let disclosure = base64_url::encode(&json!(["salt", "given_name", "Alice"]).to_string());
+        let digest = crate::sd_jwt::Disclosure::parse(&disclosure).unwrap().digest();
+        let token = format!("{}~{}~", sign(&key_pair, json!({"_sd": [digest], "_sd_alg": "sha-256"})), disclosure);
+
+        let sizes = measure_jwt(&config, "sd.jwt", &token);
+        assert_eq!(sizes.error, None);
+        assert_eq!(sizes.claim_lens["given_name"], "\"Alice\"".len());
+        assert_eq!(sizes.disclosure_lens["given_name"], 64);
+        let recommended = FitReport::new(&config, vec![sizes]).recommended_config();
+        assert_eq!(recommended.claim("given_name").unwrap().max_disclosure_len, Some(64));
+    }
+}
@@ -0,0 +1,346 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use ark_ff::PrimeField;
+use num_bigint::BigUint;
+use serde_json::{json, Value};
+use std::error::Error;
+
+use crate::daystamp::daystamp_to_date;
+use crate::config::{parse_config, ClaimType, CredentialConfig};
+use crate::prep_inputs::{claim_signal_name, pack_string_to_scalar, unpack_int_to_string_unquoted, unpack_ints_to_string_unquoted};
+use crate::structs::{IOLocations, PublicIOType};
+use crate::{ClaimValue, CrescentFr, ProofSpecInternal, VerifyError};
+
+/// Describes how a credential format (JWT, mDL, ...) maps onto the Groth16 circuit outputs,
+/// so that show proofs can be created and verified by a single code path.
+pub trait CredentialFormat: Sync {
+    /// The credential type, as it appears in the `credtype` entry of config.json
+    fn credtype(&self) -> &'static str;
+
+    /// The claim holding the expiration date. A range proof that it is in the future is part of every show proof.
+    fn expiry_claim(&self) -> &'static str;
+
+    /// The claim type (from config.json) of claims that are revealed as integers. Claims of other types are
+    /// revealed as packed strings.
+    fn integer_claim_type(&self) -> &'static str;
+
+    /// The claim holding the holder's birth date, if the format and the config support age predicates
+    fn birth_date_claim(&self, config: &CredentialConfig) -> Option<&'static str>;
+
+    /// Encode the preimage of a hashed claim, taken from the prover's aux data, in the form the
+    /// verifier hashes to recompute the digest output by the circuit
+    fn encode_preimage(&self, aux_value: &Value) -> Value;
+
+    /// Encode a string claim value as the circuit outputs it for claims revealed as a field element. Strings are
+    /// packed without their quotes in all formats (see `pack_string_to_int`), so they can be linked across formats.
+    fn encode_string_claim(&self, value: &str) -> Result<CrescentFr, Box<dyn Error>> {
+        pack_string_to_scalar(value)
+    }
+
+    /// The claims that are committed (rather than hidden or revealed) during a show
+    fn committed_claims(&self, proof_spec: &ProofSpecInternal) -> Vec<String> {
+        let mut claims = vec![self.expiry_claim().to_string()];
+        if proof_spec.device_bound {
+            claims.push("device_key_0".to_string());
+            claims.push("device_key_1".to_string());
+        }
+        for (attr, _) in &proof_spec.range_over_year {
+            claims.push(attr.clone());
+        }
+        for predicate in &proof_spec.range_predicates {
+            claims.push(predicate.attribute.clone());
+        }
+        for predicate in &proof_spec.set_predicates {
+            claims.push(predicate.attribute.clone());
+        }
+        if let Some(pseudonym) = &proof_spec.pseudonym {
+            claims.push(pseudonym.attribute.clone());
+        }
+        if let Some(attr) = &proof_spec.revocation_attribute {
+            claims.push(attr.clone());
+        }
+        if let Some(status_list) = &proof_spec.status_list {
+            claims.push(status_list.attribute.clone());
+        }
+        claims.extend(proof_spec.committed.iter().cloned());
+        claims
+    }
+
+    /// Assign a type to each of the public inputs of the Groth16 proof
+    fn io_types(&self, proof_spec: &ProofSpecInternal, io_locations: &IOLocations, inputs_len: usize) -> Result<Vec<PublicIOType>, VerifyError> {
+        let mut io_types = vec![PublicIOType::Hidden; inputs_len];
+
+        for claim in self.committed_claims(proof_spec) {
+            let io_loc = get_io_location(io_locations, &format!("{}_value", claim))?;
+            io_types[io_loc - 1] = PublicIOType::Committed;
+        }
+
+        // The issuer public key is revealed, unless the proof spec asks to hide it in a set of issuers
+        let key_type = if proof_spec.issuer_set.is_some() { PublicIOType::Committed } else { PublicIOType::Revealed };
+        for i in io_locations.get_public_key_indices() {
+            io_types[i] = key_type.clone();
+        }
+
+        // A committed attribute cannot also be revealed: its commitment would be missing from the show proof
+        let mut reveal = |io_loc: usize, attr: &str| {
+            if io_types[io_loc - 1] == PublicIOType::Committed {
+                return Err(VerifyError::InvalidProofSpec(format!("attribute {} cannot be both revealed and committed", attr)));
+            }
+            io_types[io_loc - 1] = PublicIOType::Revealed;
+            Ok(())
+        };
+        for attr in &proof_spec.revealed {
+            for io_loc in get_io_locations(io_locations, &format!("{}_value", attr))? {
+                reveal(io_loc, attr)?;
+            }
+        }
+        for attr in &proof_spec.hashed {
+            reveal(get_io_location(io_locations, &format!("{}_digest", attr))?, attr)?;
+        }
+        for io_loc in self.presence_io_locations(proof_spec, io_locations)? {
+            io_types[io_loc - 1] = PublicIOType::Revealed;
+        }
+
+        Ok(io_types)
+    }
+
+    /// The optional claims the proof spec refers to, whose presence bit is revealed
+    fn presence_claims(&self, proof_spec: &ProofSpecInternal) -> Vec<String> {
+        let mut claims = proof_spec.revealed.clone();
+        claims.extend(self.committed_claims(proof_spec));
+        claims.retain(|claim| proof_spec.optional.contains(claim));
+        claims.sort_by_key(|claim| proof_spec.optional.iter().position(|c| c == claim));
+        claims.dedup();
+        claims
+    }
+
+    /// The positions of the presence bits of `presence_claims`. The bits follow the claim values in the public inputs
+    /// of the circuit, in the order of the config.
+    fn presence_io_locations(&self, proof_spec: &ProofSpecInternal, io_locations: &IOLocations) -> Result<Vec<usize>, VerifyError> {
+        self.presence_claims(proof_spec).iter().map(|claim| get_io_location(io_locations, &format!("{}_present", claim))).collect()
+    }
+
+    /// Convert a revealed field element back to the claim value
+    fn decode_claim(&self, claim_name: &str, claim_type: &str, value: &CrescentFr) -> Result<ClaimValue, VerifyError> {
+        let value = value.into_bigint();
+        if claim_type == self.integer_claim_type() {
+            Ok(ClaimValue::Integer(value.into()))
+        } else if claim_type == "boolean" {
+            // Booleans are revealed as 0 or 1
+            match u64::try_from(BigUint::from(value)) {
+                Ok(0) => Ok(ClaimValue::Boolean(false)),
+                Ok(1) => Ok(ClaimValue::Boolean(true)),
+                _ => Err(VerifyError::ClaimDecoding(claim_name.to_string())),
+            }
+        } else if claim_type == "date" {
+            // Dates are revealed as daystamps
+            let date = u64::try_from(BigUint::from(value)).ok().and_then(daystamp_to_date);
+            Ok(ClaimValue::String(date.ok_or(VerifyError::ClaimDecoding(claim_name.to_string()))?))
+        } else {
+            let s = unpack_int_to_string_unquoted(&value).map_err(|_| VerifyError::ClaimDecoding(claim_name.to_string()))?;
+            Ok(ClaimValue::String(s))
+        }
+    }
+
+    /// Convert a string claim revealed as several field elements (see `reveal_bytes` in config.json) back to the claim value
+    fn decode_claim_fields(&self, claim_name: &str, values: &[CrescentFr]) -> Result<ClaimValue, VerifyError> {
+        let values = values.iter().map(|v| v.into_bigint()).collect::<Vec<_>>();
+        let s = unpack_ints_to_string_unquoted(&values).map_err(|_| VerifyError::ClaimDecoding(claim_name.to_string()))?;
+        Ok(ClaimValue::String(s))
+    }
+}
+
+/// JSON Web Tokens
+pub struct Jwt;
+
+impl CredentialFormat for Jwt {
+    fn credtype(&self) -> &'static str {
+        "jwt"
+    }
+    fn expiry_claim(&self) -> &'static str {
+        "exp"
+    }
+    fn integer_claim_type(&self) -> &'static str {
+        "number"
+    }
+    fn birth_date_claim(&self, config: &CredentialConfig) -> Option<&'static str> {
+        // The OpenID Connect standard claim, if the config declares it as a date (see RevealClaimDate)
+        let is_date = config.claim("birthdate").map(|entry| entry.claim_type) == Some(ClaimType::Date);
+        is_date.then_some("birthdate")
+    }
+    fn encode_preimage(&self, aux_value: &Value) -> Value {
+        // The circuit hashes the JSON encoding of the claim value, with strings decoded (see DecodeJSONString)
+        // but still quoted
+        match aux_value {
+            Value::String(s) => json!(format!("\"{}\"", s)),
+            _ => json!(aux_value.to_string()),
+        }
+    }
+}
+
+/// SD-JWTs (RFC 9901). Disclosed claims are encoded as in JWTs.
+pub struct SdJwt;
+
+impl CredentialFormat for SdJwt {
+    fn credtype(&self) -> &'static str {
+        "sd-jwt"
+    }
+    fn expiry_claim(&self) -> &'static str {
+        Jwt.expiry_claim()
+    }
+    fn integer_claim_type(&self) -> &'static str {
+        Jwt.integer_claim_type()
+    }
+    fn birth_date_claim(&self, config: &CredentialConfig) -> Option<&'static str> {
+        Jwt.birth_date_claim(config)
+    }
+    fn encode_preimage(&self, aux_value: &Value) -> Value {
+        Jwt.encode_preimage(aux_value)
+    }
+}
+
+/// ISO 18013-5 mobile driver's licenses
+pub struct Mdl;
+
+impl CredentialFormat for Mdl {
+    fn credtype(&self) -> &'static str {
+        "mdl"
+    }
+    fn expiry_claim(&self) -> &'static str {
+        "valid_until"
+    }
+    fn integer_claim_type(&self) -> &'static str {
+        "integer"
+    }
+    fn birth_date_claim(&self, _config: &CredentialConfig) -> Option<&'static str> {
+        Some("birth_date")
+    }
+    fn encode_preimage(&self, aux_value: &Value) -> Value {
+        aux_value.clone()
+    }
+}
+
+/// Look up the credential format from the credential type string (e.g., `ClientState::credtype`)
+pub fn credential_format(credtype: &str) -> Result<&'static dyn CredentialFormat, VerifyError> {
+    match credtype {
+        "jwt" => Ok(&Jwt),
+        "sd-jwt" => Ok(&SdJwt),
+        "mdl" => Ok(&Mdl),
+        _ => Err(VerifyError::UnsupportedCredentialType(credtype.to_string())),
+    }
+}
+
+/// Look up the credential format from the `credtype` entry of the config (JWT if not present)
+pub fn credential_format_from_config(config_str: &str) -> Result<&'static dyn CredentialFormat, Box<dyn Error>> {
+    let config = parse_config(config_str)?;
+    Ok(credential_format(&config.credtype)?)
+}
+
+/// Index of the commitment to the public input at `io_loc` in the list of committed inputs of a show proof.
+/// Commitments are created in the order of the inputs, see `ClientState::show_groth16`.
+pub fn commitment_index(io_types: &[PublicIOType], io_loc: usize) -> Result<usize, VerifyError> {
+    if io_loc == 0 || io_types.get(io_loc - 1) != Some(&PublicIOType::Committed) {
+        return Err(VerifyError::InvalidProofSpec(format!("public input {} is not committed", io_loc)));
+    }
+    Ok(io_types[..io_loc - 1].iter().filter(|t| **t == PublicIOType::Committed).count())
+}
+
+/// The location of a public input, labeled with the attribute name and the suffix of the signal, e.g., "exp_value".
+/// Nested attributes are labeled with their path, e.g., "/address/country_value" for the signal address_country_value.
+pub(crate) fn get_io_location(io_locations: &IOLocations, label: &str) -> Result<usize, VerifyError> {
+    io_locations.get_io_location(&claim_signal_name(label)).map_err(|_| VerifyError::UnknownAttribute(label.to_string()))
+}
+
+/// The locations of a public input that is a single field element, or an array of field elements (e.g., the value of
+/// a claim revealed with `reveal_bytes`, with signal names "name_value[0]", "name_value[1]", ...)
+pub(crate) fn get_io_locations(io_locations: &IOLocations, label: &str) -> Result<Vec<usize>, VerifyError> {
+    let signal_name = claim_signal_name(label);
+    if let Ok(location) = io_locations.get_io_location(&signal_name) {
+        return Ok(vec![location]);
+    }
+    let locations = (0..).map_while(|i| io_locations.get_io_location(&format!("{}[{}]", signal_name, i)).ok()).collect::<Vec<_>>();
+    if locations.is_empty() {
+        return Err(VerifyError::UnknownAttribute(label.to_string()));
+    }
+    Ok(locations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commitment_index() {
+        let io_types = vec![PublicIOType::Revealed, PublicIOType::Committed, PublicIOType::Hidden, PublicIOType::Committed, PublicIOType::Committed];
+        assert_eq!(commitment_index(&io_types, 2).unwrap(), 0);
+        assert_eq!(commitment_index(&io_types, 4).unwrap(), 1);
+        assert_eq!(commitment_index(&io_types, 5).unwrap(), 2);
+        for io_loc in [0, 1, 3, 6] {
+            assert!(commitment_index(&io_types, io_loc).is_err());
+        }
+    }
+
+    #[test]
+    fn test_io_types() {
+        let config_str = r#"{"alg": "ES256", "exp": {"type": "number", "reveal": true, "max_claim_byte_len": 31},
+            "email": {"type": "string", "reveal": true, "max_claim_byte_len": 31}}"#;
+        let io_locations = IOLocations::new_from_str("pubkey_x[0],1\nexp_value,2\nemail_value,3");
+        let proof_spec: crate::ProofSpec = serde_json::from_value(json!({"revealed": ["email"]})).unwrap();
+        let mut proof_spec = crate::prep_inputs::create_proof_spec_internal(&proof_spec, config_str).unwrap();
+        assert_eq!(Jwt.io_types(&proof_spec, &io_locations, 3).unwrap(), vec![PublicIOType::Revealed, PublicIOType::Committed, PublicIOType::Revealed]);
+
+        // An attribute that is committed cannot be revealed, even if the proof spec was not validated
+        proof_spec.revealed.push("exp".to_string());
+        assert!(Jwt.io_types(&proof_spec, &io_locations, 3).is_err());
+    }
+
+    #[test]
+    fn test_get_io_locations() {
+        let io_locations = IOLocations::new_from_str("modulus[0],1\nexp_value,2\naddress_country_value,3\niss_value[0],4\niss_value[1],5\niss_value[2],6");
+        assert_eq!(get_io_locations(&io_locations, "exp_value").unwrap(), vec![2]);
+        assert_eq!(get_io_locations(&io_locations, "/address/country_value").unwrap(), vec![3]);
+        assert_eq!(get_io_locations(&io_locations, "iss_value").unwrap(), vec![4, 5, 6]);
+        assert!(get_io_locations(&io_locations, "aud_value").is_err());
+        assert!(get_io_location(&io_locations, "iss_value").is_err());
+    }
+
+    #[test]
+    fn test_decode_boolean_and_date() {
+        assert_eq!(Jwt.decode_claim("email_verified", "boolean", &CrescentFr::from(1u64)).unwrap(), ClaimValue::Boolean(true));
+        assert_eq!(Jwt.decode_claim("email_verified", "boolean", &CrescentFr::from(0u64)).unwrap(), ClaimValue::Boolean(false));
+        assert!(Jwt.decode_claim("email_verified", "boolean", &CrescentFr::from(2u64)).is_err());
+        let daystamp = crate::daystamp::date_to_daystamp("1990-05-17").unwrap() as u64;
+        assert_eq!(Jwt.decode_claim("birthdate", "date", &CrescentFr::from(daystamp)).unwrap(), ClaimValue::String("1990-05-17".to_string()));
+    }
+
+    #[test]
+    fn test_birth_date_claim() {
+        let config = parse_config(r#"{"alg": "RS256", "birthdate": {"type": "date", "reveal": true}}"#).unwrap();
+        assert_eq!(Jwt.birth_date_claim(&config), Some("birthdate"));
+        assert_eq!(SdJwt.birth_date_claim(&config), Some("birthdate"));
+        let config = parse_config(r#"{"alg": "RS256", "birthdate": {"type": "string", "reveal": true, "max_claim_byte_len": 31}}"#).unwrap();
+        assert_eq!(Jwt.birth_date_claim(&config), None);
+        let config = parse_config(r#"{"alg": "RS256"}"#).unwrap();
+        assert_eq!(Jwt.birth_date_claim(&config), None);
+        assert_eq!(Mdl.birth_date_claim(&config), Some("birth_date"));
+    }
+
+    #[test]
+    fn test_encode_preimage() {
+        // Strings are hashed decoded, with their quotes, as output by the circuit
+        assert_eq!(Jwt.encode_preimage(&json!("René \"R\"")), json!("\"René \"R\"\""));
+        assert_eq!(SdJwt.encode_preimage(&json!(42)), json!("42"));
+        assert_eq!(Mdl.encode_preimage(&json!("René")), json!("René"));
+    }
+
+    #[test]
+    fn test_credential_format() {
+        assert_eq!(credential_format("jwt").unwrap().expiry_claim(), "exp");
+        assert_eq!(credential_format("mdl").unwrap().expiry_claim(), "valid_until");
+        assert_eq!(credential_format("sd-jwt").unwrap().expiry_claim(), "exp");
+        assert!(credential_format("x509").is_err());
+        assert_eq!(credential_format_from_config(r#"{"alg": "RS256"}"#).unwrap().credtype(), "jwt");
+        assert_eq!(credential_format_from_config(r#"{"alg": "RS256", "credtype": "sd-jwt"}"#).unwrap().credtype(), "sd-jwt");
+    }
+}
@@ -3,7 +3,8 @@
 
 use crate::{
     dlog::{DLogPoK, PedersenOpening},
-    rangeproof::{RangeProof, RangeProofPK, RangeProofVK},
+    rangeproof::{fits_in_bits, is_valid_bit_width, RangeProof, RangeProofPK, RangeProofVK},
+    revocation::MembershipWitness,
     structs::{IOLocations, PublicIOType},
     utils::msm_select
 };
@@ -32,7 +33,8 @@ pub struct ClientState<E: Pairing> {
     input_com_randomness: Option<E::ScalarField>,
     pub committed_input_openings: Vec<PedersenOpening<E::G1>>, //TODO: make this into a hashmap
     pub credtype : String,
-    pub config_str: String
+    pub config_str: String,
+    pub revocation_witness: Option<MembershipWitness<E>>, // witness that the credential is not revoked, kept up to date by the holder
 }
 
 /// An unlinkable showing of a valid groth16 proof satisfying a particular NP relation
@@ -74,7 +76,8 @@ impl<E: Pairing> ClientState<E> {
             input_com_randomness: None,
             committed_input_openings: Vec::new(),
             credtype : "jwt".to_string(), 
-            config_str
+            config_str,
+            revocation_witness: None,
         }
     }
 
@@ -189,7 +192,7 @@ impl<E: Pairing> ClientState<E> {
     /// Prove that a certain input to the groth16 proof is in [0,2^n)
     /// Takes as input
     /// 1. label of the input
-    /// 2. n: the number of bits
+    /// 2. n: the number of bits, a power of two supported by `range_pk`
     pub fn show_range(
         &self,
         ped_open: &PedersenOpening<E::G1>,
@@ -199,9 +202,8 @@ impl<E: Pairing> ClientState<E> {
         let mut range_proof = RangeProof::default();
 
         // prove that input is in [0, 2^n)
-        assert!(n < 64);
-        let bound = <E as Pairing>::ScalarField::from(1u64 << n);
-        assert!(ped_open.m < bound);
+        assert!(is_valid_bit_width(n, range_pk.max_bits), "Unsupported range proof bit width {}", n);
+        assert!(fits_in_bits(&ped_open.m, n));
 
         #[cfg(feature = "wasm")]
         {
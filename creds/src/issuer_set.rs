@@ -0,0 +1,171 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// Proofs that the issuer of a credential is one of a set of issuers, without revealing which one.
+// The issuer public key is split into limbs l_1, ..., l_n that are committed in the Groth16 show proof, in bases that
+// differ for each limb. The holder re-commits to each limb as D_j = g^{l_j} h^{s_j}, in common bases g and h, and
+// proves that D_j and the Groth16 commitment to l_j open to the same value. A challenge z, hashed from the
+// commitments, compresses the key to K = sum z^j l_j, committed to in D = prod D_j^{z^j} = g^K h^{sum z^j s_j}.
+// A set membership proof (see `sets`) then shows that K is one of the values K_i computed in the same way from the
+// keys of the issuer set. As z is chosen after the limbs are committed, a key that differs from all the keys of the
+// set has a different K, except with negligible probability.
+
+use ark_ec::pairing::Pairing;
+use ark_ec::CurveGroup;
+use ark_ff::Field;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::UniformRand;
+use merlin::Transcript;
+use std::error::Error;
+
+use crate::dlog::{DLogPoK, Link, PedersenOpening};
+use crate::rangeproof::RangeProofVK;
+use crate::return_error;
+use crate::revocation::MembershipProof;
+use crate::sets::{set_accumulator, set_membership_witness};
+use crate::utils::{add_to_transcript, hash_to_curve_vartime};
+
+/// Proof that the issuer key committed in a show proof is one of the keys of an issuer set
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct IssuerSetProof<E: Pairing> {
+    /// Commitments to the key limbs in the common bases
+    pub limb_commitments: Vec<E::G1>,
+    /// Proof that the commitments in the show proof and in `limb_commitments` are to the same limbs
+    pub pok: DLogPoK<E::G1>,
+    /// Proof that the compressed key is in the issuer set
+    pub membership: MembershipProof<E>,
+}
+
+fn common_bases<G: CurveGroup>() -> [G; 2] {
+    [
+        hash_to_curve_vartime::<G>("Crescent issuer set base g").into(),
+        hash_to_curve_vartime::<G>("Crescent issuer set base h").into(),
+    ]
+}
+
+// The challenge z used to compress the keys
+fn challenge<E: Pairing>(context: &[u8], show_commitments: &[E::G1], limb_commitments: &[E::G1]) -> E::ScalarField {
+    let mut ts = Transcript::new(b"Crescent issuer set");
+    add_to_transcript(&mut ts, b"context string", &context);
+    for c in show_commitments.iter().chain(limb_commitments) {
+        add_to_transcript(&mut ts, b"commitment", c);
+    }
+    let mut z_bytes = [0u8; 31];
+    ts.challenge_bytes(b"z", &mut z_bytes);
+    E::ScalarField::from_random_bytes(&z_bytes).unwrap()
+}
+
+// Compute sum z^j x_j
+fn compress<F: Field>(z: F, x: &[F]) -> F {
+    x.iter().rev().fold(F::zero(), |acc, &l| acc * z + l)
+}
+
+// Compute prod c_j^{z^j}
+fn compress_commitments<G: CurveGroup>(z: G::ScalarField, c: &[G]) -> G {
+    c.iter().rev().fold(G::zero(), |acc, &c_j| acc * z + c_j)
+}
+
+// Statement j is the show proof commitment to limb j, statement n + j is the commitment to it in the common bases
+fn limb_links(n: usize) -> Vec<Link> {
+    (0..n).map(|j| ((j, 0), (n + j, 0))).collect()
+}
+
+impl<E: Pairing> IssuerSetProof<E> {
+    /// Prove that the key whose limbs are committed in `limb_openings` is one of `issuer_set` (each key given as its limbs),
+    /// using the powers of g from the range proof parameters
+    pub fn prove(context: &[u8], limb_openings: &[PedersenOpening<E::G1>], issuer_set: &[Vec<E::ScalarField>], powers_of_g: &[E::G1Affine]) -> Result<Self, Box<dyn Error>> {
+        let n = limb_openings.len();
+        if issuer_set.iter().any(|key| key.len() != n) {
+            return_error!(format!("The keys of the issuer set must have {} limbs, as the credential's issuer key", n));
+        }
+        let mut rng = ark_std::rand::thread_rng();
+        let [g, h] = common_bases::<E::G1>();
+        let s = (0..n).map(|_| E::ScalarField::rand(&mut rng)).collect::<Vec<_>>();
+        let limb_commitments = limb_openings.iter().zip(&s).map(|(o, s_j)| g * o.m + h * s_j).collect::<Vec<_>>();
+
+        let mut y = limb_openings.iter().map(|o| o.c).collect::<Vec<_>>();
+        y.extend(&limb_commitments);
+        let mut bases = limb_openings.iter().map(|o| o.bases.iter().map(|&b| b.into()).collect()).collect::<Vec<Vec<E::G1>>>();
+        bases.extend((0..n).map(|_| vec![g, h]));
+        let mut scalars = limb_openings.iter().map(|o| vec![o.m, o.r]).collect::<Vec<_>>();
+        scalars.extend(limb_openings.iter().zip(&s).map(|(o, s_j)| vec![o.m, *s_j]));
+        let pok = DLogPoK::<E::G1>::prove_linked(Some(context), &y, &bases, &scalars, &limb_links(n));
+
+        let z = challenge::<E>(context, &y[..n], &limb_commitments);
+        let key = compress(z, &limb_openings.iter().map(|o| o.m).collect::<Vec<_>>());
+        let elements = issuer_set.iter().map(|limbs| compress(z, limbs)).collect::<Vec<_>>();
+        let witness = set_membership_witness::<E>(key, &elements, powers_of_g)
+            .map_err(|_| "The credential's issuer is not in the issuer set")?;
+        let opening = PedersenOpening {
+            bases: vec![g.into_affine(), h.into_affine()],
+            m: key,
+            r: compress(z, &s),
+            c: compress_commitments(z, &limb_commitments),
+        };
+        let membership = MembershipProof::prove(context, &opening, &witness);
+
+        Ok(IssuerSetProof { limb_commitments, pok, membership })
+    }
+
+    /// Verify the proof, where `limb_commitments[j]` is the show proof commitment to limb j in `limb_bases[j]`
+    pub fn verify(&self, context: &[u8], limb_commitments: &[E::G1], limb_bases: &[[E::G1; 2]], issuer_set: &[Vec<E::ScalarField>], vk: &RangeProofVK<E>) -> bool {
+        let n = limb_commitments.len();
+        if self.limb_commitments.len() != n || limb_bases.len() != n || self.pok.s.len() != 2 * n || issuer_set.iter().any(|key| key.len() != n) {
+            println!("Issuer set proof failed to verify, wrong number of key limbs");
+            return false;
+        }
+        let [g, h] = common_bases::<E::G1>();
+        let mut y = limb_commitments.to_vec();
+        y.extend(&self.limb_commitments);
+        let mut bases = limb_bases.iter().map(|b| b.to_vec()).collect::<Vec<_>>();
+        bases.extend((0..n).map(|_| vec![g, h]));
+        if !self.pok.verify_linked(Some(context), &bases, &y, &limb_links(n)) {
+            println!("Issuer set proof failed to verify, limb commitments do not match");
+            return false;
+        }
+
+        let z = challenge::<E>(context, limb_commitments, &self.limb_commitments);
+        let elements = issuer_set.iter().map(|limbs| compress(z, limbs)).collect::<Vec<_>>();
+        let (accumulator, pk) = match set_accumulator(&elements, vk) {
+            Ok(acc) => acc,
+            Err(_) => return false,
+        };
+        self.membership.verify(context, &compress_commitments(z, &self.limb_commitments), &[g, h], &accumulator, &pk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rangeproof::RangeProofPK;
+    use crate::{CrescentFr, CrescentPairing, G1};
+
+    #[test]
+    fn test_issuer_set_proof() {
+        let rng = &mut ark_std::test_rng();
+        let (range_pk, range_vk) = RangeProofPK::<CrescentPairing>::setup_with_degree(32, 128);
+        let powers = &range_pk.powers.powers_of_g;
+        let context = b"proof spec";
+        let n = 4;
+        let limb_bases = (0..n).map(|_| [G1::rand(rng), G1::rand(rng)]).collect::<Vec<_>>();
+        let issuer_set = (0..5).map(|_| (0..n).map(|_| CrescentFr::rand(rng)).collect::<Vec<_>>()).collect::<Vec<_>>();
+
+        let commit = |key: &[CrescentFr]| key.iter().zip(&limb_bases).map(|(l, b)| DLogPoK::<G1>::pedersen_commit(l, &b.map(|x| x.into_affine()))).collect::<Vec<_>>();
+        let openings = commit(&issuer_set[2]);
+        let commitments = openings.iter().map(|o| o.c).collect::<Vec<_>>();
+        let proof = IssuerSetProof::<CrescentPairing>::prove(context, &openings, &issuer_set, powers).unwrap();
+        assert!(proof.verify(context, &commitments, &limb_bases, &issuer_set, &range_vk));
+        assert!(!proof.verify(b"other context", &commitments, &limb_bases, &issuer_set, &range_vk));
+        assert!(!proof.verify(context, &commitments, &limb_bases, &issuer_set[3..], &range_vk));
+
+        // The proof is bound to the committed key
+        let other = commit(&issuer_set[1]).iter().map(|o| o.c).collect::<Vec<_>>();
+        assert!(!proof.verify(context, &other, &limb_bases, &issuer_set, &range_vk));
+
+        // A key that is not in the set, including one that shares limbs with keys of the set
+        let mut outsider = issuer_set[0].clone();
+        outsider[1] = issuer_set[1][1];
+        assert!(IssuerSetProof::<CrescentPairing>::prove(context, &commit(&outsider), &issuer_set, powers).is_err());
+        assert!(IssuerSetProof::<CrescentPairing>::prove(context, &openings[1..], &issuer_set, powers).is_err());
+    }
+}
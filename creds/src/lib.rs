@@ -1,11 +1,10 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
-use std::{fs, path::PathBuf, error::Error};
-use ark_bn254::{Bn254 as ECPairing, Fr};
+use std::error::Error;
+use ark_bn254::{Bn254 as ECPairing, Fr, G1Affine, G1Projective as G1};
 use ark_crypto_primitives::snark::SNARK;
 use ark_ec::pairing::Pairing;
-use ark_ff::PrimeField;
 use ark_groth16::{Groth16, PreparedVerifyingKey, ProvingKey, VerifyingKey};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
 use ark_std::{end_timer, rand::thread_rng, start_timer};
@@ -13,23 +12,36 @@ use ark_std::{end_timer, rand::thread_rng, start_timer};
 use groth16rand::{ShowGroth16, ShowRange};
 use num_bigint::BigUint;
 use num_traits::Num;
-use prep_inputs::{create_proof_spec_internal, pem_to_inputs, unpack_int_to_string_unquoted};
+use prep_inputs::{claim_signal_name, create_proof_spec_internal, pem_to_inputs};
 use serde::{Deserialize, Serialize};
-use serde_json::{json,Value};
+use serde_json::Value;
 use sha2::{Digest, Sha256};
-use utils::{read_from_file, strip_quotes, write_to_file};
-use crate::rangeproof::{RangeProofPK, RangeProofVK};
+use utils::strip_quotes;
+use crate::rangeproof::{fits_in_bits, is_valid_bit_width, RangeProofPK, RangeProofVK};
 use crate::structs::{PublicIOType, IOLocations, GenericInputsJSON};
 use crate::groth16rand::ClientState;
 use crate::utils::utc_now_seconds;
 use crate::device::{DeviceProof, ECDSASig};
-use crate::daystamp::days_to_be_age;
+use crate::dlog::PedersenOpening;
+use crate::predicates::{range_checks, BoundSide, RangeCheck, RangePredicate, SetPredicate};
+use crate::pseudonym::{PseudonymProof, PseudonymSpec};
+use crate::revocation::{Accumulator, AccumulatorPublicKey, MembershipProof};
+use crate::issuer_set::IssuerSetProof;
+use crate::sets::{set_accumulator, set_commitment, set_membership_witness, SetNonMembershipProof};
+use crate::status_list::{StatusListProof, StatusListSpec};
+use crate::policy::{Clock, VerifierPolicy};
+use crate::store::{keys, read_param, write_param, ParamStore};
+use crate::formats::{commitment_index, credential_format, credential_format_from_config, get_io_location, get_io_locations, CredentialFormat};
 
 
 #[cfg(not(feature = "wasm"))]
 use {
     ark_circom::{CircomBuilder, CircomConfig},
+    crate::ceremony::CeremonyParams,
     crate::structs::ProverInput,
+    std::fs::File,
+    std::io::BufReader,
+    std::path::Path,
 };
 
 #[cfg(feature = "wasm")]
@@ -42,13 +54,31 @@ pub mod daystamp;
 pub mod dlog;
 pub mod groth16rand;
 pub mod prep_inputs;
+pub use crescent_config::config;
+pub mod estimate;
+pub mod ceremony;
 pub mod rangeproof;
 pub mod structs;
 pub mod utils;
 pub mod device;
-
-const RANGE_PROOF_INTERVAL_BITS: usize = 32;
-const SHOW_PROOF_VALIDITY_SECONDS: u64 = 300;    // The verifier only accepts proofs fresher than this
+pub mod formats;
+pub mod store;
+pub mod policy;
+pub mod predicates;
+pub mod pseudonym;
+pub mod revocation;
+pub mod sets;
+pub mod issuer_set;
+pub mod status_list;
+pub mod sd_jwt;
+pub mod multi;
+
+/// Default width of range proofs, used for the expiration and age checks and for range predicates that don't set `bits`
+pub const RANGE_PROOF_INTERVAL_BITS: usize = 32;
+/// Largest range proof width supported by the parameters generated by `run_zksetup`
+pub const MAX_RANGE_PROOF_BITS: usize = 64;
+/// Largest set supported by the parameters generated by `run_zksetup`, for set predicates and for the revoked entries of a status list
+pub const MAX_SET_SIZE: usize = 1024;
 pub const DEFAULT_PROOF_SPEC : &str = r#"{"revealed" : ["email"]}"#;
 
 pub type CrescentPairing = ECPairing;
@@ -62,8 +92,8 @@ pub struct ProverParams<E: Pairing> {
     pub config_str : String
 }
 impl<E: Pairing> ProverParams<E> {
-    pub fn new(paths : &CachePaths) -> Result<Self, SerializationError> {
-        let prover_params : ProverParams<E> = read_from_file(&paths.prover_params)?;
+    pub fn new(store : &dyn ParamStore) -> Result<Self, SerializationError> {
+        let prover_params : ProverParams<E> = read_param(store, keys::PROVER_PARAMS)?;
         Ok(prover_params)
     }
 }
@@ -74,8 +104,8 @@ pub struct ShowParams<'b, E: Pairing> {
     range_pk: RangeProofPK<'b, E>
 }
 impl<'b, E: Pairing> ShowParams<'b, E> {
-    pub fn new(paths : &CachePaths) -> Result<Self, SerializationError> {
-        let range_pk : RangeProofPK<'b, E> = read_from_file(&paths.range_pk)?;
+    pub fn new(store : &dyn ParamStore) -> Result<Self, SerializationError> {
+        let range_pk : RangeProofPK<'b, E> = read_param(store, keys::RANGE_PK)?;
         Ok(Self{range_pk})
     }
 }
@@ -88,40 +118,68 @@ pub struct VerifierParams<E: Pairing> {
     pub range_vk: RangeProofVK<E>,
     pub io_locations_str: String, // Stored as String since IOLocations does not implement CanonicalSerialize
     pub issuer_pem: String, 
-    pub config_str: String
+    pub config_str: String,
+    pub accumulator_pk: Option<AccumulatorPublicKey<E>>, // only needed to check non-revocation proofs
+    pub accumulator: Option<Accumulator<E>>,
 }
 impl<E: Pairing> VerifierParams<E> {
-    pub fn new(paths : &CachePaths) -> Result<Self, SerializationError> {
-        let pvk : PreparedVerifyingKey<E> = read_from_file(&paths.groth16_pvk)?;
-        let vk : VerifyingKey<E> = read_from_file(&paths.groth16_vk)?;
-        let range_vk : RangeProofVK<E> = read_from_file(&paths.range_vk)?;
-        let io_locations_str = std::fs::read_to_string(&paths.io_locations)?;
-        let issuer_pem = std::fs::read_to_string(&paths.issuer_pem)?;
-        let config_str = std::fs::read_to_string(&paths.config)?;
-        Ok(Self{vk, pvk, range_vk, io_locations_str, issuer_pem, config_str})
+    pub fn new(store : &dyn ParamStore) -> Result<Self, SerializationError> {
+        let pvk : PreparedVerifyingKey<E> = read_param(store, keys::GROTH16_PVK)?;
+        let vk : VerifyingKey<E> = read_param(store, keys::GROTH16_VK)?;
+        let range_vk : RangeProofVK<E> = read_param(store, keys::RANGE_VK)?;
+        let io_locations_str = store.read_string(keys::IO_LOCATIONS)?;
+        let issuer_pem = store.read_string(keys::ISSUER_PEM)?;
+        let config_str = store.read_string(keys::CONFIG)?;
+        let accumulator_pk = if store.contains(keys::ACCUMULATOR_PK) { Some(read_param(store, keys::ACCUMULATOR_PK)?) } else { None };
+        let accumulator = if store.contains(keys::ACCUMULATOR) { Some(read_param(store, keys::ACCUMULATOR)?) } else { None };
+        Ok(Self{vk, pvk, range_vk, io_locations_str, issuer_pem, config_str, accumulator_pk, accumulator})
     }
 }
 
 // Proof specification describing what is to be proven during a Show proof.  Currently supporting selective disclosure
-// of attributes as field elements or hashed values, and range proofs.
+// of attributes as field elements or hashed values, range and set predicates, scope-exclusive pseudonyms, and non-revocation proofs (with an accumulator or a status list).
 // The range proof for the expiration date ("exp" for JWT, "valid_until" for mDL) is in the future is always done.
-#[derive(Serialize, Deserialize, Debug)]
+// Attributes listed in `committed` are committed without being revealed, so other proofs can refer to them (see `multi`).
+// Optional attributes (see `optional` in config.json) listed in `reveal_if_present` are revealed if the credential has
+// them; other attributes the proof spec refers to must be in the credential.
+// If `issuer_set` lists the (PEM-encoded) keys of several issuers, the proof hides which of them issued the credential.
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ProofSpec {
     pub revealed: Vec<String>,
+    pub reveal_if_present: Option<Vec<String>>,
     pub range_over_year: Option<std::collections::BTreeMap<String, u64>>,
+    pub range_predicates: Option<Vec<RangePredicate>>,
+    pub set_predicates: Option<Vec<SetPredicate>>,
     pub presentation_message: Option<Vec<u8>>,
     pub device_bound: Option<bool>,
+    pub pseudonym: Option<PseudonymSpec>,
+    pub revocation_attribute: Option<String>,
+    pub status_list: Option<StatusListSpec>,
+    pub issuer_set: Option<Vec<String>>,
+    pub committed: Option<Vec<String>>,
 }
 
+/// The proof spec, completed with information from the credential config
 #[derive(Serialize)]
-pub(crate) struct ProofSpecInternal {
-    pub revealed: Vec<String>,
+pub struct ProofSpecInternal {
+    pub revealed: Vec<String>, // includes reveal_if_present, in the order of the circuit's public inputs
+    pub reveal_if_present: Vec<String>,
+    pub optional: Vec<String>, // the optional claims of the credential config
     pub range_over_year: Vec<(String, u64)>,
+    pub range_predicates: Vec<RangePredicate>,
+    pub set_predicates: Vec<SetPredicate>,
     pub hashed: Vec<String>, 
     pub presentation_message : Option<Vec<u8>>,
     pub device_bound: bool,
+    pub pseudonym: Option<PseudonymSpec>,
+    pub revocation_attribute: Option<String>,
+    pub status_list: Option<StatusListSpec>,
+    pub issuer_set: Option<Vec<String>>, // PEM-encoded keys of the issuers; if set, the credential's issuer key is hidden
+    pub committed: Vec<String>,
     pub config_str: String,
     pub claim_types: std::collections::BTreeMap<String, String>, // claim name -> claim type
+    #[serde(skip)]  // bound to the proof through config_str
+    pub timezone: chrono::FixedOffset, // timezone used to evaluate dates in range predicates
 }
 
 /// Structure to hold all the parts of a show/presentation proof
@@ -129,96 +187,190 @@ pub(crate) struct ProofSpecInternal {
 pub struct ShowProof<E: Pairing> {
     pub show_groth16: ShowGroth16<E>,
     pub show_range_exp: ShowRange<E>, // non-expired range proof (always performed)
-    pub show_range_attr: Vec<ShowRange<E>>, // selective attribute range proofs
+    pub show_range_attr: Vec<ShowRange<E>>, // attribute range proofs, one per one-sided range check (see predicates::range_checks)
+    pub set_membership_proofs: Vec<MembershipProof<E>>, // one per "in" set predicate, in order
+    pub set_non_membership_proofs: Vec<SetNonMembershipProof<E>>, // one per "not_in" set predicate, in order
     pub revealed_inputs: Vec<E::ScalarField>, 
     pub revealed_preimages: Option<String>,
     pub inputs_len: usize, 
     pub cur_time: u64,
-    pub device_proof: Option<DeviceProof<E::G1>>
+    pub device_proof: Option<DeviceProof<E::G1>>,
+    pub pseudonym_proof: Option<PseudonymProof<E::G1>>,
+    pub revocation_proof: Option<MembershipProof<E>>,
+    pub status_list_proof: Option<StatusListProof<E>>,
+    pub issuer_set_proof: Option<IssuerSetProof<E>>,
 }
 
-/// Central struct to configure the paths data stored between operations
-pub struct CachePaths {
-   pub _base: String,
-   pub jwt : String,
-   pub issuer_pem : String,
-   pub config : String,
-   pub io_locations: String,
-   pub wasm: String,
-   pub r1cs: String,
-   pub _cache: String,
-   pub range_pk: String,
-   pub range_vk: String,
-   pub groth16_vk: String,
-   pub groth16_pvk: String,
-   pub prover_params: String,   
-   pub client_state: String, 
-   pub show_proof: String,
-   pub mdl_prover_inputs: String,
-   pub mdl_prover_aux: String,
-   pub proof_spec: String,
-   pub device_pub_pem: String,
-   pub device_prv_pem: String
+/// Reasons a show/presentation proof can fail to verify
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyError {
+    /// The proof spec is not compatible with the credential config
+    InvalidProofSpec(String),
+    /// The `credtype` in the credential config is not a supported credential format
+    UnsupportedCredentialType(String),
+    /// The proof spec refers to an attribute that is not an output of the circuit
+    UnknownAttribute(String),
+    /// The show proof is structurally inconsistent with the proof spec or verifier parameters
+    MalformedProof(String),
+    /// The prover did not provide the preimage of a hashed attribute
+    MissingPreimage(String),
+    /// The preimage of a hashed attribute could not be parsed
+    InvalidPreimage(String),
+    /// The issuer public key could not be converted to circuit inputs
+    InvalidIssuerKey(String),
+    /// The re-randomized Groth16 proof did not verify. This is also the result when the
+    /// credential was issued under a different issuer key than the one the verifier used.
+    InvalidGroth16Proof,
+    /// The proof was created too long ago
+    StaleProof { age_seconds: u64, max_age_seconds: u64 },
+    /// The proof was created further in the future than the verifier policy allows
+    FutureProof { ahead_seconds: u64, max_skew_seconds: u64 },
+    /// The range proof that the credential is not expired failed
+    InvalidExpiryRangeProof,
+    /// The range proof for the named attribute failed
+    InvalidRangeProof(String),
+    /// The proof spec requires device binding but the show proof has no device proof
+    MissingDeviceProof,
+    /// The device binding proof failed
+    InvalidDeviceProof,
+    /// The proof spec asks for a pseudonym but the show proof has none
+    MissingPseudonym,
+    /// The proof that the pseudonym is derived from the committed attribute failed
+    InvalidPseudonymProof,
+    /// The proof spec asks for a non-revocation proof but the show proof has none
+    MissingRevocationProof,
+    /// The prover used a different accumulator than the verifier's
+    StaleAccumulator { proof_epoch: u64, accumulator_epoch: u64 },
+    /// The proof that the credential is not revoked failed
+    InvalidRevocationProof,
+    /// The proof of a set predicate on the attribute failed
+    InvalidSetProof(String),
+    /// The proof spec asks for a status list proof but the show proof has none
+    MissingStatusListProof,
+    /// The proof that the credential's status in the status list is valid failed
+    InvalidStatusListProof,
+    /// The proof spec asks to hide the issuer in an issuer set but the show proof has no issuer set proof
+    MissingIssuerSetProof,
+    /// The proof that the issuer is in the issuer set failed
+    InvalidIssuerSetProof,
+    /// The proof spec links attributes of several credentials but the presentation has no equality proof
+    MissingEqualityProof,
+    /// The proof that linked attributes of several credentials are equal failed
+    InvalidEqualityProof,
+    /// The credential does not have an optional attribute that the proof spec requires
+    AbsentClaim(String),
+    /// The proof was valid, but a revealed attribute could not be decoded
+    ClaimDecoding(String),
 }
 
-impl CachePaths {
-    pub fn new(base_path: PathBuf) -> Self{
-        let base_path_str = base_path.into_os_string().into_string().unwrap();
-        Self::new_from_str(&base_path_str)
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyError::InvalidProofSpec(e) => write!(f, "invalid proof spec: {}", e),
+            VerifyError::UnsupportedCredentialType(t) => write!(f, "unsupported credential type: {}", t),
+            VerifyError::UnknownAttribute(attr) => write!(f, "attribute {} not found in io_locations", attr),
+            VerifyError::MalformedProof(e) => write!(f, "malformed show proof: {}", e),
+            VerifyError::MissingPreimage(attr) => write!(f, "preimage for hashed attribute {} not provided by prover", attr),
+            VerifyError::InvalidPreimage(e) => write!(f, "invalid preimage: {}", e),
+            VerifyError::InvalidIssuerKey(e) => write!(f, "failed to convert issuer public key to input values: {}", e),
+            VerifyError::InvalidGroth16Proof => write!(f, "Groth16 show proof failed to verify"),
+            VerifyError::StaleProof { age_seconds, max_age_seconds } =>
+                write!(f, "show proof created {} seconds ago, older than {} seconds", age_seconds, max_age_seconds),
+            VerifyError::FutureProof { ahead_seconds, max_skew_seconds } =>
+                write!(f, "show proof created {} seconds in the future, more than the allowed skew of {} seconds", ahead_seconds, max_skew_seconds),
+            VerifyError::InvalidExpiryRangeProof => write!(f, "range proof for the expiration date failed to verify"),
+            VerifyError::InvalidRangeProof(attr) => write!(f, "range proof for attribute {} failed to verify", attr),
+            VerifyError::MissingDeviceProof => write!(f, "device proof missing in show proof"),
+            VerifyError::InvalidDeviceProof => write!(f, "device proof failed to verify"),
+            VerifyError::MissingPseudonym => write!(f, "pseudonym missing in show proof"),
+            VerifyError::InvalidPseudonymProof => write!(f, "pseudonym proof failed to verify"),
+            VerifyError::MissingRevocationProof => write!(f, "non-revocation proof missing in show proof"),
+            VerifyError::StaleAccumulator { proof_epoch, accumulator_epoch } =>
+                write!(f, "non-revocation proof is for accumulator epoch {}, verifier has epoch {}", proof_epoch, accumulator_epoch),
+            VerifyError::InvalidRevocationProof => write!(f, "non-revocation proof failed to verify"),
+            VerifyError::InvalidSetProof(attr) => write!(f, "set predicate proof for {} failed to verify", attr),
+            VerifyError::MissingStatusListProof => write!(f, "status list proof missing in show proof"),
+            VerifyError::InvalidStatusListProof => write!(f, "status list proof failed to verify"),
+            VerifyError::MissingIssuerSetProof => write!(f, "issuer set proof missing in show proof"),
+            VerifyError::InvalidIssuerSetProof => write!(f, "issuer set proof failed to verify"),
+            VerifyError::MissingEqualityProof => write!(f, "attribute equality proof missing in presentation"),
+            VerifyError::InvalidEqualityProof => write!(f, "attribute equality proof failed to verify"),
+            VerifyError::AbsentClaim(attr) => write!(f, "optional attribute {} is absent from the credential", attr),
+            VerifyError::ClaimDecoding(attr) => write!(f, "proof was valid, but failed to decode attribute {}", attr),
+        }
     }
+}
 
-    pub fn new_from_str(base_path: &str) -> Self {
-        let base_path_str = format!("{}/", base_path);
-        if fs::metadata(&base_path_str).is_err() {
-            println!("base_path = {}", base_path_str);
-            panic!("invalid path");
-        }
-        println!("base_path_str = {}", base_path_str);
-        let cache_path = format!("{}cache/", base_path_str);
-    
-        if fs::metadata(&cache_path).is_ok() {
-            println!("Found directory {} to store data", cache_path);
-        } else {
-            println!("Creating directory {} to store data", cache_path);
-            fs::create_dir(&cache_path).unwrap();        
+impl Error for VerifyError {}
+
+/// A claim value disclosed by a verified show proof
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClaimValue {
+    Integer(BigUint),
+    String(String),
+    Boolean(bool),
+    /// Preimages of hashed attributes that are not strings (e.g., mDL arrays or maps)
+    Json(Value),
+}
+
+impl ClaimValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ClaimValue::String(s) => Some(s),
+            _ => None,
         }
+    }
+}
 
-        CachePaths {
-            _base: base_path_str.clone(),
-            jwt: format!("{}token.jwt", base_path_str),
-            issuer_pem: format!("{}issuer.pub", base_path_str),
-            config: format!("{}config.json", base_path_str),
-            io_locations: format!("{}io_locations.sym", base_path_str),
-            wasm: format!("{}main.wasm", base_path_str),
-            r1cs: format!("{}main_c.r1cs", base_path_str),
-            _cache: cache_path.clone(),
-            range_pk: format!("{}range_pk.bin", &cache_path),
-            range_vk: format!("{}range_vk.bin", &cache_path),
-            groth16_vk: format!("{}groth16_vk.bin", &cache_path),
-            groth16_pvk: format!("{}groth16_pvk.bin", &cache_path),
-            prover_params: format!("{}prover_params.bin", &cache_path),
-            client_state: format!("{}client_state.bin", &cache_path),
-            show_proof: format!("{}show_proof.bin", &cache_path),
-            mdl_prover_inputs: format!("{}prover_inputs.json", &base_path_str),
-            mdl_prover_aux: format!("{}prover_aux.json", &base_path_str),
-            proof_spec: format!("{}proof_spec.json", &base_path_str),
-            device_pub_pem: format!("{}device.pub", &base_path_str),
-            device_prv_pem: format!("{}device.prv", &base_path_str),
-        }             
+impl std::fmt::Display for ClaimValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClaimValue::Integer(n) => write!(f, "{}", n),
+            ClaimValue::String(s) => write!(f, "{}", s),
+            ClaimValue::Boolean(b) => write!(f, "{}", b),
+            ClaimValue::Json(v) => write!(f, "{}", v),
+        }
     }
 }
 
+/// The result of successfully verifying a show proof
+#[derive(Clone, Debug, PartialEq)]
+pub struct VerifiedPresentation {
+    /// Disclosed claims, keyed by claim name. Includes claims revealed as field elements and as digests.
+    pub revealed: std::collections::BTreeMap<String, ClaimValue>,
+    /// The attributes of `reveal_if_present` that the credential does not have (they are not in `revealed`)
+    pub absent: Vec<String>,
+    /// The (attribute, years) pairs of the `range_over_year` predicates that were proven
+    pub range_over_year: Vec<(String, u64)>,
+    /// The range predicates that were proven. Relative bounds ("now+30d") are relative to `proof_time`.
+    pub range_predicates: Vec<RangePredicate>,
+    /// The set predicates that were proven
+    pub set_predicates: Vec<SetPredicate>,
+    /// The time (in seconds since the UNIX epoch) the prover used when creating the proof
+    pub proof_time: u64,
+    /// True if the proof was checked to be bound to the credential's device key
+    pub device_bound: bool,
+    /// The pseudonym for the scope in the proof spec (hex encoded), if one was requested
+    pub pseudonym: Option<String>,
+    /// If the proof spec asked for a non-revocation proof, the epoch of the accumulator the credential was proven to be in
+    pub revocation_epoch: Option<u64>,
+    /// True if the credential was proven to have a valid status in the proof spec's status list
+    pub status_list_checked: bool,
+}
+
 #[cfg(not(feature = "wasm"))]
-pub fn run_zksetup(base_path: PathBuf) -> i32 {
+fn load_circom_config(store: &dyn ParamStore) -> Result<CircomConfig<ECPairing>, std::io::Error> {
+    let wasm = store.read(keys::WASM)?;
+    let r1cs = store.read(keys::R1CS)?;
+    CircomConfig::<ECPairing>::from_bytes(&wasm, &r1cs).map_err(|e| std::io::Error::other(e.to_string()))
+}
 
-    let paths = CachePaths::new(base_path);
+/// Generate the Groth16 and range proof parameters for the circuit in `store`, and write them back to `store`
+#[cfg(not(feature = "wasm"))]
+pub fn run_zksetup(store: &dyn ParamStore) -> Result<(), Box<dyn Error>> {
 
     let circom_timer = start_timer!(|| "Reading R1CS instance and witness generator");
-    let cfg = CircomConfig::<ECPairing>::new(
-        &paths.wasm,
-        &paths.r1cs,
-    )
-    .unwrap();
+    let cfg = load_circom_config(store)?;
     let builder = CircomBuilder::new(cfg);
     let circom = builder.setup();
     end_timer!(circom_timer);
@@ -228,44 +380,76 @@ pub fn run_zksetup(base_path: PathBuf) -> i32 {
     let params =
         Groth16::<ECPairing>::generate_random_parameters_with_reduction(circom, &mut rng)
             .unwrap();
+    end_timer!(groth16_setup_timer);
+
+    let range_setup_timer = start_timer!(|| "Generating parameters for range proofs");
+    let (range_pk, range_vk) = RangeProofPK::<ECPairing>::setup_with_degree(MAX_RANGE_PROOF_BITS, MAX_SET_SIZE);
+    end_timer!(range_setup_timer);
 
+    write_setup_params(store, params, &range_pk, &range_vk)
+}
+
+/// Create the initial parameters of a multi-party setup ceremony for the circuit in `store`, from the powers of tau in
+/// the .ptau file `ptau_path` (see `ceremony`)
+#[cfg(not(feature = "wasm"))]
+pub fn run_ceremony_init(store: &dyn ParamStore, ptau_path: &Path) -> Result<CeremonyParams, Box<dyn Error>> {
+    let cfg = load_circom_config(store)?;
+    let circom = CircomBuilder::new(cfg).setup();
+    let mut ptau = BufReader::new(File::open(ptau_path)?);
+    CeremonyParams::new(circom, &mut ptau, MAX_RANGE_PROOF_BITS, MAX_SET_SIZE)
+}
+
+/// Verify a setup ceremony for the circuit in `store`, and write its Groth16 and range proof parameters to `store`, as
+/// `run_zksetup` does. Returns the hashes of the contributions.
+#[cfg(not(feature = "wasm"))]
+pub fn run_ceremony_verify(store: &dyn ParamStore, ptau_path: &Path, ceremony: &CeremonyParams) -> Result<Vec<[u8; 32]>, Box<dyn Error>> {
+    let verify_timer = start_timer!(|| "Verifying the setup ceremony");
+    let cfg = load_circom_config(store)?;
+    let circom = CircomBuilder::new(cfg).setup();
+    let mut ptau = BufReader::new(File::open(ptau_path)?);
+    let hashes = ceremony.verify(circom, &mut ptau)?;
+    end_timer!(verify_timer);
+
+    let (range_pk, range_vk) = ceremony.range_powers.keys();
+    if range_vk.max_bits < MAX_RANGE_PROOF_BITS || range_vk.powers_of_g.len() <= MAX_SET_SIZE {
+        return_error!("The range proof parameters of the ceremony are too small");
+    }
+    write_setup_params(store, ceremony.params.clone(), &range_pk, &range_vk)?;
+    Ok(hashes)
+}
+
+// Write the Groth16 and range proof parameters to `store`
+#[cfg(not(feature = "wasm"))]
+fn write_setup_params(store: &dyn ParamStore, params: ProvingKey<ECPairing>, range_pk: &RangeProofPK<ECPairing>,
+    range_vk: &RangeProofVK<ECPairing>) -> Result<(), Box<dyn Error>> {
     let vk = params.vk.clone();
     let pvk = Groth16::<ECPairing>::process_vk(&params.vk).unwrap();  
-    end_timer!(groth16_setup_timer);
 
-    let range_setup_timer = start_timer!(|| "Generating parameters for range proofs");    
-    let (range_pk, range_vk) = RangeProofPK::<ECPairing>::setup(RANGE_PROOF_INTERVAL_BITS);
-    end_timer!(range_setup_timer);
-    
-    let serialize_timer = start_timer!(|| "Writing everything to files");
-    write_to_file(&range_pk, &paths.range_pk);
-    write_to_file(&range_vk, &paths.range_vk);    
-    write_to_file(&vk, &paths.groth16_vk);
-    write_to_file(&pvk, &paths.groth16_pvk);
+    let serialize_timer = start_timer!(|| "Writing everything to the parameter store");
+    write_param(store, keys::RANGE_PK, range_pk)?;
+    write_param(store, keys::RANGE_VK, range_vk)?;
+    write_param(store, keys::GROTH16_VK, &vk)?;
+    write_param(store, keys::GROTH16_PVK, &pvk)?;
 
-    let config_str = fs::read_to_string(&paths.config).unwrap_or_else(|_| panic!("Unable to read config from {} ", paths.config));
+    let config_str = store.read_string(keys::CONFIG)?;
     let prover_params = ProverParams{groth16_params: params, groth16_pvk: pvk, config_str};
-    write_to_file(&prover_params, &paths.prover_params);    
+    write_param(store, keys::PROVER_PARAMS, &prover_params)?;
     end_timer!(serialize_timer);
 
-    0
+    Ok(())
 }
 
 #[cfg(not(feature = "wasm"))]
-pub fn create_client_state(paths : &CachePaths, prover_inputs: &GenericInputsJSON, prover_aux: Option<&String>, credtype : &str) -> Result<ClientState<ECPairing>, SerializationError>
+pub fn create_client_state(store : &dyn ParamStore, prover_inputs: &GenericInputsJSON, prover_aux: Option<&String>, credtype : &str) -> Result<ClientState<ECPairing>, SerializationError>
 {
     let circom_timer = start_timer!(|| "Reading R1CS Instance and witness generator WASM");
-    let cfg = CircomConfig::<ECPairing>::new(
-        &paths.wasm,
-        &paths.r1cs,
-    )
-    .unwrap();
+    let cfg = load_circom_config(store)?;
     let mut builder = CircomBuilder::new(cfg);
     prover_inputs.push_inputs(&mut builder);
     end_timer!(circom_timer);
 
-    let load_params_timer = start_timer!(||"Reading ProverParams params from file");
-    let prover_params : ProverParams<ECPairing> = read_from_file(&paths.prover_params)?;
+    let load_params_timer = start_timer!(||"Reading ProverParams params from the parameter store");
+    let prover_params : ProverParams<ECPairing> = read_param(store, keys::PROVER_PARAMS)?;
     end_timer!(load_params_timer);
     
     let build_timer = start_timer!(|| "Witness Generation");
@@ -283,7 +467,7 @@ pub fn create_client_state(paths : &CachePaths, prover_inputs: &GenericInputsJSO
     let proof = Groth16::<ECPairing>::prove(&prover_params.groth16_params, circom, &mut rng).unwrap();    
     end_timer!(prove_timer);
 
-    let pvk : PreparedVerifyingKey<ECPairing> = read_from_file(&paths.groth16_pvk)?;
+    let pvk : PreparedVerifyingKey<ECPairing> = read_param(store, keys::GROTH16_PVK)?;
     let verify_timer = start_timer!(|| "Groth16 verify");
     let verified =
         Groth16::<ECPairing>::verify_with_processed_vk(&pvk, &inputs, &proof).unwrap();
@@ -302,664 +486,544 @@ pub fn create_client_state(paths : &CachePaths, prover_inputs: &GenericInputsJSO
     Ok(client_state)
 }
 
+/// Create a show/presentation proof for a credential. The credential format (JWT, mDL, ...) is taken from `client_state.credtype`.
 pub fn create_show_proof(client_state: &mut ClientState<ECPairing>, range_pk : &RangeProofPK<ECPairing>, io_locations: &IOLocations, proof_spec: &ProofSpec, device_signature: Option<Vec<u8>>) -> Result<ShowProof<ECPairing>, Box<dyn Error>>
 {
-    // Create Groth16 rerandomized proof for showing
-    let exp_value_pos = io_locations.get_io_location("exp_value").unwrap();
-    let mut io_types = vec![PublicIOType::Hidden; client_state.inputs.len()];
-    io_types[exp_value_pos - 1] = PublicIOType::Committed;
-
-    for i in io_locations.get_public_key_indices() {
-        io_types[i] = PublicIOType::Revealed;
-    }
-
+    let format = credential_format(&client_state.credtype)?;
     let proof_spec = create_proof_spec_internal(proof_spec, &client_state.config_str)?;
 
-    // For the attributes revealed as field elements, we set the position to Revealed and send the value
+    // Commit the expiration date (for the non-expired range proof), the device key and the range proofed attributes;
+    // reveal the issuer public key and the disclosed attributes; hide everything else
+    let io_types = format.io_types(&proof_spec, io_locations, client_state.inputs.len())?;
+
+    // For the attributes revealed as field elements, we send the value (several field elements for long strings)
     let mut revealed_inputs = vec![];
     for attr in &proof_spec.revealed {
-        let io_loc = match io_locations.get_io_location(&format!("{}_value", &attr)) {
-            Ok(loc) => loc,
-            Err(_) => {
-                return_error!(
-                    format!("Asked to reveal attribute {}, but did not find it in io_locations\nIO locations: {:?}", attr, io_locations.get_all_names()));
-            }
-        };
-
-        io_types[io_loc - 1] = PublicIOType::Revealed;
-        revealed_inputs.push(client_state.inputs[io_loc - 1]);
+        for io_loc in get_io_locations(io_locations, &format!("{}_value", &attr))? {
+            revealed_inputs.push(client_state.inputs[io_loc - 1]);
+        }
+    }
+    // For the optional attributes the proof spec refers to, we send the presence bit
+    let presence_claims = format.presence_claims(&proof_spec);
+    for (attr, io_loc) in presence_claims.iter().zip(format.presence_io_locations(&proof_spec, io_locations)?) {
+        let present = client_state.inputs[io_loc - 1];
+        if present == Fr::from(0u64) && !proof_spec.reveal_if_present.contains(attr) {
+            return_error!(format!("Attribute {} is absent from the credential, it can only be used in reveal_if_present", attr));
+        }
+        revealed_inputs.push(present);
     }
 
     // For the attributes revealed as digests, we provide the preimage, the verifier will hash it to get the field element
     let mut revealed_preimages = serde_json::Map::new();
-    for attr in &proof_spec.hashed {
-        let io_loc = match io_locations.get_io_location(&format!("{}_digest", &attr)) {
-            Ok(loc) => loc,
-            Err(_) => {
-                return_error!(
-                    format!("Asked to reveal hashed attribute {}, but did not find it in io_locations\nIO locations: {:?}", attr, io_locations.get_all_names()));
-            }
-        };        
-
-        io_types[io_loc - 1] = PublicIOType::Revealed;
-
-        if client_state.aux.is_none() {
-            return_error!(format!("Proof spec asked to reveal hashed attribute {}, but client state is missing aux data", attr));
+    if !proof_spec.hashed.is_empty() {
+        let aux = match client_state.aux.as_ref() {
+            Some(aux) => serde_json::from_str::<Value>(aux)?,
+            None => return_error!(format!("Proof spec asked to reveal hashed attribute {}, but client state is missing aux data", proof_spec.hashed[0])),
+        };
+        for attr in &proof_spec.hashed {
+            let preimage = aux.get(attr).ok_or(format!("Preimage of hashed attribute {} is missing from aux data", attr))?;
+            revealed_preimages.insert(attr.clone(), format.encode_preimage(preimage));
         }
-        let aux = serde_json::from_str::<Value>(client_state.aux.as_ref().unwrap()).unwrap();
-        let aux = aux.as_object().unwrap();
-        revealed_preimages.insert(attr.clone(), json!(aux[attr].clone().to_string()));
-    }
-
-    // If the credential is device bound, the public key attributes must be committed
-    if proof_spec.device_bound {
-        let device_key_0_pos = io_locations.get_io_location("device_key_0_value").unwrap();
-        let device_key_1_pos = io_locations.get_io_location("device_key_1_value").unwrap();
-        io_types[device_key_0_pos - 1] = PublicIOType::Committed;
-        io_types[device_key_1_pos - 1] = PublicIOType::Committed;
     }
 
     // Serialize the proof spec as the context
     let context_str = serde_json::to_string(&proof_spec).unwrap();
     let show_groth16 = client_state.show_groth16(Some(context_str.as_bytes()), &io_types);
     
-    // Create fresh range proof 
+    // Create fresh range proof for the expiration date
     let time_sec = utc_now_seconds();
     let cur_time = Fr::from( time_sec );
 
-    let mut com_exp_value = client_state.committed_input_openings[0].clone();
+    let exp_value_pos = get_io_location(io_locations, &format!("{}_value", format.expiry_claim()))?;
+    let mut com_exp_value = client_state.committed_input_openings[commitment_index(&io_types, exp_value_pos)?].clone();
     com_exp_value.m -= cur_time;
     com_exp_value.c -= com_exp_value.bases[0] * cur_time;
     let show_range_exp = client_state.show_range(&com_exp_value, RANGE_PROOF_INTERVAL_BITS, range_pk);
 
     let device_proof = 
     if proof_spec.device_bound {
-        assert!(client_state.committed_input_openings.len() >= 3);
-        let com0 = client_state.committed_input_openings[1].clone();
-        let com1 = client_state.committed_input_openings[2].clone();
-        let sig = ECDSASig::new_from_bytes(&proof_spec.presentation_message.unwrap(), &device_signature.unwrap());
-        let aux = serde_json::from_str::<Value>(client_state.aux.as_ref().unwrap()).unwrap();
-        let aux = aux.as_object().unwrap();
-        let x = BigUint::from_str_radix(aux["device_pub_x"].as_str().unwrap(), 10).unwrap();
-        let y = BigUint::from_str_radix(aux["device_pub_y"].as_str().unwrap(), 10).unwrap();
+        let device_signature = device_signature.ok_or("No device signature provided for device bound credential")?;
+        let device_key_0_pos = get_io_location(io_locations, "device_key_0_value")?;
+        let device_key_1_pos = get_io_location(io_locations, "device_key_1_value")?;
+        let com0 = client_state.committed_input_openings[commitment_index(&io_types, device_key_0_pos)?].clone();
+        let com1 = client_state.committed_input_openings[commitment_index(&io_types, device_key_1_pos)?].clone();
+        let sig = ECDSASig::new_from_bytes(proof_spec.presentation_message.as_ref().unwrap(), &device_signature);
+        let aux = serde_json::from_str::<Value>(client_state.aux.as_ref().ok_or("Device bound credential is missing aux data")?)?;
+        let x = BigUint::from_str_radix(aux["device_pub_x"].as_str().ok_or("aux data is missing device_pub_x")?, 10)?;
+        let y = BigUint::from_str_radix(aux["device_pub_y"].as_str().ok_or("aux data is missing device_pub_y")?, 10)?;
         println!("Created device proof");
         Some(DeviceProof::prove(&com0, &com1, &sig, &x, &y))
     } else {
         None
     };
 
-    // Assemble proof
-    let revealed_preimages = if proof_spec.hashed.is_empty() { 
-        assert!(revealed_preimages.is_empty());
-        None 
-    } else {
-        Some(serde_json::to_string(&revealed_preimages).unwrap())
+    let pseudonym_proof = match &proof_spec.pseudonym {
+        Some(pseudonym) => {
+            let io_loc = get_io_location(io_locations, &format!("{}_value", pseudonym.attribute))?;
+            let com = &client_state.committed_input_openings[commitment_index(&io_types, io_loc)?];
+            Some(PseudonymProof::prove(context_str.as_bytes(), com, &pseudonym.scope))
+        }
+        None => None,
     };
-    let show_range_attr= vec![]; // no attribute range proofs for JWT yet
-    Ok(ShowProof{ show_groth16, show_range_exp, show_range_attr, revealed_inputs, revealed_preimages, inputs_len: client_state.inputs.len(), cur_time: time_sec, device_proof})
-}
-
-// TODO: refactor this function and create_show_proof into one
-pub fn create_show_proof_mdl(client_state: &mut ClientState<ECPairing>, range_pk : &RangeProofPK<ECPairing>, proof_spec: &ProofSpec, io_locations: &IOLocations, device_signature: Option<Vec<u8>>) -> Result<ShowProof<ECPairing>, Box<dyn Error>>
-{
-    // Create Groth16 rerandomized proof for showing
-
-    let proof_spec = create_proof_spec_internal(proof_spec, &client_state.config_str)?;
-
-    // commit the expiration date (for non-expired range proof)
-    let valid_until_value_pos = io_locations.get_io_location("valid_until_value").unwrap();
-    let mut io_types = vec![PublicIOType::Hidden; client_state.inputs.len()];
-    io_types[valid_until_value_pos - 1] = PublicIOType::Committed;
-    // for each range proofed attribute, set the position to Committed
-    for (attr, _) in &proof_spec.range_over_year {
-        let io_loc = io_locations.get_io_location(&format!("{}_value", &attr)).unwrap();
-        io_types[io_loc - 1] = PublicIOType::Committed;
-    }
-
-    for i in io_locations.get_public_key_indices() {
-        io_types[i] = PublicIOType::Revealed;
-    }
 
-    // For the attributes revealed as field elements, we set the position to Revealed and send the value
-    let mut revealed_inputs = vec![];
-    for attr in &proof_spec.revealed {
-        let io_loc = io_locations.get_io_location(&format!("{}_value", &attr)).unwrap();
-        io_types[io_loc - 1] = PublicIOType::Revealed;
-        revealed_inputs.push(client_state.inputs[io_loc - 1]);
-    }
-
-    // For the attributes revealed as digests, we provide the preimage, the verifier will hash it to get the field element
-    let mut revealed_preimages = serde_json::Map::new();
-    for attr in &proof_spec.hashed {
-        let io_loc = match io_locations.get_io_location(&format!("{}_digest", &attr)) {
-            Ok(loc) => loc,
-            Err(_) => {
-                return_error!(
-                    format!("Asked to reveal hashed attribute {}, but did not find it in io_locations\nIO locations: {:?}", attr, io_locations.get_all_names()));
+    let revocation_proof = match &proof_spec.revocation_attribute {
+        Some(attr) => {
+            let witness = client_state.revocation_witness.as_ref().ok_or("Proof spec asks for a non-revocation proof, but the client state has no revocation witness")?;
+            let io_loc = get_io_location(io_locations, &format!("{}_value", attr))?;
+            let com = &client_state.committed_input_openings[commitment_index(&io_types, io_loc)?];
+            if com.m != witness.element {
+                return_error!(format!("The revocation witness is not for the credential's {} attribute", attr));
             }
-        };        
-
-        io_types[io_loc - 1] = PublicIOType::Revealed;
-
-        if client_state.aux.is_none() {
-            return_error!(format!("Proof spec asked to reveal hashed attribute {}, but client state is missing aux data", attr));
+            Some(MembershipProof::prove(context_str.as_bytes(), com, witness))
         }
-        let aux = serde_json::from_str::<Value>(client_state.aux.as_ref().unwrap()).unwrap();
-        let aux = aux.as_object().unwrap();
-        revealed_preimages.insert(attr.clone(), aux[attr].clone());
-    }
-
-    // If the credential is device bound, the public key attributes must be committed
-    if proof_spec.device_bound {
-        let device_key_0_pos = io_locations.get_io_location("device_key_0_value").unwrap();
-        let device_key_1_pos = io_locations.get_io_location("device_key_1_value").unwrap();
-        io_types[device_key_0_pos - 1] = PublicIOType::Committed;
-        io_types[device_key_1_pos - 1] = PublicIOType::Committed;
-    }
-
-    // Serialize the proof spec as the context
-    let context_str = serde_json::to_string(&proof_spec).unwrap();
-    let show_groth16 = client_state.show_groth16(Some(context_str.as_bytes()), &io_types);    
-    
-    // Create fresh range proof for validUntil
-    let time_sec = utc_now_seconds();
-    let cur_time = Fr::from(time_sec);
-
-    let mut com_valid_until_value = client_state.committed_input_openings[0].clone();
-    com_valid_until_value.m -= cur_time;
-    com_valid_until_value.c -= com_valid_until_value.bases[0] * cur_time;
-    let show_range_exp = client_state.show_range(&com_valid_until_value, RANGE_PROOF_INTERVAL_BITS, range_pk);
-    let device_proof = 
-    if proof_spec.device_bound {
+        None => None,
+    };
 
-        if device_signature.is_none() {
-            println!("Warning: No device signature provided for device bound credential");
+    let status_list_proof = match &proof_spec.status_list {
+        Some(status_list) => {
+            let io_loc = get_io_location(io_locations, &format!("{}_value", status_list.attribute))?;
+            let com = &client_state.committed_input_openings[commitment_index(&io_types, io_loc)?];
+            Some(StatusListProof::prove(context_str.as_bytes(), com, &status_list.list, &range_pk.powers.powers_of_g)?)
         }
-
-        assert!(client_state.committed_input_openings.len() >= 3);
-        let com0 = client_state.committed_input_openings[1].clone();
-        let com1 = client_state.committed_input_openings[2].clone();
-        let sig = ECDSASig::new_from_bytes(&proof_spec.presentation_message.unwrap(), &device_signature.unwrap());
-        let aux = serde_json::from_str::<Value>(client_state.aux.as_ref().unwrap()).unwrap();
-        let aux = aux.as_object().unwrap();
-        let x = BigUint::from_str_radix(aux["device_pub_x"].as_str().unwrap(), 10).unwrap();
-        let y = BigUint::from_str_radix(aux["device_pub_y"].as_str().unwrap(), 10).unwrap();
-        println!("Created device proof");
-        Some(DeviceProof::prove(&com0, &com1, &sig, &x, &y))
-    } else {
-        None
+        None => None,
     };
 
     let revealed_preimages = if proof_spec.hashed.is_empty() { 
-        assert!(revealed_preimages.is_empty());
         None 
     } else {
         Some(serde_json::to_string(&revealed_preimages).unwrap())
     };
 
-    let mut show_range_attr= vec![];
-    let mut commitment_index = 3; // skip the first 3 commitments (validUntil, device_key_0, device_key_1)
-    // for each range-proofed attribute, create a fresh range proof that the attribute is at least "age" years old // TODO: generalize to non-age attributes
-    for (_, age) in &proof_spec.range_over_year {
-        let days_in_age = Fr::from(days_to_be_age(*age) as u64);
-        let mut com_attr = client_state.committed_input_openings[commitment_index].clone();
-        com_attr.m -= days_in_age;
-        com_attr.c -= com_attr.bases[0] * days_in_age;
+    // For each one-sided range check (from the range_over_year and range_predicates entries), create a fresh
+    // range proof that attr - bound (for lower bounds) or bound - attr (for upper bounds) is in [0, 2^n)
+    let mut show_range_attr = vec![];
+    for check in range_checks(&proof_spec, time_sec)? {
+        let io_loc = get_io_location(io_locations, &format!("{}_value", &check.attribute))?;
+        let com_attr = range_check_opening(&client_state.committed_input_openings[commitment_index(&io_types, io_loc)?], &check);
+        if !is_valid_bit_width(check.bits, range_pk.max_bits) {
+            return_error!(format!("Range proofs of {} bits for {} are not supported, the range proof parameters support up to {} bits", check.bits, check.attribute, range_pk.max_bits));
+        }
+        if !fits_in_bits(&com_attr.m, check.bits) {
+            let op = if check.side == BoundSide::Lower { ">=" } else { "<=" };
+            return_error!(format!("Attribute {} does not satisfy the predicate {} {} {} (with a {}-bit range proof)", check.attribute, check.attribute, op, check.bound, check.bits));
+        }
+
+        show_range_attr.push(client_state.show_range(&com_attr, check.bits, range_pk));
+    }
 
-        let show_range_a = client_state.show_range(&com_attr, RANGE_PROOF_INTERVAL_BITS, range_pk);       
+    // If the issuer is hidden, prove that its key is one of the keys of the issuer set
+    let issuer_set_proof = match &proof_spec.issuer_set {
+        Some(issuer_set) => {
+            let limb_openings = io_locations.get_public_key_indices().iter()
+                .map(|i| Ok(client_state.committed_input_openings[commitment_index(&io_types, i + 1)?].clone()))
+                .collect::<Result<Vec<_>, VerifyError>>()?;
+            let issuer_keys = issuer_set_keys(issuer_set)?;
+            Some(IssuerSetProof::prove(context_str.as_bytes(), &limb_openings, &issuer_keys, &range_pk.powers.powers_of_g)?)
+        }
+        None => None,
+    };
 
-        show_range_attr.push(show_range_a);
-        commitment_index += 1;
+    // For each set predicate, prove that the committed attribute is (or is not) in the set
+    let mut set_membership_proofs = vec![];
+    let mut set_non_membership_proofs = vec![];
+    for predicate in &proof_spec.set_predicates {
+        let io_loc = get_io_location(io_locations, &format!("{}_value", predicate.attribute))?;
+        let com = &client_state.committed_input_openings[commitment_index(&io_types, io_loc)?];
+        let claim_type = proof_spec.claim_types.get(&predicate.attribute).map(|s| s.as_str()).unwrap_or("");
+        let elements = predicate.elements(format, claim_type)?;
+        let powers_of_g = &range_pk.powers.powers_of_g;
+        if predicate.is_membership() {
+            let witness = set_membership_witness::<ECPairing>(com.m, &elements, powers_of_g)
+                .map_err(|_| format!("Attribute {} does not satisfy its set predicate", predicate.attribute))?;
+            set_membership_proofs.push(MembershipProof::prove(context_str.as_bytes(), com, &witness));
+        } else {
+            let proof = SetNonMembershipProof::prove(context_str.as_bytes(), com, &elements, powers_of_g)
+                .map_err(|_| format!("Attribute {} does not satisfy its set predicate", predicate.attribute))?;
+            set_non_membership_proofs.push(proof);
+        }
     }
 
     // Assemble proof and return
-    Ok(ShowProof{ show_groth16, show_range_exp, show_range_attr, revealed_inputs, revealed_preimages, inputs_len: client_state.inputs.len(), cur_time: time_sec, device_proof})
+    Ok(ShowProof{ show_groth16, show_range_exp, show_range_attr, set_membership_proofs, set_non_membership_proofs, revealed_inputs, revealed_preimages, inputs_len: client_state.inputs.len(), cur_time: time_sec, device_proof, pseudonym_proof, revocation_proof, status_list_proof, issuer_set_proof})
 }
 
-fn sort_by_io_location(attrs: &[String], io_locations: &IOLocations) -> Vec<String> {
-    let mut attrs_with_locs: Vec<(usize, String)> = attrs
-        .iter()
-        .map(|attr| {
-            let io_loc = io_locations.get_io_location(&format!("{}_digest", attr)).unwrap();
-            (io_loc, attr.clone())
-        })
-        .collect();
+fn sort_by_io_location(attrs: &[String], io_locations: &IOLocations) -> Result<Vec<String>, VerifyError> {
+    let mut attrs_with_locs: Vec<(usize, String)> = vec![];
+    for attr in attrs {
+        let io_loc = get_io_location(io_locations, &format!("{}_digest", attr))?;
+        attrs_with_locs.push((io_loc, attr.clone()));
+    }
     attrs_with_locs.sort_by_key(|k| k.0);
-    attrs_with_locs.into_iter().map(|(_, attr)| attr).collect()
+    Ok(attrs_with_locs.into_iter().map(|(_, attr)| attr).collect())
 }
 
-pub fn verify_show(vp : &VerifierParams<ECPairing>, show_proof: &ShowProof<ECPairing>, proof_spec: &ProofSpec) -> (bool, String)
+// Parses the preimages of the hashed attributes sent by the prover, and hashes them to get the field elements
+// that are revealed in the Groth16 proof
+fn hashed_attribute_inputs(show_proof: &ShowProof<ECPairing>, proof_spec: &ProofSpecInternal, io_locations: &IOLocations) 
+-> Result<(Vec<Fr>, Value), VerifyError> 
 {
-    let io_locations = IOLocations::new_from_str(&vp.io_locations_str);
-    let exp_value_pos = io_locations.get_io_location("exp_value").unwrap();
-    let mut io_types = vec![PublicIOType::Hidden; show_proof.inputs_len];
-    io_types[exp_value_pos - 1] = PublicIOType::Committed;
-    for i in io_locations.get_public_key_indices() {
-        io_types[i] = PublicIOType::Revealed;
-    }
-
-    let proof_spec = create_proof_spec_internal(proof_spec, &vp.config_str);
-    if proof_spec.is_err() {
-        println!("Failed to create internal proof spec");
-        return (false, "".to_string());
+    let mut revealed_hashed = vec![];
+    if proof_spec.hashed.is_empty() {
+        return Ok((revealed_hashed, Value::Null));
     }
-    let proof_spec = proof_spec.unwrap();
+    let preimages = match show_proof.revealed_preimages.as_ref() {
+        Some(p) => serde_json::from_str::<Value>(p).map_err(|_| VerifyError::InvalidPreimage("failed to deserialize revealed_preimages".to_string()))?,
+        None => return Err(VerifyError::MissingPreimage(proof_spec.hashed[0].clone())),
+    };
 
-    // Set disclosed attributes to Revealed
-    for attr in &proof_spec.revealed {
-        let io_loc = io_locations.get_io_location(&format!("{}_value", &attr));
-        if io_loc.is_err() {
-            println!("Asked to reveal attribute {}, but did not find it in io_locations", attr);
-            println!("IO locations: {:?}", io_locations.get_all_names());
-            return (false, "".to_string());
-        }
-        let io_loc = io_loc.unwrap();
-        io_types[io_loc - 1] = PublicIOType::Revealed;
+    for attr in &sort_by_io_location(&proof_spec.hashed, io_locations)? {
+        let data = match preimages.get(attr) {
+            Some(Value::String(s)) => s.as_bytes(),
+            Some(_) => return Err(VerifyError::InvalidPreimage(format!("preimage for {} has unsupported type", attr))),
+            None => return Err(VerifyError::MissingPreimage(attr.clone())),
+        };
+        let digest = Sha256::digest(data);
+        let digest248 = &digest[0..digest.len()-1];
+        let digest_uint = utils::bits_to_num(digest248);
+        let digest_scalar = utils::biguint_to_scalar::<CrescentFr>(&digest_uint);
+        revealed_hashed.push(digest_scalar);
     }
 
-    // For the attributes revealed as digests, we hash the provided preimage to get the field element
-    let mut revealed_hashed = vec![];
-    let mut preimages = json!(serde_json::Value::Null);
-    if !proof_spec.hashed.is_empty() {
-        assert!(show_proof.revealed_preimages.is_some());
-        let preimages0 = serde_json::from_str::<Value>(show_proof.revealed_preimages.as_ref().unwrap());
-        if preimages0.is_err() {
-            println!("Failed to deserialize revealed_preimages");
-            return (false, "".to_string());
-        }
-        preimages = preimages0.unwrap();
-
-        let hashed_attributes = sort_by_io_location(&proof_spec.hashed, &io_locations);
-    
-        for attr in &hashed_attributes {
-            let io_loc = io_locations.get_io_location(&format!("{}_digest", &attr));
-            if io_loc.is_err() {
-                println!("Asked to reveal hashed attribute {}, but did not find it in io_locations", attr);
-                println!("IO locations: {:?}", io_locations.get_all_names());
-                return (false, "".to_string());
-            }
-            let io_loc = io_loc.unwrap();
-            io_types[io_loc - 1] = PublicIOType::Revealed;
+    Ok((revealed_hashed, preimages))
+}
 
-            let preimage = preimages.get(attr);
-            if preimage.is_none() {
-                println!("Error: preimage for hashed attribute {} not provided by prover", attr);
-                return(false, "".to_string());
+// The attributes of reveal_if_present that the credential does not have, from the presence bits that follow the
+// revealed values. The other optional attributes the proof spec refers to must be present.
+fn absent_claims(format: &dyn CredentialFormat, show_proof: &ShowProof<ECPairing>, proof_spec: &ProofSpecInternal) -> Result<Vec<String>, VerifyError> {
+    let presence_claims = format.presence_claims(proof_spec);
+    let presence_bits = &show_proof.revealed_inputs[show_proof.revealed_inputs.len() - presence_claims.len()..];
+    let mut absent = vec![];
+    for (attr, bit) in presence_claims.iter().zip(presence_bits) {
+        // The circuit checks that the bit is 0 or 1
+        if *bit == Fr::from(0u64) {
+            if !proof_spec.reveal_if_present.contains(attr) {
+                return Err(VerifyError::AbsentClaim(attr.clone()));
             }
-            
-            let data = match preimage.unwrap() {
-                Value::String(s) =>  {
-                    s.as_bytes()
-                },     
-                _ =>  {
-                    println!("Error: preimage has unsupported type");
-                    return(false, "".to_string());
-                }
-            };
-            let digest = Sha256::digest(data);
-            let digest248 = &digest[0..digest.len()-1];
-            let digest_uint = utils::bits_to_num(digest248);
-            let digest_scalar = utils::biguint_to_scalar::<CrescentFr>(&digest_uint);
-            revealed_hashed.push(digest_scalar);
+            absent.push(attr.clone());
         }
     }
+    Ok(absent)
+}
 
-    // If the credential is device bound, the device public key attributes must be committed
-    if proof_spec.device_bound {
-        let device_key_0_pos = io_locations.get_io_location("device_key_0_value").unwrap();
-        let device_key_1_pos = io_locations.get_io_location("device_key_1_value").unwrap();
-        io_types[device_key_0_pos - 1] = PublicIOType::Committed;
-        io_types[device_key_1_pos - 1] = PublicIOType::Committed;
+// Convert the revealed attributes from field elements (or preimages, for hashed attributes) to claim values.
+// The absent attributes are skipped, the circuit reveals a default value for them.
+fn decode_revealed_claims(format: &dyn CredentialFormat, show_proof: &ShowProof<ECPairing>, proof_spec: &ProofSpecInternal, io_locations: &IOLocations, preimages: &Value, absent: &[String]) 
+-> Result<std::collections::BTreeMap<String, ClaimValue>, VerifyError> 
+{
+    let mut revealed = std::collections::BTreeMap::new();
+    let mut revealed_inputs = show_proof.revealed_inputs.iter();
+    for attr_name in &proof_spec.revealed {
+        let claim_type = proof_spec.claim_types.get(attr_name).map(|s| s.as_str()).unwrap_or("");
+        let num_fields = get_io_locations(io_locations, &format!("{}_value", attr_name))?.len();
+        let values = revealed_inputs.by_ref().take(num_fields).cloned().collect::<Vec<_>>();
+        if absent.contains(attr_name) {
+            continue;
+        }
+        let attr_value = if num_fields == 1 {
+            format.decode_claim(attr_name, claim_type, &values[0])?
+        } else {
+            format.decode_claim_fields(attr_name, &values)?
+        };
+        revealed.insert(attr_name.clone(), attr_value);
     }
 
-    // Create an inputs vector with the revealed inputs and the issuer's public key
-    let public_key_inputs = pem_to_inputs::<<ECPairing as Pairing>::ScalarField>(&vp.issuer_pem);
-    if public_key_inputs.is_err() {
-        print!("Error: Failed to convert issuer public key to input values");
-        return (false, "".to_string());
+    for attr_name in &proof_spec.hashed {
+        let value = match preimages.get(attr_name) {
+            Some(Value::String(s)) => ClaimValue::String(strip_quotes(s).to_string()),
+            Some(v) => ClaimValue::Json(v.clone()),
+            None => return Err(VerifyError::ClaimDecoding(attr_name.clone())),
+        };
+        revealed.insert(attr_name.clone(), value);
     }
 
-    let mut inputs = vec![];
-    inputs.extend(revealed_hashed);
-    inputs.extend(public_key_inputs.unwrap());
-    inputs.extend(show_proof.revealed_inputs.clone());
-    
-    let context_str = serde_json::to_string(&proof_spec).unwrap();
+    Ok(revealed)
+}
 
-    let verify_timer = std::time::Instant::now();
-    let ret = show_proof.show_groth16.verify(&vp.vk, &vp.pvk, Some(context_str.as_bytes()), &io_types, &inputs);
-    if !ret {
-        println!("show_groth16.verify failed");
-        return (false, "".to_string());
+fn check_show_proof_shape(vp: &VerifierParams<ECPairing>, format: &dyn CredentialFormat, show_proof: &ShowProof<ECPairing>, proof_spec: &ProofSpecInternal, io_locations: &IOLocations) -> Result<(), VerifyError> {
+    if show_proof.inputs_len + 1 != vp.vk.gamma_abc_g1.len() {
+        return Err(VerifyError::MalformedProof(format!("proof has {} inputs, verifying key expects {}", show_proof.inputs_len, vp.vk.gamma_abc_g1.len() - 1)));
     }
-    let cur_time = Fr::from(show_proof.cur_time);
-    let now_seconds = utc_now_seconds();
-    let delta = now_seconds.saturating_sub(show_proof.cur_time);
-    println!("Proof created {} seconds ago", delta);    
-
-    if delta > SHOW_PROOF_VALIDITY_SECONDS {
-        println!("Invalid show proof -- older than {} seconds", SHOW_PROOF_VALIDITY_SECONDS);
-        return (false, "".to_string());
+    // Long strings are revealed as several field elements
+    let mut num_revealed_inputs = 0;
+    for attr in &proof_spec.revealed {
+        num_revealed_inputs += get_io_locations(io_locations, &format!("{}_value", attr))?.len();
     }
-
-    let mut ped_com_exp_value = show_proof.show_groth16.commited_inputs[0];
-    ped_com_exp_value -= vp.pvk.vk.gamma_abc_g1[exp_value_pos] * cur_time;
-    let ret = show_proof.show_range_exp.verify(
-        &ped_com_exp_value,
-        RANGE_PROOF_INTERVAL_BITS,
-        &vp.range_vk,
-        &io_locations,
-        &vp.pvk,
-        "exp_value",
-    );
-    if !ret {
-        println!("show_range.verify failed");
-        return (false, "".to_string());
+    num_revealed_inputs += format.presence_claims(proof_spec).len();
+    if show_proof.revealed_inputs.len() != num_revealed_inputs {
+        return Err(VerifyError::MalformedProof(format!("proof reveals {} field elements, proof spec asks for {}", show_proof.revealed_inputs.len(), num_revealed_inputs)));
     }
+    Ok(())
+}
 
-    if proof_spec.device_bound {
-        let device_key_0_pos = io_locations.get_io_location("device_key_0_value").unwrap();
-        let device_key_1_pos = io_locations.get_io_location("device_key_1_value").unwrap();        
-        let com0 = show_proof.show_groth16.commited_inputs[1];
-        let com1 = show_proof.show_groth16.commited_inputs[2];
-        let bases0 = vec![vp.pvk.vk.gamma_abc_g1[device_key_0_pos], vp.pvk.vk.delta_g1];
-        let bases1 = vec![vp.pvk.vk.gamma_abc_g1[device_key_1_pos], vp.pvk.vk.delta_g1];
-        let device_proof = match show_proof.device_proof.as_ref() {
-            Some(dp) => dp,
-            None => {
-                println!("DeviceProof.verify failed: device_proof missing in show_proof");
-                return (false, "Device proof missing in show_proof".to_string());
-            }
-        };
-        let ret = DeviceProof::verify(device_proof, &com0.into(), &com1.into(), &bases0, &bases1);
-        if !ret {
-            println!("DeviceProof.verify failed");
-            return (false, "".to_string());            
+// Open the commitment to attr as a commitment to attr - bound (for lower bounds) or bound - attr (for upper bounds)
+fn range_check_opening(com_attr: &PedersenOpening<G1>, check: &RangeCheck) -> PedersenOpening<G1> {
+    let bound = Fr::from(check.bound);
+    let mut com = com_attr.clone();
+    match check.side {
+        BoundSide::Lower => {
+            com.m -= bound;
+            com.c -= com.bases[0] * bound;
+        }
+        BoundSide::Upper => {
+            com.m = bound - com.m;
+            com.r = -com.r;
+            com.c = com.bases[0] * bound - com.c;
         }
-        println!("Device proof verified successfully");
     }
-    
-    println!("Verification time: {:?}", verify_timer.elapsed());  
+    com
+}
 
-    // Add the revealed attributes to the output, after converting from field element to string
-    let mut revealed = serde_json::Map::<String, Value>::new();
-    for (revealed_idx, attr_name) in proof_spec.revealed.iter().enumerate() {
-        let attr_name = attr_name.clone() + "_value";
-        let claim_type = proof_spec.claim_types.get(attr_name.trim_end_matches("_value")).map(|s| s.as_str()).unwrap_or("");
-        let attr_value = if claim_type == "number" {
-            json!(show_proof.revealed_inputs[revealed_idx].into_bigint().to_string())
-        } else {
-            match unpack_int_to_string_unquoted(&show_proof.revealed_inputs[revealed_idx].into_bigint()) {
-                Ok(val) => json!(val),
-                Err(_) => {
-                    println!("Error: Proof was valid, but failed to unpack '{}' attribute", attr_name);
-                    return (false, "".to_string());
-                }
-            }
-        };
-        revealed.insert(attr_name.clone(), attr_value);
+// The verifier's counterpart of range_check_opening, where base is the base of attr in the commitment
+fn range_check_commitment(com_attr: &G1, base: &G1Affine, check: &RangeCheck) -> G1 {
+    let bound = Fr::from(check.bound);
+    match check.side {
+        BoundSide::Lower => *com_attr - *base * bound,
+        BoundSide::Upper => *base * bound - com_attr,
     }
+}
 
-    // Add the hashed revealed attributes to the output
-    for attr_name in &proof_spec.hashed {
-        let attr_value = preimages.get(attr_name);
-        if attr_value.is_none() {
-            println!("Error: Proof was valid, but failed to find hashed attribute '{}'", attr_name);
-            return(false, "".to_string());
-        }
-        let value = match attr_value.unwrap() {
-            Value::String(s) => {
-                json!(strip_quotes(s))
-            },
-            _ => attr_value.unwrap().clone()
-        };
-        revealed.insert(attr_name.clone(), value);
+fn verify_device_proof(vp: &VerifierParams<ECPairing>, show_proof: &ShowProof<ECPairing>, io_locations: &IOLocations, io_types: &[PublicIOType]) -> Result<(), VerifyError> {
+    let device_key_0_pos = get_io_location(io_locations, "device_key_0_value")?;
+    let device_key_1_pos = get_io_location(io_locations, "device_key_1_value")?;
+    let com0 = show_proof.show_groth16.commited_inputs[commitment_index(io_types, device_key_0_pos)?];
+    let com1 = show_proof.show_groth16.commited_inputs[commitment_index(io_types, device_key_1_pos)?];
+    let bases0 = vec![vp.pvk.vk.gamma_abc_g1[device_key_0_pos], vp.pvk.vk.delta_g1];
+    let bases1 = vec![vp.pvk.vk.gamma_abc_g1[device_key_1_pos], vp.pvk.vk.delta_g1];
+    let device_proof = show_proof.device_proof.as_ref().ok_or(VerifyError::MissingDeviceProof)?;
+    if !DeviceProof::verify(device_proof, &com0.into(), &com1.into(), &bases0, &bases1) {
+        return Err(VerifyError::InvalidDeviceProof);
     }
+    println!("Device proof verified successfully");
+    Ok(())
+}
 
+// Verify the non-revocation proof against the verifier's accumulator and return the accumulator epoch
+fn verify_revocation(vp: &VerifierParams<ECPairing>, show_proof: &ShowProof<ECPairing>, attr: &str, context: &[u8], io_locations: &IOLocations, io_types: &[PublicIOType]) -> Result<u64, VerifyError> {
+    let (pk, accumulator) = match (&vp.accumulator_pk, &vp.accumulator) {
+        (Some(pk), Some(accumulator)) => (pk, accumulator),
+        _ => return Err(VerifyError::InvalidProofSpec("proof spec asks for a non-revocation proof, but the verifier has no accumulator".to_string())),
+    };
+    let revocation_proof = show_proof.revocation_proof.as_ref().ok_or(VerifyError::MissingRevocationProof)?;
+    if revocation_proof.epoch != accumulator.epoch {
+        return Err(VerifyError::StaleAccumulator { proof_epoch: revocation_proof.epoch, accumulator_epoch: accumulator.epoch });
+    }
+    let io_pos = get_io_location(io_locations, &format!("{}_value", attr))?;
+    let com = show_proof.show_groth16.commited_inputs[commitment_index(io_types, io_pos)?];
+    let bases = [vp.pvk.vk.gamma_abc_g1[io_pos].into(), vp.pvk.vk.delta_g1.into()];
+    if !revocation_proof.verify(context, &com, &bases, accumulator, pk) {
+        return Err(VerifyError::InvalidRevocationProof);
+    }
+    println!("Non-revocation proof verified successfully");
+    Ok(accumulator.epoch)
+}
 
-    (true, serde_json::to_string(&revealed).unwrap())
+// The keys of an issuer set, each as the public inputs that encode it
+fn issuer_set_keys(issuer_set: &[String]) -> Result<Vec<Vec<Fr>>, Box<dyn Error>> {
+    issuer_set.iter().map(|pem| pem_to_inputs::<Fr>(pem)).collect()
 }
 
-pub fn verify_show_mdl(vp : &VerifierParams<ECPairing>, show_proof: &ShowProof<ECPairing>, proof_spec: &ProofSpec) -> (bool, String)
-{
-    let proof_spec = create_proof_spec_internal(proof_spec, &vp.config_str);
-    if proof_spec.is_err() {
-        println!("Failed to create internal proof spec: {:?}", proof_spec.err().unwrap());
-        return (false, "".to_string());
+// Verify the proof that the (committed) issuer key is one of the keys of the issuer set
+fn verify_issuer_set(vp: &VerifierParams<ECPairing>, show_proof: &ShowProof<ECPairing>, issuer_set: &[String], context: &[u8], io_locations: &IOLocations, io_types: &[PublicIOType]) -> Result<(), VerifyError> {
+    let issuer_keys = issuer_set_keys(issuer_set).map_err(|e| VerifyError::InvalidIssuerKey(e.to_string()))?;
+    let issuer_set_proof = show_proof.issuer_set_proof.as_ref().ok_or(VerifyError::MissingIssuerSetProof)?;
+    let key_indices = io_locations.get_public_key_indices();
+    let limb_commitments = key_indices.iter().map(|i| Ok(show_proof.show_groth16.commited_inputs[commitment_index(io_types, i + 1)?])).collect::<Result<Vec<_>, VerifyError>>()?;
+    let limb_bases = key_indices.iter().map(|i| [vp.pvk.vk.gamma_abc_g1[i + 1].into(), vp.pvk.vk.delta_g1.into()]).collect::<Vec<_>>();
+    if !issuer_set_proof.verify(context, &limb_commitments, &limb_bases, &issuer_keys, &vp.range_vk) {
+        return Err(VerifyError::InvalidIssuerSetProof);
     }
-    let proof_spec = proof_spec.unwrap();
+    println!("Issuer set proof verified successfully");
+    Ok(())
+}
 
-    let io_locations = IOLocations::new_from_str(&vp.io_locations_str);
-    let valid_until_value_pos = io_locations.get_io_location("valid_until_value").unwrap();
-    let mut io_types = vec![PublicIOType::Hidden; show_proof.inputs_len];
-    io_types[valid_until_value_pos - 1] = PublicIOType::Committed;
-    // for each range proofed attribute, set the position to Committed
-    for (attr, _) in &proof_spec.range_over_year {
-        let io_loc = match io_locations.get_io_location(&format!("{}_value", &attr)) {
-            Ok(loc) => loc,
-            Err(_) => {
-                println!("Asked to prove range for attribute {}, but did not find it in io_locations", attr);
-                return (false, "".to_string());
-            }
+// Verify the proofs of the set predicates of the proof spec
+fn verify_set_predicates(vp: &VerifierParams<ECPairing>, show_proof: &ShowProof<ECPairing>, proof_spec: &ProofSpecInternal, format: &dyn CredentialFormat, context: &[u8], io_locations: &IOLocations, io_types: &[PublicIOType]) -> Result<(), VerifyError> {
+    let (membership, non_membership): (Vec<&SetPredicate>, Vec<&SetPredicate>) = proof_spec.set_predicates.iter().partition(|p| p.is_membership());
+    if show_proof.set_membership_proofs.len() != membership.len() || show_proof.set_non_membership_proofs.len() != non_membership.len() {
+        return Err(VerifyError::MalformedProof(format!("proof has {} set membership and {} set non-membership proofs, proof spec asks for {} and {}",
+            show_proof.set_membership_proofs.len(), show_proof.set_non_membership_proofs.len(), membership.len(), non_membership.len())));
+    }
+    let mut membership_proofs = show_proof.set_membership_proofs.iter();
+    let mut non_membership_proofs = show_proof.set_non_membership_proofs.iter();
+    for predicate in &proof_spec.set_predicates {
+        let io_pos = get_io_location(io_locations, &format!("{}_value", predicate.attribute))?;
+        let com = show_proof.show_groth16.commited_inputs[commitment_index(io_types, io_pos)?];
+        let bases = [vp.pvk.vk.gamma_abc_g1[io_pos].into(), vp.pvk.vk.delta_g1.into()];
+        let claim_type = proof_spec.claim_types.get(&predicate.attribute).map(|s| s.as_str()).unwrap_or("");
+        let elements = predicate.elements(format, claim_type).map_err(|e| VerifyError::InvalidProofSpec(e.to_string()))?;
+        let valid = if predicate.is_membership() {
+            let (accumulator, pk) = set_accumulator(&elements, &vp.range_vk).map_err(|e| VerifyError::InvalidProofSpec(e.to_string()))?;
+            membership_proofs.next().unwrap().verify(context, &com, &bases, &accumulator, &pk)
+        } else {
+            let set_com = set_commitment::<ECPairing>(&elements, &vp.range_vk.powers_of_g).map_err(|e| VerifyError::InvalidProofSpec(e.to_string()))?;
+            non_membership_proofs.next().unwrap().verify(context, &com, &bases, &set_com, &vp.range_vk)
         };
-        io_types[io_loc - 1] = PublicIOType::Committed;
+        if !valid {
+            return Err(VerifyError::InvalidSetProof(predicate.attribute.clone()));
+        }
+        println!("set predicate proof for {} succeeded", predicate.attribute);
     }
+    Ok(())
+}
 
-    for i in io_locations.get_public_key_indices() {
-        io_types[i] = PublicIOType::Revealed;
+// Verify the proof that the credential is not revoked in the status list of the proof spec
+fn verify_status_list(vp: &VerifierParams<ECPairing>, show_proof: &ShowProof<ECPairing>, status_list: &StatusListSpec, context: &[u8], io_locations: &IOLocations, io_types: &[PublicIOType]) -> Result<(), VerifyError> {
+    let list_commitment = status_list.list.commitment::<ECPairing>(&vp.range_vk.powers_of_g)
+        .map_err(|e| VerifyError::InvalidProofSpec(e.to_string()))?;
+    let status_list_proof = show_proof.status_list_proof.as_ref().ok_or(VerifyError::MissingStatusListProof)?;
+    let io_pos = get_io_location(io_locations, &format!("{}_value", status_list.attribute))?;
+    let com = show_proof.show_groth16.commited_inputs[commitment_index(io_types, io_pos)?];
+    let bases = [vp.pvk.vk.gamma_abc_g1[io_pos].into(), vp.pvk.vk.delta_g1.into()];
+    if !status_list_proof.verify(context, &com, &bases, &list_commitment, &vp.range_vk) {
+        return Err(VerifyError::InvalidStatusListProof);
     }
+    println!("Status list proof verified successfully");
+    Ok(())
+}
 
-    // Set attributes to Revealed
-    for attr in &proof_spec.revealed {
-        let io_loc = io_locations.get_io_location(&format!("{}_value", &attr));
-        if io_loc.is_err() {
-            println!("Asked to reveal attribute {}, but did not find it in io_locations", attr);
-            println!("IO locations: {:?}", io_locations.get_all_names());
-            return (false, "".to_string());
-        }
-        let io_loc = io_loc.unwrap();
-        io_types[io_loc - 1] = PublicIOType::Revealed;
+// Verify the pseudonym proof and return the encoded pseudonym
+fn verify_pseudonym(vp: &VerifierParams<ECPairing>, show_proof: &ShowProof<ECPairing>, pseudonym: &PseudonymSpec, context: &[u8], io_locations: &IOLocations, io_types: &[PublicIOType]) -> Result<String, VerifyError> {
+    let io_pos = get_io_location(io_locations, &format!("{}_value", pseudonym.attribute))?;
+    let com = show_proof.show_groth16.commited_inputs[commitment_index(io_types, io_pos)?];
+    let bases = [vp.pvk.vk.gamma_abc_g1[io_pos].into(), vp.pvk.vk.delta_g1.into()];
+    let pseudonym_proof = show_proof.pseudonym_proof.as_ref().ok_or(VerifyError::MissingPseudonym)?;
+    if !pseudonym_proof.verify(context, &com, &bases, &pseudonym.scope) {
+        return Err(VerifyError::InvalidPseudonymProof);
     }
+    println!("Pseudonym proof verified successfully");
+    Ok(pseudonym_proof.encode())
+}
 
-    // For the attributes revealed as digests, we hash the provided preimage to get the field element
-    let mut revealed_hashed = vec![];
-    let mut preimages = json!(serde_json::Value::Null);
-    if !proof_spec.hashed.is_empty() {
-        assert!(show_proof.revealed_preimages.is_some());
-        let preimages0 = serde_json::from_str::<Value>(show_proof.revealed_preimages.as_ref().unwrap());
-        if preimages0.is_err() {
-            println!("Failed to deserialize revealed_preimages");
-            return (false, "".to_string());
-        }
-        preimages = preimages0.unwrap();
-        let hashed_attributes = sort_by_io_location(&proof_spec.hashed, &io_locations);
-    
-        for attr in &hashed_attributes {
-            let io_loc = io_locations.get_io_location(&format!("{}_digest", &attr));
-            if io_loc.is_err() {
-                println!("Asked to reveal hashed attribute {}, but did not find it in io_locations", attr);
-                println!("IO locations: {:?}", io_locations.get_all_names());
-                return (false, "".to_string());
-            }
-            let io_loc = io_loc.unwrap();
-            io_types[io_loc - 1] = PublicIOType::Revealed;
+/// Verify a show/presentation proof. The credential format (JWT, mDL, ...) is taken from the `credtype` entry of the config.
+/// The freshness of the proof is checked against `clock`, according to `policy`.
+pub fn verify_show(vp : &VerifierParams<ECPairing>, show_proof: &ShowProof<ECPairing>, proof_spec: &ProofSpec, policy: &VerifierPolicy, clock: &dyn Clock) -> Result<VerifiedPresentation, VerifyError>
+{
+    let format = credential_format_from_config(&vp.config_str)
+        .map_err(|e| VerifyError::InvalidProofSpec(e.to_string()))?;
+    let proof_spec = create_proof_spec_internal(proof_spec, &vp.config_str)
+        .map_err(|e| VerifyError::InvalidProofSpec(e.to_string()))?;
+    let io_locations = IOLocations::new_from_str(&vp.io_locations_str);
+    check_show_proof_shape(vp, format, show_proof, &proof_spec, &io_locations)?;
 
-            let preimage = preimages.get(attr);
-            if preimage.is_none() {
-                println!("Error: preimage for hashed attribute {} not provided by prover", attr);
-                return(false, "".to_string());
-            }
-            
-            let data = match preimage.unwrap() {
-                Value::String(s) =>  {
-                    s.as_bytes()
-                },     
-                _ =>  {
-                    println!("Error: preimage has unsupported type");
-                    return(false, "".to_string());
-                }
-            };
-            let digest = Sha256::digest(data);
-            let digest248 = &digest[0..digest.len()-1];
-            let digest_uint = utils::bits_to_num(digest248);
-            let digest_scalar = utils::biguint_to_scalar::<CrescentFr>(&digest_uint);
-            revealed_hashed.push(digest_scalar);
-        }
-    }
+    let io_types = format.io_types(&proof_spec, &io_locations, show_proof.inputs_len)?;
 
-    // If the credential is device bound, the device public key attributes must be committed
-    if proof_spec.device_bound {
-        let device_key_0_pos = io_locations.get_io_location("device_key_0_value").unwrap();
-        let device_key_1_pos = io_locations.get_io_location("device_key_1_value").unwrap();
-        io_types[device_key_0_pos - 1] = PublicIOType::Committed;
-        io_types[device_key_1_pos - 1] = PublicIOType::Committed;
-    }
+    // For the attributes revealed as digests, we hash the provided preimage to get the field element
+    let (revealed_hashed, preimages) = hashed_attribute_inputs(show_proof, &proof_spec, &io_locations)?;
+
+    // Create an inputs vector with the revealed inputs and the issuer's public key (unless it is hidden in an issuer set)
+    let public_key_inputs = if proof_spec.issuer_set.is_some() {
+        vec![]
+    } else {
+        pem_to_inputs::<<ECPairing as Pairing>::ScalarField>(&vp.issuer_pem)
+            .map_err(|e| VerifyError::InvalidIssuerKey(e.to_string()))?
+    };
 
-    // Create an inputs vector with the inputs from the prover, and the issuer's public key
-    let public_key_inputs = pem_to_inputs::<<ECPairing as Pairing>::ScalarField>(&vp.issuer_pem);
-    if public_key_inputs.is_err() {
-        print!("Error: Failed to convert issuer public key to input values");
-        return (false, "".to_string());
-    }
     let mut inputs = vec![];
     inputs.extend(revealed_hashed);
-    inputs.extend(public_key_inputs.unwrap());
+    inputs.extend(public_key_inputs);
     inputs.extend(show_proof.revealed_inputs.clone());
     
     let context_str = serde_json::to_string(&proof_spec).unwrap();
 
     let verify_timer = std::time::Instant::now();
-    let ret: bool = show_proof.show_groth16.verify(&vp.vk, &vp.pvk, Some(context_str.as_bytes()), &io_types, &inputs);
-    if !ret {
-        println!("show_groth16.verify failed");
-        return (false, "".to_string());
+    if !show_proof.show_groth16.verify(&vp.vk, &vp.pvk, Some(context_str.as_bytes()), &io_types, &inputs) {
+        return Err(VerifyError::InvalidGroth16Proof);
     }
-    let cur_time = Fr::from(show_proof.cur_time);
-    let now_seconds = utc_now_seconds();
-    let delta = now_seconds.saturating_sub(show_proof.cur_time);
-    println!("Proof created {} seconds ago", delta);    
-
-    if delta > SHOW_PROOF_VALIDITY_SECONDS {
-        println!("Invalid show proof -- older than {} seconds", SHOW_PROOF_VALIDITY_SECONDS);
-        return (false, "".to_string());
-    }  
+    policy.check_freshness(show_proof.cur_time, clock)?;
+    let absent = absent_claims(format, show_proof, &proof_spec)?;
 
-    let mut ped_com_valid_until_value = show_proof.show_groth16.commited_inputs[0];
-    ped_com_valid_until_value -= vp.pvk.vk.gamma_abc_g1[valid_until_value_pos] * cur_time;
+    let cur_time = Fr::from(show_proof.cur_time);
+    let exp_label = format!("{}_value", format.expiry_claim());
+    let exp_value_pos = get_io_location(&io_locations, &exp_label)?;
+    let mut ped_com_exp_value = show_proof.show_groth16.commited_inputs[commitment_index(&io_types, exp_value_pos)?];
+    ped_com_exp_value -= vp.pvk.vk.gamma_abc_g1[exp_value_pos] * cur_time;
     let ret = show_proof.show_range_exp.verify(
-        &ped_com_valid_until_value,
+        &ped_com_exp_value,
         RANGE_PROOF_INTERVAL_BITS,
         &vp.range_vk,
         &io_locations,
         &vp.pvk,
-        "valid_until_value",
+        &exp_label,
     );
     if !ret {
-        println!("show_range_exp.verify failed");
-        return (false, "".to_string());
-    }      
-
-    for (i, show_range_attr) in show_proof.show_range_attr.iter().enumerate() {
-        let commitment_index = i + 3; // skip the first 3 (validUntil, device_key_0, device_key_1)
-        let attr_name = &proof_spec.range_over_year[i].0;
-        let attr_label = format!("{}_value", &attr_name);
-        let age = proof_spec.range_over_year[i].1;
-        let days_in_age = Fr::from(days_to_be_age(age) as u64);
-        let mut ped_com_attr_value = show_proof.show_groth16.commited_inputs[commitment_index];
-        let io_pos = match io_locations.get_io_location(&attr_label) {
-            Ok(loc) => loc,
-            Err(_) => {
-                println!("Asked to prove range for attribute {}, but did not find it in io_locations", attr_name);
-                return (false, "".to_string());
-            }
-        };
-        ped_com_attr_value -= vp.pvk.vk.gamma_abc_g1[io_pos] * days_in_age;
+        return Err(VerifyError::InvalidExpiryRangeProof);
+    }
+
+    let checks = range_checks(&proof_spec, show_proof.cur_time).map_err(|e| VerifyError::InvalidProofSpec(e.to_string()))?;
+    if show_proof.show_range_attr.len() != checks.len() {
+        return Err(VerifyError::MalformedProof(format!("proof has {} attribute range proofs, proof spec asks for {}", show_proof.show_range_attr.len(), checks.len())));
+    }
+    for (show_range_attr, check) in show_proof.show_range_attr.iter().zip(&checks) {
+        let attr_label = format!("{}_value", claim_signal_name(&check.attribute));
+        let io_pos = get_io_location(&io_locations, &attr_label)?;
+        let com_attr = show_proof.show_groth16.commited_inputs[commitment_index(&io_types, io_pos)?];
+        let ped_com_attr_value = range_check_commitment(&com_attr, &vp.pvk.vk.gamma_abc_g1[io_pos], check);
 
         let ret = show_range_attr.verify(
             &ped_com_attr_value,
-            RANGE_PROOF_INTERVAL_BITS,
+            check.bits,
             &vp.range_vk,
             &io_locations,
             &vp.pvk,
             &attr_label,
         );
         if !ret {
-            println!("show_range_attr.verify failed");
-            return (false, "".to_string());
+            return Err(VerifyError::InvalidRangeProof(check.attribute.clone()));
         }
-        println!("range proof for {} such that age is over {} succeeded", attr_name, age);
+        let op = if check.side == BoundSide::Lower { ">=" } else { "<=" };
+        println!("range proof for {} {} {} succeeded", check.attribute, op, check.bound);
     }
 
-    if proof_spec.device_bound {
-        let device_key_0_pos = io_locations.get_io_location("device_key_0_value").unwrap();
-        let device_key_1_pos = io_locations.get_io_location("device_key_1_value").unwrap();        
-        let com0 = show_proof.show_groth16.commited_inputs[1];
-        let com1 = show_proof.show_groth16.commited_inputs[2];
-        let bases0 = vec![vp.pvk.vk.gamma_abc_g1[device_key_0_pos], vp.pvk.vk.delta_g1];
-        let bases1 = vec![vp.pvk.vk.gamma_abc_g1[device_key_1_pos], vp.pvk.vk.delta_g1];
-        let device_proof = match show_proof.device_proof.as_ref() {
-            Some(dp) => dp,
-            None => {
-                println!("DeviceProof.verify failed: device_proof missing in show_proof");
-                return (false, "Device proof missing in show_proof".to_string());
-            }
-        };
-        let ret = DeviceProof::verify(device_proof, &com0.into(), &com1.into(), &bases0, &bases1);
-        if !ret {
-            println!("DeviceProof.verify failed");
-            return (false, "".to_string());            
-        }
-        println!("Device proof verified successfully");
+    verify_set_predicates(vp, show_proof, &proof_spec, format, context_str.as_bytes(), &io_locations, &io_types)?;
+    if let Some(issuer_set) = &proof_spec.issuer_set {
+        verify_issuer_set(vp, show_proof, issuer_set, context_str.as_bytes(), &io_locations, &io_types)?;
     }
 
-    println!("Verification time: {:?}", verify_timer.elapsed());  
-
-    // Add the revealed attributes to the output, after converting from field element to string
-    let mut revealed = serde_json::Map::<String, Value>::new();
-    for (revealed_idx, attr_name) in proof_spec.revealed.iter().enumerate() {
-        let attr_name = attr_name.clone() + "_value";
-        let claim_type = proof_spec.claim_types.get(attr_name.trim_end_matches("_value")).map(|s| s.as_str()).unwrap_or("");
-        let attr_value = if claim_type == "integer" {
-            json!(show_proof.revealed_inputs[revealed_idx].into_bigint().to_string())
-        } else {
-            match unpack_int_to_string_unquoted(&show_proof.revealed_inputs[revealed_idx].into_bigint()) {
-                Ok(val) => json!(val),
-                Err(_) => {
-                    println!("Error: Proof was valid, but failed to unpack '{}' attribute", attr_name);
-                    return (false, "".to_string());
-                }
-            }
-        };
-        revealed.insert(attr_name.clone(), attr_value);
+    if proof_spec.device_bound {
+        verify_device_proof(vp, show_proof, &io_locations, &io_types)?;
     }
-
-    // Add the hashed revealed attributes to the output
-    for attr_name in &proof_spec.hashed {
-        let attr_value = preimages.get(attr_name);
-        if attr_value.is_none() {
-            println!("Error: Proof was valid, but failed to find hashed attribute '{}'", attr_name);
-            return(false, "".to_string());
-        }
-        let value = match attr_value.unwrap() {
-            Value::String(s) => {
-                json!(strip_quotes(s))
-            },
-            _ => attr_value.unwrap().clone()
-        };
-        revealed.insert(attr_name.clone(), value);
+    let pseudonym = match &proof_spec.pseudonym {
+        Some(pseudonym) => Some(verify_pseudonym(vp, show_proof, pseudonym, context_str.as_bytes(), &io_locations, &io_types)?),
+        None => None,
+    };
+    let revocation_epoch = match &proof_spec.revocation_attribute {
+        Some(attr) => Some(verify_revocation(vp, show_proof, attr, context_str.as_bytes(), &io_locations, &io_types)?),
+        None => None,
+    };
+    if let Some(status_list) = &proof_spec.status_list {
+        verify_status_list(vp, show_proof, status_list, context_str.as_bytes(), &io_locations, &io_types)?;
     }
+    
+    println!("Verification time: {:?}", verify_timer.elapsed());  
 
-    (true, serde_json::to_string(&revealed).unwrap())
+    let revealed = decode_revealed_claims(format, show_proof, &proof_spec, &io_locations, &preimages, &absent)?;
+
+    Ok(VerifiedPresentation {
+        revealed,
+        absent,
+        range_over_year: proof_spec.range_over_year.clone(),
+        range_predicates: proof_spec.range_predicates.clone(),
+        set_predicates: proof_spec.set_predicates.clone(),
+        proof_time: show_proof.cur_time,
+        device_bound: proof_spec.device_bound,
+        pseudonym,
+        revocation_epoch,
+        status_list_checked: proof_spec.status_list.is_some(),
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{device::TestDevice, prep_inputs::{parse_config, prepare_prover_inputs}};
+    use crate::{config::parse_config, device::TestDevice, prep_inputs::prepare_prover_inputs, store::FsParamStore, policy::SystemClock};
+    use serde_json::json;
     use serial_test::serial;
+    use std::fs;
 
     // We run the end-to-end tests with [serial] because they use a lot of memory, 
     // if two are run at the same time some machines do not have enough RAM
+    // The tests of the newer parameter sets are ignored by default, since their test vectors are not in the tree. Run
+    // `./run_setup.sh <param>` in circuit_setup/scripts first, then the test, e.g., `cargo test --release -- --ignored end_to_end_test_ps256`.
 
     #[test]
     #[serial]
@@ -976,6 +1040,66 @@ mod tests {
     pub fn end_to_end_test_rs256_db() {
         run_test("rs256-db", "jwt");
     }
+    #[test]
+    #[serial]
+    #[ignore = "needs the test vectors from `circuit_setup/scripts/run_setup.sh es256`"]
+    pub fn end_to_end_test_es256() {
+        run_test("es256", "jwt");
+    }
+    #[test]
+    #[serial]
+    #[ignore = "needs the test vectors from `circuit_setup/scripts/run_setup.sh ps256`"]
+    pub fn end_to_end_test_ps256() {
+        run_test("ps256", "jwt");
+    }
+    #[test]
+    #[serial]
+    #[ignore = "needs the test vectors from `circuit_setup/scripts/run_setup.sh rs384`"]
+    pub fn end_to_end_test_rs384() {
+        run_test("rs384", "jwt");
+    }
+    #[test]
+    #[serial]
+    #[ignore = "needs the test vectors from `circuit_setup/scripts/run_setup.sh rs512`"]
+    pub fn end_to_end_test_rs512() {
+        run_test("rs512", "jwt");
+    }
+    #[test]
+    #[serial]
+    #[ignore = "needs the test vectors from `circuit_setup/scripts/run_setup.sh rs256-3072`"]
+    pub fn end_to_end_test_rs256_3072() {
+        run_test("rs256-3072", "jwt");
+    }
+    #[test]
+    #[serial]
+    #[ignore = "needs the test vectors from `circuit_setup/scripts/run_setup.sh rs256-4096`"]
+    pub fn end_to_end_test_rs256_4096() {
+        run_test("rs256-4096", "jwt");
+    }
+    #[test]
+    #[serial]
+    #[ignore = "needs the test vectors from `circuit_setup/scripts/run_setup.sh sd-jwt`"]
+    pub fn end_to_end_test_sd_jwt() {
+        run_test("sd-jwt", "sd-jwt");
+    }
+    #[test]
+    #[serial]
+    #[ignore = "needs the test vectors from `circuit_setup/scripts/run_setup.sh rs256-nested`"]
+    pub fn end_to_end_test_rs256_nested() {
+        run_test("rs256-nested", "jwt");
+    }
+    #[test]
+    #[serial]
+    #[ignore = "needs the test vectors from `circuit_setup/scripts/run_setup.sh rs256-age`"]
+    pub fn end_to_end_test_rs256_age() {
+        run_test("rs256-age", "jwt");
+    }
+    #[test]
+    #[serial]
+    #[ignore = "needs the test vectors from `circuit_setup/scripts/run_setup.sh rs256-optional`"]
+    pub fn end_to_end_test_rs256_optional() {
+        run_test("rs256-optional", "jwt");
+    }
 
     #[test]
     #[serial]
@@ -983,84 +1107,100 @@ mod tests {
         run_test("mdl1", "mdl");
     }
 
+    #[test]
+    fn test_range_check_commitments() {
+        use crate::rangeproof::RangeProof;
+        use ark_std::UniformRand;
+
+        let rng = &mut ark_std::test_rng();
+        let (range_pk, range_vk) = RangeProofPK::<ECPairing>::setup(RANGE_PROOF_INTERVAL_BITS);
+        let bases = [G1Affine::rand(rng), G1Affine::rand(rng)];
+        let attr = 739_000u64;  // a daystamp
+        let r = Fr::rand(rng);
+        let c = bases[0] * Fr::from(attr) + bases[1] * r;
+        let opening = PedersenOpening { bases: bases.to_vec(), m: Fr::from(attr), r, c };
+
+        for (side, bound, holds) in [(BoundSide::Lower, attr - 10, true), (BoundSide::Lower, attr, true), (BoundSide::Upper, attr + 10, true), 
+                                     (BoundSide::Upper, attr, true), (BoundSide::Upper, attr - 1, false), (BoundSide::Lower, attr + 1, false)] {
+            let check = RangeCheck { attribute: "birth_date".to_string(), side, bound, bits: RANGE_PROOF_INTERVAL_BITS };
+            let com = range_check_opening(&opening, &check);
+            assert_eq!(com.c, range_check_commitment(&c, &bases[0], &check));
+            assert_eq!(com.c, bases[0] * com.m + bases[1] * com.r);
+            assert_eq!(fits_in_bits(&com.m, check.bits), holds);
+            if holds {
+                let proof = RangeProof::prove_n_bits(&com, RANGE_PROOF_INTERVAL_BITS, &range_pk.powers);
+                let bases_g1 = [bases[0].into(), bases[1].into()];
+                assert!(proof.verify_n_bits(&range_check_commitment(&c, &bases[0], &check), &bases_g1, RANGE_PROOF_INTERVAL_BITS, &range_vk));
+            }
+        }
+    }
+
     fn run_test(name: &str, cred_type: &str) {
-        let base_path = PathBuf::from(format!("test-vectors/{}", name));
-        let paths = CachePaths::new(base_path.clone());
+        let store = FsParamStore::new(format!("test-vectors/{}", name));
+        fs::create_dir_all(store.path("cache")).unwrap();
 
         println!("Running end-to-end-test for {}, credential type {}", name, cred_type);
         println!("Requires that `../setup/run_setup.sh {}` has already been run", name);
         println!("These tests are slow; best run with the `--release` flag"); 
 
         println!("Running zksetup");
-        let ret = run_zksetup(base_path);
-        assert!(ret == 0);
+        run_zksetup(&store).unwrap();
 
         println!("Running prove (creating client state)");
-        let config_str = fs::read_to_string(&paths.config).unwrap_or_else(|_| panic!("Unable to read config from {} ", paths.config));
+        let config_str = store.read_string(keys::CONFIG).unwrap_or_else(|_| panic!("Unable to read config from {} ", keys::CONFIG));
         let config = parse_config(&config_str).expect("Failed to parse config");
     
         let (prover_inputs, prover_aux) = 
         if cred_type == "mdl" {
-            (GenericInputsJSON::new(&paths.mdl_prover_inputs), Some(fs::read_to_string(&paths.mdl_prover_aux).unwrap()))
+            let prover_inputs = serde_json::from_str(&store.read_string(keys::MDL_PROVER_INPUTS).unwrap()).unwrap();
+            (GenericInputsJSON{prover_inputs}, Some(store.read_string(keys::MDL_PROVER_AUX).unwrap()))
         }
         else {
-            let jwt = fs::read_to_string(&paths.jwt).unwrap_or_else(|_| panic!("Unable to read JWT file from {}", paths.jwt));
-            let issuer_pem = fs::read_to_string(&paths.issuer_pem).unwrap_or_else(|_| panic!("Unable to read issuer public key PEM from {} ", paths.issuer_pem));   
-            let device_pub_pem = fs::read_to_string(&paths.device_pub_pem).ok();
+            let jwt = store.read_string(keys::JWT).unwrap_or_else(|_| panic!("Unable to read JWT file from {}", keys::JWT));
+            let issuer_pem = store.read_string(keys::ISSUER_PEM).unwrap_or_else(|_| panic!("Unable to read issuer public key PEM from {} ", keys::ISSUER_PEM));   
+            let device_pub_pem = store.read_string(keys::DEVICE_PUB_PEM).ok();
             let (prover_inputs_json, prover_aux_json, _public_ios_json) = 
                 prepare_prover_inputs(&config, &jwt, &issuer_pem, device_pub_pem.as_deref()).expect("Failed to prepare prover inputs");    
             (GenericInputsJSON{prover_inputs: prover_inputs_json}, Some(json!(prover_aux_json).to_string()))
         };
             
-        let client_state = create_client_state(&paths, &prover_inputs, prover_aux.as_ref(), cred_type).unwrap();
-        // We read and write the client state and proof to disk for testing, to be consistent with the command-line tool
-        write_to_file(&client_state, &paths.client_state);
-        let mut client_state: ClientState<CrescentPairing> = read_from_file(&paths.client_state).unwrap();
+        let client_state = create_client_state(&store, &prover_inputs, prover_aux.as_ref(), cred_type).unwrap();
+        // We read and write the client state and proof to the store for testing, to be consistent with the command-line tool
+        write_param(&store, keys::CLIENT_STATE, &client_state).unwrap();
+        let mut client_state: ClientState<CrescentPairing> = read_param(&store, keys::CLIENT_STATE).unwrap();
 
         println!("Running show");
         let pm = "some presentation message".to_string();
-        let io_locations = IOLocations::new(&paths.io_locations);    
-        let range_pk : RangeProofPK<CrescentPairing> = read_from_file(&paths.range_pk).unwrap();
-        assert!(PathBuf::from(&paths.proof_spec).exists());
-        let ps_raw = fs::read_to_string(&paths.proof_spec).expect("Proof spec file exists, but failed while reading it");
+        let io_locations = IOLocations::new_from_str(&store.read_string(keys::IO_LOCATIONS).unwrap());
+        let range_pk : RangeProofPK<CrescentPairing> = read_param(&store, keys::RANGE_PK).unwrap();
+        assert!(store.contains(keys::PROOF_SPEC));
+        let ps_raw = store.read_string(keys::PROOF_SPEC).expect("Proof spec file exists, but failed while reading it");
         let mut proof_spec : ProofSpec = serde_json::from_str(&ps_raw).unwrap();
         proof_spec.presentation_message = Some(pm.as_bytes().to_vec());
         let device_signature = 
         if proof_spec.device_bound.is_some() && proof_spec.device_bound.unwrap() {
-            let device = TestDevice::new_from_file(&paths.device_prv_pem);
+            let device = TestDevice::new_from_pem(&store.read_string(keys::DEVICE_PRV_PEM).unwrap());
             Some(device.sign(proof_spec.presentation_message.as_ref().unwrap()))
         } else {
             None
         };
-        let proof = if cred_type == "mdl" {
-            create_show_proof_mdl(&mut client_state, &range_pk, &proof_spec, &io_locations, device_signature)
-        } else {
-            create_show_proof(&mut client_state, &range_pk, &io_locations, &proof_spec, device_signature)
-        };
+        let proof = create_show_proof(&mut client_state, &range_pk, &io_locations, &proof_spec, device_signature);
         assert!(proof.is_ok());
         let show_proof = proof.unwrap();
 
-        write_to_file(&show_proof, &paths.show_proof);
-        let show_proof : ShowProof<CrescentPairing> = read_from_file(&paths.show_proof).unwrap();
+        write_param(&store, keys::SHOW_PROOF, &show_proof).unwrap();
+        let show_proof : ShowProof<CrescentPairing> = read_param(&store, keys::SHOW_PROOF).unwrap();
 
         print!("Running verify");
-        let pvk : PreparedVerifyingKey<CrescentPairing> = read_from_file(&paths.groth16_pvk).unwrap();
-        let vk : VerifyingKey<CrescentPairing> = read_from_file(&paths.groth16_vk).unwrap();
-        let range_vk : RangeProofVK<CrescentPairing> = read_from_file(&paths.range_vk).unwrap();
-        let io_locations_str = std::fs::read_to_string(&paths.io_locations).unwrap();
-        let issuer_pem = std::fs::read_to_string(&paths.issuer_pem).unwrap();
-    
-        let vp = VerifierParams{vk, pvk, range_vk, io_locations_str, issuer_pem, config_str: config_str.clone()};
-        assert!(PathBuf::from(&paths.proof_spec).exists());
-        let ps_raw = fs::read_to_string(&paths.proof_spec).expect("Proof spec file exists, but failed while reading it");
+        let vp = VerifierParams::<CrescentPairing>::new(&store).unwrap();
+        let ps_raw = store.read_string(keys::PROOF_SPEC).expect("Proof spec file exists, but failed while reading it");
         let mut proof_spec : ProofSpec = serde_json::from_str(&ps_raw).unwrap();
         proof_spec.presentation_message = Some(pm.as_bytes().to_vec());
-        let (verify_result, _data) = if cred_type == "mdl" {
-            verify_show_mdl(&vp, &show_proof, &proof_spec)
-        } else {
-            verify_show(&vp, &show_proof, &proof_spec)
-        };
-        assert!(verify_result);
+        let verify_result = verify_show(&vp, &show_proof, &proof_spec, &VerifierPolicy::default(), &SystemClock);
+        assert!(verify_result.is_ok(), "verify failed: {}", verify_result.unwrap_err());
+        let presentation = verify_result.unwrap();
+        assert_eq!(presentation.proof_time, show_proof.cur_time);
+        assert_eq!(presentation.device_bound, proof_spec.device_bound.unwrap_or(false));
     }
 
 }
@@ -1,16 +1,22 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
-use ark_groth16::{VerifyingKey,PreparedVerifyingKey};
-use ark_serialize::CanonicalSerialize;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::thread_rng;
+use crescent::ceremony::CeremonyParams;
 use crescent::device::TestDevice;
 use crescent::groth16rand::{ClientState, ShowGroth16};
-use crescent::rangeproof::{RangeProofPK, RangeProofVK};
-use crescent::utils::{read_from_file, string_to_byte_vec, write_to_file};
-use crescent::{create_client_state, create_show_proof, create_show_proof_mdl, run_zksetup, verify_show, verify_show_mdl, CachePaths, ShowProof, VerifierParams, ProofSpec};
+use crescent::rangeproof::RangeProofPK;
+use crescent::policy::{SystemClock, VerifierPolicy};
+use crescent::status_list::StatusListFile;
+use crescent::store::{keys, read_param, write_param, FsParamStore, ParamStore};
+use crescent::utils::string_to_byte_vec;
+use crescent::{create_client_state, create_show_proof, run_ceremony_init, run_ceremony_verify, run_zksetup, verify_show, ShowProof, VerifierParams, ProofSpec};
 use crescent::CrescentPairing;
-use crescent::prep_inputs::{prepare_prover_inputs, parse_config};
-use crescent::structs::{GenericInputsJSON, IOLocations, ProverInput};
+use crescent::config::{load_config, parse_config};
+use crescent::estimate::{estimate_constraints, measure_jwt, FitReport};
+use crescent::prep_inputs::prepare_prover_inputs;
+use crescent::structs::{GenericInputsJSON, IOLocations};
 use serde_json::json;
 use sha2::{Digest, Sha256};
 use std::env::current_dir;
@@ -26,10 +32,25 @@ fn main() {
         Command::Zksetup{ name } => {
             let name_path = format!("test-vectors/{}", name);
             let base_path = root.join(name_path);
-            let ret = run_zksetup(base_path);
-            if ret == 0 {
-                
-            }
+            let store = open_store(base_path);
+            run_zksetup(&store).expect("zksetup failed");
+        }
+        Command::CeremonyInit { name, ptau, output } => {
+            let name_path = format!("test-vectors/{}", name);
+            let base_path = root.join(name_path);
+            let store = open_store(base_path);
+            let ceremony = run_ceremony_init(&store, &ptau).expect("ceremony-init failed");
+            write_ceremony(&output, &ceremony);
+            println!("Wrote the initial parameters of the ceremony to {}", output.display());
+        }
+        Command::Contribute { input, output } => {
+            run_contribute(input, output);
+        }
+        Command::VerifyCeremony { name, ptau, input } => {
+            let name_path = format!("test-vectors/{}", name);
+            let base_path = root.join(name_path);
+            let store = open_store(base_path);
+            run_verify_ceremony(&store, ptau, input);
         }
         Command::Prove { name } | Command::Prepare { name } => {
             let name_path = format!("test-vectors/{}", name);
@@ -46,6 +67,18 @@ fn main() {
             let base_path = root.join(name_path);
             run_verifier(base_path, presentation_message);
         }
+        Command::CheckConfig { config } => {
+            match load_config(config) {
+                Ok(config) => println!("Config is valid: {} credential signed with {}, {} claims", config.credtype, config.alg, config.claims.len()),
+                Err(e) => {
+                    eprintln!("Invalid config: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Estimate { config, credentials } => {
+            run_estimate(config, credentials);
+        }
     }
 }
 
@@ -64,6 +97,34 @@ pub enum Command {
         name: String,
     },
 
+    #[structopt(about = "Create the initial parameters of a multi-party setup ceremony, from a phase-1 .ptau file.")]
+    CeremonyInit {
+        #[structopt(long)]
+        name: String,
+        #[structopt(parse(from_os_str), long)]
+        ptau: PathBuf,
+        #[structopt(parse(from_os_str), long)]
+        output: PathBuf,
+    },
+
+    #[structopt(about = "Contribute randomness to the parameters of a setup ceremony.")]
+    Contribute {
+        #[structopt(parse(from_os_str), long)]
+        input: PathBuf,
+        #[structopt(parse(from_os_str), long)]
+        output: PathBuf,
+    },
+
+    #[structopt(about = "Verify the contributions of a setup ceremony, and write its public params (instead of zksetup).")]
+    VerifyCeremony {
+        #[structopt(long)]
+        name: String,
+        #[structopt(parse(from_os_str), long)]
+        ptau: PathBuf,
+        #[structopt(parse(from_os_str), long)]
+        input: PathBuf,
+    },
+
     #[structopt(about = "Run prover. (deprecated, use `prepare` instead)")]
     Prove {
         #[structopt(long)]
@@ -90,34 +151,133 @@ pub enum Command {
         #[structopt(long, about = "Optional presentation message to include in the proof.")]
         presentation_message: Option<String>,
     },
+
+    #[structopt(about = "Check a config.json file before running the circuit setup.")]
+    CheckConfig {
+        #[structopt(parse(from_os_str), long)]
+        config: PathBuf,
+    },
+
+    #[structopt(about = "Estimate the circuit size of a config.json file, and check sample JWTs or SD-JWTs against it.")]
+    Estimate {
+        #[structopt(parse(from_os_str), long)]
+        config: PathBuf,
+        #[structopt(parse(from_os_str), about = "Sample credentials, one token per file.")]
+        credentials: Vec<PathBuf>,
+    },
 }
 
 
+// Open the parameter store for a test vector directory, creating the cache/ subdirectory that holds
+// the outputs of zksetup, prepare and show if needed
+fn open_store(base_path: PathBuf) -> FsParamStore {
+    if !base_path.is_dir() {
+        println!("base_path = {}", base_path.display());
+        panic!("invalid path");
+    }
+    let store = FsParamStore::new(base_path);
+    let cache_path = store.path("cache");
+    if !cache_path.is_dir() {
+        println!("Creating directory {} to store data", cache_path.display());
+        fs::create_dir(&cache_path).unwrap();
+    }
+    store
+}
+
+// The ceremony files are checked when read: all the points must be in the right groups
+fn read_ceremony(path: &PathBuf) -> CeremonyParams {
+    let data = fs::read(path).unwrap_or_else(|e| panic!("Unable to read {}: {}", path.display(), e));
+    CeremonyParams::deserialize_uncompressed(data.as_slice()).unwrap_or_else(|e| panic!("Invalid ceremony file {}: {}", path.display(), e))
+}
+
+fn write_ceremony(path: &PathBuf, ceremony: &CeremonyParams) {
+    let mut data = Vec::with_capacity(ceremony.uncompressed_size());
+    ceremony.serialize_uncompressed(&mut data).unwrap();
+    fs::write(path, data).unwrap_or_else(|e| panic!("Unable to write {}: {}", path.display(), e));
+}
+
+pub fn run_contribute(input: PathBuf, output: PathBuf) {
+    let mut ceremony = read_ceremony(&input);
+    let hash = ceremony.contribute(&mut thread_rng());
+    write_ceremony(&output, &ceremony);
+    println!("Wrote contribution {} to {}", ceremony.contributions.len(), output.display());
+    println!("Contribution hash: {}", hex::encode(hash));
+}
+
+pub fn run_verify_ceremony(store: &FsParamStore, ptau: PathBuf, input: PathBuf) {
+    let ceremony = read_ceremony(&input);
+    match run_ceremony_verify(store, &ptau, &ceremony) {
+        Ok(hashes) => {
+            println!("The ceremony is valid, with {} contributions:", hashes.len());
+            for (i, hash) in hashes.iter().enumerate() {
+                println!("  {}: {}", i + 1, hex::encode(hash));
+            }
+        }
+        Err(e) => {
+            eprintln!("Invalid ceremony: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+pub fn run_estimate(config_path: PathBuf, credentials: Vec<PathBuf>) {
+    let config = load_config(config_path).unwrap_or_else(|e| {
+        eprintln!("Invalid config: {}", e);
+        std::process::exit(1);
+    });
+    println!("Estimated circuit size (constraints):");
+    println!("{}", estimate_constraints(&config));
+    if credentials.is_empty() {
+        return;
+    }
+    if config.is_mdl() {
+        eprintln!("mDL credentials are checked with the fit-check tool of circuit_setup/mdl-tools");
+        std::process::exit(1);
+    }
+
+    let sizes = credentials.iter().map(|path| {
+        let name = path.display().to_string();
+        match fs::read_to_string(path) {
+            Ok(token) => measure_jwt(&config, &name, &token),
+            Err(e) => panic!("Unable to read credential {}: {}", name, e),
+        }
+    }).collect::<Vec<_>>();
+    let report = FitReport::new(&config, sizes);
+    println!("{}", report);
+    if report.failures().next().is_some() {
+        std::process::exit(1);
+    }
+}
+
 pub fn run_prover(
     base_path: PathBuf,
 ) {
-    let paths = CachePaths::new(base_path);
-    let config_str = fs::read_to_string(&paths.config).unwrap_or_else(|_| panic!("Unable to read config from {} ", paths.config));
+    let store = open_store(base_path);
+    let config_str = store.read_string(keys::CONFIG).unwrap_or_else(|_| panic!("Unable to read config from {} ", store.path(keys::CONFIG).display()));
     let config = parse_config(&config_str).expect("Failed to parse config");
 
-    let client_state = 
-    if config.contains_key("credtype") && config.get("credtype").unwrap() == "mdl" {
-        let prover_inputs = GenericInputsJSON::new(&paths.mdl_prover_inputs);
-        let prover_aux_string = fs::read_to_string(&paths.mdl_prover_aux).unwrap();
-        create_client_state(&paths, &prover_inputs, Some(&prover_aux_string), "mdl").unwrap()
+    let mut client_state = 
+    if config.is_mdl() {
+        let prover_inputs = GenericInputsJSON{prover_inputs: serde_json::from_str(&store.read_string(keys::MDL_PROVER_INPUTS).unwrap()).unwrap()};
+        let prover_aux_string = store.read_string(keys::MDL_PROVER_AUX).unwrap();
+        create_client_state(&store, &prover_inputs, Some(&prover_aux_string), "mdl").unwrap()
     }
     else {
-        let jwt = fs::read_to_string(&paths.jwt).unwrap_or_else(|_| panic!("Unable to read JWT file from {}", paths.jwt));
-        let issuer_pem = fs::read_to_string(&paths.issuer_pem).unwrap_or_else(|_| panic!("Unable to read issuer public key PEM from {} ", paths.issuer_pem));   
-        let device_pub_pem = fs::read_to_string(&paths.device_pub_pem).ok();
+        let jwt = store.read_string(keys::JWT).unwrap_or_else(|_| panic!("Unable to read JWT file from {}", store.path(keys::JWT).display()));
+        let issuer_pem = store.read_string(keys::ISSUER_PEM).unwrap_or_else(|_| panic!("Unable to read issuer public key PEM from {} ", store.path(keys::ISSUER_PEM).display()));   
+        let device_pub_pem = store.read_string(keys::DEVICE_PUB_PEM).ok();
         let (prover_inputs_json, prover_aux_json, _public_ios_json) = 
             prepare_prover_inputs(&config, &jwt, &issuer_pem, device_pub_pem.as_deref()).expect("Failed to prepare prover inputs");    
         let prover_inputs = GenericInputsJSON{prover_inputs: prover_inputs_json};
         let prover_aux_string = json!(prover_aux_json).to_string();
-        create_client_state(&paths, &prover_inputs, Some(&prover_aux_string), "jwt").unwrap()
+        create_client_state(&store, &prover_inputs, Some(&prover_aux_string), &config.credtype).unwrap()
     };
 
-    write_to_file(&client_state, &paths.client_state);
+    if store.contains(keys::REVOCATION_WITNESS) {
+        client_state.revocation_witness = Some(read_param(&store, keys::REVOCATION_WITNESS).unwrap());
+    }
+
+    write_param(&store, keys::CLIENT_STATE, &client_state).unwrap();
 }
 
 fn _show_groth16_proof_size(show_groth16: &ShowGroth16<CrescentPairing>) -> usize {
@@ -165,10 +325,11 @@ fn show_proof_size(show_proof: &ShowProof<CrescentPairing>) -> usize {
     total
 }
 
-fn load_proof_spec(proof_spec_file_path : &str, presentation_message: Option<String>) -> ProofSpec {
-    let ps_raw = if PathBuf::from(proof_spec_file_path).exists() {
+fn load_proof_spec(store : &FsParamStore, presentation_message: Option<String>) -> ProofSpec {
+    let proof_spec_file_path = store.path(keys::PROOF_SPEC).display().to_string();
+    let ps_raw = if store.contains(keys::PROOF_SPEC) {
         println!("Using proof spec file {}", proof_spec_file_path);
-        fs::read_to_string(proof_spec_file_path).expect("Proof spec file exists, but failed while reading it")
+        store.read_string(keys::PROOF_SPEC).expect("Proof spec file exists, but failed while reading it")
     } else {
         println!("Proof spec file not found; using default (looked for file: {}) ", proof_spec_file_path);
         crescent::DEFAULT_PROOF_SPEC.to_string()
@@ -183,6 +344,13 @@ fn load_proof_spec(proof_spec_file_path : &str, presentation_message: Option<Str
         ps.presentation_message = string_to_byte_vec(presentation_message);
     }
 
+    // Use the status list from the test vector directory, if there is one
+    if let Some(status_list) = ps.status_list.as_mut() {
+        if store.contains(keys::STATUS_LIST) {
+            status_list.list = StatusListFile::open(store.path(keys::STATUS_LIST)).expect("Failed to load the status list").list().clone();
+        }
+    }
+
     if ps.device_bound.is_some() && ps.device_bound.unwrap() {
         let pm_bytes = ps.presentation_message.expect("Presentation message is required for device-bound credentials");
         // We hash here in the CLI tool, but applications calling the
@@ -200,60 +368,67 @@ pub fn run_show(
     presentation_message: Option<String>
 ) {
     let proof_timer = std::time::Instant::now();
-    let paths = CachePaths::new(base_path);
-    let io_locations = IOLocations::new(&paths.io_locations);
-    let mut client_state: ClientState<CrescentPairing> = read_from_file(&paths.client_state).unwrap();
-    let range_pk : RangeProofPK<CrescentPairing> = read_from_file(&paths.range_pk).unwrap();
+    let store = open_store(base_path);
+    let io_locations = IOLocations::new_from_str(&store.read_string(keys::IO_LOCATIONS).unwrap());
+    let mut client_state: ClientState<CrescentPairing> = read_param(&store, keys::CLIENT_STATE).unwrap();
+    let range_pk : RangeProofPK<CrescentPairing> = read_param(&store, keys::RANGE_PK).unwrap();
 
     // load the proof spec (also hashes the presentation message if the cred is device bound)
-    let proof_spec = load_proof_spec(&paths.proof_spec, presentation_message);
+    let proof_spec = load_proof_spec(&store, presentation_message);
     let device_signature = 
     if proof_spec.device_bound.is_some() && proof_spec.device_bound.unwrap() {
-        let device = TestDevice::new_from_file(&paths.device_prv_pem);
+        let device = TestDevice::new_from_pem(&store.read_string(keys::DEVICE_PRV_PEM).unwrap());
         Some(device.sign(proof_spec.presentation_message.as_ref().unwrap()))
     } else {
         None
     };
 
-    let show_proof = if client_state.credtype == "mdl" {
-        create_show_proof_mdl(&mut client_state, &range_pk, &proof_spec, &io_locations, device_signature).unwrap()
-    } else {
-        create_show_proof(&mut client_state, &range_pk, &io_locations, &proof_spec, device_signature).unwrap()
-    };
+    let show_proof = create_show_proof(&mut client_state, &range_pk, &io_locations, &proof_spec, device_signature).unwrap();
     println!("Proving time: {:?}", proof_timer.elapsed());
 
     let _ = show_proof_size(&show_proof);
 
-    write_to_file(&show_proof, &paths.show_proof);
+    write_param(&store, keys::SHOW_PROOF, &show_proof).unwrap();
 }
 
 pub fn run_verifier(base_path: PathBuf, presentation_message: Option<String>) {
-    let paths = CachePaths::new(base_path);
-    let show_proof : ShowProof<CrescentPairing> = read_from_file(&paths.show_proof).unwrap();
-    let pvk : PreparedVerifyingKey<CrescentPairing> = read_from_file(&paths.groth16_pvk).unwrap();
-    let vk : VerifyingKey<CrescentPairing> = read_from_file(&paths.groth16_vk).unwrap();
-    let range_vk : RangeProofVK<CrescentPairing> = read_from_file(&paths.range_vk).unwrap();
-    let io_locations_str = std::fs::read_to_string(&paths.io_locations).unwrap();
-    let issuer_pem = std::fs::read_to_string(&paths.issuer_pem).unwrap();
-    let config_str = std::fs::read_to_string(&paths.config).unwrap();
-    let config_json: serde_json::Value = serde_json::from_str(&config_str).unwrap();
-    // read the credtype from the config, default to "jwt" if not present
-    let credtype = config_json.get("credtype").and_then(|v| v.as_str()).unwrap_or("jwt");
-    let vp = VerifierParams{vk, pvk, range_vk, io_locations_str, issuer_pem, config_str};
-
-    let proof_spec = load_proof_spec(&paths.proof_spec, presentation_message);  
+    let store = open_store(base_path);
+    let show_proof : ShowProof<CrescentPairing> = read_param(&store, keys::SHOW_PROOF).unwrap();
+    let vp = VerifierParams::<CrescentPairing>::new(&store).unwrap();
+
+    let proof_spec = load_proof_spec(&store, presentation_message);  
     println!("show_proof.show_range_attr.len() = {}", show_proof.show_range_attr.len());
-    let (verify_result, data) = if credtype == "mdl" {
-        verify_show_mdl(&vp, &show_proof, &proof_spec)
-    } else {
-        verify_show(&vp, &show_proof, &proof_spec)
-    };
+    let verify_result = verify_show(&vp, &show_proof, &proof_spec, &VerifierPolicy::default(), &SystemClock);
 
-    if verify_result {
-        println!("Verify succeeded, got data '{}'", data);
-    }
-    else {
-        println!("Verify failed")
+    match verify_result {
+        Ok(presentation) => {
+            println!("Verify succeeded");
+            for (name, value) in &presentation.revealed {
+                println!("  revealed {} = {}", name, value);
+            }
+            for name in &presentation.absent {
+                println!("  {} is absent from the credential", name);
+            }
+            for (name, years) in &presentation.range_over_year {
+                println!("  proved {} is over {} years", name, years);
+            }
+            for predicate in &presentation.range_predicates {
+                println!("  proved {}", serde_json::to_string(predicate).unwrap());
+            }
+            for predicate in &presentation.set_predicates {
+                println!("  proved {}", serde_json::to_string(predicate).unwrap());
+            }
+            if let Some(pseudonym) = &presentation.pseudonym {
+                println!("  pseudonym = {}", pseudonym);
+            }
+            if let Some(epoch) = presentation.revocation_epoch {
+                println!("  proved the credential is not revoked (accumulator epoch {})", epoch);
+            }
+            if presentation.status_list_checked {
+                println!("  proved the credential's status in the status list is valid");
+            }
+        }
+        Err(e) => println!("Verify failed: {}", e),
     }
 
 }
\ No newline at end of file
@@ -0,0 +1,293 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use ark_ec::{pairing::Pairing, CurveGroup, VariableBaseMSM};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+use crate::dlog::{DLogPoK, Link, PedersenOpening};
+use crate::formats::{commitment_index, credential_format_from_config, get_io_location};
+use crate::groth16rand::ClientState;
+use crate::policy::{Clock, VerifierPolicy};
+use crate::prep_inputs::create_proof_spec_internal;
+use crate::return_error;
+use crate::rangeproof::RangeProofPK;
+use crate::structs::IOLocations;
+use crate::{create_show_proof, verify_show, CrescentPairing, ProofSpec, ShowProof, VerifiedPresentation, VerifierParams, VerifyError};
+
+/// An attribute of one of the credentials of a multi-credential presentation, identified by the
+/// credential's position in `MultiProofSpec::credentials`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AttributeRef {
+    pub credential: usize,
+    pub attribute: String,
+}
+
+/// Proof specification for a presentation of several credentials, e.g.,
+/// ```json
+/// {
+///     "credentials": [{"revealed": ["email"]}, {"revealed": ["resident_state"]}],
+///     "equal": [[{"credential": 0, "attribute": "given_name"}, {"credential": 1, "attribute": "given_name"}]],
+///     "presentation_message": [1, 2, 3, 4]
+/// }
+/// ```
+/// Each entry of `equal` is a group of attributes proven to have the same value, without revealing it. The values are
+/// compared as field elements, so the attributes must be encoded the same way in each credential: integers, and strings
+/// revealed as a single field element (packed without their quotes, see `CredentialFormat::encode_string_claim`), can be
+/// linked across formats. Attributes revealed as digests or as several field elements cannot be linked.
+/// The presentation message replaces the one of each credential's proof spec, which binds the shows together.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MultiProofSpec {
+    pub credentials: Vec<ProofSpec>,
+    #[serde(default)]
+    pub equal: Vec<Vec<AttributeRef>>,
+    pub presentation_message: Option<Vec<u8>>,
+}
+
+/// One show proof per credential, and a proof that the attributes in each group of `MultiProofSpec::equal` are equal
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MultiShowProof<E: Pairing> {
+    pub show_proofs: Vec<ShowProof<E>>,
+    pub equality_proof: Option<DLogPoK<E::G1>>,
+}
+
+/// What the prover needs to show one of the credentials of a multi-credential presentation
+pub struct CredentialShow<'a, 'b> {
+    pub client_state: &'a mut ClientState<CrescentPairing>,
+    pub range_pk: &'a RangeProofPK<'b, CrescentPairing>,
+    pub io_locations: &'a IOLocations,
+    pub device_signature: Option<Vec<u8>>,
+}
+
+impl MultiProofSpec {
+    /// Check that the equality groups refer to credentials in the presentation
+    fn validate(&self) -> Result<(), VerifyError> {
+        for group in &self.equal {
+            if group.len() < 2 {
+                return Err(VerifyError::InvalidProofSpec("an equality group must have at least two attributes".to_string()));
+            }
+            if let Some(attr) = group.iter().find(|a| a.credential >= self.credentials.len()) {
+                return Err(VerifyError::InvalidProofSpec(format!("attribute {} refers to credential {}, but the presentation has {} credentials", attr.attribute, attr.credential, self.credentials.len())));
+            }
+        }
+        Ok(())
+    }
+
+    /// The proof spec used for the show of credential `i`: the linked attributes are committed, and the
+    /// presentation message is the one of the presentation
+    pub fn credential_spec(&self, i: usize) -> ProofSpec {
+        let mut spec = self.credentials[i].clone();
+        let mut committed = spec.committed.take().unwrap_or_default();
+        for attr in self.equal.iter().flatten().filter(|a| a.credential == i) {
+            if !committed.contains(&attr.attribute) {
+                committed.push(attr.attribute.clone());
+            }
+        }
+        spec.committed = if committed.is_empty() { None } else { Some(committed) };
+        spec.presentation_message = self.presentation_message.clone();
+        spec
+    }
+
+    fn context(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap()
+    }
+}
+
+// The position of the attribute in the public inputs of the credential's Groth16 proof, and the index of its commitment in the show
+fn committed_attribute(config_str: &str, io_locations: &IOLocations, proof_spec: &ProofSpec, attribute: &str, inputs_len: usize) -> Result<(usize, usize), VerifyError> {
+    let format = credential_format_from_config(config_str).map_err(|e| VerifyError::InvalidProofSpec(e.to_string()))?;
+    let proof_spec = create_proof_spec_internal(proof_spec, config_str).map_err(|e| VerifyError::InvalidProofSpec(e.to_string()))?;
+    let io_types = format.io_types(&proof_spec, io_locations, inputs_len)?;
+    let io_pos = get_io_location(io_locations, &format!("{}_value", attribute))?;
+    Ok((io_pos, commitment_index(&io_types, io_pos)?))
+}
+
+/// Prove that, in each group, the commitments open to the same value. Each statement is a commitment in its own pair of bases.
+pub fn prove_equalities<G: CurveGroup + VariableBaseMSM>(context: &[u8], groups: &[Vec<&PedersenOpening<G>>]) -> DLogPoK<G> {
+    let openings = groups.iter().flatten().collect::<Vec<_>>();
+    let y = openings.iter().map(|o| o.c).collect::<Vec<_>>();
+    let bases = openings.iter().map(|o| o.bases.iter().map(|&b| b.into()).collect()).collect::<Vec<Vec<G>>>();
+    let scalars = openings.iter().map(|o| vec![o.m, o.r]).collect::<Vec<_>>();
+    DLogPoK::prove_linked(Some(context), &y, &bases, &scalars, &equality_links(groups))
+}
+
+/// Verify a proof created with `prove_equalities`, where each statement is a commitment and its bases
+pub fn verify_equalities<G: CurveGroup + VariableBaseMSM>(proof: &DLogPoK<G>, context: &[u8], groups: &[Vec<(G, [G; 2])>]) -> bool {
+    let statements = groups.iter().flatten().collect::<Vec<_>>();
+    let y = statements.iter().map(|(c, _)| *c).collect::<Vec<_>>();
+    let bases = statements.iter().map(|(_, b)| b.to_vec()).collect::<Vec<_>>();
+    proof.verify_linked(Some(context), &bases, &y, &equality_links(groups))
+}
+
+// Link the committed value of the first statement of each group to the other statements of the group
+fn equality_links<T>(groups: &[Vec<T>]) -> Vec<Link> {
+    let mut links = vec![];
+    let mut first = 0;
+    for group in groups {
+        for k in 1..group.len() {
+            links.push(((first, 0), (first + k, 0)));
+        }
+        first += group.len();
+    }
+    links
+}
+
+/// Create a presentation of several credentials, with one show proof per credential (see `create_show_proof`) and a
+/// proof that the attributes in each group of `multi_spec.equal` have the same value
+pub fn create_multi_show_proof(credentials: &mut [CredentialShow], multi_spec: &MultiProofSpec) -> Result<MultiShowProof<CrescentPairing>, Box<dyn Error>> {
+    multi_spec.validate()?;
+    if credentials.len() != multi_spec.credentials.len() {
+        return_error!(format!("Proof spec is for {} credentials, but {} were provided", multi_spec.credentials.len(), credentials.len()));
+    }
+
+    // The show of each credential refreshes its commitments, so we keep the openings of the linked attributes after each show
+    let mut openings = vec![vec![]; credentials.len()];
+    let mut show_proofs = vec![];
+    for (i, cred) in credentials.iter_mut().enumerate() {
+        let proof_spec = multi_spec.credential_spec(i);
+        let show_proof = create_show_proof(cred.client_state, cred.range_pk, cred.io_locations, &proof_spec, cred.device_signature.take())?;
+        for attr in proof_spec.committed.iter().flatten() {
+            let (_, com_index) = committed_attribute(&cred.client_state.config_str, cred.io_locations, &proof_spec, attr, cred.client_state.inputs.len())?;
+            openings[i].push((attr.clone(), cred.client_state.committed_input_openings[com_index].clone()));
+        }
+        show_proofs.push(show_proof);
+    }
+
+    let mut groups = vec![];
+    for group in &multi_spec.equal {
+        let mut group_openings = vec![];
+        for attr in group {
+            let (_, opening) = openings[attr.credential].iter().find(|(a, _)| *a == attr.attribute).unwrap();
+            group_openings.push(opening);
+        }
+        if group_openings.iter().any(|o| o.m != group_openings[0].m) {
+            return_error!(format!("Attribute {} of credential {} does not have the same value as the attributes it should be equal to", group[0].attribute, group[0].credential));
+        }
+        groups.push(group_openings);
+    }
+    let equality_proof = if groups.is_empty() { None } else { Some(prove_equalities(&multi_spec.context(), &groups)) };
+
+    Ok(MultiShowProof { show_proofs, equality_proof })
+}
+
+/// Verify a multi-credential presentation, where `vps[i]` are the verifier parameters of the issuer of credential `i`.
+/// Returns the verified presentation of each credential.
+pub fn verify_multi_show(vps: &[&VerifierParams<CrescentPairing>], proof: &MultiShowProof<CrescentPairing>, multi_spec: &MultiProofSpec, policy: &VerifierPolicy, clock: &dyn Clock) -> Result<Vec<VerifiedPresentation>, VerifyError> {
+    multi_spec.validate()?;
+    if vps.len() != multi_spec.credentials.len() || proof.show_proofs.len() != multi_spec.credentials.len() {
+        return Err(VerifyError::MalformedProof(format!("proof spec is for {} credentials, got {} show proofs and {} verifier parameters", multi_spec.credentials.len(), proof.show_proofs.len(), vps.len())));
+    }
+
+    let mut presentations = vec![];
+    for (i, (vp, show_proof)) in vps.iter().zip(&proof.show_proofs).enumerate() {
+        presentations.push(verify_show(vp, show_proof, &multi_spec.credential_spec(i), policy, clock)?);
+    }
+
+    if multi_spec.equal.is_empty() {
+        return Ok(presentations);
+    }
+    let mut groups = vec![];
+    for group in &multi_spec.equal {
+        let mut statements = vec![];
+        for attr in group {
+            let (vp, show_proof) = (vps[attr.credential], &proof.show_proofs[attr.credential]);
+            let io_locations = IOLocations::new_from_str(&vp.io_locations_str);
+            let (io_pos, com_index) = committed_attribute(&vp.config_str, &io_locations, &multi_spec.credential_spec(attr.credential), &attr.attribute, show_proof.inputs_len)?;
+            let com = show_proof.show_groth16.commited_inputs[com_index];
+            statements.push((com, [vp.pvk.vk.gamma_abc_g1[io_pos].into(), vp.pvk.vk.delta_g1.into()]));
+        }
+        groups.push(statements);
+    }
+    let equality_proof = proof.equality_proof.as_ref().ok_or(VerifyError::MissingEqualityProof)?;
+    if !verify_equalities(equality_proof, &multi_spec.context(), &groups) {
+        return Err(VerifyError::InvalidEqualityProof);
+    }
+    println!("Equality proof verified successfully");
+
+    Ok(presentations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CrescentFr, G1};
+    use ark_std::UniformRand;
+
+    #[test]
+    fn test_equality_proof() {
+        let rng = &mut ark_std::test_rng();
+        let bases = (0..3).map(|_| [G1::rand(rng), G1::rand(rng)]).collect::<Vec<_>>();
+        let affine = |b: &[G1; 2]| b.map(|x| x.into_affine());
+        let (name, other) = (CrescentFr::rand(rng), CrescentFr::rand(rng));
+        let context = b"multi proof spec";
+
+        let a = DLogPoK::<G1>::pedersen_commit(&name, &affine(&bases[0]));
+        let b = DLogPoK::<G1>::pedersen_commit(&name, &affine(&bases[1]));
+        let c = DLogPoK::<G1>::pedersen_commit(&other, &affine(&bases[2]));
+        let d = DLogPoK::<G1>::pedersen_commit(&other, &affine(&bases[0]));
+
+        let proof = prove_equalities(context, &[vec![&a, &b], vec![&c, &d]]);
+        let statements = [vec![(a.c, bases[0]), (b.c, bases[1])], vec![(c.c, bases[2]), (d.c, bases[0])]];
+        assert!(verify_equalities(&proof, context, &statements));
+        assert!(!verify_equalities(&proof, b"other proof spec", &statements));
+
+        // The commitments must open to equal values
+        let forged = prove_equalities(context, &[vec![&a, &c]]);
+        assert!(!verify_equalities(&forged, context, &[vec![(a.c, bases[0]), (c.c, bases[2])]]));
+    }
+
+    #[test]
+    fn test_link_jwt_and_mdl_strings() {
+        use crate::config::parse_config;
+        use crate::formats::{CredentialFormat, Jwt, Mdl};
+        use crate::prep_inputs::prepare_prover_inputs;
+        use crate::ClaimValue;
+        use jwt_simple::prelude::*;
+        use std::str::FromStr;
+
+        // The given_name output by the JWT circuit, and by the mDL circuit (see prepare-prover-input in mdl-tools)
+        let key_pair = ES256KeyPair::generate();
+        let token = key_pair.sign(Claims::with_custom_claims(serde_json::json!({"given_name": "Alice"}), Duration::from_hours(1))).unwrap();
+        let config = parse_config(r#"{"alg": "ES256", "given_name": {"type": "string", "reveal": true, "max_claim_byte_len": 31}}"#).unwrap();
+        let (prover_inputs, _, _) = prepare_prover_inputs(&config, &token, &key_pair.public_key().to_pem().unwrap(), None).unwrap();
+        let jwt_name = CrescentFr::from_str(prover_inputs["given_name_value"].as_str().unwrap()).unwrap();
+        let mdl_name = Mdl.encode_string_claim("Alice").unwrap();
+        assert_eq!(jwt_name, mdl_name);
+        assert_eq!(Jwt.decode_claim("given_name", "string", &jwt_name).unwrap(), ClaimValue::String("Alice".to_string()));
+
+        // The committed names are linked across the two shows
+        let rng = &mut ark_std::test_rng();
+        let (jwt_bases, mdl_bases) = ([G1::rand(rng), G1::rand(rng)], [G1::rand(rng), G1::rand(rng)]);
+        let jwt_commitment = DLogPoK::<G1>::pedersen_commit(&jwt_name, &jwt_bases.map(|b| b.into_affine()));
+        let mdl_commitment = DLogPoK::<G1>::pedersen_commit(&mdl_name, &mdl_bases.map(|b| b.into_affine()));
+        let proof = prove_equalities(b"jwt and mdl", &[vec![&jwt_commitment, &mdl_commitment]]);
+        assert!(verify_equalities(&proof, b"jwt and mdl", &[vec![(jwt_commitment.c, jwt_bases), (mdl_commitment.c, mdl_bases)]]));
+
+        let other_commitment = DLogPoK::<G1>::pedersen_commit(&Mdl.encode_string_claim("Alicia").unwrap(), &mdl_bases.map(|b| b.into_affine()));
+        let forged = prove_equalities(b"jwt and mdl", &[vec![&jwt_commitment, &other_commitment]]);
+        assert!(!verify_equalities(&forged, b"jwt and mdl", &[vec![(jwt_commitment.c, jwt_bases), (other_commitment.c, mdl_bases)]]));
+    }
+
+    #[test]
+    fn test_credential_spec() {
+        let multi_spec: MultiProofSpec = serde_json::from_str(r#"{
+            "credentials": [{"revealed": ["email"], "committed": ["family_name"]}, {"revealed": []}],
+            "equal": [[{"credential": 0, "attribute": "given_name"}, {"credential": 1, "attribute": "given_name"}],
+                      [{"credential": 0, "attribute": "family_name"}, {"credential": 1, "attribute": "family_name"}]],
+            "presentation_message": [1, 2, 3]
+        }"#).unwrap();
+        assert!(multi_spec.validate().is_ok());
+        let spec0 = multi_spec.credential_spec(0);
+        assert_eq!(spec0.committed.unwrap(), vec!["family_name", "given_name"]);
+        assert_eq!(spec0.presentation_message.unwrap(), vec![1, 2, 3]);
+        assert_eq!(multi_spec.credential_spec(1).committed.unwrap(), vec!["given_name", "family_name"]);
+        assert_eq!(equality_links(&multi_spec.equal), vec![((0, 0), (1, 0)), ((2, 0), (3, 0))]);
+
+        let mut invalid = multi_spec.clone();
+        invalid.equal[0][1].credential = 2;
+        assert!(invalid.validate().is_err());
+        invalid.equal = vec![vec![AttributeRef { credential: 0, attribute: "given_name".to_string() }]];
+        assert!(invalid.validate().is_err());
+    }
+}
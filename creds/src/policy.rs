@@ -0,0 +1,99 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use crate::utils::utc_now_seconds;
+use crate::VerifyError;
+
+/// Source of the current time used by the verifier, in seconds since the UNIX epoch
+pub trait Clock {
+    fn now_seconds(&self) -> u64;
+}
+
+/// The system clock (or the JavaScript clock in wasm builds)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_seconds(&self) -> u64 {
+        utc_now_seconds()
+    }
+}
+
+/// A clock that always returns the same time, for deterministic tests and replaying stored proofs
+#[derive(Clone, Copy, Debug)]
+pub struct FixedClock(pub u64);
+
+impl Clock for FixedClock {
+    fn now_seconds(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Per-relying party settings for verifying show proofs
+#[derive(Clone, Debug, PartialEq)]
+pub struct VerifierPolicy {
+    /// Reject proofs created more than this many seconds ago
+    pub max_proof_age_seconds: u64,
+    /// If set, reject proofs whose creation time is more than this many seconds ahead of the verifier's clock.
+    /// The creation time is chosen by the prover and anchors the expiration check, so a proof dated in the
+    /// future would otherwise remain fresh until the verifier's clock catches up.
+    pub max_future_skew_seconds: Option<u64>,
+}
+
+impl VerifierPolicy {
+    pub const DEFAULT_MAX_PROOF_AGE_SECONDS: u64 = 300;
+
+    /// Check that a proof created at `proof_time` is acceptable at the time given by `clock`
+    pub fn check_freshness(&self, proof_time: u64, clock: &dyn Clock) -> Result<(), VerifyError> {
+        let now_seconds = clock.now_seconds();
+
+        if proof_time > now_seconds {
+            let ahead_seconds = proof_time - now_seconds;
+            println!("Proof created {} seconds in the future", ahead_seconds);
+            if let Some(max_skew_seconds) = self.max_future_skew_seconds {
+                if ahead_seconds > max_skew_seconds {
+                    return Err(VerifyError::FutureProof { ahead_seconds, max_skew_seconds });
+                }
+            }
+            return Ok(());
+        }
+
+        let age_seconds = now_seconds - proof_time;
+        println!("Proof created {} seconds ago", age_seconds);
+        if age_seconds > self.max_proof_age_seconds {
+            return Err(VerifyError::StaleProof { age_seconds, max_age_seconds: self.max_proof_age_seconds });
+        }
+        Ok(())
+    }
+}
+
+impl Default for VerifierPolicy {
+    fn default() -> Self {
+        Self {
+            max_proof_age_seconds: Self::DEFAULT_MAX_PROOF_AGE_SECONDS,
+            max_future_skew_seconds: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_freshness() {
+        let now = 1_700_000_000;
+        let clock = FixedClock(now);
+        let policy = VerifierPolicy { max_proof_age_seconds: 60, max_future_skew_seconds: Some(5) };
+
+        assert!(policy.check_freshness(now, &clock).is_ok());
+        assert!(policy.check_freshness(now - 60, &clock).is_ok());
+        assert_eq!(policy.check_freshness(now - 61, &clock), Err(VerifyError::StaleProof { age_seconds: 61, max_age_seconds: 60 }));
+        assert!(policy.check_freshness(now + 5, &clock).is_ok());
+        assert_eq!(policy.check_freshness(now + 6, &clock), Err(VerifyError::FutureProof { ahead_seconds: 6, max_skew_seconds: 5 }));
+
+        // Without a skew limit, proofs from the future are accepted
+        let policy = VerifierPolicy { max_future_skew_seconds: None, ..policy };
+        assert!(policy.check_freshness(now + 3600, &clock).is_ok());
+    }
+}
@@ -0,0 +1,279 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use chrono::{FixedOffset, NaiveDate, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+use crate::daystamp::{age_threshold_daystamp, daystamp_at};
+use crate::formats::CredentialFormat;
+use crate::{CrescentFr, ProofSpecInternal, RANGE_PROOF_INTERVAL_BITS};
+
+/// A range predicate on a committed attribute: `min <= attribute`, `attribute <= max`, or both.
+/// In a proof spec, this is written as, e.g., `{"attribute": "height", "min": 150}`,
+/// `{"attribute": "issue_date", "max": "2024-01-01"}` or `{"attribute": "exp", "min": "now+30d"}`.
+/// The optional `bits` entry sets the width of the range proofs: the distance between the attribute and each bound
+/// must be less than 2^bits. It must be a power of two, at most the width supported by the range proof parameters
+/// (`MAX_RANGE_PROOF_BITS`), and defaults to `RANGE_PROOF_INTERVAL_BITS`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RangePredicate {
+    pub attribute: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<PredicateBound>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<PredicateBound>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bits: Option<usize>,
+}
+
+/// A bound of a range predicate. Integers are compared to the attribute value as is. Strings are either
+/// a date ("YYYY-MM-DD") or the proof time ("now"), optionally with an offset ("now+30d", "now-12h";
+/// units are d, h, m and s). For attributes of type "date", dates and times are converted to daystamps;
+/// for other attributes (e.g., "exp") they are converted to seconds since the UNIX epoch.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum PredicateBound {
+    Integer(u64),
+    Text(String),
+}
+
+/// A set predicate on a committed attribute: the attribute is one of the values in `in`, or none of the values in `not_in`.
+/// In a proof spec, this is written as, e.g., `{"attribute": "resident_state", "in": ["WA", "OR", "CA"]}` or
+/// `{"attribute": "nationality", "not_in": ["XX", "YY"]}`. Exactly one of `in` and `not_in` must be set, with at most
+/// `MAX_SET_SIZE` values.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SetPredicate {
+    pub attribute: String,
+    #[serde(rename = "in", default, skip_serializing_if = "Option::is_none")]
+    pub in_set: Option<Vec<SetValue>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_in: Option<Vec<SetValue>>,
+}
+
+/// A value of a set predicate. Integers are compared to the attribute value as is. Strings are dates ("YYYY-MM-DD")
+/// for attributes of type "date", and are otherwise encoded as the credential format encodes string claims.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum SetValue {
+    Integer(u64),
+    Text(String),
+}
+
+/// Which side of a one-sided range check the bound is on
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum BoundSide {
+    /// attribute >= bound; we prove that attribute - bound is in [0, 2^n)
+    Lower,
+    /// attribute <= bound; we prove that bound - attribute is in [0, 2^n)
+    Upper,
+}
+
+/// A one-sided range check, with the bound resolved to the attribute's encoding at the proof time.
+/// Each check corresponds to one range proof in `ShowProof::show_range_attr`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RangeCheck {
+    pub attribute: String,
+    pub side: BoundSide,
+    pub bound: u64,
+    /// Width of the range proof
+    pub bits: usize,
+}
+
+impl RangePredicate {
+    /// The width of the range proofs for this predicate
+    pub fn bits(&self) -> usize {
+        self.bits.unwrap_or(RANGE_PROOF_INTERVAL_BITS)
+    }
+}
+
+impl SetPredicate {
+    /// True for an `in` predicate, false for a `not_in` predicate
+    pub fn is_membership(&self) -> bool {
+        self.in_set.is_some()
+    }
+
+    /// The values of the set
+    pub fn values(&self) -> &[SetValue] {
+        self.in_set.as_ref().or(self.not_in.as_ref()).map(|v| v.as_slice()).unwrap_or(&[])
+    }
+
+    /// The values of the set encoded as the attribute is in the credential, whose format is `format`
+    pub(crate) fn elements(&self, format: &dyn CredentialFormat, claim_type: &str) -> Result<Vec<CrescentFr>, Box<dyn Error>> {
+        self.values().iter().map(|v| v.encode(format, claim_type)).collect()
+    }
+}
+
+impl SetValue {
+    fn encode(&self, format: &dyn CredentialFormat, claim_type: &str) -> Result<CrescentFr, Box<dyn Error>> {
+        match self {
+            SetValue::Integer(n) => Ok(CrescentFr::from(*n)),
+            SetValue::Text(s) if claim_type == "date" => {
+                use chrono::Datelike;
+                let date = NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| format!("invalid set value {}, expected a date YYYY-MM-DD", s))?;
+                Ok(CrescentFr::from(date.num_days_from_ce() as u64))
+            }
+            SetValue::Text(s) => format.encode_string_claim(s),
+        }
+    }
+}
+
+impl PredicateBound {
+    /// Convert the bound to an integer comparable with the attribute, whose claim type is `claim_type`
+    pub fn resolve(&self, claim_type: &str, proof_time: u64, tz: &FixedOffset) -> Result<u64, Box<dyn Error>> {
+        let is_date = claim_type == "date";
+        match self {
+            PredicateBound::Integer(n) => Ok(*n),
+            PredicateBound::Text(s) if s.starts_with("now") => {
+                let time = proof_time as i64 + parse_offset_seconds(&s[3..])?;
+                if time < 0 {
+                    return Err(format!("predicate bound {} is before the UNIX epoch", s).into());
+                }
+                if is_date {
+                    Ok(daystamp_at(time as u64, tz) as u64)
+                } else {
+                    Ok(time as u64)
+                }
+            }
+            PredicateBound::Text(s) => {
+                let date = NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| format!("invalid predicate bound {}, expected an integer, YYYY-MM-DD or now[+-]N(d|h|m|s)", s))?;
+                if is_date {
+                    use chrono::Datelike;
+                    Ok(date.num_days_from_ce() as u64)
+                } else {
+                    let midnight = tz.from_local_datetime(&date.and_time(NaiveTime::MIN)).single().ok_or(format!("invalid date {}", s))?;
+                    u64::try_from(midnight.timestamp()).map_err(|_| format!("predicate bound {} is before the UNIX epoch", s).into())
+                }
+            }
+        }
+    }
+}
+
+// Parse an offset such as "", "+30d" or "-12h" to seconds
+fn parse_offset_seconds(offset: &str) -> Result<i64, Box<dyn Error>> {
+    if offset.is_empty() {
+        return Ok(0);
+    }
+    let err = || format!("invalid time offset {}, expected [+-]N(d|h|m|s)", offset);
+    let (sign, rest) = match offset.as_bytes()[0] {
+        b'+' => (1, &offset[1..]),
+        b'-' => (-1, &offset[1..]),
+        _ => return Err(err().into()),
+    };
+    // the unit is the last character, which may not be ASCII in an invalid offset
+    let (n, unit) = match rest.char_indices().last() {
+        Some((i, _)) if i > 0 => rest.split_at(i),
+        _ => return Err(err().into()),
+    };
+    let n : i64 = n.parse().map_err(|_| err())?;
+    let unit_seconds = match unit {
+        "d" => 86400,
+        "h" => 3600,
+        "m" => 60,
+        "s" => 1,
+        _ => return Err(err().into()),
+    };
+    n.checked_mul(unit_seconds).map(|s| sign * s).ok_or_else(|| err().into())
+}
+
+/// The one-sided range checks required by the proof spec at `proof_time`, in the order the range proofs appear in
+/// the show proof: first the `range_over_year` predicates, then each predicate's lower bound followed by its upper bound.
+pub(crate) fn range_checks(proof_spec: &ProofSpecInternal, proof_time: u64) -> Result<Vec<RangeCheck>, Box<dyn Error>> {
+    let mut checks = vec![];
+    for (attr, age) in &proof_spec.range_over_year {
+        // Being at least `age` years old means being born on or before the threshold date
+        let bound = age_threshold_daystamp(*age, proof_time, &proof_spec.timezone) as u64;
+        checks.push(RangeCheck { attribute: attr.clone(), side: BoundSide::Upper, bound, bits: RANGE_PROOF_INTERVAL_BITS });
+    }
+    for predicate in &proof_spec.range_predicates {
+        let claim_type = proof_spec.claim_types.get(&predicate.attribute).map(|s| s.as_str()).unwrap_or("");
+        if let Some(min) = &predicate.min {
+            let bound = min.resolve(claim_type, proof_time, &proof_spec.timezone)?;
+            checks.push(RangeCheck { attribute: predicate.attribute.clone(), side: BoundSide::Lower, bound, bits: predicate.bits() });
+        }
+        if let Some(max) = &predicate.max {
+            let bound = max.resolve(claim_type, proof_time, &proof_spec.timezone)?;
+            checks.push(RangeCheck { attribute: predicate.attribute.clone(), side: BoundSide::Upper, bound, bits: predicate.bits() });
+        }
+    }
+    Ok(checks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daystamp::parse_timezone;
+
+    #[test]
+    fn test_parse_predicates() {
+        let predicates : Vec<RangePredicate> = serde_json::from_str(r#"[
+            {"attribute": "height", "min": 150},
+            {"attribute": "issue_date", "max": "2024-01-01"},
+            {"attribute": "exp", "min": "now+30d"},
+            {"attribute": "x", "min": 1, "max": 10},
+            {"attribute": "amount", "max": 5000000000, "bits": 64}
+        ]"#).unwrap();
+        assert_eq!(predicates[0].min, Some(PredicateBound::Integer(150)));
+        assert_eq!(predicates[0].max, None);
+        assert_eq!(predicates[1].max, Some(PredicateBound::Text("2024-01-01".to_string())));
+        assert_eq!(predicates[3].min, Some(PredicateBound::Integer(1)));
+        assert_eq!(predicates[3].max, Some(PredicateBound::Integer(10)));
+        assert_eq!(predicates[3].bits(), RANGE_PROOF_INTERVAL_BITS);
+        assert_eq!(predicates[4].bits(), 64);
+
+        // The default width is not serialized, so existing proof specs keep the same context
+        assert_eq!(serde_json::to_string(&predicates[3]).unwrap(), r#"{"attribute":"x","min":1,"max":10}"#);
+    }
+
+    #[test]
+    fn test_set_predicates() {
+        use crate::formats::{Jwt, Mdl};
+        let predicates : Vec<SetPredicate> = serde_json::from_str(r#"[
+            {"attribute": "resident_state", "in": ["WA", "OR", "CA"]},
+            {"attribute": "height", "not_in": [150, 160]},
+            {"attribute": "birth_date", "in": ["2000-01-01"]}
+        ]"#).unwrap();
+        assert!(predicates[0].is_membership());
+        assert!(!predicates[1].is_membership());
+        assert_eq!(predicates[1].values(), &[SetValue::Integer(150), SetValue::Integer(160)]);
+        assert_eq!(serde_json::to_string(&predicates[0]).unwrap(), r#"{"attribute":"resident_state","in":["WA","OR","CA"]}"#);
+
+        // Strings are encoded without their quotes, in JWTs as in mDLs
+        let wa = CrescentFr::from(u64::from_le_bytes([b'W', b'A', 0, 0, 0, 0, 0, 0]));
+        assert_eq!(predicates[0].elements(&Jwt, "string").unwrap()[0], wa);
+        assert_eq!(predicates[0].elements(&Mdl, "string").unwrap()[0], wa);
+        assert_eq!(predicates[1].elements(&Mdl, "integer").unwrap(), vec![CrescentFr::from(150u64), CrescentFr::from(160u64)]);
+        let day = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        use chrono::Datelike;
+        assert_eq!(predicates[2].elements(&Mdl, "date").unwrap(), vec![CrescentFr::from(day.num_days_from_ce() as u64)]);
+
+        let too_long = SetPredicate { attribute: "x".to_string(), in_set: Some(vec![SetValue::Text("a".repeat(32))]), not_in: None };
+        assert!(too_long.elements(&Mdl, "string").is_err());
+    }
+
+    #[test]
+    fn test_resolve_bounds() {
+        let utc = parse_timezone("UTC").unwrap();
+        let now = 1_767_225_600; // 2026-01-01T00:00:00Z
+        let today = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        use chrono::Datelike;
+
+        let b = PredicateBound::Integer(150);
+        assert_eq!(b.resolve("integer", now, &utc).unwrap(), 150);
+
+        let b = PredicateBound::Text("now+30d".to_string());
+        assert_eq!(b.resolve("number", now, &utc).unwrap(), now + 30 * 86400);
+        assert_eq!(b.resolve("date", now, &utc).unwrap(), today.num_days_from_ce() as u64 + 30);
+        let b = PredicateBound::Text("now-1h".to_string());
+        assert_eq!(b.resolve("number", now, &utc).unwrap(), now - 3600);
+        assert_eq!(b.resolve("date", now, &utc).unwrap(), today.num_days_from_ce() as u64 - 1);
+
+        let b = PredicateBound::Text("2026-01-01".to_string());
+        assert_eq!(b.resolve("date", now, &utc).unwrap(), today.num_days_from_ce() as u64);
+        assert_eq!(b.resolve("number", now, &utc).unwrap(), now);
+        assert_eq!(b.resolve("number", now, &parse_timezone("+01:00").unwrap()).unwrap(), now - 3600);
+
+        for bad in ["now+", "now+30", "now*3d", "now+3w", "now+3é", "now+é", "now+é3d", "2026-13-01", "tomorrow"] {
+            assert!(PredicateBound::Text(bad.to_string()).resolve("number", now, &utc).is_err(), "{}", bad);
+        }
+    }
+}
@@ -7,47 +7,34 @@ use p256::ecdsa::VerifyingKey;
 use p256::pkcs8::DecodePublicKey;
 use serde_json::Value;
 use serde_json::json;
-use lazy_static::lazy_static;
-use std::collections::HashSet;
 use num_bigint::{BigInt, BigUint};
 use num_traits::FromPrimitive;
 use std::ops::{Shl, BitAnd};
 use std::error::Error;
-use std::fs;
-use ark_std::path::PathBuf;
 use ark_ff::BigInteger;
 use crate::return_error;
+use crate::daystamp::{date_to_daystamp, parse_timezone};
 use crate::ProofSpec;
 use crate::ProofSpecInternal;
+use crate::CrescentFr;
+use crate::MAX_SET_SIZE;
+use std::str::FromStr;
+use crate::MAX_RANGE_PROOF_BITS;
+use crate::rangeproof::{is_valid_bit_width, MIN_RANGE_PROOF_BITS};
+use crate::sd_jwt::{Disclosure, SdJwtToken};
+use crate::formats::credential_format_from_config;
+use crate::config::{base64_decoded_size, hash_block_len, is_rsa_alg, parse_config, ClaimConfig, ClaimType, CredentialConfig,
+    CIRCOM_RS256_LIMB_BITS, DEFAULT_MAX_DISCLOSURE_LENGTH, MAX_FIELD_BYTE_LEN};
+pub use crate::config::{claim_path, claim_signal_name};
 
-// If not set in config.json, the max_cred_len is set to this value. 
-const DEFAULT_MAX_TOKEN_LENGTH : usize = 2048;
-const CIRCOM_RS256_LIMB_BITS : usize = 121;
 const CIRCOM_ES256_LIMB_BITS : usize = 43;  // Limb size required by ecdsa-p256 circuit
-const MAX_FIELD_BYTE_LEN : usize = 31;  
-lazy_static! {
-    static ref CRESCENT_SUPPORTED_ALGS: HashSet<&'static str> = {
-        let mut set = HashSet::new();
-        set.insert("RS256");
-        set.insert("ES256");
-        set
-    };
-}
-lazy_static! {
-    static ref CRESCENT_CONFIG_KEYS: HashSet<&'static str> = {
-        let mut set = HashSet::new();
-        set.insert("alg");
-        set.insert("credtype");
-        set.insert("max_cred_len");
-        set.insert("device_bound");
-        set
-    };
-}
+const CIRCOM_ES256_NUM_LIMBS : usize = 6;   // Number of limbs of P-256 field elements and scalars in the ecdsa-p256 circuit
 
+// The type of the issuer's public key: "RSA" for the RSA-based algorithms (RS256, RS384, RS512, PS256), or "ES256"
 pub fn pem_key_type(key : &str) -> Result<&str, &str> {
 
         if RS256PublicKey::from_pem(key).is_ok() {
-            Ok("RS256")
+            Ok("RSA")
         } 
         else if ES256PublicKey::from_pem(key).is_ok() {
             Ok("ES256")
@@ -62,17 +49,16 @@ pub fn pem_to_inputs<F>(issuer_pem : &str) -> Result<Vec<F>, Box<dyn std::error:
 {
     
     let inputs = match pem_key_type(issuer_pem) {
-        Ok("RS256") => {
+        Ok("RSA") => {
             let issuer_pub = RS256PublicKey::from_pem(issuer_pem).unwrap();
             let limbs = to_circom_ints(&issuer_pub.to_components().n, CIRCOM_RS256_LIMB_BITS)?;
             limbs.into_iter().map(|x| F::from_le_bytes_mod_order(&x.to_bytes_le().1)).collect::<Vec<F>>()
         }
         Ok("ES256") =>  {
             let issuer_pub = ES256PublicKey::from_pem(issuer_pem).unwrap();
-            let x = &issuer_pub.public_key().to_bytes_uncompressed()[1..33];    // byte 1 is 0x04, per SEC1 `Elliptic-Curve-Point-to-Octet-String` 
-            let y = &issuer_pub.public_key().to_bytes_uncompressed()[33..65];
-            let limbs_x = to_circom_ints(x, CIRCOM_ES256_LIMB_BITS)?;
-            let limbs_y = to_circom_ints(y, CIRCOM_ES256_LIMB_BITS)?;
+            let (x, y) = es256_public_key_coordinates(&issuer_pub);
+            let limbs_x = to_es256_circom_ints(&x)?;
+            let limbs_y = to_es256_circom_ints(&y)?;
             let limbs_x_fe = limbs_x.into_iter().map(|a| F::from_le_bytes_mod_order(&a.to_bytes_le().1)).collect::<Vec<F>>();
             let limbs_y_fe = limbs_y.into_iter().map(|a| F::from_le_bytes_mod_order(&a.to_bytes_le().1)).collect::<Vec<F>>();
             let mut limbs = limbs_x_fe;
@@ -91,20 +77,57 @@ pub fn pem_to_inputs<F>(issuer_pem : &str) -> Result<Vec<F>, Box<dyn std::error:
 
 }
 
+/// Verify the issuer's signature on a JWT, for the signature algorithm `alg` (from config.json)
+pub fn verify_jwt(alg : &str, token_str : &str, issuer_pem : &str) -> Result<(), Box<dyn Error>> {
+    let claims_limited_set = match alg {
+        "RS256" => RS256PublicKey::from_pem(issuer_pem)?.verify_token::<NoCustomClaims>(token_str, None),
+        "RS384" => RS384PublicKey::from_pem(issuer_pem)?.verify_token::<NoCustomClaims>(token_str, None),
+        "RS512" => RS512PublicKey::from_pem(issuer_pem)?.verify_token::<NoCustomClaims>(token_str, None),
+        "PS256" => PS256PublicKey::from_pem(issuer_pem)?.verify_token::<NoCustomClaims>(token_str, None),
+        "ES256" => ES256PublicKey::from_pem(issuer_pem)?.verify_token::<NoCustomClaims>(token_str, None),
+        _ => return_error!(format!("Unsupported algorithm {}", alg)),
+    };
+    if claims_limited_set.is_err() {
+        return_error!("Token failed to verify");
+    }
+    Ok(())
+}
+
+// The x and y coordinates of a P-256 public key, as big-endian bytes
+fn es256_public_key_coordinates(key : &ES256PublicKey) -> (Vec<u8>, Vec<u8>) {
+    let bytes = key.public_key().to_bytes_uncompressed();   // byte 0 is 0x04, per SEC1 `Elliptic-Curve-Point-to-Octet-String` 
+    (bytes[1..33].to_vec(), bytes[33..65].to_vec())
+}
+
 type JsonMap = serde_json::Map<String, Value>;
 
-pub fn prepare_prover_inputs(config : &serde_json::Map<String, Value>, token_str : &str, issuer_pem : &str, device_pub_pem : Option<&str>) -> 
+pub fn prepare_prover_inputs(config : &CredentialConfig, token_str : &str, issuer_pem : &str, device_pub_pem : Option<&str>) -> 
 Result<(JsonMap, JsonMap, JsonMap), Box<dyn Error>>
 {
+    prepare_prover_inputs_internal(config, token_str, Some(issuer_pem), device_pub_pem)
+}
 
-    let issuer_pub = match config["alg"].as_str().unwrap() {
-        "RS256" => RS256PublicKey::from_pem(issuer_pem)?,
-        _ => return_error!("Unsupported algorithm"),
-    };    
+// Check that a token fits the config as prepare_prover_inputs does, without the issuer's public key: the signature is
+// not verified, so only the errors due to the token's header, payload and disclosures are found (see estimate.rs)
+pub(crate) fn check_prover_inputs(config : &CredentialConfig, token_str : &str) -> Result<(), Box<dyn Error>> {
+    prepare_prover_inputs_internal(config, token_str, None, None).map(|_| ())
+}
 
-    let claims_limited_set = issuer_pub.verify_token::<NoCustomClaims>(token_str, None);
-    if claims_limited_set.is_err() {
-        return_error!("Token failed to verify");
+fn prepare_prover_inputs_internal(config : &CredentialConfig, token_str : &str, issuer_pem : Option<&str>, device_pub_pem : Option<&str>) -> 
+Result<(JsonMap, JsonMap, JsonMap), Box<dyn Error>>
+{
+
+    let alg_str = config.alg.as_str();
+
+    // For SD-JWTs, the issuer's signature is on the JWT before the disclosures
+    let sd_jwt = if config.credtype == "sd-jwt" {
+        Some(SdJwtToken::parse(token_str)?)
+    } else {
+        None
+    };
+    let token_str = sd_jwt.as_ref().map(|t| t.issuer_jwt.as_str()).unwrap_or(token_str);
+    if let Some(issuer_pem) = issuer_pem {
+        verify_jwt(alg_str, token_str, issuer_pem)?;
     }
 
     let mut parts = token_str.split('.');
@@ -115,8 +138,10 @@ Result<(JsonMap, JsonMap, JsonMap), Box<dyn Error>>
     let jwt_header_decoded = String::from_utf8(base64_url::decode(jwt_header_b64)?)?;
     let claims_decoded = String::from_utf8(base64_url::decode(claims_b64)?)?;
     
-    let claims: Value =
-        serde_json::from_slice(&Base64UrlSafeNoPadding::decode_to_vec(claims_b64, None)?)?;
+    let claims: Value = match &sd_jwt {
+        Some(t) => t.claims()?,
+        None => serde_json::from_slice(&Base64UrlSafeNoPadding::decode_to_vec(claims_b64, None)?)?,
+    };
 
     // Convert the base64 encoded header and payload to UTF-8 integers in base-10 (e.g., 'e' -> 101, 'y' -> 121, ...)        
     let mut header_utf8 = to_utf8_integers(jwt_header_b64);
@@ -126,23 +151,24 @@ Result<(JsonMap, JsonMap, JsonMap), Box<dyn Error>>
     let mut prepad_m = header_utf8.clone();
     prepad_m.append(&mut payload_utf8.clone());
 
-    let mut padded_m = sha256_padding(&prepad_m);
+    let block_len = hash_block_len(alg_str);
+    let mut padded_m = sha2_padding(&prepad_m, block_len);
 
     let msg_len_after_sha2_padding = padded_m.len() as u64;
 
-    if msg_len_after_sha2_padding > config["max_cred_len"].as_u64().unwrap() {
-        let errmsg = format!("Error: JWT too large.  Current token JSON header + payload is {} bytes ({} bytes after SHA256 padding), but maximum length supported is {} bytes.\nThe config file value `max_cred_len` would have to be increased to {} bytes (currently config['max_cred_len'] = {})", 
+    if msg_len_after_sha2_padding > config.max_cred_len as u64 {
+        let errmsg = format!("Error: JWT too large.  Current token JSON header + payload is {} bytes ({} bytes after SHA2 padding), but maximum length supported is {} bytes.\nThe config file value `max_cred_len` would have to be increased to {} bytes (currently config['max_cred_len'] = {})", 
         header_utf8.len() + payload_utf8.len(), 
         msg_len_after_sha2_padding, 
-        base64_decoded_size(config["max_cred_len"].as_u64().unwrap()), 
-        header_utf8.len() + payload_utf8.len() + 64, config["max_cred_len"].as_u64().unwrap()
+        base64_decoded_size(config.max_cred_len as u64), 
+        header_utf8.len() + payload_utf8.len() + block_len, config.max_cred_len
         );
 
         return_error!(errmsg);
     }
 
     // Add additional zero padding for Circom
-    while padded_m.len() < config["max_cred_len"].as_u64().unwrap() as usize {
+    while padded_m.len() < config.max_cred_len {
         padded_m.push(0);
     }
   
@@ -152,25 +178,9 @@ Result<(JsonMap, JsonMap, JsonMap), Box<dyn Error>>
     let mut prover_aux_json = serde_json::Map::new();
     prover_inputs_json.insert("message".to_string(), json!(padded_m.into_iter().map(|c| c.to_string()).collect::<Vec<_>>()));
 
-    // Signature
-    let alg_str = config["alg"].as_str().unwrap();
-    if alg_str == "RS256" {
-        let limbs = b64_to_circom_limbs(signature_b64, CIRCOM_RS256_LIMB_BITS)?;
-        prover_inputs_json.insert("signature".to_string(), json!(limbs));
-    }
-    else {
-        return_error!(format!("Unsupported algorithm {}", alg_str));
-    }
-
-    // Issuer's public key
-    if alg_str == "RS256" {
-        let modulus_bytes = issuer_pub.to_components().n;
-        let limbs = to_circom_limbs(&modulus_bytes, CIRCOM_RS256_LIMB_BITS)?;
-        prover_inputs_json.insert("modulus".to_string(), json!(limbs));
-        public_ios_json.insert("modulus".to_string(), json!(limbs));
-    }
-    else {
-        return_error!(format!("Unsupported algorithm {}", alg_str));
+    // Signature and issuer's public key
+    if let Some(issuer_pem) = issuer_pem {
+        prepare_prover_signature_inputs(config, signature_b64, issuer_pem, &mut prover_inputs_json, &mut public_ios_json)?;
     }
 
     // Other values the prover needs
@@ -181,67 +191,144 @@ Result<(JsonMap, JsonMap, JsonMap), Box<dyn Error>>
 
     let header_pad = base_64_decoded_header_padding(period_idx)?;
     let header_and_payload = format!("{}{}{}", jwt_header_decoded, header_pad, claims_decoded);
-    prepare_prover_claim_inputs(&header_and_payload, config, &claims, &mut prover_inputs_json)?;
+    prepare_prover_claim_inputs(&header_and_payload, config, &claims, sd_jwt.as_ref(), &mut prover_inputs_json)?;
     prepare_prover_aux(&header_and_payload, config, &claims, device_pub_pem, &mut prover_aux_json)?;
 
     Ok((prover_inputs_json, prover_aux_json, public_ios_json))
 
 }
 
-// For each of the claims that are specified in the config file, the prover will need some info about each one
-// (e.g., the value, where in the payload it starts and ends)
-fn prepare_prover_claim_inputs(header_and_payload: &str, config: &serde_json::Map<String, Value>, claims: &Value, prover_inputs_json : &mut  serde_json::Map<String, Value>) -> Result<(), Box<dyn Error>> {
-    let msg = header_and_payload;
+// The issuer's signature and public key
+fn prepare_prover_signature_inputs(config: &CredentialConfig, signature_b64: &str, issuer_pem: &str, prover_inputs_json : &mut JsonMap, public_ios_json : &mut JsonMap) -> Result<(), Box<dyn Error>> {
+    let alg_str = config.alg.as_str();
+    if is_rsa_alg(alg_str) {
+        // The circuit is set up for a fixed key size
+        let modulus_bytes = RS256PublicKey::from_pem(issuer_pem)?.to_components().n;
+        let key_bits = BigUint::from_bytes_be(&modulus_bytes).bits();
+        let expected_key_bits = config.rsa_key_bits.ok_or("Invalid value for rsa_key_bits")?;
+        if key_bits != expected_key_bits {
+            return_error!(format!("Issuer key is a {}-bit RSA key, but config['rsa_key_bits'] = {}", key_bits, expected_key_bits));
+        }
+        let limbs = to_circom_limbs(&modulus_bytes, CIRCOM_RS256_LIMB_BITS)?;
+        prover_inputs_json.insert("modulus".to_string(), json!(limbs));
+        public_ios_json.insert("modulus".to_string(), json!(limbs));
 
-    if !is_minified(msg) {
-        return_error!("JSON is not minified, Circom circuit will fail.")
+        // The signature may be shorter than the modulus, it gets the same number of limbs
+        let mut signature_limbs = b64_to_circom_limbs(signature_b64, CIRCOM_RS256_LIMB_BITS)?;
+        if signature_limbs.len() > limbs.len() {
+            return_error!("Invalid RSA signature: larger than the modulus");
+        }
+        signature_limbs.resize(limbs.len(), "0".to_string());
+        prover_inputs_json.insert("signature".to_string(), json!(signature_limbs));
     }
-    let keys = config.keys();
-
-    for key in keys {
-        if CRESCENT_CONFIG_KEYS.contains(key.as_str()) {
-            continue;
+    else if alg_str == "ES256" {
+        // The signature is R||S, see https://www.rfc-editor.org/rfc/rfc7518#section-3.4
+        let signature_bytes = base64_url::decode(signature_b64)?;
+        if signature_bytes.len() != 64 {
+            return_error!(format!("Invalid ES256 signature length: {}", signature_bytes.len()));
         }
+        let (r_bytes, s_bytes) = signature_bytes.split_at(32);
+        prover_inputs_json.insert("signature_r".to_string(), json!(to_es256_circom_limbs(r_bytes)?));
+        prover_inputs_json.insert("signature_s".to_string(), json!(to_es256_circom_limbs(s_bytes)?));
+
+        let (x, y) = es256_public_key_coordinates(&ES256PublicKey::from_pem(issuer_pem)?);
+        let limbs_x = to_es256_circom_limbs(&x)?;
+        let limbs_y = to_es256_circom_limbs(&y)?;
+        prover_inputs_json.insert("pubkey_x".to_string(), json!(limbs_x));
+        prover_inputs_json.insert("pubkey_y".to_string(), json!(limbs_y));
+        public_ios_json.insert("pubkey_x".to_string(), json!(limbs_x));
+        public_ios_json.insert("pubkey_y".to_string(), json!(limbs_y));
+    }
+    else {
+        return_error!(format!("Unsupported algorithm {}", alg_str));
+    }
 
-        let name = key.clone();
-        let name = name.as_str();
+    Ok(())
+}
 
-        let entry = config[name].as_object().ok_or(format!("Config file entry for claim {}, does not have object type", name))?;
+// For each of the claims that are specified in the config file, the prover will need some info about each one
+// (e.g., the value, where in the payload it starts and ends)
+fn prepare_prover_claim_inputs(header_and_payload: &str, config: &CredentialConfig, claims: &Value, sd_jwt: Option<&SdJwtToken>, prover_inputs_json : &mut  serde_json::Map<String, Value>) -> Result<(), Box<dyn Error>> {
+    let msg = header_and_payload;
 
-        let type_string = entry["type"].as_str().ok_or(format!("Config file entry for claim {}, is missing 'type'", name))?;
+    for (name, entry) in &config.claims {
+        let name = name.as_str();
+        let claim_type = entry.claim_type;
 
-        let claim_name = format!("\"{}\"", name);
-        let (claim_l, claim_r) = find_value_interval(msg, &claim_name, type_string)?;
+        let signal_name = claim_signal_name(name);
+        // An optional claim that is absent from the token is matched in a default JSON document, see OptionalClaim in match_claim.circom
+        let absent = entry.optional && claims.get(&claim_path(name)[0]).is_none();
+        if entry.optional {
+            prover_inputs_json.insert(format!("{}_present", signal_name), json!(if absent { "0" } else { "1" }));
+        }
+        let value = if absent { claim_type.default_value()? } else { claim_value(claims, name).clone() };
+        // The claim value as it appears in the token, e.g., with its escape sequences for a string
+        let (claim_l, claim_r, literal) = if absent {
+            // The default JSON is {"name":<default value>}
+            let value_l = format!("{{\"{}\":", name).len();
+            prover_inputs_json.insert(format!("{}_value_l", signal_name), json!(value_l.to_string()));
+            (1, value_l + value.to_string().len(), value.to_string())
+        } else if entry.sd {
+            let disclosure = sd_jwt.and_then(|t| t.disclosure(name)).ok_or(format!("Claim {} is not disclosed in the SD-JWT", name))?;
+            prepare_prover_disclosure_inputs(msg, name, entry, disclosure, prover_inputs_json)?;
+            let disclosure_json = disclosure.json()?;
+            let (claim_l, value_l, claim_r) = find_disclosure_value_interval(&disclosure_json, &format!("\"{}\"", name), claim_type)?;
+            (claim_l, claim_r, disclosure_json[value_l..claim_r].to_string())
+        } else {
+            // The objects containing a nested claim are matched by the circuit, see write_parent_objects in prepare_setup.py
+            let mut intervals = find_path_intervals(msg, name, claim_type)?;
+            let (claim_l, value_l, claim_r) = intervals.pop().ok_or(format!("Invalid claim path {}", name))?;
+            let segments = name.split('/').collect::<Vec<_>>();
+            for (k, (object_l, object_value_l, object_r)) in intervals.into_iter().enumerate() {
+                let object_name = format!("{}_object", claim_signal_name(&segments[..k + 2].join("/")));
+                prover_inputs_json.insert(format!("{}_l", object_name), json!(object_l.to_string()));
+                prover_inputs_json.insert(format!("{}_value_l", object_name), json!(object_value_l.to_string()));
+                prover_inputs_json.insert(format!("{}_r", object_name), json!(object_r.to_string()));
+            }
+            prover_inputs_json.insert(format!("{}_value_l", signal_name), json!(value_l.to_string()));
+            (claim_l, claim_r, msg[value_l..claim_r].to_string())
+        };
+        if claim_type == ClaimType::String && entry.is_revealed() {
+            check_string_literal(name, &literal, entry)?;
+        }
 
-        let name_l = format!("{}_l", name);
-        let name_r = format!("{}_r", name);
+        let name_l = format!("{}_l", signal_name);
+        let name_r = format!("{}_r", signal_name);
 
         prover_inputs_json.insert(name_l, json!(claim_l.to_string()));
         prover_inputs_json.insert(name_r, json!(claim_r.to_string()));
 
-        if entry.contains_key("reveal") {
-            let reveal = entry["reveal"].as_bool().ok_or(format!("reveal for claim {} is not of type bool", name))?;
-            if reveal {
-                match type_string {
-                    "number" => {
-                        prover_inputs_json.insert(format!("{}_value", name), json!(claims[name].clone().to_string()));
-                    }
-                    "string" => {
-                        let max_claim_byte_len = entry["max_claim_byte_len"].as_u64().unwrap();    // validated by load_config
-                        let packed = if entry.contains_key("reveal_domain_only") && 
-                                        entry["reveal_domain_only"].as_bool().ok_or("reveal_domain_only is not of type bool")? {
-                            let domain = get_domain(claims[name].as_str().ok_or("invalid_type")?)?;
-                            pack_string_to_int_unquoted(domain, max_claim_byte_len.try_into()?)?
-                        }
-                        
-                        else {
-                            pack_string_to_int(claims[name].as_str().ok_or("invalid_type")?, max_claim_byte_len.try_into()?)?
-                        };
-                        prover_inputs_json.insert(format!("{}_value", name), json!(packed));
-                    }
-                    _ => {
-                        return_error!("Can only reveal number types and string types as a single field element for now. See also `reveal_bytes`.")
+        if entry.reveal_bytes {
+            let packed = pack_string_to_ints(value.as_str().ok_or("invalid_type")?, entry.max_claim_byte_len())?;
+            prover_inputs_json.insert(format!("{}_value", signal_name), json!(packed));
+        }
+        else if entry.reveal {
+            match claim_type {
+                ClaimType::Number => {
+                    prover_inputs_json.insert(format!("{}_value", signal_name), json!(value.to_string()));
+                }
+                // See RevealClaimBoolean and RevealClaimDate in match_claim.circom
+                ClaimType::Boolean => {
+                    let value = value.as_bool().ok_or("invalid_type")?;
+                    prover_inputs_json.insert(format!("{}_value", signal_name), json!((value as u8).to_string()));
+                }
+                ClaimType::Date => {
+                    let daystamp = date_to_daystamp(value.as_str().ok_or("invalid_type")?)?;
+                    prover_inputs_json.insert(format!("{}_value", signal_name), json!(daystamp.to_string()));
+                }
+                ClaimType::String => {
+                    let packed = if entry.reveal_domain_only {
+                        // The circuit reveals 0, the packing of an empty domain, for an absent claim
+                        let domain = if absent { "" } else { get_domain(value.as_str().ok_or("invalid_type")?)? };
+                        pack_string_to_int_unquoted(domain, entry.max_claim_byte_len())?
                     }
+                    else {
+                        pack_string_to_int(value.as_str().ok_or("invalid_type")?, entry.max_claim_byte_len())?
+                    };
+                    prover_inputs_json.insert(format!("{}_value", signal_name), json!(packed));
+                }
+                _ => {
+                    return_error!(format!("Claim {} has type {}, which cannot be revealed in a JWT", name, claim_type.as_str()))
                 }
             }
         }
@@ -250,41 +337,62 @@ fn prepare_prover_claim_inputs(header_and_payload: &str, config: &serde_json::Ma
     Ok(())
 }
 
+// The interval of the top-level "_sd" array of an SD-JWT payload, see SDArray in sd_jwt.circom
+fn find_sd_array_interval(header_and_payload: &str) -> Result<MemberInterval, Box<dyn Error>> {
+    let (l, value_l, r) = find_path_intervals(header_and_payload, "_sd", ClaimType::Array)?.pop().ok_or("Failed to find the _sd array in token payload")?;
+    if !header_and_payload[value_l..].starts_with('[') || r <= value_l {
+        return_error!("Invalid SD-JWT: '_sd' is not an array");
+    }
+    Ok((l, value_l, r))
+}
+
+// For a claim in an SD-JWT disclosure, the circuit hashes the disclosure and finds the digest in the "_sd" array of
+// the payload, see SDJWTDisclosure in sd_jwt.circom
+fn prepare_prover_disclosure_inputs(header_and_payload: &str, name: &str, entry: &ClaimConfig, disclosure: &Disclosure, prover_inputs_json : &mut  serde_json::Map<String, Value>) -> Result<(), Box<dyn Error>> {
+    let max_disclosure_len = entry.max_disclosure_len.unwrap_or(DEFAULT_MAX_DISCLOSURE_LENGTH);
+    let mut padded_disclosure = sha2_padding(&to_utf8_integers(&disclosure.encoded), 64);
+    let padded_len = padded_disclosure.len();
+    if padded_len > max_disclosure_len {
+        return_error!(format!("Disclosure of claim {} too large ({} bytes after SHA256 padding), largest allowed by configuration is {} bytes", name, padded_len, max_disclosure_len));
+    }
+    padded_disclosure.resize(max_disclosure_len, 0);
+
+    let (sd_l, sd_value_l, sd_r) = find_sd_array_interval(header_and_payload)?;
+    let digest_l = sd_value_l + header_and_payload[sd_value_l..sd_r].find(&format!("\"{}\"", disclosure.digest()))
+        .ok_or(format!("Digest of the disclosure of claim {} not found in the _sd array of the token payload", name))?;
+    prover_inputs_json.insert("sd_array_l".to_string(), json!(sd_l.to_string()));
+    prover_inputs_json.insert("sd_array_value_l".to_string(), json!(sd_value_l.to_string()));
+    prover_inputs_json.insert("sd_array_r".to_string(), json!(sd_r.to_string()));
+    let signal_name = claim_signal_name(name);
+    prover_inputs_json.insert(format!("{}_disclosure", signal_name), json!(padded_disclosure.into_iter().map(|c| c.to_string()).collect::<Vec<_>>()));
+    prover_inputs_json.insert(format!("{}_disclosure_padded_bytes", signal_name), json!(padded_len.to_string()));
+    prover_inputs_json.insert(format!("{}_digest_l", signal_name), json!(digest_l.to_string()));
+    Ok(())
+}
+
 // The prover needs the pre-images of the hashed attributes, and optionally, the device public key.
 // The digests are outputs of the circuit and made available to the prover during witness generation. 
 // When showing the credential, if the prover selectively discloses a hashed attribute, they need the
 // preimage to send to the verifier.
-fn prepare_prover_aux(_header_and_payload: &str, config: &serde_json::Map<String, Value>, claims: &Value, device_key_pem : Option<&str>, prover_aux_json : &mut  serde_json::Map<String, Value>) -> Result<(), Box<dyn Error>> {
+fn prepare_prover_aux(_header_and_payload: &str, config: &CredentialConfig, claims: &Value, device_key_pem : Option<&str>, prover_aux_json : &mut  serde_json::Map<String, Value>) -> Result<(), Box<dyn Error>> {
 
     // Get the preimages for the hashed attributes
-    for key in config.keys() {
-        if CRESCENT_CONFIG_KEYS.contains(key.as_str()) {
-            continue;
-        }
-
-        let name = key.clone();
-        let name = name.as_str();
-        let entry = config[name].as_object().ok_or(format!("Config file entry for claim {}, does not have object type", name))?;
-        let type_string = entry["type"].as_str().ok_or(format!("Config file entry for claim {}, is missing 'type'", name))?;
-
-        if entry.contains_key("reveal_digest") {
-            let reveal = entry["reveal_digest"].as_bool().ok_or(format!("reveal_digest for predicate {} is not of type bool", name))?;
-            if reveal {
-                match type_string {
-                    "number" => {
-                        prover_aux_json.insert(name.to_string(), json!(claims[name].clone()));
-                    }
-                    "string" => {
-                        let max_claim_byte_len = entry["max_claim_byte_len"].as_u64().unwrap();    // validated by load_config
-                        let claim_value = claims[name].as_str().ok_or("invalid_type")?;
-                        if claim_value.len() > max_claim_byte_len as usize {
-                            return_error!(format!("Claim too large ({} bytes), largest allowed by configuration is {} bytes", claim_value.len(), max_claim_byte_len));
-                        }
-                        prover_aux_json.insert(name.to_string(), json!(claim_value));
-                    }
-                    _ => {
-                        return_error!("Can only reveal number types and string types as a single field element for now. See also `reveal_bytes`.")
+    for (name, entry) in &config.claims {
+        if entry.reveal_digest {
+            match entry.claim_type {
+                ClaimType::Number => {
+                    prover_aux_json.insert(name.to_string(), json!(claim_value(claims, name).clone()));
+                }
+                ClaimType::String => {
+                    let max_claim_byte_len = entry.max_claim_byte_len();
+                    let claim_value = claim_value(claims, name).as_str().ok_or("invalid_type")?;
+                    if claim_value.len() > max_claim_byte_len {
+                        return_error!(format!("Claim too large ({} bytes), largest allowed by configuration is {} bytes", claim_value.len(), max_claim_byte_len));
                     }
+                    prover_aux_json.insert(name.to_string(), json!(claim_value));
+                }
+                _ => {
+                    return_error!("Can only reveal the digests of number types and string types")
                 }
             }
         }
@@ -313,11 +421,12 @@ fn get_domain(s: &str) -> Result<&str, Box<std::io::Error>> {
 }
 
 fn pack_string_to_int(s: &str, n_bytes: usize) -> Result<String, Box<std::io::Error>> {
-    // Must match function "RevealClaimValue" in match_claim.circom, so we add quotes to the string
-
-    //First convert "s" to bytes and pad with zeros
-    let s_quoted = format!("\"{}\"",s);
-    pack_string_to_int_unquoted(&s_quoted, n_bytes)
+    // Must match function "RevealClaimValue" in match_claim.circom, which decodes the string with its quotes in
+    // n_bytes, then packs it without them, as mDL strings are packed
+    if s.len() + 2 > n_bytes {
+        return_error!(format!("String to large to convert to integer of n_bytes = {}", n_bytes));
+    }
+    pack_string_to_int_unquoted(s, n_bytes)
 }
 fn pack_string_to_int_unquoted(s: &str, n_bytes: usize) -> Result<String, Box<std::io::Error>> {
     // Must match function "RevealDomainOnly" in match_claim.circom
@@ -331,109 +440,201 @@ fn pack_string_to_int_unquoted(s: &str, n_bytes: usize) -> Result<String, Box<st
     for _ in 0 .. n_bytes - s_bytes.len() {
         s_bytes.push(0x00);
     }
-    // Convert to an integer with base-256 digits equal to s_bytes
+    Ok(pack_bytes_to_int(&s_bytes))
+}
+
+// Convert to an integer with base-256 digits equal to s_bytes
+fn pack_bytes_to_int(s_bytes: &[u8]) -> String {
     let mut n = BigInt::from_u32(0).unwrap();
     let twofiftysix = BigInt::from_u32(256).unwrap();
     for i in 0..s_bytes.len() {
         assert!(i < u32::MAX as usize);
         n += s_bytes[i] * twofiftysix.pow(i as u32);
     }
-    
-    Ok(n.to_str_radix(10))
+    n.to_str_radix(10)
+}
+
+// Pack a string claim revealed with the `reveal_bytes` option into n_bytes / MAX_FIELD_BYTE_LEN integers.
+// Must match function "RevealClaimValueFields" in match_claim.circom, so the quotes are included as in pack_string_to_int
+fn pack_string_to_ints(s: &str, n_bytes: usize) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut s_bytes = format!("\"{}\"", s).into_bytes();
+    if s_bytes.len() > n_bytes {
+        return_error!(format!("Claim too large ({} bytes with quotes), largest allowed by configuration is {} bytes", s_bytes.len(), n_bytes));
+    }
+    s_bytes.resize(n_bytes, 0x00);
+    Ok(s_bytes.chunks(MAX_FIELD_BYTE_LEN).map(pack_bytes_to_int).collect())
+}
+
+/// Pack a string into a field element, as `pack_string_to_int_unquoted` does with the largest length that fits
+pub(crate) fn pack_string_to_scalar(s: &str) -> Result<CrescentFr, Box<dyn Error>> {
+    let packed = pack_string_to_int_unquoted(s, 31)?;
+    Ok(CrescentFr::from_str(&packed).map_err(|_| "Failed to convert packed string to a field element")?)
 }
 
+/// Unpack a string claim revealed as a single integer, see `pack_string_to_int`
 pub fn unpack_int_to_string_unquoted(s_int: &ark_ff::BigInteger256) -> Result<String, Box<std::io::Error>> {
+    unpack_bytes_to_string(s_int.to_bytes_le())
+}
+
+/// Unpack a string claim revealed as several integers, see `pack_string_to_ints`
+pub fn unpack_ints_to_string_unquoted(s_ints: &[ark_ff::BigInteger256]) -> Result<String, Box<std::io::Error>> {
+    let s_bytes = s_ints.iter().flat_map(|n| n.to_bytes_le().into_iter().take(MAX_FIELD_BYTE_LEN)).collect();
+    Ok(crate::utils::strip_quotes(&unpack_bytes_to_string(s_bytes)?).to_string())
+}
 
-    let s_bytes = s_int.to_bytes_le();
+fn unpack_bytes_to_string(s_bytes: Vec<u8>) -> Result<String, Box<std::io::Error>> {
     let s_bytes_trimmed: Vec<u8> = s_bytes.into_iter().rev().skip_while(|&x| x == 0).collect::<Vec<u8>>().into_iter().rev().collect();  // strip trailing zeros
     let string = String::from_utf8(s_bytes_trimmed);
     if string.is_err() {
         return_error!("Failed to convert to string");
     }
-    Ok(crate::utils::strip_quotes(string.unwrap().as_str()).to_string())
+    Ok(string.unwrap())
 }
 
-fn find_value_interval(msg: &str, claim_name: &str, type_string: &str) -> Result<(usize, usize), Box<dyn Error>> {
-    let l = msg.find(claim_name).ok_or(format!("Failed to find claim {} in token payload", claim_name))?;
-    let value_start = l + claim_name.len();
-    let mut r = 0;
-    match type_string {
-        "string" => {
-            let close_quote = msg[value_start+2..].find("\"").ok_or(format!("Parse error, no closing quote, claim {}", claim_name))?;
-            r = close_quote + value_start + 3;
-        },
-        "number" => {
-            for (i, c) in msg[value_start + 1..].chars().enumerate() {
-                if "0123456789".find(c).is_none() {
-                    r = value_start + 1 + i;
-                    break;
-                }
-            }
-        },
-        "bool" => {
-            for (i, c) in msg[value_start + 1..].chars().enumerate() {
-                if "truefalse".find(c).is_none() {
-                    r = value_start + 1 + i;
-                    break;
-                }
-            }            
-        },
-        "null" => {
-            r = value_start + 4;
-        }, 
-        "array" => {
-            let mut nested_level = 0;
-            for (i, c) in msg[value_start..].chars().enumerate() {
-                if c == '[' {
-                    nested_level += 1;
-                }
-                else if c == ']' {
-                    nested_level -= 1;
-                    if nested_level == 0 {
-                        r = value_start + i + 1;
-                        break;
-                    }
-                }
+// The value of a (possibly nested) claim, or null if the credential does not have it
+pub(crate) fn claim_value<'a>(claims: &'a Value, name: &str) -> &'a Value {
+    let value = if name.starts_with('/') { claims.pointer(name) } else { claims.get(name) };
+    value.unwrap_or(&Value::Null)
+}
+
+// The whitespace allowed between JSON tokens (RFC 8259)
+const JSON_WHITESPACE: [char; 4] = [' ', '\t', '\n', '\r'];
+
+// The circuit decodes a revealed string literal, with its quotes and escape sequences, in max_claim_byte_len bytes,
+// and only supports the \uXXXX escapes of the Basic Multilingual Plane (see DecodeJSONString in json_string.circom)
+fn check_string_literal(name: &str, literal: &str, entry: &ClaimConfig) -> Result<(), Box<dyn Error>> {
+    let max_claim_byte_len = entry.max_claim_byte_len();
+    if literal.len() > max_claim_byte_len {
+        return_error!(format!("Claim {} too large ({} bytes with quotes and escape sequences), largest allowed by configuration is {} bytes", name, literal.len(), max_claim_byte_len));
+    }
+    let mut escaped = false;
+    for (i, b) in literal.bytes().enumerate() {
+        if escaped && b == b'u' {
+            let code_unit = literal.get(i + 1..i + 5).and_then(|hex| u16::from_str_radix(hex, 16).ok())
+                .ok_or(format!("Claim {} has an invalid escape sequence", name))?;
+            if (0xD800..=0xDFFF).contains(&code_unit) {
+                return_error!(format!("Claim {} has a surrogate pair escape sequence, which is not supported", name));
             }
-        },
-        "object" => {
-            let mut nested_level = 0;
-            for (i, c) in msg[value_start..].chars().enumerate() {
-                if c == '{' {
-                    nested_level += 1;
-                }
-                else if c == '}' {
-                    nested_level -= 1;
-                    if nested_level == 0 {
-                        r = value_start + i + 1;
-                        break;
-                    }
-                }
+        }
+        escaped = b == b'\\' && !escaped;
+    }
+    Ok(())
+}
+
+// The start of the value of a member whose name ends at name_end, i.e., past whitespace, a colon and whitespace.
+// None if the name is not followed by a colon, e.g., if it is a string value.
+fn skip_name_separator(msg: &str, name_end: usize) -> Option<usize> {
+    let after_colon = msg[name_end..].trim_start_matches(JSON_WHITESPACE).strip_prefix(':')?;
+    Some(msg.len() - after_colon.trim_start_matches(JSON_WHITESPACE).len())
+}
+
+// The start of the name, the start of the value and the end of the value of a member of a JSON object
+pub(crate) type MemberInterval = (usize, usize, usize);
+
+// Find each segment of the path of a claim, and return the intervals of the objects containing the claim, followed
+// by the interval of the claim. As checked by the circuit (see MatchClaimName and MatchParentObject), the segment i
+// is at nesting level i + 1, in the object value of the segment i - 1.
+pub(crate) fn find_path_intervals(msg: &str, name: &str, claim_type: ClaimType) -> Result<Vec<MemberInterval>, Box<dyn Error>> {
+    // The nesting level after each byte, as object_nested_level in the circuit
+    let mut levels = Vec::with_capacity(msg.len());
+    let mut level = 0;
+    for b in msg.bytes() {
+        match b {
+            b'{' => level += 1,
+            b'}' => level -= 1,
+            _ => (),
+        }
+        levels.push(level);
+    }
+
+    let path = claim_path(name);
+    let mut intervals = vec![];
+    let (mut start, mut end) = (0, msg.len());
+    for (i, segment) in path.iter().enumerate() {
+        let level = i as i64 + 1;
+        let is_claim = i + 1 == path.len();
+        let claim_name = format!("\"{}\"", segment);
+        let (l, value_start) = msg[start..end].match_indices(&claim_name).map(|(j, _)| start + j)
+            .filter(|&j| levels[j] == level)
+            .find_map(|j| Some((j, skip_name_separator(msg, j + claim_name.len())?)))
+            .ok_or(format!("Failed to find claim {} in token payload", name))?;
+        if is_claim {
+            intervals.push((l, value_start, find_value_end(msg, value_start, &claim_name, claim_type)?));
+        } else {
+            if !msg[value_start..].starts_with('{') {
+                return_error!(format!("Claim {} of the path {} is not an object", segment, name));
             }
-        },
-        _ => return_error!(format!("Unsupported claim type: {}", type_string)),
+            // The object ends where the nesting level drops back to the level of its name
+            end = (value_start..msg.len()).find(|&j| levels[j] == level).map(|j| j + 1)
+                .ok_or(format!("Parse error, object {} of the path {} is not closed", segment, name))?;
+            start = value_start;
+            intervals.push((l, value_start, end));
+        }
     }
-    Ok((l,r))
+    Ok(intervals)
 }
 
-fn is_minified(msg: &str) -> bool {
-    // Check for extra spaces, e.g.,
-    //     "exp" : 123456789
-    // is not sufficiently minified, but
-    //     "exp":123456789
-    // is minified. Our Circom circuit currently does not support extra space(s).
-    if msg.contains("\": ") {
-        return false;
+// Find a claim in an SD-JWT disclosure ["salt", "name", value], see MatchDisclosureName in sd_jwt.circom
+pub(crate) fn find_disclosure_value_interval(disclosure_json: &str, claim_name: &str, claim_type: ClaimType) -> Result<MemberInterval, Box<dyn Error>> {
+    let name_template = format!("{},", claim_name);
+    let l = disclosure_json.find(&name_template).ok_or(format!("Failed to find claim {} in disclosure", claim_name))?;
+    // Skip the comma and an optional space
+    let mut value_start = l + name_template.len();
+    if disclosure_json[value_start..].starts_with(' ') {
+        value_start += 1;
     }
-    true
+    let r = find_value_end(disclosure_json, value_start, claim_name, claim_type)?;
+    Ok((l, value_start, r))
 }
-    
+
+// The end of the value starting at msg[value_start]
+fn find_value_end(msg: &str, value_start: usize, claim_name: &str, claim_type: ClaimType) -> Result<usize, Box<dyn Error>> {
+    let value = &msg.as_bytes()[value_start..];
+    // The index of the first byte that does not satisfy `pred`, or the end of msg
+    let end_of = |pred: fn(&u8) -> bool| value_start + value.iter().position(|b| !pred(b)).unwrap_or(value.len());
+    let r = match claim_type {
+        // Dates are strings "YYYY-MM-DD". The string ends at the first quote that is not escaped.
+        ClaimType::String | ClaimType::Date => {
+            let mut escaped = false;
+            let close_quote = value.iter().enumerate().skip(1).find(|&(_, &b)| {
+                let is_end = b == b'"' && !escaped;
+                escaped = b == b'\\' && !escaped;
+                is_end
+            });
+            value_start + close_quote.ok_or(format!("Parse error, no closing quote, claim {}", claim_name))?.0 + 1
+        },
+        ClaimType::Number => end_of(|b| b.is_ascii_digit()),
+        ClaimType::Boolean => end_of(|b| b"truefalse".contains(b)),
+        ClaimType::Null => value_start + 4,
+        ClaimType::Array => find_bracket_end(value, b'[', b']').map(|i| value_start + i).unwrap_or(0),
+        ClaimType::Object => find_bracket_end(value, b'{', b'}').map(|i| value_start + i).unwrap_or(0),
+        ClaimType::Integer => return_error!(format!("Unsupported claim type: {}", claim_type.as_str())),
+    };
+    Ok(r)
+}
+
+// The end of the array or object starting at value[0], after the bracket that closes it
+fn find_bracket_end(value: &[u8], open: u8, close: u8) -> Option<usize> {
+    let mut nested_level = 0;
+    for (i, &b) in value.iter().enumerate() {
+        if b == open {
+            nested_level += 1;
+        } else if b == close {
+            nested_level -= 1;
+            if nested_level == 0 {
+                return Some(i + 1);
+            }
+        }
+    }
+    None
+}
+
 // This function creates zero-padding to go between the JSON header and payload
 // in order to match what the Circom base64 decoder outputs.
 // If the header must include padding "=" or "==" to be a multiple of four for base64
 // decoding, then the decoding circuit outputs 0's for these padding characters.
 // (Software decoders don't have this output, but it's quite awkward to do in a circuit)
-fn base_64_decoded_header_padding(header_len: usize) -> Result<String, Box<dyn std::error::Error>> {
+pub(crate) fn base_64_decoded_header_padding(header_len: usize) -> Result<String, Box<dyn std::error::Error>> {
 
     if header_len % 4 == 0 {
         Ok("".to_string())
@@ -477,14 +678,32 @@ fn to_circom_ints(n_bytes: &[u8], limb_size: usize)-> Result<Vec<BigInt>, Box<dy
     Ok(limbs)
 }
 
+// Convert a P-256 field element or scalar to limbs. The ecdsa-p256 circuit expects a fixed number of limbs, so
+// the leading zero limbs of small values are kept.
+fn to_es256_circom_ints(n_bytes: &[u8]) -> Result<Vec<BigInt>, Box<dyn std::error::Error>> {
+    let mut limbs = to_circom_ints(n_bytes, CIRCOM_ES256_LIMB_BITS)?;
+    if limbs.len() > CIRCOM_ES256_NUM_LIMBS {
+        return_error!("Value is too large for a P-256 field element");
+    }
+    limbs.resize(CIRCOM_ES256_NUM_LIMBS, BigInt::from(0));
+    Ok(limbs)
+}
+
+fn to_es256_circom_limbs(n_bytes: &[u8]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let limbs = to_es256_circom_ints(n_bytes)?;
+    Ok(limbs.into_iter().map(|l| l.to_str_radix(10)).collect())
+}
+
 fn b64_to_circom_limbs(n_b64: &str, limb_size: usize) -> Result<Vec<String>, Box<dyn std::error::Error>> {
     let n_bytes = base64_url::decode(n_b64)?;
     to_circom_limbs(&n_bytes, limb_size)
 }
 
-fn sha256_padding(prepad_m: &[u32]) -> Vec<u32> {
+// Padding of SHA-256 (block_len = 64) or SHA-384 and SHA-512 (block_len = 128), see FIPS 180-4, Section 5.1
+pub(crate) fn sha2_padding(prepad_m: &[u32], block_len: usize) -> Vec<u32> {
     // Calculate the message length in bits
-    let msg_length_bits = (prepad_m.len() * 8) as u64;
+    let msg_length_bits = (prepad_m.len() * 8) as u128;
+    let length_len = block_len / 8;
 
     // Create a mutable vector to hold the padded message
     let mut padded_m = prepad_m.to_vec();
@@ -492,93 +711,22 @@ fn sha256_padding(prepad_m: &[u32]) -> Vec<u32> {
     // Append the padding byte 0x80
     padded_m.push(0x80);
 
-    // Append zero bytes until the length of the padded message is congruent to 448 modulo 512
-    while (padded_m.len()) % 64 != 56 {
+    // Append zero bytes until there is just enough room left in the block for the length
+    while (padded_m.len()) % block_len != block_len - length_len {
         padded_m.push(0);
     }
 
-    // Append the original message length as a 64-bit big-endian integer
-    padded_m.extend_from_slice(&msg_length_bits.to_be_bytes().map(|b| b as u32));
+    // Append the original message length as a 64-bit (SHA-256) or 128-bit (SHA-384, SHA-512) big-endian integer
+    let length_bytes = msg_length_bits.to_be_bytes();
+    padded_m.extend(length_bytes[16 - length_len..].iter().map(|b| *b as u32));
 
     padded_m
 }
 
-fn base64_decoded_size(encoded_len : u64) -> u64 {
-    encoded_len.div_ceil(4) * 3
-}
-
-fn to_utf8_integers(input_bytes: &str) -> Vec<u32> {
+pub(crate) fn to_utf8_integers(input_bytes: &str) -> Vec<u32> {
     input_bytes.chars().map(|c| c as u32).collect()
 }
 
-pub fn load_config(path: PathBuf) -> Result<serde_json::Map<String, Value>, Box<dyn Error>> {
-    let config_str = fs::read_to_string(path)?;
-    parse_config(&config_str)
-}
-
-pub fn parse_config(config_str: &str) -> Result<serde_json::Map<String, Value>, Box<dyn Error>> {
-    let mut config_v: Value = serde_json::from_str(config_str)?;
-    let config: &mut serde_json::Map<String, Value> = config_v.as_object_mut().ok_or("Invalid config")?;
-
-    // Validate config
-    if !config.contains_key("alg") {
-        return_error!("'alg' field is missing from config file");
-    }
-
-    let alg_copy = config.get("alg").unwrap().clone();
-    let alg = alg_copy.as_str().ok_or("alg field is not a string")?;    
-    if !CRESCENT_SUPPORTED_ALGS.contains(alg) {
-        return_error!(format!("{} algorithm is unsupported", config["alg"]));
-    }
-
-    // Set defaults
-    if !config.contains_key("max_cred_len") {
-        config.insert("max_cred_len".to_string(), json!(DEFAULT_MAX_TOKEN_LENGTH));
-    }
-    else {
-        if !config["max_cred_len"].is_u64() {
-            return_error!("max_cred_len must have integer type");
-        }
-        let max_cred_len = config["max_cred_len"].as_u64().ok_or("Invalid value for max_cred_len")?;
-        if max_cred_len % 64 != 0 {
-            let round = (64 - (max_cred_len % 64)) + max_cred_len;
-            config["max_cred_len"] = json!(round);
-            println!("Warning: max_cred_len not a multiple of 64. Rounded from {} to {}", max_cred_len, round);
-        }
-    }
-
-    if !config.contains_key("device_bound") {
-        config.insert("device_bound".to_string(), json!(false));
-    }
-
-    if config.get("device_bound").unwrap().as_bool().ok_or("expected bool type")? {
-        let device_key_entry = format!(r#" 
-            {{
-            "type": "number",
-            "reveal": true,
-            "max_claim_byte_len": {}
-            }}
-        "#,
-         2*MAX_FIELD_BYTE_LEN);
-        config.insert("device_key_0".to_string(), serde_json::from_str(&device_key_entry)?);
-        config.insert("device_key_1".to_string(), serde_json::from_str(&device_key_entry)?);
-    }
-
-    // For all the config entries about claims (e.g, "email", "exp", etc.) make sure that if the claim 
-    // is to be revealed, that max_claim_byte_len is set
-    for (key, _) in config.clone() {
-        if !CRESCENT_CONFIG_KEYS.contains(key.as_str()) {
-            let claim_entry = config.get(key.as_str()).unwrap().as_object().ok_or("expected object type")?.clone();
-            if claim_entry.contains_key("reveal") && claim_entry["reveal"].as_bool().unwrap_or(false) && !claim_entry.contains_key("max_claim_byte_len") {
-                return_error!(format!("Config entry for claim {} has reveal flag set but is missing 'max_claim_byte_len'", key));
-            }
-        }
-    }
-
-    Ok(config.clone())
-}
-
-
 // Create the internal version of the ProofSpec object.  This combines information from the config file and the
 // provided ProofSpec to create a mode detailed object. 
 pub(crate) fn create_proof_spec_internal(proof_spec: &ProofSpec, config_str: &str) -> Result<ProofSpecInternal, Box<dyn Error>> {
@@ -586,34 +734,107 @@ pub(crate) fn create_proof_spec_internal(proof_spec: &ProofSpec, config_str: &st
     let mut revealed = vec![];
     let mut hashed = vec![];
     // Build claim_types map
-    let mut claim_types = std::collections::BTreeMap::new();
-    for (key, value) in config.iter() {
-        // Only process claim entries, skip config keys
-        if crate::prep_inputs::CRESCENT_CONFIG_KEYS.contains(key.as_str()) {
-            continue;
-        }
-        if let Some(obj) = value.as_object() {
-            if let Some(type_str) = obj.get("type").and_then(|v| v.as_str()) {
-                claim_types.insert(key.clone(), type_str.to_string());
-            }
-        }
-    }
+    let claim_types = config.claims.iter().map(|(name, entry)| (name.clone(), entry.claim_type.as_str().to_string())).collect();
+    let optional = config.claims.iter().filter(|(_, entry)| entry.optional).map(|(name, _)| name.clone()).collect::<Vec<_>>();
 
     // Check that all revealed attributes are in the config
     for attr in &proof_spec.revealed {
-        let config_entry = config.get(attr.as_str()).ok_or(format!("Attribute {} not found in config", attr))?;
-        if config_entry.get("reveal_digest").is_some() && config_entry.get("reveal_digest").ok_or("Expected boolean value for 'reveal_digest'")?.as_bool().unwrap() {
+        let config_entry = config.claim(attr).ok_or(format!("Attribute {} not found in config", attr))?;
+        if config_entry.reveal_digest {
             hashed.push(attr.to_string());
         }
         else {
             revealed.push(attr.to_string());
         }
     }
+    let reveal_if_present = proof_spec.reveal_if_present.clone().unwrap_or_default();
+    for attr in &reveal_if_present {
+        if !optional.contains(attr) {
+            return_error!(format!("Attribute {} is in reveal_if_present, but is not an optional claim of the config", attr));
+        }
+        if revealed.contains(attr) {
+            return_error!(format!("Attribute {} cannot be both in revealed and in reveal_if_present", attr));
+        }
+        revealed.push(attr.to_string());
+    }
+    // The values are revealed in the order of the circuit's public inputs, which follows the config
+    revealed.sort_by_key(|attr| config.claims.iter().position(|(name, _)| name == attr));
     // Convert range_over_year from ProofSpec (which must be JSON-compatible) to Vec<(String, usize)>
     let range_over_year = match &proof_spec.range_over_year {
         Some(map) => map.iter().map(|(k, v)| (k.clone(), *v)).collect(),
         None => Vec::new(),
     };
+    let range_predicates = proof_spec.range_predicates.clone().unwrap_or_default();
+    for attr in range_over_year.iter().map(|(attr, _)| attr).chain(range_predicates.iter().map(|p| &p.attribute)) {
+        // Range proofs are on committed attributes, they cannot be combined with disclosure
+        if revealed.contains(attr) || hashed.contains(attr) {
+            return_error!(format!("Attribute {} cannot be both revealed and used in a range predicate", attr));
+        }
+    }
+    for (attr, _) in &range_over_year {
+        // The age threshold is a daystamp, see age_threshold_daystamp
+        if config.claim(attr).map(|entry| entry.claim_type) != Some(ClaimType::Date) {
+            return_error!(format!("Attribute {} must have type date to be used in range_over_year", attr));
+        }
+    }
+    let pseudonym = proof_spec.pseudonym.clone();
+    if let Some(pseudonym) = &pseudonym {
+        // Revealing the attribute would let verifiers link pseudonyms across scopes
+        if revealed.contains(&pseudonym.attribute) || hashed.contains(&pseudonym.attribute) {
+            return_error!(format!("Attribute {} cannot be both revealed and used for a pseudonym", pseudonym.attribute));
+        }
+    }
+    let revocation_attribute = proof_spec.revocation_attribute.clone();
+    if let Some(attr) = &revocation_attribute {
+        // Revealing the identifier would make shows linkable
+        if revealed.contains(attr) || hashed.contains(attr) {
+            return_error!(format!("Attribute {} cannot be both revealed and used for revocation", attr));
+        }
+    }
+    let status_list = proof_spec.status_list.clone();
+    if let Some(status_list) = &status_list {
+        if revealed.contains(&status_list.attribute) || hashed.contains(&status_list.attribute) {
+            return_error!(format!("Attribute {} cannot be both revealed and used as a status list index", status_list.attribute));
+        }
+        if ![1, 2, 4, 8].contains(&status_list.list.bits) {
+            return_error!(format!("Invalid status list: bits must be 1, 2, 4 or 8, found {}", status_list.list.bits));
+        }
+    }
+    let set_predicates = proof_spec.set_predicates.clone().unwrap_or_default();
+    for predicate in &set_predicates {
+        if revealed.contains(&predicate.attribute) || hashed.contains(&predicate.attribute) {
+            return_error!(format!("Attribute {} cannot be both revealed and used in a set predicate", predicate.attribute));
+        }
+        if predicate.in_set.is_some() == predicate.not_in.is_some() {
+            return_error!(format!("Set predicate on {} must have exactly one of 'in' and 'not_in'", predicate.attribute));
+        }
+        if predicate.is_membership() && predicate.values().is_empty() {
+            return_error!(format!("Set predicate on {} has an empty 'in' set", predicate.attribute));
+        }
+        if predicate.values().len() > MAX_SET_SIZE {
+            return_error!(format!("Set predicate on {} has {} values, at most {} are supported", predicate.attribute, predicate.values().len(), MAX_SET_SIZE));
+        }
+    }
+    let issuer_set = proof_spec.issuer_set.clone();
+    if let Some(issuer_set) = &issuer_set {
+        if issuer_set.is_empty() || issuer_set.len() > MAX_SET_SIZE {
+            return_error!(format!("The issuer set must have between 1 and {} issuer keys, found {}", MAX_SET_SIZE, issuer_set.len()));
+        }
+    }
+    let committed = proof_spec.committed.clone().unwrap_or_default();
+    for attr in &committed {
+        if revealed.contains(attr) || hashed.contains(attr) {
+            return_error!(format!("Attribute {} cannot be both revealed and committed", attr));
+        }
+    }
+    for predicate in &range_predicates {
+        if predicate.min.is_none() && predicate.max.is_none() {
+            return_error!(format!("Range predicate on {} has neither a min nor a max bound", predicate.attribute));
+        }
+        if !is_valid_bit_width(predicate.bits(), MAX_RANGE_PROOF_BITS) {
+            return_error!(format!("Range predicate on {} has invalid bits {}, expected a power of two between {} and {}", predicate.attribute, predicate.bits(), MIN_RANGE_PROOF_BITS, MAX_RANGE_PROOF_BITS));
+        }
+    }
     let presentation_message = proof_spec.presentation_message.clone();
     let device_bound = proof_spec.device_bound.unwrap_or(false);
 
@@ -621,13 +842,474 @@ pub(crate) fn create_proof_spec_internal(proof_spec: &ProofSpec, config_str: &st
         return_error!("Proof spec indicates the credential is device bound, but is missing the presentation message");
     }
 
-    Ok(ProofSpecInternal {
+    // Age predicates are evaluated at the proof time, in the issuer's timezone (UTC by default)
+    let timezone = parse_timezone(config.timezone.as_deref().unwrap_or("UTC"))?;
+
+    let proof_spec = ProofSpecInternal {
         revealed,
+        reveal_if_present,
+        optional,
         hashed,
         range_over_year,
+        range_predicates,
         presentation_message,
         device_bound,
+        pseudonym,
+        revocation_attribute,
+        status_list,
+        set_predicates,
+        issuer_set,
+        committed,
         config_str: config_str.to_owned(),
         claim_types,
-    })
-}
\ No newline at end of file
+        timezone,
+    };
+
+    // The attributes committed to in the show proof (the expiration date, the device key, and the attributes of the
+    // predicates above) cannot be revealed
+    let format = credential_format_from_config(config_str)?;
+    for attr in format.committed_claims(&proof_spec) {
+        if proof_spec.revealed.contains(&attr) || proof_spec.hashed.contains(&attr) {
+            return_error!(format!("Attribute {} cannot be both revealed and committed", attr));
+        }
+    }
+    Ok(proof_spec)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DEFAULT_MAX_TOKEN_LENGTH;
+
+    #[test]
+    fn test_es256_prover_inputs() {
+        let key_pair = ES256KeyPair::generate();
+        let issuer_pem = key_pair.public_key().to_pem().unwrap();
+        let token = key_pair.sign(Claims::create(Duration::from_hours(1))).unwrap();
+        let config = parse_config(r#"{"alg": "ES256", "exp": {"type": "number", "reveal": true, "max_claim_byte_len": 31}}"#).unwrap();
+        assert_eq!(pem_key_type(&issuer_pem), Ok("ES256"));
+
+        let (prover_inputs, _, public_ios) = prepare_prover_inputs(&config, &token, &issuer_pem, None).unwrap();
+        for name in ["signature_r", "signature_s", "pubkey_x", "pubkey_y"] {
+            assert_eq!(prover_inputs[name].as_array().unwrap().len(), CIRCOM_ES256_NUM_LIMBS);
+        }
+        assert!(prover_inputs.get("modulus").is_none());
+
+        // The public key inputs of the circuit match the ones the verifier computes from the PEM key
+        let key_limbs = public_ios["pubkey_x"].as_array().unwrap().iter().chain(public_ios["pubkey_y"].as_array().unwrap())
+            .map(|l| CrescentFr::from_str(l.as_str().unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(key_limbs, pem_to_inputs::<CrescentFr>(&issuer_pem).unwrap());
+
+        // A token signed by another issuer, or checked against the wrong algorithm, is rejected
+        let other_token = ES256KeyPair::generate().sign(Claims::create(Duration::from_hours(1))).unwrap();
+        assert!(prepare_prover_inputs(&config, &other_token, &issuer_pem, None).is_err());
+        assert!(verify_jwt("RS256", &token, &issuer_pem).is_err());
+    }
+
+    #[test]
+    fn test_rsa_prover_inputs() {
+        // Key generation is slow in debug builds, so one key is used for all the RSA algorithms
+        let key_der = RS256KeyPair::generate(2048).unwrap().to_der().unwrap();
+        let issuer_pem = RS256KeyPair::from_der(&key_der).unwrap().public_key().to_pem().unwrap();
+        assert_eq!(pem_key_type(&issuer_pem), Ok("RSA"));
+        let claims = || Claims::create(Duration::from_hours(1));
+        let tokens = [
+            ("RS256", RS256KeyPair::from_der(&key_der).unwrap().sign(claims()).unwrap()),
+            ("RS384", RS384KeyPair::from_der(&key_der).unwrap().sign(claims()).unwrap()),
+            ("RS512", RS512KeyPair::from_der(&key_der).unwrap().sign(claims()).unwrap()),
+            ("PS256", PS256KeyPair::from_der(&key_der).unwrap().sign(claims()).unwrap()),
+        ];
+
+        for (alg, token) in &tokens {
+            let config = parse_config(&format!(r#"{{"alg": "{}", "exp": {{"type": "number", "reveal": true, "max_claim_byte_len": 31}}}}"#, alg)).unwrap();
+            assert_eq!(config.rsa_key_bits, Some(2048));
+            let (prover_inputs, _, public_ios) = prepare_prover_inputs(&config, token, &issuer_pem, None).unwrap();
+            assert_eq!(prover_inputs["signature"].as_array().unwrap().len(), 17);
+            assert_eq!(prover_inputs["message"].as_array().unwrap().len(), DEFAULT_MAX_TOKEN_LENGTH);
+            let padded_len = prover_inputs["message_padded_bytes"].as_str().unwrap().parse::<usize>().unwrap();
+            assert_eq!(padded_len % hash_block_len(alg), 0);
+
+            let modulus_limbs = public_ios["modulus"].as_array().unwrap().iter()
+                .map(|l| CrescentFr::from_str(l.as_str().unwrap()).unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(modulus_limbs, pem_to_inputs::<CrescentFr>(&issuer_pem).unwrap());
+
+            // The circuit is set up for the key size in the config
+            let config = parse_config(&format!(r#"{{"alg": "{}", "rsa_key_bits": 3072}}"#, alg)).unwrap();
+            assert!(prepare_prover_inputs(&config, token, &issuer_pem, None).is_err());
+        }
+
+        // The token must have been signed with the algorithm in the config
+        let config = parse_config(r#"{"alg": "PS256"}"#).unwrap();
+        assert!(prepare_prover_inputs(&config, &tokens[0].1, &issuer_pem, None).is_err());
+    }
+
+    #[test]
+    fn test_rsa_key_bits_config() {
+        assert_eq!(parse_config(r#"{"alg": "RS256", "rsa_key_bits": 4096}"#).unwrap().rsa_key_bits, Some(4096));
+        assert!(parse_config(r#"{"alg": "RS256", "rsa_key_bits": 1024}"#).is_err());
+        assert!(parse_config(r#"{"alg": "ES256", "rsa_key_bits": 2048}"#).is_err());
+        assert!(parse_config(r#"{"alg": "ES256"}"#).unwrap().rsa_key_bits.is_none());
+
+        // SHA-384 and SHA-512 hash the token in 128-byte blocks
+        assert_eq!(parse_config(r#"{"alg": "RS512", "max_cred_len": 1088}"#).unwrap().max_cred_len, 1152);
+        assert_eq!(parse_config(r#"{"alg": "RS256", "max_cred_len": 1088}"#).unwrap().max_cred_len, 1088);
+    }
+
+    #[test]
+    fn test_sha2_padding() {
+        let m = vec![0x61u32; 3];
+        let padded = sha2_padding(&m, 64);
+        assert_eq!(padded.len(), 64);
+        assert_eq!(padded[3], 0x80);
+        assert_eq!(padded[63], 24);
+        assert!(padded[4..63].iter().all(|b| *b == 0));
+
+        let padded = sha2_padding(&m, 128);
+        assert_eq!(padded.len(), 128);
+        assert_eq!(padded[3], 0x80);
+        assert_eq!(padded[127], 24);
+        assert!(padded[4..127].iter().all(|b| *b == 0));
+
+        // No room for the length in the first block
+        assert_eq!(sha2_padding(&vec![0u32; 112], 128).len(), 256);
+        assert_eq!(sha2_padding(&vec![0u32; 111], 128).len(), 128);
+    }
+
+    #[test]
+    fn test_sd_jwt_prover_inputs() {
+        let given_name = Disclosure::parse(&base64_url::encode(r#"["2GLC42sKQveCfGfryNRN9w", "given_name", "John"]"#)).unwrap();
+        let age = Disclosure::parse(&base64_url::encode(r#"["eluV5Og3gSNII8EYnsxA_A","age",42]"#)).unwrap();
+        let key_pair = ES256KeyPair::generate();
+        let issuer_pem = key_pair.public_key().to_pem().unwrap();
+        let payload = json!({"_sd": [given_name.digest(), age.digest()], "_sd_alg": "sha-256"});
+        let issuer_jwt = key_pair.sign(Claims::with_custom_claims(payload, Duration::from_hours(1))).unwrap();
+        let token = format!("{}~{}~{}~", issuer_jwt, given_name.encoded, age.encoded);
+        let config = parse_config(r#"{"alg": "ES256", "credtype": "sd-jwt",
+            "exp": {"type": "number", "reveal": true, "max_claim_byte_len": 31},
+            "given_name": {"type": "string", "reveal": true, "max_claim_byte_len": 31, "sd": true},
+            "age": {"type": "number", "reveal": true, "max_claim_byte_len": 31, "sd": true, "max_disclosure_len": 192}}"#).unwrap();
+        assert_eq!(config.claim("given_name").unwrap().max_disclosure_len, Some(DEFAULT_MAX_DISCLOSURE_LENGTH));
+
+        let (prover_inputs, _, _) = prepare_prover_inputs(&config, &token, &issuer_pem, None).unwrap();
+        assert_eq!(prover_inputs["given_name_disclosure"].as_array().unwrap().len(), DEFAULT_MAX_DISCLOSURE_LENGTH);
+        assert_eq!(prover_inputs["age_disclosure"].as_array().unwrap().len(), 192);
+        assert_eq!(prover_inputs["given_name_value"], json!(pack_string_to_int("John", 31).unwrap()));
+        assert_eq!(prover_inputs["age_value"], json!("42"));
+        assert!(prover_inputs.contains_key("exp_l"));
+
+        // The positions are those of the name and the value in the decoded disclosure, with or without a space
+        for (disclosure, value) in [(&given_name, "\"John\""), (&age, "42")] {
+            let json = disclosure.json().unwrap();
+            let l = prover_inputs[&format!("{}_l", disclosure.name)].as_str().unwrap().parse::<usize>().unwrap();
+            let r = prover_inputs[&format!("{}_r", disclosure.name)].as_str().unwrap().parse::<usize>().unwrap();
+            assert!(json[l..].starts_with(&format!("\"{}\",", disclosure.name)));
+            assert!(json[..r].ends_with(value));
+        }
+
+        // Every claim with the 'sd' flag must be disclosed
+        assert!(prepare_prover_inputs(&config, &format!("{}~{}~", issuer_jwt, age.encoded), &issuer_pem, None).is_err());
+        // The 'sd' flag is only for SD-JWTs, and disclosures must fit in max_disclosure_len
+        assert!(parse_config(r#"{"alg": "ES256", "age": {"type": "number", "sd": true}}"#).is_err());
+        assert!(parse_config(r#"{"alg": "ES256", "credtype": "sd-jwt", "age": {"type": "number", "sd": true, "max_disclosure_len": 100}}"#).is_err());
+        let config = parse_config(r#"{"alg": "ES256", "credtype": "sd-jwt", "given_name": {"type": "string", "sd": true, "max_disclosure_len": 64}}"#).unwrap();
+        assert!(prepare_prover_inputs(&config, &token, &issuer_pem, None).is_err());
+    }
+
+    #[test]
+    fn test_sd_jwt_digest_in_sd_array() {
+        let given_name = Disclosure::parse(&base64_url::encode(r#"["2GLC42sKQveCfGfryNRN9w", "given_name", "John"]"#)).unwrap();
+        let key_pair = ES256KeyPair::generate();
+        let issuer_pem = key_pair.public_key().to_pem().unwrap();
+        let config = parse_config(r#"{"alg": "ES256", "credtype": "sd-jwt",
+            "exp": {"type": "number", "reveal": true, "max_claim_byte_len": 31},
+            "given_name": {"type": "string", "reveal": true, "max_claim_byte_len": 31, "sd": true}}"#).unwrap();
+        // The token and the decoded header and payload, as matched by the circuit
+        let sign = |payload: Value| {
+            let issuer_jwt = key_pair.sign(Claims::with_custom_claims(payload, Duration::from_hours(1))).unwrap();
+            let parts = issuer_jwt.split('.').collect::<Vec<_>>();
+            let header = String::from_utf8(base64_url::decode(parts[0]).unwrap()).unwrap();
+            let payload = String::from_utf8(base64_url::decode(parts[1]).unwrap()).unwrap();
+            let msg = format!("{}{}{}", header, base_64_decoded_header_padding(parts[0].len()).unwrap(), payload);
+            (format!("{}~{}~", issuer_jwt, given_name.encoded), msg)
+        };
+        let quoted_digest = format!("\"{}\"", given_name.digest());
+
+        // The digest is also the value of another claim, that comes first in the payload: the prover points to the
+        // element of the "_sd" array
+        let (token, msg) = sign(json!({"display_name": given_name.digest(), "_sd": ["0".repeat(43), given_name.digest()], "_sd_alg": "sha-256"}));
+        assert!(msg.find(&quoted_digest).unwrap() < msg.find("\"_sd\"").unwrap());
+        let (prover_inputs, _, _) = prepare_prover_inputs(&config, &token, &issuer_pem, None).unwrap();
+        let input = |key: &str| prover_inputs[key].as_str().unwrap().parse::<usize>().unwrap();
+        assert!(msg[input("sd_array_l")..].starts_with("\"_sd\""));
+        let sd_array = &msg[input("sd_array_value_l")..input("sd_array_r")];
+        assert_eq!(sd_array, format!("[\"{}\",{}]", "0".repeat(43), quoted_digest));
+        let digest_l = input("given_name_digest_l");
+        assert!(msg[digest_l..].starts_with(&quoted_digest));
+        assert_eq!(&msg[digest_l - 1..digest_l], ",");
+        assert!(input("sd_array_value_l") < digest_l && digest_l + 45 < input("sd_array_r"));
+
+        // The digest is only the value of another claim, as if the holder chose a display name that is the digest of
+        // a forged disclosure
+        let (token, _) = sign(json!({"display_name": given_name.digest(), "_sd": ["0".repeat(43)], "_sd_alg": "sha-256"}));
+        assert!(prepare_prover_inputs(&config, &token, &issuer_pem, None).is_err());
+        let (token, _) = sign(json!({"display_name": given_name.digest(), "_sd_alg": "sha-256"}));
+        assert!(prepare_prover_inputs(&config, &token, &issuer_pem, None).is_err());
+        let (token, _) = sign(json!({"_sd": given_name.digest(), "_sd_alg": "sha-256"}));
+        assert!(prepare_prover_inputs(&config, &token, &issuer_pem, None).is_err());
+    }
+
+    #[test]
+    fn test_nested_claim_prover_inputs() {
+        assert_eq!(claim_path("/vc/credentialSubject/birthDate"), vec!["vc", "credentialSubject", "birthDate"]);
+        assert_eq!(claim_path("/a~1b/c~0d"), vec!["a/b", "c~d"]);
+        assert_eq!(claim_path("exp"), vec!["exp"]);
+        assert_eq!(claim_signal_name("/vc/credentialSubject/birthDate"), "vc_credentialSubject_birthDate");
+        assert_eq!(claim_signal_name("/address/country_value"), "address_country_value");
+        assert_eq!(claim_signal_name("exp_value"), "exp_value");
+
+        // Decoys named "country" come before the claims, in a nested object and in the header
+        let key_pair = ES256KeyPair::generate();
+        let issuer_pem = key_pair.public_key().to_pem().unwrap();
+        let payload = json!({"address": {"area": {"country": "DE"}, "country": "US"}, "country": "FR"});
+        let token = key_pair.with_key_id("\"country\":").sign(Claims::with_custom_claims(payload, Duration::from_hours(1))).unwrap();
+        let config = parse_config(r#"{"alg": "ES256",
+            "/address/country": {"type": "string", "reveal": true, "max_claim_byte_len": 31},
+            "country": {"type": "string", "reveal_digest": true, "max_claim_byte_len": 31}}"#).unwrap();
+
+        let (prover_inputs, prover_aux, _) = prepare_prover_inputs(&config, &token, &issuer_pem, None).unwrap();
+        assert_eq!(prover_inputs["address_country_value"], json!(pack_string_to_int("US", 31).unwrap()));
+        assert_eq!(prover_aux["country"], json!("FR"));
+        let parts = token.split('.').collect::<Vec<_>>();
+        let header = String::from_utf8(base64_url::decode(parts[0]).unwrap()).unwrap();
+        let payload = String::from_utf8(base64_url::decode(parts[1]).unwrap()).unwrap();
+        let msg = format!("{}{}{}", header, base_64_decoded_header_padding(parts[0].len()).unwrap(), payload);
+        let interval = |name: &str| {
+            let l = prover_inputs[&format!("{}_l", name)].as_str().unwrap().parse::<usize>().unwrap();
+            let r = prover_inputs[&format!("{}_r", name)].as_str().unwrap().parse::<usize>().unwrap();
+            &msg[l..r]
+        };
+        assert_eq!(interval("address_object"), r#""address":{"area":{"country":"DE"},"country":"US"}"#);
+        assert_eq!(interval("address_country"), r#""country":"US""#);
+        assert_eq!(interval("country"), r#""country":"FR""#);
+
+        // A claim that is not in its parent object is not found
+        let config = parse_config(r#"{"alg": "ES256", "/address/area/exp": {"type": "number"}}"#).unwrap();
+        assert!(prepare_prover_inputs(&config, &token, &issuer_pem, None).is_err());
+        let config = parse_config(r#"{"alg": "ES256", "/address/country/code": {"type": "string"}}"#).unwrap();
+        assert!(prepare_prover_inputs(&config, &token, &issuer_pem, None).is_err());
+
+        assert!(parse_config(r#"{"alg": "ES256", "/address//country": {"type": "string"}}"#).is_err());
+        assert!(parse_config(r#"{"alg": "ES256", "/address/country": {"type": "string"}, "address_country": {"type": "string"}}"#).is_err());
+        assert!(parse_config(r#"{"alg": "ES256", "credtype": "sd-jwt", "/address/country": {"type": "string", "sd": true}}"#).is_err());
+    }
+
+    #[test]
+    fn test_reveal_bytes() {
+        let key_pair = ES256KeyPair::generate();
+        let issuer_pem = key_pair.public_key().to_pem().unwrap();
+        let iss = "https://login.microsoftonline.com/12345678-1234-abcd-1234-abcdef124567/v2.0";
+        let token = key_pair.sign(Claims::create(Duration::from_hours(1)).with_issuer(iss)).unwrap();
+        let config = parse_config(r#"{"alg": "ES256", "iss": {"type": "string", "reveal_bytes": true, "max_claim_byte_len": 93}}"#).unwrap();
+
+        let (prover_inputs, _, _) = prepare_prover_inputs(&config, &token, &issuer_pem, None).unwrap();
+        let packed = prover_inputs["iss_value"].as_array().unwrap();
+        assert_eq!(packed.len(), 3);
+        let ints = packed.iter().map(|n| CrescentFr::from_str(n.as_str().unwrap()).unwrap().into_bigint()).collect::<Vec<_>>();
+        assert_eq!(unpack_ints_to_string_unquoted(&ints).unwrap(), iss);
+        // The first field element packs the first 31 bytes, with the opening quote
+        assert_eq!(packed[0], json!(pack_string_to_int_unquoted(&format!("\"{}", &iss[..30]), 31).unwrap()));
+
+        // Multi-byte characters may be split across field elements
+        let name = "Zoë Ångström-Müller de la Peña y Sánchez";
+        let ints = pack_string_to_ints(name, 62).unwrap().iter().map(|n| CrescentFr::from_str(n).unwrap().into_bigint()).collect::<Vec<_>>();
+        assert_eq!(unpack_ints_to_string_unquoted(&ints).unwrap(), name);
+        assert!(pack_string_to_ints(name, 31).is_err());
+
+        let config = parse_config(r#"{"alg": "ES256", "iss": {"type": "string", "reveal_bytes": true, "max_claim_byte_len": 62}}"#).unwrap();
+        assert!(prepare_prover_inputs(&config, &token, &issuer_pem, None).is_err());
+        assert!(parse_config(r#"{"alg": "ES256", "iss": {"type": "string", "reveal_bytes": true, "max_claim_byte_len": 64}}"#).is_err());
+        assert!(parse_config(r#"{"alg": "ES256", "iss": {"type": "string", "reveal_bytes": true}}"#).is_err());
+        assert!(parse_config(r#"{"alg": "ES256", "exp": {"type": "number", "reveal_bytes": true, "max_claim_byte_len": 62}}"#).is_err());
+    }
+
+    #[test]
+    fn test_boolean_and_date_claims() {
+        let key_pair = ES256KeyPair::generate();
+        let issuer_pem = key_pair.public_key().to_pem().unwrap();
+        let payload = json!({"age_over_18": true, "birthdate": "1990-05-17", "email_verified": false});
+        let token = key_pair.sign(Claims::with_custom_claims(payload, Duration::from_hours(1))).unwrap();
+        let config = parse_config(r#"{"alg": "ES256",
+            "age_over_18": {"type": "boolean"},
+            "birthdate": {"type": "date", "reveal": true},
+            "email_verified": {"type": "boolean", "reveal": true}}"#).unwrap();
+
+        let (prover_inputs, _, _) = prepare_prover_inputs(&config, &token, &issuer_pem, None).unwrap();
+        assert_eq!(prover_inputs["email_verified_value"], json!("0"));
+        assert_eq!(prover_inputs["birthdate_value"], json!(date_to_daystamp("1990-05-17").unwrap().to_string()));
+        assert!(!prover_inputs.contains_key("age_over_18_value"));
+        let parts = token.split('.').collect::<Vec<_>>();
+        let header = String::from_utf8(base64_url::decode(parts[0]).unwrap()).unwrap();
+        let payload = String::from_utf8(base64_url::decode(parts[1]).unwrap()).unwrap();
+        let msg = format!("{}{}{}", header, base_64_decoded_header_padding(parts[0].len()).unwrap(), payload);
+        let interval = |name: &str| {
+            let l = prover_inputs[&format!("{}_l", name)].as_str().unwrap().parse::<usize>().unwrap();
+            let r = prover_inputs[&format!("{}_r", name)].as_str().unwrap().parse::<usize>().unwrap();
+            &msg[l..r]
+        };
+        assert_eq!(interval("age_over_18"), r#""age_over_18":true"#);
+        assert_eq!(interval("birthdate"), r#""birthdate":"1990-05-17""#);
+        assert_eq!(interval("email_verified"), r#""email_verified":false"#);
+
+        // Dates outside of the range supported by the circuit
+        let token = key_pair.sign(Claims::with_custom_claims(json!({"birthdate": "1880-01-01"}), Duration::from_hours(1))).unwrap();
+        assert!(prepare_prover_inputs(&config, &token, &issuer_pem, None).is_err());
+
+        assert!(parse_config(r#"{"alg": "ES256", "birthdate": {"type": "date", "reveal_digest": true, "max_claim_byte_len": 31}}"#).is_err());
+        assert!(parse_config(r#"{"alg": "ES256", "email_verified": {"type": "boolean", "reveal_bytes": true, "max_claim_byte_len": 31}}"#).is_err());
+    }
+
+    #[test]
+    fn test_optional_claims() {
+        let key_pair = ES256KeyPair::generate();
+        let issuer_pem = key_pair.public_key().to_pem().unwrap();
+        let config_str = r#"{"alg": "ES256",
+            "email": {"type": "string", "reveal": true, "max_claim_byte_len": 31},
+            "middle_name": {"type": "string", "optional": true, "reveal": true, "max_claim_byte_len": 31},
+            "email_verified": {"type": "boolean", "optional": true, "reveal": true}}"#;
+        let config = parse_config(config_str).unwrap();
+
+        let payload = json!({"email": "alice@example.com", "email_verified": true});
+        let token = key_pair.sign(Claims::with_custom_claims(payload, Duration::from_hours(1))).unwrap();
+        let (prover_inputs, _, _) = prepare_prover_inputs(&config, &token, &issuer_pem, None).unwrap();
+        assert!(!prover_inputs.contains_key("email_present"));
+        assert_eq!(prover_inputs["email_verified_present"], json!("1"));
+        assert_eq!(prover_inputs["email_verified_value"], json!("1"));
+        // The absent claim is matched against its default value, {"middle_name":""}
+        assert_eq!(prover_inputs["middle_name_present"], json!("0"));
+        assert_eq!(prover_inputs["middle_name_l"], json!("1"));
+        assert_eq!(prover_inputs["middle_name_r"], json!(r#"{"middle_name":"""#.len().to_string()));
+        assert_eq!(prover_inputs["middle_name_value"], json!(pack_string_to_int("", 31).unwrap()));
+
+        // Optional claims are only revealed by reveal_if_present, and the revealed values follow the config order
+        let spec = |value: Value| serde_json::from_value::<ProofSpec>(value).unwrap();
+        let proof_spec = create_proof_spec_internal(&spec(json!({"revealed": ["email"], "reveal_if_present": ["email_verified", "middle_name"]})), config_str).unwrap();
+        assert_eq!(proof_spec.revealed, vec!["email", "middle_name", "email_verified"]);
+        assert_eq!(proof_spec.optional, vec!["middle_name", "email_verified"]);
+        assert!(create_proof_spec_internal(&spec(json!({"revealed": [], "reveal_if_present": ["email"]})), config_str).is_err());
+        assert!(create_proof_spec_internal(&spec(json!({"revealed": ["middle_name"], "reveal_if_present": ["middle_name"]})), config_str).is_err());
+
+        assert!(parse_config(r#"{"alg": "ES256", "exp": {"type": "number", "optional": true}}"#).is_err());
+        assert!(parse_config(r#"{"alg": "ES256", "email": {"type": "string", "optional": true, "reveal_digest": true, "max_claim_byte_len": 31}}"#).is_err());
+        assert!(parse_config(r#"{"alg": "ES256", "/address/country": {"type": "string", "optional": true, "max_claim_byte_len": 31}}"#).is_err());
+    }
+
+    #[test]
+    fn test_committed_attributes_not_revealed() {
+        let config_str = r#"{"alg": "ES256",
+            "exp": {"type": "number", "reveal": true, "max_claim_byte_len": 31},
+            "email": {"type": "string", "reveal": true, "max_claim_byte_len": 31},
+            "tenant": {"type": "string", "reveal": true, "max_claim_byte_len": 31}}"#;
+        let spec = |value: Value| serde_json::from_value::<ProofSpec>(value).unwrap();
+        assert!(create_proof_spec_internal(&spec(json!({"revealed": ["email"], "pseudonym": {"scope": "s", "attribute": "tenant"}})), config_str).is_ok());
+        for bad in [
+            json!({"revealed": ["exp"]}),
+            json!({"revealed": ["email"], "pseudonym": {"scope": "s", "attribute": "email"}}),
+            json!({"revealed": ["email"], "revocation_attribute": "email"}),
+            json!({"revealed": ["email"], "status_list": {"attribute": "email", "bits": 1, "lst": "eNrbuRgAAhcBXQ"}}),
+            json!({"revealed": ["email"], "set_predicates": [{"attribute": "email", "in": ["a@example.com"]}]}),
+            json!({"revealed": ["email"], "committed": ["email"]}),
+        ] {
+            assert!(create_proof_spec_internal(&spec(bad.clone()), config_str).is_err(), "{}", bad);
+        }
+    }
+
+    // jwt_simple serializes minified claims with its own escaping, sign the payload as given. Returns the token and
+    // the message the circuit matches the claims in.
+    fn sign_es256_payload(key_pair: &ES256KeyPair, payload: &str) -> (String, String) {
+        use p256::ecdsa::signature::Signer;
+
+        let header = r#"{"alg":"ES256","typ":"JWT"}"#;
+        let header_b64 = base64_url::encode(header);
+        let signing_input = format!("{}.{}", header_b64, base64_url::encode(payload));
+        let signing_key = p256::ecdsa::SigningKey::from_slice(&key_pair.to_bytes()).unwrap();
+        let signature: p256::ecdsa::Signature = signing_key.sign(signing_input.as_bytes());
+        let token = format!("{}.{}", signing_input, base64_url::encode(&signature.to_bytes()));
+        let msg = format!("{}{}{}", header, base_64_decoded_header_padding(header_b64.len()).unwrap(), payload);
+        (token, msg)
+    }
+
+    // The part of msg from the input `{name}_{from}` to the input `{name}_r`
+    fn claim_interval<'a>(prover_inputs: &serde_json::Map<String, Value>, msg: &'a str, name: &str, from: &str) -> &'a str {
+        let l = prover_inputs[&format!("{}_{}", name, from)].as_str().unwrap().parse::<usize>().unwrap();
+        let r = prover_inputs[&format!("{}_r", name)].as_str().unwrap().parse::<usize>().unwrap();
+        &msg[l..r]
+    }
+
+    #[test]
+    fn test_non_minified_payload() {
+        let key_pair = ES256KeyPair::generate();
+        let issuer_pem = key_pair.public_key().to_pem().unwrap();
+        let exp = Clock::now_since_epoch().as_secs() + 3600;
+        let payload = format!("{{\n  \"exp\" : {} ,\n  \"address\": {{\"country\":\t\"US\"}},\n  \"email\":\r\n \"alice@example.com\"\n}}", exp);
+        let (token, msg) = sign_es256_payload(&key_pair, &payload);
+
+        let config = parse_config(r#"{"alg": "ES256",
+            "exp": {"type": "number", "reveal": true, "max_claim_byte_len": 31},
+            "/address/country": {"type": "string", "reveal": true, "max_claim_byte_len": 31},
+            "email": {"type": "string", "reveal": true, "max_claim_byte_len": 31}}"#).unwrap();
+        let (prover_inputs, _, _) = prepare_prover_inputs(&config, &token, &issuer_pem, None).unwrap();
+        assert_eq!(prover_inputs["exp_value"], json!(exp.to_string()));
+        assert_eq!(prover_inputs["email_value"], json!(pack_string_to_int("alice@example.com", 31).unwrap()));
+
+        let interval = |name: &str, from: &str| claim_interval(&prover_inputs, &msg, name, from);
+        // The value starts after the colon and the whitespace around it
+        assert_eq!(interval("exp", "l"), format!("\"exp\" : {}", exp));
+        assert_eq!(interval("exp", "value_l"), exp.to_string());
+        assert_eq!(interval("address_object", "value_l"), "{\"country\":\t\"US\"}");
+        assert_eq!(interval("address_country", "value_l"), r#""US""#);
+        assert_eq!(interval("email", "l"), "\"email\":\r\n \"alice@example.com\"");
+        assert_eq!(interval("email", "value_l"), r#""alice@example.com""#);
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let key_pair = ES256KeyPair::generate();
+        let issuer_pem = key_pair.public_key().to_pem().unwrap();
+        let exp = Clock::now_since_epoch().as_secs() + 3600;
+        let config = parse_config(r#"{"alg": "ES256",
+            "exp": {"type": "number", "reveal": true, "max_claim_byte_len": 31},
+            "name": {"type": "string", "reveal": true, "max_claim_byte_len": 31},
+            "city": {"type": "string", "reveal": true, "max_claim_byte_len": 31}}"#).unwrap();
+
+        // The string ends at the first unescaped quote, and the revealed value is the decoded string. The byte
+        // indices are past the multibyte characters.
+        let payload = format!(r#"{{"tags":["é"],"name":"Ren\u00e9 \"R\\\"","city":"Zürich","exp":{}}}"#, exp);
+        let (token, msg) = sign_es256_payload(&key_pair, &payload);
+        let (prover_inputs, _, _) = prepare_prover_inputs(&config, &token, &issuer_pem, None).unwrap();
+        assert_eq!(claim_interval(&prover_inputs, &msg, "name", "value_l"), r#""Ren\u00e9 \"R\\\"""#);
+        assert_eq!(prover_inputs["name_value"], json!(pack_string_to_int("René \"R\\\"", 31).unwrap()));
+        assert_eq!(claim_interval(&prover_inputs, &msg, "city", "value_l"), r#""Zürich""#);
+        assert_eq!(prover_inputs["city_value"], json!(pack_string_to_int("Zürich", 31).unwrap()));
+        assert_eq!(claim_interval(&prover_inputs, &msg, "exp", "value_l"), exp.to_string());
+
+        // Surrogate pairs are not supported
+        let payload = format!(r#"{{"name":"\ud83d\ude00","city":"Paris","exp":{}}}"#, exp);
+        let (token, _) = sign_es256_payload(&key_pair, &payload);
+        assert!(prepare_prover_inputs(&config, &token, &issuer_pem, None).is_err());
+
+        // The literal must fit in max_claim_byte_len, even if the decoded string does
+        let payload = format!(r#"{{"name":"\u00e9\u00e9\u00e9\u00e9\u00e9","city":"Paris","exp":{}}}"#, exp);
+        let (token, _) = sign_es256_payload(&key_pair, &payload);
+        assert!(prepare_prover_inputs(&config, &token, &issuer_pem, None).is_err());
+    }
+
+    #[test]
+    fn test_es256_limbs() {
+        let limbs = to_es256_circom_ints(&[0u8; 31].iter().chain(&[5u8]).copied().collect::<Vec<_>>()).unwrap();
+        assert_eq!(limbs.len(), CIRCOM_ES256_NUM_LIMBS);
+        assert_eq!(limbs[0], BigInt::from(5));
+        assert!(limbs[1..].iter().all(|l| *l == BigInt::from(0)));
+        assert!(to_es256_circom_ints(&[0xffu8; 33]).is_err());
+    }
+}
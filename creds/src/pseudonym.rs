@@ -0,0 +1,104 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use ark_ec::{CurveGroup, VariableBaseMSM};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use serde::{Deserialize, Serialize};
+
+use crate::dlog::{DLogPoK, PedersenOpening};
+use crate::utils::hash_to_curve_vartime;
+
+/// Request for a scope-exclusive pseudonym, written in a proof spec as, e.g.,
+/// `"pseudonym": {"scope": "https://verifier.example.com", "attribute": "sub"}`.
+/// The pseudonym is `H(scope)^attribute`: it is the same in every show for the same scope and credential attribute,
+/// and pseudonyms for different scopes cannot be linked. The attribute is committed, not revealed. Since anyone can
+/// compute `H(scope)^x` for a guess `x`, the attribute should be a high-entropy value such as a unique user identifier.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PseudonymSpec {
+    pub scope: String,
+    pub attribute: String,
+}
+
+/// A pseudonym and a proof that it is derived from the value in a Pedersen commitment
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PseudonymProof<G: CurveGroup> {
+    pub pseudonym: G,
+    pub pok: DLogPoK<G>,
+}
+
+/// The base `H(scope)` of the pseudonyms for `scope`
+pub fn scope_base<G: CurveGroup>(scope: &str) -> G {
+    hash_to_curve_vartime::<G>(&format!("Crescent pseudonym scope {}", scope)).into()
+}
+
+impl<G: CurveGroup + VariableBaseMSM> PseudonymProof<G> {
+    /// Compute the pseudonym `H(scope)^m` of the value `m` committed in `ped_open`, and prove that the
+    /// commitment and the pseudonym have the same exponent
+    pub fn prove(context: &[u8], ped_open: &PedersenOpening<G>, scope: &str) -> Self {
+        let base = scope_base::<G>(scope);
+        let pseudonym = base * ped_open.m;
+        let com_bases = ped_open.bases.iter().map(|&x| x.into()).collect::<Vec<G>>();
+        let pok = DLogPoK::<G>::prove(
+            Some(context),
+            &[ped_open.c, pseudonym],
+            &[com_bases, vec![base]],
+            &[vec![ped_open.m, ped_open.r], vec![ped_open.m]],
+            Some(vec![(0, 0)]),
+        );
+
+        PseudonymProof { pseudonym, pok }
+    }
+
+    /// Verify the proof, where `ped_com` is a commitment in `bases` to the attribute the pseudonym is derived from
+    pub fn verify(&self, context: &[u8], ped_com: &G, bases: &[G; 2], scope: &str) -> bool {
+        let base = scope_base::<G>(scope);
+        self.pok.verify(
+            Some(context),
+            &[bases.to_vec(), vec![base]],
+            &[*ped_com, self.pseudonym],
+            Some(vec![(0, 0)]),
+        )
+    }
+
+    /// The pseudonym as a hex string, the form given to the verifier application
+    pub fn encode(&self) -> String {
+        let mut bytes = Vec::new();
+        self.pseudonym.into_affine().serialize_compressed(&mut bytes).unwrap();
+        hex::encode(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CrescentFr, G1};
+    use ark_std::UniformRand;
+
+    #[test]
+    fn test_pseudonym() {
+        let rng = &mut ark_std::test_rng();
+        let bases = [G1::rand(rng), G1::rand(rng)];
+        let bases_affine = bases.map(|b| b.into_affine());
+        let m = CrescentFr::rand(rng);
+        let context = b"proof spec";
+
+        let open1 = DLogPoK::<G1>::pedersen_commit(&m, &bases_affine);
+        let proof1 = PseudonymProof::prove(context, &open1, "site-a");
+        assert!(proof1.verify(context, &open1.c, &bases, "site-a"));
+        assert!(!proof1.verify(context, &open1.c, &bases, "site-b"));
+        assert!(!proof1.verify(b"other proof spec", &open1.c, &bases, "site-a"));
+
+        // A fresh commitment to the same value gives the same pseudonym for the same scope, and a different one for another scope
+        let open2 = DLogPoK::<G1>::pedersen_commit(&m, &bases_affine);
+        let proof2 = PseudonymProof::prove(context, &open2, "site-a");
+        assert!(proof2.verify(context, &open2.c, &bases, "site-a"));
+        assert_eq!(proof1.encode(), proof2.encode());
+        let proof3 = PseudonymProof::prove(context, &open2, "site-b");
+        assert_ne!(proof1.encode(), proof3.encode());
+
+        // The pseudonym must match the committed value
+        let other = DLogPoK::<G1>::pedersen_commit(&(m + CrescentFr::from(1u64)), &bases_affine);
+        let forged = PseudonymProof { pseudonym: proof1.pseudonym, pok: PseudonymProof::prove(context, &other, "site-a").pok };
+        assert!(!forged.verify(context, &other.c, &bases, "site-a"));
+    }
+}
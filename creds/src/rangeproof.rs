@@ -20,24 +20,47 @@ use ark_std::{One, Zero};
 use merlin::Transcript;
 use rand::thread_rng;
 
+/// The smallest supported bit width; the blinding of g needs a domain of at least this size
+pub const MIN_RANGE_PROOF_BITS: usize = 4;
 
 #[derive(Clone, Debug, CanonicalDeserialize, CanonicalSerialize, PartialEq)]
 pub struct RangeProofPK<'b, E: Pairing> {
     pub powers: Powers<'b, E>,
+    /// The largest bit width supported by these parameters
+    pub max_bits: usize,
 }
 
 impl<E: Pairing> RangeProofPK<'_, E> {
+    /// Generate parameters for range proofs of up to `n` bits; proofs for any power of two width up to `n` can
+    /// then be created and verified with the same keys
     pub fn setup(n: usize) -> (Self, RangeProofVK<E>) {
+        Self::setup_with_degree(n, 4 * n)
+    }
+
+    /// Like `setup`, with at least `max_degree` + 1 powers of g. The additional powers are used to commit to
+    /// status lists (see `status_list.rs`), and are included in the verifying key.
+    pub fn setup_with_degree(n: usize, max_degree: usize) -> (Self, RangeProofVK<E>) {
+        assert!(n.is_power_of_two(), "n must be a power of two");
         let mut rng = thread_rng();
         let params = ark_poly_commit::kzg10::KZG10::<E, DensePolynomial<E::ScalarField>>::setup(
-            4 * n,
+            max_degree.max(4 * n),
             true,
             &mut rng,
         )
         .expect("Setup failed");
-        let powers_of_g = params.powers_of_g.to_vec();
         let powers_of_gamma_g: Vec<E::G1Affine> =
             (0..=4 * n).map(|i| params.powers_of_gamma_g[&i]).collect();
+        Self::from_powers(n, params.powers_of_g.to_vec(), powers_of_gamma_g, params.h, params.beta_h)
+    }
+
+    /// Parameters for range proofs of up to `n` bits from the powers beta^i g and beta^i gamma_g of a secret beta,
+    /// with beta_h = beta h, e.g., from the powers of tau of a setup ceremony (see `ceremony.rs`). The discrete log
+    /// of gamma_g in base g must be unknown, and there must be at least 4n + 1 powers of each.
+    pub fn from_powers(n: usize, powers_of_g: Vec<E::G1Affine>, mut powers_of_gamma_g: Vec<E::G1Affine>, h: E::G2Affine,
+        beta_h: E::G2Affine) -> (Self, RangeProofVK<E>) {
+        assert!(n.is_power_of_two(), "n must be a power of two");
+        assert!(powers_of_g.len() > 4 * n && powers_of_gamma_g.len() > 4 * n, "Not enough powers");
+        powers_of_gamma_g.truncate(4 * n + 1);
 
         let com_f_basis: [E::G1; 4] = [
             powers_of_gamma_g[0].into(),
@@ -46,35 +69,50 @@ impl<E: Pairing> RangeProofPK<'_, E> {
             powers_of_g[0].into(),
         ];
 
-        let powers = ark_poly_commit::kzg10::Powers::<E> {
-            powers_of_g: ark_std::borrow::Cow::Owned(powers_of_g),
-            powers_of_gamma_g: ark_std::borrow::Cow::Owned(powers_of_gamma_g),
+        let kzg_vk = ark_poly_commit::kzg10::VerifierKey::<E> {
+            g: powers_of_g[0],
+            gamma_g: powers_of_gamma_g[0],
+            h,
+            beta_h,
+            prepared_h: h.into(),
+            prepared_beta_h: beta_h.into(),
         };
 
-        let kzg_vk = ark_poly_commit::kzg10::VerifierKey::<E> {
-            g: params.powers_of_g[0],
-            gamma_g: params.powers_of_gamma_g[&0],
-            h: params.h,
-            beta_h: params.beta_h,
-            prepared_h: params.prepared_h.clone(),
-            prepared_beta_h: params.prepared_beta_h.clone(),
+        let powers = ark_poly_commit::kzg10::Powers::<E> {
+            powers_of_g: ark_std::borrow::Cow::Owned(powers_of_g.clone()),
+            powers_of_gamma_g: ark_std::borrow::Cow::Owned(powers_of_gamma_g),
         };
 
         (
-            RangeProofPK { powers },
+            RangeProofPK { powers, max_bits: n },
             RangeProofVK {
                 kzg_vk,
                 com_f_basis,
+                max_bits: n,
+                powers_of_g,
             },
         )
     }
- 
 }
 
 #[derive(Clone, Debug, CanonicalDeserialize, CanonicalSerialize)]
 pub struct RangeProofVK<E: Pairing> {
     pub kzg_vk: ark_poly_commit::kzg10::VerifierKey<E>,
     pub com_f_basis: [E::G1; 4],
+    /// The largest bit width supported by these parameters
+    pub max_bits: usize,
+    /// The powers of g from the proving key, used by the verifier to commit to status lists
+    pub powers_of_g: Vec<E::G1Affine>,
+}
+
+/// Check that `n` is a bit width supported by range proofs with parameters for up to `max_bits` bits
+pub fn is_valid_bit_width(n: usize, max_bits: usize) -> bool {
+    n.is_power_of_two() && n >= MIN_RANGE_PROOF_BITS && n <= max_bits
+}
+
+/// Check whether `m` is in [0, 2^n)
+pub fn fits_in_bits<F: PrimeField>(m: &F, n: usize) -> bool {
+    m.into_bigint().num_bits() as usize <= n
 }
 
 /// A range proofthat a value is in [0,2^n). Following the notation in https://hackmd.io/@dabo/B1U4kx8XI
@@ -133,7 +171,7 @@ impl<E: Pairing> RangeProof<E> {
 
         // Finally we link com_f to ped_open via a dleq proof
 
-        assert!(powers.powers_of_g.len() >= n + 2, "Not enough powers of g");
+        assert!(powers.powers_of_g.len() > 4 * n, "Not enough powers of g");
         assert!(n.is_power_of_two(), "n must be a power of two");
 
         let mut rng = thread_rng();
@@ -248,6 +286,7 @@ impl<E: Pairing> RangeProof<E> {
         let (com_g, rand_g) = KZG10::commit(powers, &g_blinded, Some(2), Some(&mut rng)).unwrap(); // Opened twice
 
         let mut ts = Transcript::new(&[0u8]);
+        ts.append_u64(b"n_bits", n as u64);
         add_to_transcript(&mut ts, b"com_f", &com_f);
         add_to_transcript(&mut ts, b"com_g", &com_g);
 
@@ -346,10 +385,15 @@ impl<E: Pairing> RangeProof<E> {
         n: usize,
         vk: &RangeProofVK<E>,
     ) -> bool {
+        if !is_valid_bit_width(n, vk.max_bits) {
+            println!("Range proof failed to verify, unsupported bit width {} (parameters support up to {} bits)", n, vk.max_bits);
+            return false;
+        }
         let domain = Radix2EvaluationDomain::<E::ScalarField>::new(n).unwrap();
 
         // rederive the challenges
         let mut ts = Transcript::new(&[0u8]);
+        ts.append_u64(b"n_bits", n as u64);
         add_to_transcript(&mut ts, b"com_f", &self.com_f);
         add_to_transcript(&mut ts, b"com_g", &self.com_g);
 
@@ -435,8 +479,10 @@ mod tests {
         r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
     };
     use ark_std::{rand::SeedableRng, UniformRand, Zero};
-    use crate::{groth16rand::ClientState, rangeproof::RangeProofPK, 
-        structs::{IOLocations, PublicIOType}, CrescentPairing, CrescentFr};
+    use ark_ec::CurveGroup;
+    use ark_std::One;
+    use crate::{dlog::PedersenOpening, groth16rand::ClientState, rangeproof::{fits_in_bits, is_valid_bit_width, RangeProof, RangeProofPK},
+        structs::{IOLocations, PublicIOType}, CrescentPairing, CrescentFr, G1};
     use std::collections::BTreeMap;
     
     const NUM_CONSTRAINTS: usize = (1 << 10) - 100;
@@ -485,6 +531,42 @@ mod tests {
         }
     }
     
+    #[test]
+    pub fn range_bit_widths() {
+        let rng = &mut ark_std::test_rng();
+        let (range_pk, range_vk) = RangeProofPK::<CrescentPairing>::setup(64);
+        assert_eq!(range_pk.max_bits, 64);
+        assert_eq!(range_vk.max_bits, 64);
+
+        let bases = [G1::rand(rng), G1::rand(rng)];
+        let mut open = |m: CrescentFr| {
+            let r = CrescentFr::rand(rng);
+            PedersenOpening { bases: bases.map(|b| b.into_affine()).to_vec(), m, r, c: bases[0] * m + bases[1] * r }
+        };
+
+        for (n, m) in [(4, 15u64), (16, 40_000), (32, 1_700_000_000), (64, u64::MAX)] {
+            let ped_open = open(CrescentFr::from(m));
+            assert!(fits_in_bits(&ped_open.m, n));
+            let proof = RangeProof::prove_n_bits(&ped_open, n, &range_pk.powers);
+            assert!(proof.verify_n_bits(&ped_open.c, &bases, n, &range_vk));
+
+            // The width is bound to the proof
+            for other in [4, 8, 16, 32, 64].into_iter().filter(|&other| other != n) {
+                assert!(!proof.verify_n_bits(&ped_open.c, &bases, other, &range_vk));
+            }
+        }
+        assert!(!fits_in_bits(&(CrescentFr::from(u64::MAX) + CrescentFr::one()), 64));
+
+        // Widths that are not powers of two, or are larger than the parameters support, are rejected
+        assert!(!is_valid_bit_width(48, 64));
+        assert!(!is_valid_bit_width(128, 64));
+        assert!(!is_valid_bit_width(2, 64));
+        let (_, small_vk) = RangeProofPK::<CrescentPairing>::setup(32);
+        let ped_open = open(CrescentFr::from(u64::MAX));
+        let proof = RangeProof::prove_n_bits(&ped_open, 64, &range_pk.powers);
+        assert!(!proof.verify_n_bits(&ped_open.c, &bases, 64, &small_vk));
+    }
+
     #[test]
     pub fn range_test() {
         let rng = &mut ark_std::rand::rngs::StdRng::seed_from_u64(0u64);
@@ -0,0 +1,289 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// Revocation with a pairing-based accumulator over the identifiers of the valid (non-revoked) credentials.
+// The accumulator is V = g^{prod (s + x)} over the identifiers x, where s is the issuer's secret key. The witness
+// for x is W = V^{1/(s + x)}, which satisfies e(W, h^s h^x) = e(V, h). The issuer adds a credential's identifier
+// when the credential is issued and removes it to revoke the credential; after each change the holders update
+// their witnesses from the published update, without the secret key.
+// In a show proof, the holder proves knowledge of a witness for the identifier committed in the Groth16 proof.
+// The proof randomizes the witness as W' = W^r and sends W' and C = V^r W'^{-x}; then e(W', h^s) = e(C, h) and a
+// sigma proof shows that C has the form V^r W'^{-x}, for the committed x.
+
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{Field, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{rand::Rng, UniformRand};
+use std::collections::BTreeSet;
+use std::error::Error;
+
+use crate::dlog::{DLogPoK, PedersenOpening};
+use crate::return_error;
+
+/// Public key of an accumulator: a base h of G2 and h^s
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize, PartialEq)]
+pub struct AccumulatorPublicKey<E: Pairing> {
+    pub h: E::G2Affine,
+    pub h_s: E::G2Affine,
+}
+
+/// The published state of an accumulator. The epoch is incremented on each addition and removal.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize, PartialEq)]
+pub struct Accumulator<E: Pairing> {
+    pub value: E::G1Affine,
+    pub epoch: u64,
+}
+
+/// A change to the accumulator, published by the issuer so that holders can update their witnesses
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize, PartialEq)]
+pub struct AccumulatorUpdate<E: Pairing> {
+    /// The identifier that was added or removed
+    pub element: E::ScalarField,
+    /// True for an addition, false for a removal (revocation)
+    pub added: bool,
+    /// The accumulator after the change
+    pub accumulator: Accumulator<E>,
+}
+
+/// Issuer-side state of an accumulator
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct AccumulatorManager<E: Pairing> {
+    s: E::ScalarField,
+    pub public_key: AccumulatorPublicKey<E>,
+    pub accumulator: Accumulator<E>,
+    pub members: BTreeSet<E::ScalarField>,
+    /// All the changes, in order; `updates[i]` moves the accumulator from epoch i to epoch i + 1
+    pub updates: Vec<AccumulatorUpdate<E>>,
+}
+
+impl<E: Pairing> AccumulatorManager<E> {
+    /// Create a new, empty accumulator
+    pub fn new<R: Rng>(rng: &mut R) -> Self {
+        let s = E::ScalarField::rand(rng);
+        let h = E::G2Affine::generator();
+        let public_key = AccumulatorPublicKey { h, h_s: (h * s).into_affine() };
+        let accumulator = Accumulator { value: E::G1::rand(rng).into_affine(), epoch: 0 };
+        AccumulatorManager { s, public_key, accumulator, members: BTreeSet::new(), updates: vec![] }
+    }
+
+    /// Add the identifier of a newly issued credential, and return the credential's witness
+    pub fn add(&mut self, element: E::ScalarField) -> Result<MembershipWitness<E>, Box<dyn Error>> {
+        if self.members.contains(&element) || (self.s + element).is_zero() {
+            return_error!("Element is already in the accumulator, or cannot be added");
+        }
+        let value = (self.accumulator.value * (self.s + element)).into_affine();
+        self.update(element, true, value);
+        self.witness(element)
+    }
+
+    /// Remove the identifier of a revoked credential
+    pub fn remove(&mut self, element: E::ScalarField) -> Result<(), Box<dyn Error>> {
+        if !self.members.remove(&element) {
+            return_error!("Element is not in the accumulator");
+        }
+        let value = (self.accumulator.value * (self.s + element).inverse().unwrap()).into_affine();
+        self.update(element, false, value);
+        Ok(())
+    }
+
+    /// The witness for an identifier in the accumulator, at the current epoch
+    pub fn witness(&self, element: E::ScalarField) -> Result<MembershipWitness<E>, Box<dyn Error>> {
+        if !self.members.contains(&element) {
+            return_error!("Element is not in the accumulator");
+        }
+        let witness = (self.accumulator.value * (self.s + element).inverse().unwrap()).into_affine();
+        Ok(MembershipWitness { element, witness, accumulator: self.accumulator.clone() })
+    }
+
+    /// The updates a holder with a witness for `epoch` must apply to reach the current epoch
+    pub fn updates_since(&self, epoch: u64) -> &[AccumulatorUpdate<E>] {
+        let start = usize::try_from(epoch).unwrap_or(usize::MAX).min(self.updates.len());
+        &self.updates[start..]
+    }
+
+    fn update(&mut self, element: E::ScalarField, added: bool, value: E::G1Affine) {
+        if added {
+            self.members.insert(element);
+        }
+        self.accumulator = Accumulator { value, epoch: self.accumulator.epoch + 1 };
+        self.updates.push(AccumulatorUpdate { element, added, accumulator: self.accumulator.clone() });
+    }
+}
+
+/// A holder's witness that `element` is in the accumulator
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize, PartialEq)]
+pub struct MembershipWitness<E: Pairing> {
+    pub element: E::ScalarField,
+    pub witness: E::G1Affine,
+    /// The accumulator the witness is for
+    pub accumulator: Accumulator<E>,
+}
+
+impl<E: Pairing> MembershipWitness<E> {
+    /// Check the witness against its accumulator
+    pub fn verify(&self, pk: &AccumulatorPublicKey<E>) -> bool {
+        let h_s_x = pk.h_s + pk.h * self.element;
+        E::multi_pairing([self.witness, (-self.accumulator.value.into_group()).into_affine()], [h_s_x.into_affine(), pk.h]).is_zero()
+    }
+
+    /// Apply the issuer's updates, in order, to move the witness to the latest accumulator.
+    /// Updates for epochs the witness has already reached are skipped. Fails if the element was removed.
+    pub fn update(&mut self, updates: &[AccumulatorUpdate<E>]) -> Result<(), Box<dyn Error>> {
+        let epoch = self.accumulator.epoch;
+        for update in updates.iter().filter(|u| u.accumulator.epoch > epoch) {
+            if update.accumulator.epoch != self.accumulator.epoch + 1 {
+                return_error!(format!("Missing accumulator update for epoch {}", self.accumulator.epoch + 1));
+            }
+            if update.element == self.element {
+                return_error!("The credential was revoked");
+            }
+            let y_minus_x = update.element - self.element;
+            let witness = if update.added {
+                // V' = V^{s+y}, W' = V W^{y-x}
+                self.accumulator.value.into_group() + self.witness * y_minus_x
+            } else {
+                // V' = V^{1/(s+y)}, W' = (W / V')^{1/(y-x)}
+                (self.witness.into_group() - update.accumulator.value) * y_minus_x.inverse().unwrap()
+            };
+            self.witness = witness.into_affine();
+            self.accumulator = update.accumulator.clone();
+        }
+        Ok(())
+    }
+}
+
+/// Zero-knowledge proof that the value in a Pedersen commitment is in the accumulator
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MembershipProof<E: Pairing> {
+    /// The epoch of the accumulator used by the prover
+    pub epoch: u64,
+    pub w_bar: E::G1,
+    pub c_bar: E::G1,
+    pub pok: DLogPoK<E::G1>,
+}
+
+impl<E: Pairing> MembershipProof<E> {
+    /// Prove that the value committed in `ped_open` is the element of `witness`
+    pub fn prove(context: &[u8], ped_open: &PedersenOpening<E::G1>, witness: &MembershipWitness<E>) -> Self {
+        assert_eq!(ped_open.m, witness.element);
+        let mut rng = ark_std::rand::thread_rng();
+        let r = loop {
+            let r = E::ScalarField::rand(&mut rng);
+            if !r.is_zero() {
+                break r;
+            }
+        };
+        let acc: E::G1 = witness.accumulator.value.into();
+        let w_bar = witness.witness * r;
+        let c_bar = acc * r - w_bar * witness.element;
+
+        let com_bases = ped_open.bases.iter().map(|&x| x.into()).collect::<Vec<E::G1>>();
+        let pok = DLogPoK::<E::G1>::prove(
+            Some(context),
+            &[ped_open.c, c_bar],
+            &[com_bases, vec![acc, -w_bar]],
+            &[vec![ped_open.m, ped_open.r], vec![r, ped_open.m]],
+            Some(vec![(0, 1)]),
+        );
+
+        MembershipProof { epoch: witness.accumulator.epoch, w_bar, c_bar, pok }
+    }
+
+    /// Verify the proof, where `ped_com` is a commitment in `bases` to the element
+    pub fn verify(&self, context: &[u8], ped_com: &E::G1, bases: &[E::G1; 2], accumulator: &Accumulator<E>, pk: &AccumulatorPublicKey<E>) -> bool {
+        if self.w_bar.is_zero() {
+            println!("Membership proof failed to verify, the randomized witness is the identity");
+            return false;
+        }
+        if !E::multi_pairing([self.w_bar, -self.c_bar], [pk.h_s, pk.h]).is_zero() {
+            println!("Membership proof failed to verify, pairing check failed");
+            return false;
+        }
+        self.pok.verify(
+            Some(context),
+            &[bases.to_vec(), vec![accumulator.value.into(), -self.w_bar]],
+            &[*ped_com, self.c_bar],
+            Some(vec![(0, 1)]),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CrescentFr, CrescentPairing, G1};
+
+    #[test]
+    fn test_accumulator() {
+        let rng = &mut ark_std::test_rng();
+        let mut manager = AccumulatorManager::<CrescentPairing>::new(rng);
+        let pk = manager.public_key.clone();
+
+        let ids: Vec<CrescentFr> = (0..4).map(|_| CrescentFr::rand(rng)).collect();
+        let mut witnesses: Vec<MembershipWitness<CrescentPairing>> = ids.iter().map(|&id| manager.add(id).unwrap()).collect();
+        assert!(manager.add(ids[0]).is_err());
+
+        // Older witnesses are stale until updated
+        assert!(witnesses[3].verify(&pk));
+        assert!(witnesses[0].accumulator != manager.accumulator);
+        for w in witnesses.iter_mut() {
+            w.update(manager.updates_since(w.accumulator.epoch)).unwrap();
+            assert_eq!(w.accumulator, manager.accumulator);
+            assert!(w.verify(&pk));
+            assert_eq!(w, &manager.witness(w.element).unwrap());
+        }
+
+        // Revoke ids[1]
+        manager.remove(ids[1]).unwrap();
+        assert!(manager.remove(ids[1]).is_err());
+        assert!(manager.witness(ids[1]).is_err());
+        assert!(witnesses[1].clone().update(manager.updates_since(witnesses[1].accumulator.epoch)).is_err());
+        let mut stale = witnesses[1].clone();
+        stale.accumulator = manager.accumulator.clone();
+        assert!(!stale.verify(&pk));
+        for i in [0, 2, 3] {
+            let w = &mut witnesses[i];
+            w.update(&manager.updates).unwrap();
+            assert!(w.verify(&pk));
+        }
+
+        // Updates can't be skipped
+        let mut w = manager.witness(ids[0]).unwrap();
+        manager.add(CrescentFr::rand(rng)).unwrap();
+        manager.remove(ids[2]).unwrap();
+        assert!(w.update(&manager.updates[manager.updates.len() - 1..]).is_err());
+        w.update(&manager.updates).unwrap();
+        assert!(w.verify(&pk));
+    }
+
+    #[test]
+    fn test_membership_proof() {
+        let rng = &mut ark_std::test_rng();
+        let mut manager = AccumulatorManager::<CrescentPairing>::new(rng);
+        let pk = manager.public_key.clone();
+        let id = CrescentFr::rand(rng);
+        let other = CrescentFr::rand(rng);
+        let witness = manager.add(id).unwrap();
+        manager.add(other).unwrap();
+        let witness = { let mut w = witness; w.update(&manager.updates).unwrap(); w };
+
+        let bases = [G1::rand(rng), G1::rand(rng)];
+        let bases_affine = bases.map(|b| b.into_affine());
+        let ped_open = DLogPoK::<G1>::pedersen_commit(&id, &bases_affine);
+        let context = b"proof spec";
+
+        let proof = MembershipProof::prove(context, &ped_open, &witness);
+        assert_eq!(proof.epoch, manager.accumulator.epoch);
+        assert!(proof.verify(context, &ped_open.c, &bases, &manager.accumulator, &pk));
+        assert!(!proof.verify(b"other context", &ped_open.c, &bases, &manager.accumulator, &pk));
+
+        // The commitment must be to the element
+        let ped_other = DLogPoK::<G1>::pedersen_commit(&other, &bases_affine);
+        assert!(!proof.verify(context, &ped_other.c, &bases, &manager.accumulator, &pk));
+
+        // After revocation, the proof fails against the new accumulator
+        manager.remove(id).unwrap();
+        assert!(!proof.verify(context, &ped_open.c, &bases, &manager.accumulator, &pk));
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// SD-JWT credentials (RFC 9901, Selective Disclosure for JWTs). The issuer signs a JWT whose payload contains the
+// digests of the selectively disclosable claims, in an `_sd` array; the claims themselves are in salted disclosures
+// appended to the JWT: `<issuer-signed JWT>~<disclosure 1>~...~<disclosure N>~<optional key binding JWT>`.
+// The holder can present the credential to a plain SD-JWT verifier by sending a subset of the disclosures (see
+// `SdJwtToken::present`), or create Crescent show proofs from it, with the disclosed claims fed to the circuit.
+
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+
+use crate::return_error;
+
+/// The separator of the issuer-signed JWT, the disclosures and the key binding JWT
+pub const SD_JWT_SEPARATOR: char = '~';
+
+/// A disclosure of an object property: the base64url encoding of the JSON array `[salt, name, value]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disclosure {
+    pub encoded: String,
+    pub salt: String,
+    pub name: String,
+    pub value: Value,
+}
+
+impl Disclosure {
+    pub fn parse(encoded: &str) -> Result<Self, Box<dyn Error>> {
+        let array: Value = serde_json::from_slice(&base64_url::decode(encoded)?)?;
+        match array.as_array().map(|a| a.as_slice()) {
+            Some([Value::String(salt), Value::String(name), value]) => Ok(Disclosure {
+                encoded: encoded.to_string(),
+                salt: salt.clone(),
+                name: name.clone(),
+                value: value.clone(),
+            }),
+            _ => return_error!(format!("Invalid disclosure {}: expected an array [salt, name, value]", encoded)),
+        }
+    }
+
+    /// The decoded disclosure, as it is hashed and matched by the circuit
+    pub fn json(&self) -> Result<String, Box<dyn Error>> {
+        Ok(String::from_utf8(base64_url::decode(&self.encoded)?)?)
+    }
+
+    /// The digest of the disclosure, as it appears in the `_sd` array of the issuer-signed JWT
+    pub fn digest(&self) -> String {
+        base64_url::encode(&Sha256::digest(self.encoded.as_bytes()))
+    }
+}
+
+/// A parsed SD-JWT
+#[derive(Debug, Clone, PartialEq)]
+pub struct SdJwtToken {
+    pub issuer_jwt: String,
+    pub disclosures: Vec<Disclosure>,
+    pub key_binding_jwt: Option<String>,
+}
+
+impl SdJwtToken {
+    pub fn parse(token_str: &str) -> Result<Self, Box<dyn Error>> {
+        let mut parts = token_str.trim().split(SD_JWT_SEPARATOR).collect::<Vec<_>>();
+        if parts.len() < 2 {
+            return_error!("Invalid SD-JWT: missing '~' after the issuer-signed JWT");
+        }
+        // The last part is the key binding JWT, or empty
+        let key_binding_jwt = parts.pop().filter(|kb| !kb.is_empty()).map(|kb| kb.to_string());
+        let issuer_jwt = parts.remove(0).to_string();
+        let disclosures = parts.into_iter().map(Disclosure::parse).collect::<Result<Vec<_>, _>>()?;
+        Ok(SdJwtToken { issuer_jwt, disclosures, key_binding_jwt })
+    }
+
+    /// The payload of the issuer-signed JWT. The caller is responsible for checking the signature.
+    pub fn payload(&self) -> Result<Map<String, Value>, Box<dyn Error>> {
+        let payload_b64 = self.issuer_jwt.split('.').nth(1).ok_or("Missing JWT claims")?;
+        let payload: Value = serde_json::from_slice(&base64_url::decode(payload_b64)?)?;
+        Ok(payload.as_object().ok_or("JWT claims are not a JSON object")?.clone())
+    }
+
+    /// The claims of the credential: the claims of the issuer-signed JWT, with the top-level disclosed claims in place of
+    /// their digests. Disclosures of nested claims are not processed.
+    pub fn claims(&self) -> Result<Value, Box<dyn Error>> {
+        let mut claims = self.payload()?;
+        if let Some(alg) = claims.remove("_sd_alg") {
+            if alg != "sha-256" {
+                return_error!(format!("Unsupported SD-JWT digest algorithm {}", alg));
+            }
+        }
+        let digests = match claims.remove("_sd") {
+            Some(Value::Array(digests)) => digests,
+            Some(_) => return_error!("Invalid SD-JWT: '_sd' is not an array"),
+            None => vec![],
+        };
+        for disclosure in &self.disclosures {
+            if !digests.iter().any(|d| d.as_str() == Some(disclosure.digest().as_str())) {
+                continue;
+            }
+            if claims.contains_key(&disclosure.name) {
+                return_error!(format!("Invalid SD-JWT: claim {} is disclosed more than once", disclosure.name));
+            }
+            claims.insert(disclosure.name.clone(), disclosure.value.clone());
+        }
+        Ok(Value::Object(claims))
+    }
+
+    /// The disclosure of the top-level claim `name`
+    pub fn disclosure(&self, name: &str) -> Option<&Disclosure> {
+        self.disclosures.iter().find(|d| d.name == name)
+    }
+
+    /// Present the credential to a plain SD-JWT verifier, disclosing only the claims in `names`
+    pub fn present(&self, names: &[&str]) -> Result<String, Box<dyn Error>> {
+        let mut presentation = format!("{}{}", self.issuer_jwt, SD_JWT_SEPARATOR);
+        for name in names {
+            let disclosure = self.disclosure(name).ok_or(format!("Claim {} is not disclosed in the SD-JWT", name))?;
+            presentation.push_str(&disclosure.encoded);
+            presentation.push(SD_JWT_SEPARATOR);
+        }
+        Ok(presentation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jwt_simple::prelude::*;
+    use serde_json::json;
+
+    // Create a disclosure as an issuer would, with the separators of the examples in RFC 9901
+    fn make_disclosure(salt: &str, name: &str, value: Value) -> Disclosure {
+        let json = format!("[\"{}\", \"{}\", {}]", salt, name, value);
+        Disclosure::parse(&base64_url::encode(&json)).unwrap()
+    }
+
+    #[test]
+    fn test_sd_jwt() {
+        // Example disclosure from RFC 9901, Section 4.2.1
+        let disclosure = Disclosure::parse("WyJfMjZiYzRMVC1hYzZxMktJNmNCVzVlcyIsICJmYW1pbHlfbmFtZSIsICJNw7ZiaXVzIl0").unwrap();
+        assert_eq!(disclosure.name, "family_name");
+        assert_eq!(disclosure.value, json!("Möbius"));
+        assert_eq!(disclosure.digest(), "X9yH0Ajrdm1Oij4tWso9UzzKJvPoDxwmuEcO3XAdRC0");
+        assert!(Disclosure::parse(&base64_url::encode("[\"salt\", \"name\"]")).is_err());
+
+        let given_name = make_disclosure("2GLC42sKQveCfGfryNRN9w", "given_name", json!("John"));
+        let age = make_disclosure("eluV5Og3gSNII8EYnsxA_A", "age", json!(42));
+        let key_pair = ES256KeyPair::generate();
+        let issuer_jwt = key_pair.sign(Claims::with_custom_claims(json!({"_sd": [given_name.digest(), age.digest()], "_sd_alg": "sha-256", "iss": "https://example.com"}), Duration::from_hours(1))).unwrap();
+        let token_str = format!("{}~{}~{}~", issuer_jwt, given_name.encoded, age.encoded);
+
+        let token = SdJwtToken::parse(&token_str).unwrap();
+        assert_eq!(token.issuer_jwt, issuer_jwt);
+        assert_eq!(token.disclosures.len(), 2);
+        assert!(token.key_binding_jwt.is_none());
+        let claims = token.claims().unwrap();
+        assert_eq!(claims["given_name"], json!("John"));
+        assert_eq!(claims["age"], json!(42));
+        assert_eq!(claims["iss"], json!("https://example.com"));
+        assert!(claims.get("_sd").is_none());
+
+        // A presentation to a plain SD-JWT verifier only has the selected disclosures
+        let presentation = token.present(&["age"]).unwrap();
+        assert_eq!(presentation, format!("{}~{}~", issuer_jwt, age.encoded));
+        let claims = SdJwtToken::parse(&presentation).unwrap().claims().unwrap();
+        assert_eq!(claims["age"], json!(42));
+        assert!(claims.get("given_name").is_none());
+        assert!(token.present(&["family_name"]).is_err());
+
+        // A disclosure whose digest is not in the signed payload is ignored
+        let forged = make_disclosure("2GLC42sKQveCfGfryNRN9w", "given_name", json!("Eve"));
+        let claims = SdJwtToken::parse(&format!("{}~{}~", issuer_jwt, forged.encoded)).unwrap().claims().unwrap();
+        assert!(claims.get("given_name").is_none());
+
+        assert!(SdJwtToken::parse(&issuer_jwt).is_err());
+    }
+}
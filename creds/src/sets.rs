@@ -0,0 +1,223 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// Proofs that a committed value is, or is not, in a public set, with the set committed to with the KZG powers g^{b^i}
+// of the range proof parameters.
+// Non-membership: the elements y_1, ..., y_k of the set are the roots of f(X) = prod (X - y_i), and the set is
+// committed to as V = g^{f(b)}. For a value x, f(X) = q(X)(X - x) + d where d = f(x) is non-zero iff x is not in
+// the set, and W = g^{q(b)} satisfies e(W, h^b h^{-x}) e(g, h)^d = e(V, h).
+// In a show proof, the holder randomizes the witness as W' = W^r and sends W', C = V^r W'^x g^t and E = P^t, where
+// t = -dr. Then e(W', h^b) = e(C, h), a sigma proof shows that C and E have this form for the committed x, and
+// E != 1 shows that t, and therefore d, is non-zero.
+// Membership: the set is committed to as V = g^{f(b)} with f(X) = prod (X + y_i), which is an accumulator with the
+// secret key b (see `revocation`): the witness W = g^{f(b)/(b + x)} of an element x is computed from the powers of g,
+// and the membership proof is `revocation::MembershipProof`.
+
+use ark_ec::pairing::Pairing;
+use ark_ec::CurveGroup;
+use ark_ff::{PrimeField, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::UniformRand;
+use std::error::Error;
+
+use crate::dlog::{DLogPoK, Link, PedersenOpening};
+use crate::rangeproof::RangeProofVK;
+use crate::return_error;
+use crate::revocation::{Accumulator, AccumulatorPublicKey, MembershipWitness};
+use crate::utils::{hash_to_curve_vartime, msm_select};
+
+// Statement 0 is the commitment to x, statement 1 is C and statement 2 is E
+const LINKS: [Link; 2] = [((0, 0), (1, 1)), ((1, 2), (2, 0))];
+
+// Coefficients of prod (X - y) over the elements y, from the constant term up
+fn set_polynomial<F: PrimeField>(elements: &[F]) -> Vec<F> {
+    let mut coeffs = vec![F::one()];
+    for &y in elements {
+        coeffs.push(F::zero());
+        for i in (1..coeffs.len()).rev() {
+            coeffs[i] = coeffs[i - 1] - y * coeffs[i];
+        }
+        coeffs[0] *= -y;
+    }
+    coeffs
+}
+
+// Divide f by (X - x), returning the quotient and the remainder f(x)
+fn divide_by_linear<F: PrimeField>(f: &[F], x: F) -> (Vec<F>, F) {
+    let mut q = vec![F::zero(); f.len() - 1];
+    let mut acc = F::zero();
+    for i in (1..f.len()).rev() {
+        acc = f[i] + x * acc;
+        q[i - 1] = acc;
+    }
+    (q, f[0] + x * acc)
+}
+
+// Commit to the polynomial with the powers of g
+fn commit_polynomial<E: Pairing>(f: &[E::ScalarField], powers_of_g: &[E::G1Affine]) -> Result<E::G1, Box<dyn Error>> {
+    if f.len() > powers_of_g.len() {
+        return_error!(format!("Set has {} elements, the parameters support up to {}", f.len() - 1, powers_of_g.len() - 1));
+    }
+    Ok(msm_select::<E::G1>(&powers_of_g[..f.len()], f))
+}
+
+/// Commitment to a set for non-membership proofs (see `SetNonMembershipProof`), with the powers of g from the range proof parameters
+pub fn set_commitment<E: Pairing>(elements: &[E::ScalarField], powers_of_g: &[E::G1Affine]) -> Result<E::G1, Box<dyn Error>> {
+    commit_polynomial::<E>(&set_polynomial(elements), powers_of_g)
+}
+
+fn membership_polynomial<F: PrimeField>(elements: &[F]) -> Vec<F> {
+    set_polynomial(&elements.iter().map(|&y| -y).collect::<Vec<F>>())
+}
+
+/// The accumulator and public key for membership proofs in a set (see `revocation::MembershipProof`)
+pub fn set_accumulator<E: Pairing>(elements: &[E::ScalarField], vk: &RangeProofVK<E>) -> Result<(Accumulator<E>, AccumulatorPublicKey<E>), Box<dyn Error>> {
+    let value = commit_polynomial::<E>(&membership_polynomial(elements), &vk.powers_of_g)?;
+    let pk = AccumulatorPublicKey { h: vk.kzg_vk.h, h_s: vk.kzg_vk.beta_h };
+    Ok((Accumulator { value: value.into_affine(), epoch: 0 }, pk))
+}
+
+/// The witness that `x` is in the set, for a membership proof against the accumulator from `set_accumulator`
+pub fn set_membership_witness<E: Pairing>(x: E::ScalarField, elements: &[E::ScalarField], powers_of_g: &[E::G1Affine]) -> Result<MembershipWitness<E>, Box<dyn Error>> {
+    let f = membership_polynomial(elements);
+    let value = commit_polynomial::<E>(&f, powers_of_g)?;
+    let (q, d) = divide_by_linear(&f, -x);
+    if !d.is_zero() {
+        return_error!("The value is not in the set");
+    }
+    let witness = msm_select::<E::G1>(&powers_of_g[..q.len()], &q).into_affine();
+    Ok(MembershipWitness { element: x, witness, accumulator: Accumulator { value: value.into_affine(), epoch: 0 } })
+}
+
+fn base_p<G: CurveGroup>() -> G {
+    hash_to_curve_vartime::<G>("Crescent set non-membership base").into()
+}
+
+/// Zero-knowledge proof that the value in a Pedersen commitment is not in a set
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SetNonMembershipProof<E: Pairing> {
+    pub w_bar: E::G1,
+    pub c_bar: E::G1,
+    pub e: E::G1,
+    pub pok: DLogPoK<E::G1>,
+}
+
+impl<E: Pairing> SetNonMembershipProof<E> {
+    /// Prove that the value committed in `ped_open` is not one of `elements`, using the powers of g from the range proof parameters
+    pub fn prove(context: &[u8], ped_open: &PedersenOpening<E::G1>, elements: &[E::ScalarField], powers_of_g: &[E::G1Affine]) -> Result<Self, Box<dyn Error>> {
+        let f = set_polynomial(elements);
+        let v = commit_polynomial::<E>(&f, powers_of_g)?;
+        let x = ped_open.m;
+        let (q, d) = divide_by_linear(&f, x);
+        if d.is_zero() {
+            return_error!("The value is in the set");
+        }
+
+        let mut rng = ark_std::rand::thread_rng();
+        let r = E::ScalarField::rand(&mut rng);
+        let t = -d * r;
+        let g: E::G1 = powers_of_g[0].into();
+        let w_bar = msm_select::<E::G1>(&powers_of_g[..q.len()], &q) * r;
+        let c_bar = v * r + w_bar * x + g * t;
+        let p = base_p::<E::G1>();
+        let e = p * t;
+
+        let com_bases = ped_open.bases.iter().map(|&b| b.into()).collect::<Vec<E::G1>>();
+        let pok = DLogPoK::<E::G1>::prove_linked(
+            Some(context),
+            &[ped_open.c, c_bar, e],
+            &[com_bases, vec![v, w_bar, g], vec![p]],
+            &[vec![x, ped_open.r], vec![r, x, t], vec![t]],
+            &LINKS,
+        );
+
+        Ok(SetNonMembershipProof { w_bar, c_bar, e, pok })
+    }
+
+    /// Verify the proof, where `ped_com` is a commitment in `bases` to the value and `set_commitment` is the
+    /// commitment to the set (see `set_commitment`)
+    pub fn verify(&self, context: &[u8], ped_com: &E::G1, bases: &[E::G1; 2], set_commitment: &E::G1, vk: &RangeProofVK<E>) -> bool {
+        if self.e.is_zero() {
+            println!("Set non-membership proof failed to verify, the value is in the set");
+            return false;
+        }
+        if !E::multi_pairing([self.w_bar, -self.c_bar], [vk.kzg_vk.beta_h, vk.kzg_vk.h]).is_zero() {
+            println!("Set non-membership proof failed to verify, pairing check failed");
+            return false;
+        }
+        let g: E::G1 = vk.kzg_vk.g.into();
+        self.pok.verify_linked(
+            Some(context),
+            &[bases.to_vec(), vec![*set_commitment, self.w_bar, g], vec![base_p::<E::G1>()]],
+            &[*ped_com, self.c_bar, self.e],
+            &LINKS,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rangeproof::RangeProofPK;
+    use crate::revocation::MembershipProof;
+    use crate::{CrescentFr, CrescentPairing, G1};
+
+    #[test]
+    fn test_polynomial() {
+        let elements = [3u64, 5, 8].map(CrescentFr::from);
+        let f = set_polynomial(&elements);
+        let eval = |x: CrescentFr| f.iter().rev().fold(CrescentFr::zero(), |acc, c| acc * x + c);
+        for y in elements {
+            assert!(eval(y).is_zero());
+            assert!(divide_by_linear(&f, y).1.is_zero());
+        }
+        let x = CrescentFr::from(4u64);
+        let (q, d) = divide_by_linear(&f, x);
+        assert_eq!(d, eval(x));
+        let y = CrescentFr::from(11u64);
+        let q_y = q.iter().rev().fold(CrescentFr::zero(), |acc, c| acc * y + c);
+        assert_eq!(q_y * (y - x) + d, eval(y));
+    }
+
+    #[test]
+    fn test_set_proofs() {
+        let rng = &mut ark_std::test_rng();
+        let (range_pk, range_vk) = RangeProofPK::<CrescentPairing>::setup_with_degree(32, 128);
+        let powers = &range_pk.powers.powers_of_g;
+        let bases = [G1::rand(rng), G1::rand(rng)];
+        let bases_affine = bases.map(|b| b.into_affine());
+        let context = b"proof spec";
+
+        let set = [7u64, 12, 1000].map(CrescentFr::from);
+        let other_set = [7u64, 12, 1001].map(CrescentFr::from);
+        let v = set_commitment::<CrescentPairing>(&set, &range_vk.powers_of_g).unwrap();
+        let (acc, pk) = set_accumulator::<CrescentPairing>(&set, &range_vk).unwrap();
+        let (other_acc, _) = set_accumulator::<CrescentPairing>(&other_set, &range_vk).unwrap();
+
+        for x in set {
+            let ped_open = DLogPoK::<G1>::pedersen_commit(&x, &bases_affine);
+            let witness = set_membership_witness::<CrescentPairing>(x, &set, powers).unwrap();
+            assert!(witness.verify(&pk));
+            let proof = MembershipProof::prove(context, &ped_open, &witness);
+            assert!(proof.verify(context, &ped_open.c, &bases, &acc, &pk));
+            assert!(!proof.verify(b"other context", &ped_open.c, &bases, &acc, &pk));
+            assert!(!proof.verify(context, &ped_open.c, &bases, &other_acc, &pk));
+            assert!(SetNonMembershipProof::<CrescentPairing>::prove(context, &ped_open, &set, powers).is_err());
+        }
+
+        for x in [0u64, 8, 1001].map(CrescentFr::from) {
+            let ped_open = DLogPoK::<G1>::pedersen_commit(&x, &bases_affine);
+            assert!(set_membership_witness::<CrescentPairing>(x, &set, powers).is_err());
+            let proof = SetNonMembershipProof::<CrescentPairing>::prove(context, &ped_open, &set, powers).unwrap();
+            assert!(proof.verify(context, &ped_open.c, &bases, &v, &range_vk));
+            assert!(!proof.verify(b"other context", &ped_open.c, &bases, &v, &range_vk));
+            let v_other = set_commitment::<CrescentPairing>(&other_set, &range_vk.powers_of_g).unwrap();
+            assert!(!proof.verify(context, &ped_open.c, &bases, &v_other, &range_vk));
+        }
+
+        // Too many elements for the parameters
+        let large = (0..129u64).map(CrescentFr::from).collect::<Vec<_>>();
+        assert!(set_commitment::<CrescentPairing>(&large, &range_vk.powers_of_g).is_err());
+        assert!(set_accumulator::<CrescentPairing>(&large, &range_vk).is_err());
+    }
+}
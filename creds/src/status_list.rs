@@ -0,0 +1,312 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// Proofs that a credential is not revoked in a Token Status List (draft-ietf-oauth-status-list), without revealing
+// the credential's index in the list: a proof that the index is not in the set of revoked indices (see `sets`).
+// A set commitment holds at most as many elements as the degree of the range proof parameters, so the revoked indices
+// are split into chunks of that size, committed to separately, and the proof has one non-membership proof per chunk,
+// all for the same committed index. The proof size and the prover and verifier time grow with the number of revoked
+// entries, not with the length of the list.
+
+use ark_ec::pairing::Pairing;
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use flate2::read::ZlibDecoder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::error::Error;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::dlog::PedersenOpening;
+use crate::rangeproof::RangeProofVK;
+use crate::return_error;
+use crate::sets::{set_commitment, SetNonMembershipProof};
+
+/// A status list, in the form of the `status_list` claim of a Status List Token: `{"bits": 1, "lst": "eNrbuRgAAhcBXQ"}`.
+/// `lst` is the base64url-encoded, zlib-compressed byte array of statuses, `bits` bits per credential, starting from the
+/// least significant bits of the first byte. A status of 0 means the credential is valid.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StatusList {
+    pub bits: u8,
+    pub lst: String,
+}
+
+/// Request for a status list check in a proof spec. The list is given inline, e.g.,
+/// `"status_list": {"attribute": "status_idx", "bits": 1, "lst": "eNrbuRgAAhcBXQ"}`, where `attribute` is the
+/// credential's (committed) index in the list.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StatusListSpec {
+    pub attribute: String,
+    #[serde(flatten)]
+    pub list: StatusList,
+}
+
+impl StatusList {
+    /// Parse the payload of a Status List Token (`{"status_list": {...}, ...}`) or a bare status list.
+    /// The caller is responsible for checking the token's signature and freshness.
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        let value: Value = serde_json::from_str(json)?;
+        let list = value.get("status_list").unwrap_or(&value);
+        let list: StatusList = serde_json::from_value(list.clone())?;
+        if ![1, 2, 4, 8].contains(&list.bits) {
+            return_error!(format!("Invalid status list: bits must be 1, 2, 4 or 8, found {}", list.bits));
+        }
+        Ok(list)
+    }
+
+    /// The decompressed byte array of statuses
+    pub fn bytes(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let compressed = base64_url::decode(&self.lst)?;
+        let mut bytes = vec![];
+        ZlibDecoder::new(compressed.as_slice()).read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// The status of each credential in the list
+    pub fn statuses(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        if ![1, 2, 4, 8].contains(&self.bits) {
+            return_error!(format!("Invalid status list: bits must be 1, 2, 4 or 8, found {}", self.bits));
+        }
+        let bits = self.bits as usize;
+        let mask = ((1u16 << bits) - 1) as u8;
+        let bytes = self.bytes()?;
+        let mut statuses = Vec::with_capacity(bytes.len() * 8 / bits);
+        for byte in bytes {
+            for shift in (0..8).step_by(bits) {
+                statuses.push((byte >> shift) & mask);
+            }
+        }
+        Ok(statuses)
+    }
+
+    /// The indices of the credentials whose status is not valid (non-zero)
+    pub fn revoked_indices(&self) -> Result<Vec<u64>, Box<dyn Error>> {
+        Ok(self.statuses()?.iter().enumerate().filter(|(_, &s)| s != 0).map(|(i, _)| i as u64).collect())
+    }
+
+    /// The revoked indices, as field elements
+    pub fn revoked_set<F: PrimeField>(&self) -> Result<Vec<F>, Box<dyn Error>> {
+        Ok(self.revoked_indices()?.into_iter().map(F::from).collect())
+    }
+
+    /// The revoked indices, as field elements, in chunks of at most `chunk_size` indices. A list with no revoked
+    /// credentials has a single, empty chunk.
+    pub fn revoked_chunks<F: PrimeField>(&self, chunk_size: usize) -> Result<Vec<Vec<F>>, Box<dyn Error>> {
+        if chunk_size == 0 {
+            return_error!("Invalid status list chunk size 0");
+        }
+        let revoked = self.revoked_set()?;
+        if revoked.is_empty() {
+            return Ok(vec![vec![]]);
+        }
+        Ok(revoked.chunks(chunk_size).map(|chunk| chunk.to_vec()).collect())
+    }
+
+    /// Commit to the revoked indices with the powers of g from the range proof parameters, one commitment per chunk
+    /// of at most `powers_of_g.len() - 1` indices
+    pub fn commitment<E: Pairing>(&self, powers_of_g: &[E::G1Affine]) -> Result<Vec<E::G1>, Box<dyn Error>> {
+        self.revoked_chunks(chunk_size(powers_of_g))?
+            .iter()
+            .map(|chunk| set_commitment::<E>(chunk, powers_of_g))
+            .collect()
+    }
+}
+
+// The number of revoked indices in a chunk, the degree of the powers of g
+fn chunk_size<G>(powers_of_g: &[G]) -> usize {
+    powers_of_g.len().saturating_sub(1)
+}
+
+/// A status list loaded from a local file (the payload of a Status List Token, or a bare status list, as JSON),
+/// that a verifier can refresh when the issuer publishes a new list
+#[derive(Debug, Clone)]
+pub struct StatusListFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    list: StatusList,
+}
+
+impl StatusListFile {
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, Box<dyn Error>> {
+        let path = path.into();
+        let (list, modified) = Self::read(&path)?;
+        Ok(Self { path, modified, list })
+    }
+
+    /// Reload the list if the file was modified since it was last read. Returns true if the list was reloaded.
+    pub fn refresh(&mut self) -> Result<bool, Box<dyn Error>> {
+        let modified = std::fs::metadata(&self.path)?.modified().ok();
+        if modified.is_some() && modified == self.modified {
+            return Ok(false);
+        }
+        let (list, modified) = Self::read(&self.path)?;
+        self.list = list;
+        self.modified = modified;
+        Ok(true)
+    }
+
+    pub fn list(&self) -> &StatusList {
+        &self.list
+    }
+
+    fn read(path: &Path) -> Result<(StatusList, Option<SystemTime>), Box<dyn Error>> {
+        let modified = std::fs::metadata(path)?.modified().ok();
+        let list = StatusList::from_json(&std::fs::read_to_string(path)?)?;
+        Ok((list, modified))
+    }
+}
+
+/// Zero-knowledge proof that the index in a Pedersen commitment is not revoked in a status list: a proof that the
+/// index is not in each chunk of `StatusList::revoked_chunks`, verified against `StatusList::commitment`
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct StatusListProof<E: Pairing> {
+    pub chunk_proofs: Vec<SetNonMembershipProof<E>>,
+}
+
+impl<E: Pairing> StatusListProof<E> {
+    /// Prove that the index committed in `ped_open` is not revoked in `list`, using the powers of g from the range proof parameters
+    pub fn prove(context: &[u8], ped_open: &PedersenOpening<E::G1>, list: &StatusList, powers_of_g: &[E::G1Affine]) -> Result<Self, Box<dyn Error>> {
+        let chunk_proofs = list.revoked_chunks(chunk_size(powers_of_g))?
+            .iter()
+            .map(|chunk| SetNonMembershipProof::prove(context, ped_open, chunk, powers_of_g))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(StatusListProof { chunk_proofs })
+    }
+
+    /// Verify the proof, where `ped_com` is a commitment in `bases` to the index and `list_commitment` is the
+    /// commitment to the list (see `StatusList::commitment`)
+    pub fn verify(&self, context: &[u8], ped_com: &E::G1, bases: &[E::G1; 2], list_commitment: &[E::G1], vk: &RangeProofVK<E>) -> bool {
+        if self.chunk_proofs.len() != list_commitment.len() {
+            println!("Status list proof failed to verify, expected {} chunk proofs, found {}", list_commitment.len(), self.chunk_proofs.len());
+            return false;
+        }
+        self.chunk_proofs.iter().zip(list_commitment).all(|(proof, v)| proof.verify(context, ped_com, bases, v, vk))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dlog::DLogPoK;
+    use crate::rangeproof::RangeProofPK;
+    use crate::{CrescentFr, CrescentPairing, G1};
+    use ark_ec::CurveGroup;
+    use ark_std::UniformRand;
+    use flate2::{write::ZlibEncoder, Compression};
+    use std::io::Write;
+
+    fn make_list(bits: u8, bytes: &[u8]) -> StatusList {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        StatusList { bits, lst: base64_url::encode(&encoder.finish().unwrap()) }
+    }
+
+    #[test]
+    fn test_parse_status_list() {
+        // Example from the Token Status List draft
+        let list = StatusList::from_json(r#"{"status_list": {"bits": 1, "lst": "eNrbuRgAAhcBXQ"}}"#).unwrap();
+        assert_eq!(list.bytes().unwrap(), vec![0xb9, 0xa3]);
+        assert_eq!(list.statuses().unwrap(), vec![1, 0, 0, 1, 1, 1, 0, 1, 1, 1, 0, 0, 0, 1, 0, 1]);
+        assert_eq!(list.revoked_indices().unwrap(), vec![0, 3, 4, 5, 7, 8, 9, 13, 15]);
+
+        let list = make_list(2, &[0b11_10_01_00]);
+        assert_eq!(list.statuses().unwrap(), vec![0, 1, 2, 3]);
+        assert!(StatusList::from_json(r#"{"bits": 3, "lst": "eNrbuRgAAhcBXQ"}"#).is_err());
+    }
+
+    #[test]
+    fn test_status_list_proof() {
+        let rng = &mut ark_std::test_rng();
+        let (range_pk, range_vk) = RangeProofPK::<CrescentPairing>::setup_with_degree(32, 256);
+        let powers = &range_pk.powers.powers_of_g;
+        let bases = [G1::rand(rng), G1::rand(rng)];
+        let bases_affine = bases.map(|b| b.into_affine());
+        let context = b"proof spec";
+
+        // Credentials 1, 9 and 10 are revoked
+        let list = make_list(1, &[0b0000_0010, 0b0000_0110, 0]);
+        let v = list.commitment::<CrescentPairing>(&range_vk.powers_of_g).unwrap();
+        for idx in [0u64, 2, 8, 11, 23] {
+            let ped_open = DLogPoK::<G1>::pedersen_commit(&CrescentFr::from(idx), &bases_affine);
+            let proof = StatusListProof::<CrescentPairing>::prove(context, &ped_open, &list, powers).unwrap();
+            assert!(proof.verify(context, &ped_open.c, &bases, &v, &range_vk));
+            assert!(!proof.verify(b"other context", &ped_open.c, &bases, &v, &range_vk));
+
+            // The proof is for this list only
+            let other = make_list(1, &[0b0000_0010, 0b0000_0110, 1 << (idx % 8)]);
+            let v_other = other.commitment::<CrescentPairing>(&range_vk.powers_of_g).unwrap();
+            assert!(!proof.verify(context, &ped_open.c, &bases, &v_other, &range_vk));
+        }
+        for idx in [1u64, 9, 10] {
+            let ped_open = DLogPoK::<G1>::pedersen_commit(&CrescentFr::from(idx), &bases_affine);
+            assert!(StatusListProof::<CrescentPairing>::prove(context, &ped_open, &list, powers).is_err());
+        }
+
+        // A list with no revoked credentials
+        let empty = make_list(1, &[0, 0]);
+        let v = empty.commitment::<CrescentPairing>(&range_vk.powers_of_g).unwrap();
+        let ped_open = DLogPoK::<G1>::pedersen_commit(&CrescentFr::from(5u64), &bases_affine);
+        let proof = StatusListProof::<CrescentPairing>::prove(context, &ped_open, &empty, powers).unwrap();
+        assert!(proof.verify(context, &ped_open.c, &bases, &v, &range_vk));
+    }
+
+    #[test]
+    fn test_large_status_list() {
+        let rng = &mut ark_std::test_rng();
+        let (range_pk, range_vk) = RangeProofPK::<CrescentPairing>::setup_with_degree(32, 256);
+        let powers = &range_pk.powers.powers_of_g;
+        let bases = [G1::rand(rng), G1::rand(rng)];
+        let bases_affine = bases.map(|b| b.into_affine());
+        let context = b"proof spec";
+
+        // A list of 100000 credentials, with every 97th revoked: more revoked entries than the degree of the parameters
+        let mut bytes = vec![0u8; 100_000 / 8];
+        for i in (0..100_000).step_by(97) {
+            bytes[i / 8] |= 1 << (i % 8);
+        }
+        let list = make_list(1, &bytes);
+        assert_eq!(list.statuses().unwrap().len(), 100_000);
+        assert_eq!(list.revoked_indices().unwrap().len(), 1031);
+        let v = list.commitment::<CrescentPairing>(&range_vk.powers_of_g).unwrap();
+        assert_eq!(v.len(), 5);
+
+        // Not revoked, in the first and in the last chunk
+        for idx in [1u64, 99_999] {
+            let ped_open = DLogPoK::<G1>::pedersen_commit(&CrescentFr::from(idx), &bases_affine);
+            let proof = StatusListProof::<CrescentPairing>::prove(context, &ped_open, &list, powers).unwrap();
+            assert_eq!(proof.chunk_proofs.len(), 5);
+            assert!(proof.verify(context, &ped_open.c, &bases, &v, &range_vk));
+
+            // Each chunk is checked
+            let mut truncated = proof.clone();
+            truncated.chunk_proofs.pop();
+            assert!(!truncated.verify(context, &ped_open.c, &bases, &v, &range_vk));
+            let mut swapped = proof.clone();
+            swapped.chunk_proofs.swap(0, 4);
+            assert!(!swapped.verify(context, &ped_open.c, &bases, &v, &range_vk));
+        }
+
+        // Revoked, in the last chunk
+        let ped_open = DLogPoK::<G1>::pedersen_commit(&CrescentFr::from(97u64 * 1030), &bases_affine);
+        assert!(StatusListProof::<CrescentPairing>::prove(context, &ped_open, &list, powers).is_err());
+    }
+
+    #[test]
+    fn test_status_list_file() {
+        let path = std::env::temp_dir().join(format!("crescent-status-list-test-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"status_list": {"bits": 1, "lst": "eNrbuRgAAhcBXQ"}}"#).unwrap();
+        let mut file = StatusListFile::open(&path).unwrap();
+        assert_eq!(file.list().bits, 1);
+        assert!(!file.refresh().unwrap());
+
+        let list = make_list(2, &[0]);
+        std::fs::write(&path, serde_json::to_string(&list).unwrap()).unwrap();
+        let modified = SystemTime::now() + std::time::Duration::from_secs(10);
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        assert!(file.refresh().unwrap());
+        assert_eq!(file.list(), &list);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
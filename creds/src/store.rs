@@ -0,0 +1,180 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+/// Names of the parameters and artifacts kept in a `ParamStore`. The filesystem store uses them as paths
+/// relative to its base directory, matching the layout of `creds/test-vectors/<name>/`.
+pub mod keys {
+    // Inputs, produced by circuit setup or by the issuer
+    pub const JWT: &str = "token.jwt";
+    pub const ISSUER_PEM: &str = "issuer.pub";
+    pub const CONFIG: &str = "config.json";
+    pub const IO_LOCATIONS: &str = "io_locations.sym";
+    pub const WASM: &str = "main.wasm";
+    pub const R1CS: &str = "main_c.r1cs";
+    pub const MDL_PROVER_INPUTS: &str = "prover_inputs.json";
+    pub const MDL_PROVER_AUX: &str = "prover_aux.json";
+    pub const PROOF_SPEC: &str = "proof_spec.json";
+    pub const DEVICE_PUB_PEM: &str = "device.pub";
+    pub const DEVICE_PRV_PEM: &str = "device.prv";
+
+    // Revocation, published by the issuer (the accumulator and its public key) or given to the holder (the witness)
+    pub const ACCUMULATOR_PK: &str = "accumulator_pk.bin";
+    pub const ACCUMULATOR: &str = "accumulator.bin";
+    pub const REVOCATION_WITNESS: &str = "revocation_witness.bin";
+    pub const STATUS_LIST: &str = "status_list.json";
+
+    // Outputs of zksetup, prepare and show
+    pub const RANGE_PK: &str = "cache/range_pk.bin";
+    pub const RANGE_VK: &str = "cache/range_vk.bin";
+    pub const GROTH16_VK: &str = "cache/groth16_vk.bin";
+    pub const GROTH16_PVK: &str = "cache/groth16_pvk.bin";
+    pub const PROVER_PARAMS: &str = "cache/prover_params.bin";
+    pub const CLIENT_STATE: &str = "cache/client_state.bin";
+    pub const SHOW_PROOF: &str = "cache/show_proof.bin";
+}
+
+/// Storage for the parameters and intermediate artifacts used by setup, prove, show and verify
+pub trait ParamStore {
+    /// Read the item stored under `key`. Fails with `ErrorKind::NotFound` if there is none.
+    fn read(&self, key: &str) -> Result<Vec<u8>, Error>;
+
+    /// Store `data` under `key`, replacing any previous value
+    fn write(&self, key: &str, data: &[u8]) -> Result<(), Error>;
+
+    fn contains(&self, key: &str) -> bool {
+        self.read(key).is_ok()
+    }
+
+    fn read_string(&self, key: &str) -> Result<String, Error> {
+        String::from_utf8(self.read(key)?).map_err(|e| Error::new(ErrorKind::InvalidData, format!("{} is not valid UTF-8: {}", key, e)))
+    }
+}
+
+/// Read and deserialize an object stored under `key`
+pub fn read_param<T>(store: &dyn ParamStore, key: &str) -> Result<T, SerializationError>
+where
+    T: CanonicalDeserialize
+{
+    let buf = store.read(key)?;
+    T::deserialize_uncompressed_unchecked(buf.as_slice())
+}
+
+/// Serialize an object and store it under `key`
+pub fn write_param<T>(store: &dyn ParamStore, key: &str, obj: &T) -> Result<(), SerializationError>
+where
+    T: CanonicalSerialize
+{
+    let mut buf = Vec::with_capacity(obj.uncompressed_size());
+    obj.serialize_uncompressed(&mut buf)?;
+    store.write(key, &buf)?;
+    Ok(())
+}
+
+/// Store backed by a directory. Keys are paths relative to the base directory.
+/// Directories are not created; writing to a key whose parent directory is missing fails.
+#[derive(Clone, Debug)]
+pub struct FsParamStore {
+    base: PathBuf,
+}
+
+impl FsParamStore {
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        Self { base: base.into() }
+    }
+
+    pub fn base(&self) -> &PathBuf {
+        &self.base
+    }
+
+    /// The filesystem path of the item stored under `key`
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.base.join(key)
+    }
+}
+
+impl ParamStore for FsParamStore {
+    fn read(&self, key: &str) -> Result<Vec<u8>, Error> {
+        std::fs::read(self.path(key))
+    }
+
+    fn write(&self, key: &str, data: &[u8]) -> Result<(), Error> {
+        std::fs::write(self.path(key), data)
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.path(key).is_file()
+    }
+}
+
+/// Store that keeps everything in memory, e.g., for services that load parameters from a database
+#[derive(Debug, Default)]
+pub struct MemParamStore {
+    items: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl MemParamStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ParamStore for MemParamStore {
+    fn read(&self, key: &str) -> Result<Vec<u8>, Error> {
+        let items = self.items.read().map_err(|_| Error::other("MemParamStore lock poisoned"))?;
+        items.get(key).cloned().ok_or_else(|| Error::new(ErrorKind::NotFound, format!("{} not found in store", key)))
+    }
+
+    fn write(&self, key: &str, data: &[u8]) -> Result<(), Error> {
+        let mut items = self.items.write().map_err(|_| Error::other("MemParamStore lock poisoned"))?;
+        items.insert(key.to_string(), data.to_vec());
+        Ok(())
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.items.read().map(|items| items.contains_key(key)).unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CrescentFr;
+    use ark_std::UniformRand;
+
+    fn roundtrip(store: &dyn ParamStore) {
+        assert!(!store.contains(keys::CONFIG));
+        assert_eq!(store.read(keys::CONFIG).unwrap_err().kind(), ErrorKind::NotFound);
+        store.write(keys::CONFIG, br#"{"alg": "RS256"}"#).unwrap();
+        assert!(store.contains(keys::CONFIG));
+        assert_eq!(store.read_string(keys::CONFIG).unwrap(), r#"{"alg": "RS256"}"#);
+
+        let x = CrescentFr::rand(&mut ark_std::test_rng());
+        write_param(store, keys::CLIENT_STATE, &x).unwrap();
+        let y: CrescentFr = read_param(store, keys::CLIENT_STATE).unwrap();
+        assert_eq!(x, y);
+    }
+
+    #[test]
+    fn test_mem_store() {
+        roundtrip(&MemParamStore::new());
+    }
+
+    #[test]
+    fn test_fs_store() {
+        let dir = std::env::temp_dir().join(format!("crescent-store-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("cache")).unwrap();
+        roundtrip(&FsParamStore::new(&dir));
+
+        // The store does not create missing directories
+        let store = FsParamStore::new(dir.join("missing"));
+        assert!(store.write(keys::RANGE_PK, b"").is_err());
+        assert!(!dir.join("missing").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,430 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// The credential config (config.json): the credential type and signature algorithm of the circuit, and the claims it
+// matches, with how each of them is revealed. The same config is used by the circuit setup scripts, the prover and the
+// verifier; `parse_config` checks it for JWTs, SD-JWTs and mDLs alike, so that an invalid config is rejected before
+// running the setup.
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::return_error;
+
+// If not set in config.json, the max_cred_len is set to this value.
+pub const DEFAULT_MAX_TOKEN_LENGTH : usize = 2048;
+const DEFAULT_RSA_KEY_BITS : u64 = 2048;
+const RSA_KEY_SIZES : [u64; 3] = [2048, 3072, 4096];
+// If not set in config.json, the max_disclosure_len of a claim in an SD-JWT disclosure is set to this value
+pub const DEFAULT_MAX_DISCLOSURE_LENGTH : usize = 128;
+lazy_static! {
+    static ref CRESCENT_SUPPORTED_ALGS: HashSet<&'static str> = {
+        let mut set = HashSet::new();
+        set.insert("RS256");
+        set.insert("RS384");
+        set.insert("RS512");
+        set.insert("PS256");
+        set.insert("ES256");
+        set
+    };
+}
+/// The number of bytes packed in a field element by the circuits
+pub const MAX_FIELD_BYTE_LEN : usize = 31;
+/// The limb size of RSA moduli and signatures in the RSA circuits
+pub const CIRCOM_RS256_LIMB_BITS : usize = 121;
+/// The claims that device bound credentials have, holding the two halves of the x coordinate of the device public key
+pub const DEVICE_KEY_CLAIMS : [&str; 2] = ["device_key_0", "device_key_1"];
+
+/// The type of a claim. JWTs have `number` claims, mDLs have `integer` claims; the other types are shared.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClaimType {
+    Number,
+    Integer,
+    String,
+    Boolean,
+    Date,
+    Null,
+    Array,
+    Object,
+}
+
+impl ClaimType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClaimType::Number => "number",
+            ClaimType::Integer => "integer",
+            ClaimType::String => "string",
+            ClaimType::Boolean => "boolean",
+            ClaimType::Date => "date",
+            ClaimType::Null => "null",
+            ClaimType::Array => "array",
+            ClaimType::Object => "object",
+        }
+    }
+
+    /// Booleans and dates are revealed as integers (0/1, or a daystamp), which have a fixed length
+    pub fn is_fixed_len(&self) -> bool {
+        matches!(self, ClaimType::Boolean | ClaimType::Date)
+    }
+
+    /// The value an optional claim takes in the circuit when it is absent from the token, see claim_default_value in
+    /// crescent_helper.py
+    pub fn default_value(&self) -> Result<Value, Box<dyn Error>> {
+        match self {
+            ClaimType::String => Ok(json!("")),
+            ClaimType::Number => Ok(json!(0)),
+            ClaimType::Boolean => Ok(json!(false)),
+            ClaimType::Date => Ok(json!("1900-01-01")),
+            _ => return_error!(format!("Optional claims of type {} are not supported", self.as_str())),
+        }
+    }
+}
+
+/// The config entry of a claim, e.g., `"email": {"type": "string", "reveal": true, "max_claim_byte_len": 31}`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ClaimConfig {
+    #[serde(rename = "type")]
+    pub claim_type: ClaimType,
+    /// Revealed as a field element
+    #[serde(default)]
+    pub reveal: bool,
+    /// Revealed as a digest, for values that may be larger than a field element
+    #[serde(default)]
+    pub reveal_digest: bool,
+    /// Revealed as `max_claim_byte_len / 31` field elements, for strings larger than a field element
+    #[serde(default)]
+    pub reveal_bytes: bool,
+    /// Only the domain of an email address is revealed
+    #[serde(default)]
+    pub reveal_domain_only: bool,
+    /// The claim may be absent from the token
+    #[serde(default)]
+    pub optional: bool,
+    /// The claim is in an SD-JWT disclosure
+    #[serde(default)]
+    pub sd: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_claim_byte_len: Option<usize>,
+    /// Set to its default for the claims in disclosures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_disclosure_len: Option<usize>,
+}
+
+impl ClaimConfig {
+    /// Whether the value of the claim is output by the circuit, as one or more field elements or as a digest
+    pub fn is_revealed(&self) -> bool {
+        self.reveal || self.reveal_bytes || self.reveal_digest
+    }
+
+    /// The largest value of the claim, set for the claims that are not revealed with a fixed length
+    pub fn max_claim_byte_len(&self) -> usize {
+        self.max_claim_byte_len.unwrap_or(0)
+    }
+}
+
+/// A credential config. The claims are in the order of config.json, which is the order of the circuit's public inputs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CredentialConfig {
+    pub alg: String,
+    /// One of "jwt" (the default), "sd-jwt" or "mdl"
+    pub credtype: String,
+    /// The largest credential supported by the circuit, after hash padding
+    pub max_cred_len: usize,
+    /// The size of the issuer's modulus, for RSA algorithms
+    pub rsa_key_bits: Option<u64>,
+    pub device_bound: bool,
+    /// The issuer's timezone, for age predicates
+    pub timezone: Option<String>,
+    pub claims: Vec<(String, ClaimConfig)>,
+}
+
+impl CredentialConfig {
+    /// The config entry of a claim
+    pub fn claim(&self, name: &str) -> Option<&ClaimConfig> {
+        self.claims.iter().find(|(claim_name, _)| claim_name == name).map(|(_, entry)| entry)
+    }
+
+    pub fn is_mdl(&self) -> bool {
+        self.credtype == "mdl"
+    }
+}
+
+/// Whether alg is one of the RSA signature algorithms, RSASSA-PKCS1-v1_5 or RSASSA-PSS
+pub fn is_rsa_alg(alg : &str) -> bool {
+    matches!(alg, "RS256" | "RS384" | "RS512" | "PS256")
+}
+
+/// The block size in bytes of the hash function used by alg; SHA-384 and SHA-512 use 1024-bit blocks
+pub fn hash_block_len(alg : &str) -> usize {
+    match alg {
+        "RS384" | "RS512" => 128,
+        _ => 64,
+    }
+}
+
+/// The number of bytes encoded by encoded_len base64 characters, rounded up to a multiple of 3
+pub fn base64_decoded_size(encoded_len : u64) -> u64 {
+    encoded_len.div_ceil(4) * 3
+}
+
+/// The segments of the path of a claim. Claims are top-level claim names, or JSON pointers (RFC 6901) to nested
+/// claims, e.g., "/address/country".
+pub fn claim_path(name: &str) -> Vec<String> {
+    match name.strip_prefix('/') {
+        Some(pointer) => pointer.split('/').map(|s| s.replace("~1", "/").replace("~0", "~")).collect(),
+        None => vec![name.to_string()],
+    }
+}
+
+/// The prefix of the circuit signal names of a claim, e.g., "address_country" for "/address/country".
+/// Must match claim_signal_name in crescent_helper.py.
+pub fn claim_signal_name(name: &str) -> String {
+    let name = name.strip_prefix('/').unwrap_or(name);
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect()
+}
+
+pub fn load_config(path: PathBuf) -> Result<CredentialConfig, Box<dyn Error>> {
+    let config_str = fs::read_to_string(path)?;
+    parse_config(&config_str)
+}
+
+/// Parse and validate a credential config, setting the defaults of the optional fields
+pub fn parse_config(config_str: &str) -> Result<CredentialConfig, Box<dyn Error>> {
+    let config_v: Value = serde_json::from_str(config_str)?;
+    let config = config_v.as_object().ok_or("Invalid config")?;
+
+    let alg = config.get("alg").ok_or("'alg' field is missing from config file")?.as_str().ok_or("alg field is not a string")?.to_string();
+    if !CRESCENT_SUPPORTED_ALGS.contains(alg.as_str()) {
+        return_error!(format!("{} algorithm is unsupported", alg));
+    }
+    let credtype = optional_field(config, "credtype", Value::as_str)?.unwrap_or("jwt").to_string();
+    if !["jwt", "sd-jwt", "mdl"].contains(&credtype.as_str()) {
+        return_error!(format!("Unsupported credtype {}, must be one of jwt, sd-jwt or mdl", credtype));
+    }
+    if credtype == "mdl" && alg != "ES256" {
+        return_error!(format!("{} algorithm is unsupported for mDLs, only ES256 is", alg));
+    }
+
+    // The circuit hashes the credential in blocks of the hash function
+    let block_len = hash_block_len(&alg);
+    let mut max_cred_len = optional_field(config, "max_cred_len", Value::as_u64)?.map(|v| v as usize).unwrap_or(DEFAULT_MAX_TOKEN_LENGTH);
+    if max_cred_len == 0 {
+        return_error!("max_cred_len must be positive");
+    }
+    if max_cred_len % block_len != 0 {
+        let round = (block_len - (max_cred_len % block_len)) + max_cred_len;
+        println!("Warning: max_cred_len not a multiple of {}. Rounded from {} to {}", block_len, max_cred_len, round);
+        max_cred_len = round;
+    }
+
+    let rsa_key_bits = optional_field(config, "rsa_key_bits", Value::as_u64)?;
+    let rsa_key_bits = if is_rsa_alg(&alg) {
+        let key_bits = rsa_key_bits.unwrap_or(DEFAULT_RSA_KEY_BITS);
+        if !RSA_KEY_SIZES.contains(&key_bits) {
+            return_error!(format!("Unsupported RSA key size {}, must be one of {:?}", key_bits, RSA_KEY_SIZES));
+        }
+        Some(key_bits)
+    }
+    else if rsa_key_bits.is_some() {
+        return_error!(format!("rsa_key_bits is only supported for RSA algorithms, not {}", alg));
+    }
+    else {
+        None
+    };
+
+    let device_bound = optional_field(config, "device_bound", Value::as_bool)?.unwrap_or(false);
+    let timezone = optional_field(config, "timezone", Value::as_str)?.map(|tz| tz.to_string());
+
+    // All other entries are claims (e.g, "email", "exp", etc.)
+    let mut claims = vec![];
+    for (key, value) in config {
+        if ["alg", "credtype", "max_cred_len", "rsa_key_bits", "device_bound", "timezone"].contains(&key.as_str()) {
+            continue;
+        }
+        if !value.is_object() {
+            return_error!(format!("Config entry for claim {} does not have object type", key));
+        }
+        let entry = serde_json::from_value::<ClaimConfig>(value.clone()).map_err(|e| format!("Config entry for claim {} is invalid: {}", key, e))?;
+        claims.push((key.clone(), entry));
+    }
+    if device_bound {
+        for name in DEVICE_KEY_CLAIMS {
+            let entry = ClaimConfig {
+                claim_type: if credtype == "mdl" { ClaimType::Integer } else { ClaimType::Number },
+                reveal: true,
+                reveal_digest: false,
+                reveal_bytes: false,
+                reveal_domain_only: false,
+                optional: false,
+                sd: false,
+                max_claim_byte_len: Some(2 * MAX_FIELD_BYTE_LEN),
+                max_disclosure_len: None,
+            };
+            claims.push((name.to_string(), entry));
+        }
+    }
+
+    let mut config = CredentialConfig { alg, credtype, max_cred_len, rsa_key_bits, device_bound, timezone, claims };
+    validate_claims(&mut config)?;
+    Ok(config)
+}
+
+// An optional field of the config, None if absent and an error if it does not have the expected type
+fn optional_field<'a, T>(config: &'a Map<String, Value>, name: &str, as_type: fn(&'a Value) -> Option<T>) -> Result<Option<T>, Box<dyn Error>> {
+    match config.get(name) {
+        Some(value) => Ok(Some(as_type(value).ok_or(format!("Config field {} has an invalid type", name))?)),
+        None => Ok(None),
+    }
+}
+
+// Check that each claim can be matched and revealed as configured, by the circuit of the credential type
+fn validate_claims(config: &mut CredentialConfig) -> Result<(), Box<dyn Error>> {
+    let mdl = config.is_mdl();
+    let sd_jwt = config.credtype == "sd-jwt";
+    let mut signal_names = HashSet::new();
+    for (key, entry) in config.claims.iter_mut() {
+        let claim_type = entry.claim_type.as_str();
+        let path = claim_path(key);
+        if path.iter().any(|segment| segment.is_empty()) {
+            return_error!(format!("Config entry for claim {} has an empty path segment", key));
+        }
+        if mdl && path.len() > 1 {
+            return_error!(format!("Config entry for claim {} has a nested path, which mDLs do not support", key));
+        }
+        // Claims are identified by their signal names in the circuit
+        if !signal_names.insert(claim_signal_name(key)) {
+            return_error!(format!("Config entry for claim {} has the same circuit signal name as another claim", key));
+        }
+        // mDLs have integers rather than numbers, and the mDL circuit only supports integers, strings and dates
+        let supported = if mdl {
+            matches!(entry.claim_type, ClaimType::Integer | ClaimType::String | ClaimType::Date)
+        } else {
+            entry.claim_type != ClaimType::Integer
+        };
+        if !supported {
+            return_error!(format!("Config entry for claim {} has type {}, which is unsupported for credtype {}", key, claim_type, config.credtype));
+        }
+
+        if entry.claim_type.is_fixed_len() {
+            if entry.reveal_digest || entry.reveal_bytes || entry.reveal_domain_only {
+                return_error!(format!("Config entry for claim {} has type {}, which can only be revealed with the reveal flag", key, claim_type));
+            }
+        }
+        else if entry.is_revealed() && entry.max_claim_byte_len() == 0 {
+            return_error!(format!("Config entry for claim {} is revealed but is missing 'max_claim_byte_len'", key));
+        }
+        if entry.is_revealed() && matches!(entry.claim_type, ClaimType::Null | ClaimType::Array | ClaimType::Object) {
+            return_error!(format!("Config entry for claim {} has type {}, which cannot be revealed", key, claim_type));
+        }
+        // Strings revealed as a single field element are packed in MAX_FIELD_BYTE_LEN bytes
+        if entry.reveal && !entry.reveal_bytes && entry.claim_type == ClaimType::String && entry.max_claim_byte_len() > MAX_FIELD_BYTE_LEN {
+            return_error!(format!("Config entry for claim {} is revealed as a field element, and must have max_claim_byte_len be at most {}; see reveal_bytes and reveal_digest", key, MAX_FIELD_BYTE_LEN));
+        }
+        if entry.reveal_domain_only && (entry.claim_type != ClaimType::String || !entry.reveal || mdl) {
+            return_error!(format!("Config entry for claim {} has reveal_domain_only flag set, which requires a JWT string claim with the reveal flag", key));
+        }
+        // Long strings are revealed as several field elements of MAX_FIELD_BYTE_LEN bytes
+        if entry.reveal_bytes {
+            if entry.claim_type != ClaimType::String || mdl {
+                return_error!(format!("Config entry for claim {} has reveal_bytes flag set, but only strings of JWTs can be revealed as bytes", key));
+            }
+            if !entry.max_claim_byte_len().is_multiple_of(MAX_FIELD_BYTE_LEN) {
+                return_error!(format!("Config entry for claim {} has reveal_bytes flag set, and must have max_claim_byte_len be a multiple of {}", key, MAX_FIELD_BYTE_LEN));
+            }
+        }
+        if entry.reveal_digest && !matches!(entry.claim_type, ClaimType::Number | ClaimType::Integer | ClaimType::String) {
+            return_error!(format!("Config entry for claim {} has reveal_digest flag set, but only numbers and strings can be hashed", key));
+        }
+        // Optional claims may be absent from the token, see OptionalClaim in match_claim.circom
+        if entry.optional {
+            if mdl || path.len() > 1 || entry.sd || DEVICE_KEY_CLAIMS.contains(&key.as_str()) || key == "exp" {
+                return_error!(format!("Config entry for claim {} cannot be optional, only top-level claims of JWTs that are not in disclosures can be", key));
+            }
+            if entry.reveal_digest || entry.claim_type.default_value().is_err() {
+                return_error!(format!("Optional claim {} must have type string, number, boolean or date, and cannot be revealed with reveal_digest", key));
+            }
+        }
+        // Claims of SD-JWTs may be in a disclosure, rather than in the issuer-signed JWT
+        if entry.sd {
+            if !sd_jwt {
+                return_error!(format!("Config entry for claim {} has the 'sd' flag set, but the credential type is not 'sd-jwt'", key));
+            }
+            if path.len() > 1 {
+                return_error!(format!("Config entry for claim {} has the 'sd' flag set, only top-level claims can be in disclosures", key));
+            }
+            let max_disclosure_len = *entry.max_disclosure_len.get_or_insert(DEFAULT_MAX_DISCLOSURE_LENGTH);
+            if max_disclosure_len == 0 || max_disclosure_len % 64 != 0 {
+                return_error!(format!("Config entry for claim {} must have max_disclosure_len be a multiple of 64", key));
+            }
+        }
+        else if entry.max_disclosure_len.is_some() {
+            return_error!(format!("Config entry for claim {} has max_disclosure_len, but is not in a disclosure", key));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = parse_config(r#"{"alg": "RS256", "max_cred_len": 1000,
+            "exp": {"type": "number", "reveal": true, "max_claim_byte_len": 31},
+            "email": {"type": "string", "reveal": true, "reveal_domain_only": true, "max_claim_byte_len": 31},
+            "aud": {"type": "string", "reveal_digest": true, "max_claim_byte_len": 62}}"#).unwrap();
+        assert_eq!(config.credtype, "jwt");
+        assert_eq!(config.max_cred_len, 1024);
+        assert_eq!(config.rsa_key_bits, Some(DEFAULT_RSA_KEY_BITS));
+        assert_eq!(config.claims.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), vec!["exp", "email", "aud"]);
+        assert!(config.claim("email").unwrap().reveal_domain_only);
+        assert_eq!(config.claim("aud").unwrap().claim_type, ClaimType::String);
+        assert!(config.claim("iss").is_none());
+
+        // Device bound credentials have the claims holding the device key
+        let config = parse_config(r#"{"alg": "ES256", "device_bound": true}"#).unwrap();
+        assert_eq!(config.claim("device_key_1").unwrap().max_claim_byte_len, Some(2 * MAX_FIELD_BYTE_LEN));
+        assert!(config.rsa_key_bits.is_none());
+
+        // The errors name the offending claim
+        let error = |config_str: &str| parse_config(config_str).unwrap_err().to_string();
+        assert!(error(r#"{"alg": "ES256", "email": {"type": "string", "reveal": true}}"#).contains("claim email"));
+        assert!(error(r#"{"alg": "ES256", "email": {"type": "string", "reveal_digets": true}}"#).contains("claim email"));
+        assert!(error(r#"{"alg": "ES256", "email": {"type": "text"}}"#).contains("claim email"));
+        assert!(error(r#"{"alg": "ES256", "email": "string"}"#).contains("claim email"));
+        assert!(error(r#"{"alg": "ES256", "aud": {"type": "string", "reveal_digest": true}}"#).contains("claim aud"));
+        assert!(error(r#"{"alg": "ES256", "iss": {"type": "string", "reveal": true, "max_claim_byte_len": 62}}"#).contains("claim iss"));
+        assert!(error(r#"{"alg": "ES256", "address": {"type": "object", "reveal": true, "max_claim_byte_len": 31}}"#).contains("claim address"));
+        assert!(error(r#"{"alg": "ES256", "exp": {"type": "number", "max_disclosure_len": 128}}"#).contains("claim exp"));
+        assert!(parse_config(r#"{"alg": "ES256", "max_cred_len": "1024"}"#).is_err());
+        assert!(parse_config(r#"{"alg": "ES256", "credtype": "x509"}"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_mdl_config() {
+        let config = parse_config(r#"{"credtype": "mdl", "alg": "ES256", "device_bound": true, "max_cred_len": 1792, "timezone": "America/Los_Angeles",
+            "birth_date": {"type": "date", "reveal": true, "max_claim_byte_len": 31},
+            "height": {"type": "integer", "reveal": true, "max_claim_byte_len": 31},
+            "family_name": {"type": "string", "reveal_digest": true, "max_claim_byte_len": 62}}"#).unwrap();
+        assert!(config.is_mdl());
+        assert_eq!(config.timezone.as_deref(), Some("America/Los_Angeles"));
+        assert_eq!(config.claim("height").unwrap().claim_type, ClaimType::Integer);
+        assert_eq!(config.claim("device_key_0").unwrap().claim_type, ClaimType::Integer);
+
+        // The JWT and mDL types and flags are not interchangeable
+        let error = |config_str: &str| parse_config(config_str).unwrap_err().to_string();
+        assert!(error(r#"{"credtype": "mdl", "alg": "ES256", "height": {"type": "number", "reveal": true, "max_claim_byte_len": 31}}"#).contains("claim height"));
+        assert!(error(r#"{"alg": "ES256", "height": {"type": "integer", "reveal": true, "max_claim_byte_len": 31}}"#).contains("claim height"));
+        assert!(error(r#"{"credtype": "mdl", "alg": "ES256", "family_name": {"type": "string", "reveal_bytes": true, "max_claim_byte_len": 62}}"#).contains("claim family_name"));
+        assert!(error(r#"{"credtype": "mdl", "alg": "ES256", "/address/country": {"type": "string"}}"#).contains("claim /address/country"));
+        assert!(parse_config(r#"{"credtype": "mdl", "alg": "RS256"}"#).is_err());
+    }
+}
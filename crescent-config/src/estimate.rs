@@ -0,0 +1,347 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// Circuit size estimates and credential fit checks, to size a config before running the setup.
+// `estimate_constraints` approximates the number of R1CS constraints of the circuit that prepare_setup.py (JWTs and
+// SD-JWTs) or prepare_mdl_setup.py (mDLs) generate for a config, from the cost of each circuit template. It is meant
+// to compare configs: the exact count is printed by circom during the setup (see the log file in generated_files).
+// `FitReport` reports the sizes of sample credentials, measured as the prover would (see measure_jwt in creds, and
+// fit-check in mdl-tools), and recommends the smallest `max_cred_len`, `max_claim_byte_len` and `max_disclosure_len`
+// values they all fit in.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::config::{base64_decoded_size, claim_path, hash_block_len, is_rsa_alg, ClaimConfig, ClaimType, CredentialConfig,
+    CIRCOM_RS256_LIMB_BITS, DEFAULT_MAX_DISCLOSURE_LENGTH, DEVICE_KEY_CLAIMS, MAX_FIELD_BYTE_LEN};
+
+// Approximate constraint counts of the templates that dominate the circuit size
+const SHA256_BLOCK_CONSTRAINTS : usize = 29_400;        // Sha256General, per 64-byte block
+const SHA512_BLOCK_CONSTRAINTS : usize = 77_000;        // Sha512Bytes, per 128-byte block
+const BYTE_TO_BITS_CONSTRAINTS : usize = 9;             // Num2Bits(8), per hashed byte
+const RSA_2048_CONSTRAINTS : usize = 150_000;           // RSAVerifier65537 with 17 limbs, grows with the square of the limbs
+const RSA_2048_LIMBS : usize = 17;
+const ECDSA_P256_CONSTRAINTS : usize = 1_950_000;       // ECDSAVerifyNoPubkeyCheck
+const BASE64_BYTE_CONSTRAINTS : usize = 165;            // JWTB64Decode, per base64 character
+const NESTING_BYTE_CONSTRAINTS : usize = 4;             // object_nested_level, per JSON byte
+const MATCH_NAME_BYTE_CONSTRAINTS : usize = 17;         // MatchClaimName, per JSON byte, besides the name comparison
+const PARENT_OBJECT_BYTE_CONSTRAINTS : usize = 4;       // MatchParentObject, per JSON byte
+const INTERVAL_BYTE_CONSTRAINTS : usize = 5;            // IntervalIndicator, per byte
+const SUBSTRING_BYTE_CONSTRAINTS : usize = 8;           // MatchSubstring, per byte, plus 2 per byte and field element
+const VALIDATE_BYTE_CONSTRAINTS : usize = 5;            // ValidateClaimValue, per JSON byte
+const NUMBER_END_BYTE_CONSTRAINTS : usize = 6;          // ExcludeWhitespace and AssertEndNumber, per JSON byte
+const DECODE_STRING_BYTE_CONSTRAINTS : usize = 130;     // DecodeJSONString, per byte, plus 3/2 per byte squared
+const DATE_CONSTRAINTS : usize = 200;                   // Daystamp
+const SD_DIGEST_B64_LEN : usize = 43;                   // The base64url encoding of a SHA-256 digest in the _sd array
+const SD_NAME_LEN : usize = 5;                          // "_sd", with the quotes
+const SD_ARRAY_BYTE_CONSTRAINTS : usize = 4;            // SDArray, per JSON byte, besides MatchClaimName and IntervalIndicator
+const MDL_PREIMAGE_LEN : usize = 128;                   // The padded IssuerSignedItem of an mDL claim
+const MDL_ENCODED_DIGEST_LEN : usize = 35;              // The digest of an mDL claim, as encoded in the MSO
+
+/// The estimated number of constraints of a circuit, by component
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitEstimate {
+    pub components: Vec<(String, usize)>,
+}
+
+impl CircuitEstimate {
+    pub fn total(&self) -> usize {
+        self.components.iter().map(|(_, constraints)| constraints).sum()
+    }
+
+    fn add(&mut self, component: String, constraints: usize) {
+        self.components.push((component, constraints));
+    }
+}
+
+impl fmt::Display for CircuitEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (component, constraints) in &self.components {
+            writeln!(f, "  {:<48} {:>12}", component, constraints)?;
+        }
+        write!(f, "  {:<48} {:>12}", "Total (estimate)", self.total())
+    }
+}
+
+// Matching a substring of substr_len bytes in msg_len bytes, with its interval (see MatchSubstring)
+fn substring_constraints(msg_len: usize, substr_len: usize) -> usize {
+    let fields = substr_len.div_ceil(MAX_FIELD_BYTE_LEN);
+    (2 * INTERVAL_BYTE_CONSTRAINTS + SUBSTRING_BYTE_CONSTRAINTS + 2 * fields) * msg_len
+}
+
+// SHA-256 of max_len bytes, padded in the circuit (see Sha256General)
+fn sha256_constraints(max_len: usize) -> usize {
+    (max_len * 8 + 65).div_ceil(512) * SHA256_BLOCK_CONSTRAINTS + BYTE_TO_BITS_CONSTRAINTS * max_len
+}
+
+// Revealing the value of a claim, as in prepare_setup.py, from json_len bytes
+fn reveal_constraints(entry: &ClaimConfig, json_len: usize, decode_strings: bool) -> usize {
+    let n = entry.max_claim_byte_len();
+    let is_string = entry.claim_type == ClaimType::String;
+    match entry.claim_type {
+        // RevealClaimBoolean and RevealClaimDate extract "false" and "YYYY-MM-DD" at the value
+        ClaimType::Boolean if entry.reveal => (INTERVAL_BYTE_CONSTRAINTS + 5) * json_len,
+        ClaimType::Date if entry.reveal => (INTERVAL_BYTE_CONSTRAINTS + 12) * json_len + DATE_CONSTRAINTS,
+        _ if entry.is_revealed() => {
+            let mut constraints = substring_constraints(json_len, n);
+            if is_string && decode_strings {
+                constraints += DECODE_STRING_BYTE_CONSTRAINTS * n + 3 * n * n / 2;
+            }
+            if !is_string {
+                constraints += NUMBER_END_BYTE_CONSTRAINTS * json_len;
+            }
+            if entry.reveal_digest {
+                constraints += sha256_constraints(n);
+            }
+            constraints
+        }
+        _ => VALIDATE_BYTE_CONSTRAINTS * json_len,
+    }
+}
+
+/// Estimate the number of constraints of the circuit of a config
+pub fn estimate_constraints(config: &CredentialConfig) -> CircuitEstimate {
+    let mut estimate = CircuitEstimate { components: vec![] };
+    let max_msg_len = config.max_cred_len;
+
+    // Hash and signature of the credential
+    let block_len = hash_block_len(&config.alg);
+    let block_constraints = if block_len == 64 { SHA256_BLOCK_CONSTRAINTS } else { SHA512_BLOCK_CONSTRAINTS };
+    estimate.add(format!("Hash of the credential ({} blocks)", max_msg_len / block_len),
+        max_msg_len / block_len * block_constraints + BYTE_TO_BITS_CONSTRAINTS * max_msg_len);
+    if is_rsa_alg(&config.alg) {
+        let key_bits = config.rsa_key_bits.unwrap_or(2048) as usize;
+        let limbs = key_bits.div_ceil(CIRCOM_RS256_LIMB_BITS);
+        let mut constraints = RSA_2048_CONSTRAINTS * limbs * limbs / (RSA_2048_LIMBS * RSA_2048_LIMBS);
+        if config.alg == "PS256" {
+            // MGF1 hashes the digest for each 32 bytes of the mask, and the salted digest is hashed again
+            constraints += ((key_bits / 8 - 33).div_ceil(32) + 2) * SHA256_BLOCK_CONSTRAINTS;
+        }
+        estimate.add(format!("{} signature ({}-bit key)", config.alg, key_bits), constraints);
+    } else {
+        estimate.add(format!("{} signature", config.alg), ECDSA_P256_CONSTRAINTS);
+    }
+
+    if config.is_mdl() {
+        estimate_mdl_claims(config, &mut estimate);
+    } else {
+        estimate_jwt_claims(config, &mut estimate);
+    }
+    estimate
+}
+
+fn estimate_jwt_claims(config: &CredentialConfig, estimate: &mut CircuitEstimate) {
+    let max_msg_len = config.max_cred_len;
+    let json_len = base64_decoded_size(max_msg_len as u64) as usize;
+    estimate.add("Base64 decoding".to_string(), BASE64_BYTE_CONSTRAINTS * max_msg_len);
+    estimate.add("JSON nesting levels".to_string(), NESTING_BYTE_CONSTRAINTS * json_len);
+
+    // Each object containing a nested claim is matched once, see write_parent_objects in prepare_setup.py
+    let mut objects = vec![];
+    let mut sd_array_matched = false;
+    for (name, entry) in &config.claims {
+        let path = claim_path(name);
+        let mut constraints = 0;
+        for k in 1..path.len() {
+            let prefix = path[..k].join("/");
+            if !objects.contains(&prefix) {
+                objects.push(prefix);
+                constraints += (path[k - 1].len() + 2 + MATCH_NAME_BYTE_CONSTRAINTS + PARENT_OBJECT_BYTE_CONSTRAINTS) * json_len;
+            }
+        }
+        let name_len = path[path.len() - 1].len() + 2;
+
+        // Claims in disclosures are matched in the decoded disclosure, once its digest is found in the "_sd" array
+        // of the payload. The array is matched once, see write_sd_array in prepare_setup.py.
+        let claim_json_len = if entry.sd {
+            if !sd_array_matched {
+                sd_array_matched = true;
+                estimate.add("SD-JWT _sd array".to_string(), (SD_NAME_LEN + MATCH_NAME_BYTE_CONSTRAINTS + INTERVAL_BYTE_CONSTRAINTS + SD_ARRAY_BYTE_CONSTRAINTS) * json_len);
+            }
+            let max_disclosure_len = entry.max_disclosure_len.unwrap_or(DEFAULT_MAX_DISCLOSURE_LENGTH);
+            constraints += BASE64_BYTE_CONSTRAINTS * max_disclosure_len
+                + max_disclosure_len / 64 * SHA256_BLOCK_CONSTRAINTS + BYTE_TO_BITS_CONSTRAINTS * max_disclosure_len
+                + substring_constraints(json_len, SD_DIGEST_B64_LEN) + json_len;
+            max_disclosure_len * 3 / 4
+        } else {
+            json_len
+        };
+        if entry.optional {
+            // MatchNoClaimName compares the name at each position, and OptionalClaim selects the JSON bytes
+            constraints += (3 * name_len.div_ceil(MAX_FIELD_BYTE_LEN) + 6) * json_len;
+        }
+        constraints += (name_len + MATCH_NAME_BYTE_CONSTRAINTS) * claim_json_len;
+        constraints += reveal_constraints(entry, claim_json_len, true);
+        estimate.add(format!("Claim {}", name), constraints);
+    }
+}
+
+fn estimate_mdl_claims(config: &CredentialConfig, estimate: &mut CircuitEstimate) {
+    let max_msg_len = config.max_cred_len;
+    estimate.add("Validity date".to_string(), 2 * substring_constraints(max_msg_len, MAX_FIELD_BYTE_LEN));
+    if config.device_bound {
+        estimate.add("Device key".to_string(), substring_constraints(max_msg_len, 65));
+    }
+
+    // Each claim is the preimage of a digest in the MSO, see prepare_mdl_setup.py
+    for (name, entry) in &config.claims {
+        if DEVICE_KEY_CLAIMS.contains(&name.as_str()) {
+            continue;
+        }
+        let mut constraints = substring_constraints(MDL_PREIMAGE_LEN, name.len() + 1)
+            + MDL_PREIMAGE_LEN / 64 * SHA256_BLOCK_CONSTRAINTS + BYTE_TO_BITS_CONSTRAINTS * MDL_PREIMAGE_LEN
+            + substring_constraints(max_msg_len, MDL_ENCODED_DIGEST_LEN);
+        constraints += match entry.claim_type {
+            ClaimType::Date => DATE_CONSTRAINTS,
+            ClaimType::String => reveal_constraints(entry, MDL_PREIMAGE_LEN, false),
+            _ => 0,
+        };
+        estimate.add(format!("Claim {}", name), constraints);
+    }
+}
+
+/// The sizes of a sample credential that are bounded by the config, or the reason the prover would reject it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CredentialSizes {
+    /// The file name of the credential
+    pub name: String,
+    /// The signed data after hash padding, bounded by `max_cred_len`
+    pub cred_len: Option<usize>,
+    /// The claims bounded by `max_claim_byte_len`. For JWTs, it is the length of the value as written in the payload,
+    /// e.g., with the quotes and escape sequences of a string.
+    pub claim_lens: BTreeMap<String, usize>,
+    /// The disclosures of an SD-JWT after hash padding, bounded by `max_disclosure_len`
+    pub disclosure_lens: BTreeMap<String, usize>,
+    /// The error the prover fails with, if any
+    pub error: Option<String>,
+}
+
+/// The sample credentials checked against a config
+pub struct FitReport<'a> {
+    pub config: &'a CredentialConfig,
+    pub credentials: Vec<CredentialSizes>,
+}
+
+impl<'a> FitReport<'a> {
+    pub fn new(config: &'a CredentialConfig, credentials: Vec<CredentialSizes>) -> Self {
+        FitReport { config, credentials }
+    }
+
+    /// The credentials the prover would reject, with the reason
+    pub fn failures(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.credentials.iter().filter_map(|c| c.error.as_deref().map(|e| (c.name.as_str(), e)))
+    }
+
+    /// The config with the smallest sizes that fit all the credentials that could be measured. Claims revealed as a
+    /// single field element keep their length, which must be 31 bytes.
+    pub fn recommended_config(&self) -> CredentialConfig {
+        let mut config = self.config.clone();
+        let block_len = hash_block_len(&config.alg);
+        if let Some(cred_len) = self.credentials.iter().filter_map(|c| c.cred_len).max() {
+            config.max_cred_len = cred_len.div_ceil(block_len) * block_len;
+        }
+        for (name, entry) in config.claims.iter_mut() {
+            let max_len = self.credentials.iter().filter_map(|c| c.claim_lens.get(name)).max();
+            if let Some(&max_len) = max_len {
+                if entry.reveal_bytes {
+                    entry.max_claim_byte_len = Some(max_len.div_ceil(MAX_FIELD_BYTE_LEN).max(1) * MAX_FIELD_BYTE_LEN);
+                } else if entry.reveal_digest {
+                    entry.max_claim_byte_len = Some(max_len);
+                }
+            }
+            let max_disclosure_len = self.credentials.iter().filter_map(|c| c.disclosure_lens.get(name)).max();
+            if let Some(&max_disclosure_len) = max_disclosure_len {
+                entry.max_disclosure_len = Some(max_disclosure_len);
+            }
+        }
+        config
+    }
+}
+
+impl fmt::Display for FitReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let config = self.config;
+        writeln!(f, "Credentials:")?;
+        for c in &self.credentials {
+            let cred_len = c.cred_len.map(|len| len.to_string()).unwrap_or("?".to_string());
+            let status = if c.error.is_some() { "FAILS" } else { "fits" };
+            writeln!(f, "  {:<40} {:>6} / {} bytes  {}", c.name, cred_len, config.max_cred_len, status)?;
+        }
+
+        writeln!(f, "Claims (largest value in the credentials / configured maximum):")?;
+        for (name, entry) in &config.claims {
+            if let Some(max_len) = entry.max_claim_byte_len {
+                let largest = self.credentials.iter().filter_map(|c| c.claim_lens.get(name)).max();
+                let largest = largest.map(|len| len.to_string()).unwrap_or("-".to_string());
+                let note = if entry.reveal && !entry.reveal_bytes && largest.parse().is_ok_and(|len: usize| len > MAX_FIELD_BYTE_LEN) {
+                    "  (too long for a field element, see reveal_bytes and reveal_digest)"
+                } else {
+                    ""
+                };
+                writeln!(f, "  {:<40} {:>6} / {} bytes{}", name, largest, max_len, note)?;
+            }
+            if entry.sd {
+                let largest = self.credentials.iter().filter_map(|c| c.disclosure_lens.get(name)).max();
+                let largest = largest.map(|len| len.to_string()).unwrap_or("-".to_string());
+                writeln!(f, "  {:<40} {:>6} / {} bytes", format!("{} (disclosure)", name), largest, entry.max_disclosure_len.unwrap_or(DEFAULT_MAX_DISCLOSURE_LENGTH))?;
+            }
+        }
+
+        let failures = self.failures().collect::<Vec<_>>();
+        if !failures.is_empty() {
+            writeln!(f, "Credentials the prover would reject:")?;
+            for (name, error) in failures {
+                writeln!(f, "  {}: {}", name, error)?;
+            }
+        }
+
+        let recommended = self.recommended_config();
+        writeln!(f, "Recommended config values:")?;
+        writeln!(f, "  max_cred_len: {}", recommended.max_cred_len)?;
+        for ((name, entry), (_, current)) in recommended.claims.iter().zip(&config.claims) {
+            if entry.max_claim_byte_len != current.max_claim_byte_len {
+                writeln!(f, "  {}: max_claim_byte_len: {}", name, entry.max_claim_byte_len())?;
+            }
+            if entry.max_disclosure_len != current.max_disclosure_len {
+                writeln!(f, "  {}: max_disclosure_len: {}", name, entry.max_disclosure_len.unwrap_or(DEFAULT_MAX_DISCLOSURE_LENGTH))?;
+            }
+        }
+        write!(f, "  estimated constraints: {} (currently {})", estimate_constraints(&recommended).total(), estimate_constraints(config).total())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    const CONFIG: &str = r#"{"alg": "ES256", "max_cred_len": 1024,
+        "exp": {"type": "number"},
+        "email": {"type": "string", "reveal_digest": true, "max_claim_byte_len": 64},
+        "iss": {"type": "string", "reveal_bytes": true, "max_claim_byte_len": 62}}"#;
+
+    #[test]
+    fn test_estimate_constraints() {
+        let config = parse_config(CONFIG).unwrap();
+        let estimate = estimate_constraints(&config);
+        assert!(estimate.components.iter().any(|(name, _)| name == "Claim email"));
+        assert!(estimate.total() > ECDSA_P256_CONSTRAINTS);
+
+        // The circuit grows with the credential and the claims, and RSA is cheaper to verify than ECDSA
+        let larger = parse_config(&CONFIG.replace("1024", "2048")).unwrap();
+        assert!(estimate_constraints(&larger).total() > estimate.total());
+        let longer_email = parse_config(&CONFIG.replace("64}", "128}")).unwrap();
+        assert!(estimate_constraints(&longer_email).total() > estimate.total());
+        let rsa = parse_config(&CONFIG.replace("ES256", "RS256")).unwrap();
+        assert!(estimate_constraints(&rsa).total() < estimate.total());
+        let rsa_4096 = parse_config(&CONFIG.replace("\"ES256\"", "\"RS256\", \"rsa_key_bits\": 4096")).unwrap();
+        assert!(estimate_constraints(&rsa_4096).total() > estimate_constraints(&rsa).total());
+
+        let mdl = parse_config(r#"{"credtype": "mdl", "alg": "ES256", "max_cred_len": 1024, "device_bound": true,
+            "family_name": {"type": "string", "reveal": true, "max_claim_byte_len": 31},
+            "birth_date": {"type": "date", "reveal": true}}"#).unwrap();
+        let estimate = estimate_constraints(&mdl);
+        assert!(estimate.components.iter().any(|(name, _)| name == "Device key"));
+        assert!(!estimate.components.iter().any(|(name, _)| name == "Claim device_key_0"));
+    }
+}
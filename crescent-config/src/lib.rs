@@ -0,0 +1,19 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+// The credential config and the circuit size estimates, shared by the Crescent prover (creds) and the mDL tools
+// (circuit_setup/mdl-tools). This crate only depends on serde, so that the tools checking configs and credentials do
+// not build the proof system.
+
+pub mod config;
+pub mod estimate;
+
+macro_rules! return_error {
+    ($msg:expr) => {
+        {
+            println!("{}", $msg);
+            return Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, $msg)));
+        }
+    };
+}
+pub(crate) use return_error;
@@ -34,6 +34,17 @@ impl<E: Pairing> CircomConfig<E> {
             sanity_check: false,
         })
     }
+
+    /// Instantiates a config from the contents of the witness generator WASM and R1CS files
+    pub fn from_bytes(wtns: &[u8], r1cs: &[u8]) -> Result<Self> {
+        let wtns = WitnessCalculator::from_bytes(wtns)?;
+        let r1cs = R1CSFile::new(std::io::Cursor::new(r1cs))?.into();
+        Ok(Self {
+            wtns,
+            r1cs,
+            sanity_check: false,
+        })
+    }
 }
 
 impl<E: Pairing> CircomBuilder<E> {
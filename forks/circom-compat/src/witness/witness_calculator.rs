@@ -63,6 +63,12 @@ impl WitnessCalculator {
         Self::from_module(module)
     }
 
+    pub fn from_bytes(wasm: &[u8]) -> Result<Self> {
+        let store = Store::default();
+        let module = Module::new(&store, wasm)?;
+        Self::from_module(module)
+    }
+
     pub fn from_module(module: Module) -> Result<Self> {
         let store = module.store();
 
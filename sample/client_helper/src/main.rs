@@ -4,11 +4,14 @@
 #[macro_use] extern crate rocket;
 
 use crescent::groth16rand::ClientState;
-use crescent::prep_inputs::{parse_config, prepare_prover_inputs};
+use crescent::config::parse_config;
+use crescent::prep_inputs::prepare_prover_inputs;
 use crescent::rangeproof::RangeProofPK;
 use crescent::structs::{GenericInputsJSON, IOLocations};
-use crescent::{create_client_state, create_show_proof, create_show_proof_mdl, CachePaths, CrescentPairing, ProofSpec};
-use crescent::utils::{read_from_b64url, read_from_file, write_to_b64url};
+use crescent::{create_client_state, create_show_proof, CrescentPairing, ProofSpec};
+use crescent::store::{keys, read_param, FsParamStore, ParamStore};
+use crescent::formats::credential_format;
+use crescent::utils::{read_from_b64url, write_to_b64url};
 use crescent::ProverParams;
 use crescent::device::TestDevice;
 
@@ -36,7 +39,7 @@ use std::cmp::min;
 //       The code we use in unit tests to make web requests doesn't work from a route handler, we need to investigate.  It may 
 //       only be suitable for testing, there is probably a better way.
 //       Also we'll need some caching of the parameters to avoid fetching large files multiple times.
-//       For caching the client helper could re-use the CachePaths struct and approach.
+//       For caching the client helper could use an in-memory ParamStore.
 const CRESCENT_DATA_BASE_PATH : &str = "./data/creds";
 const CRESCENT_SHARED_DATA_SUFFIX : &str = "shared";
 
@@ -185,27 +188,27 @@ async fn prepare(cred_info: Json<CredInfo>, state: &State<SharedState>) -> Json<
                 println!("got issuer_url = {}", &cred_info.issuer_url);
             }
 
-            let paths = CachePaths::new_from_str(&cred_folder);
+            let store = FsParamStore::new(&cred_folder);
 
             println!("Loading prover params");
-            let prover_params = ProverParams::<CrescentPairing>::new(&paths).map_err(|_| "Failed to create prover params")?;
+            let prover_params = ProverParams::<CrescentPairing>::new(&store).map_err(|_| "Failed to create prover params")?;
             println!("Parsing config");
             let config = parse_config(&prover_params.config_str).map_err(|_| "Failed to parse config")?;
 
-            let range_pk: RangeProofPK<CrescentPairing> = read_from_file(&paths.range_pk).map_err(|_| "Failed to read range proof pk")?;
+            let range_pk: RangeProofPK<CrescentPairing> = read_param(&store, keys::RANGE_PK).map_err(|_| "Failed to read range proof pk")?;
             println!("Serializing range proof pk");
             let range_pk_b64 = write_to_b64url(&range_pk);
             println!("Reading IO locations file");
-            let io_locations_str: String = fs::read_to_string(&paths.io_locations).map_err(|_| "Failed to read IO locations file")?;
+            let io_locations_str: String = store.read_string(keys::IO_LOCATIONS).map_err(|_| "Failed to read IO locations file")?;
 
             let client_state = 
             if cred_type == "mdl" {
-                let client_state : ClientState<CrescentPairing> = read_from_file(&paths.client_state).map_err(|_| "Failed to read client state")?;
+                let client_state : ClientState<CrescentPairing> = read_param(&store, keys::CLIENT_STATE).map_err(|_| "Failed to read client state")?;
                 client_state
             }
             else {
                 println!("Loading issuer public key");
-                let issuer_pem = fs::read_to_string(&paths.issuer_pem).map_err(|_| "Unable to read issuer public key PEM")?;                
+                let issuer_pem = store.read_string(keys::ISSUER_PEM).map_err(|_| "Unable to read issuer public key PEM")?;                
                 println!("Creating prover inputs");
                 let (prover_inputs_json, prover_aux_json, _public_ios_json) = prepare_prover_inputs(&config, &cred_info.cred, &issuer_pem, None).map_err(|_| "Failed to prepare prover inputs")?;
                 let prover_inputs = GenericInputsJSON { prover_inputs: prover_inputs_json };
@@ -213,14 +216,14 @@ async fn prepare(cred_info: Json<CredInfo>, state: &State<SharedState>) -> Json<
 
                 println!("Creating client state... this is slow...");
 
-                create_client_state(&paths, &prover_inputs, Some(&prover_aux_string), "jwt").map_err(|_| "Failed to create client state")?
+                create_client_state(&store, &prover_inputs, Some(&prover_aux_string), "jwt").map_err(|_| "Failed to create client state")?
             };
 
             let client_state_b64 = write_to_b64url(&client_state);
             println!("Done, client state is a base64_url encoded string that is {} chars long", client_state_b64.len());
             
             // save the path to the device private key if the credential is device-bound
-            let device_priv_key_path = paths.device_prv_pem.clone();
+            let device_priv_key_path = store.path(keys::DEVICE_PRV_PEM).display().to_string();
             let show_data = ShowData { client_state_b64, range_pk_b64, io_locations_str, device_priv_key_path };
             println!("Task complete, storing ShowData (size: {:?} bytes, took {:?})",
                 show_data.client_state_b64.len() + show_data.io_locations_str.len() + show_data.range_pk_b64.len(), start_time.elapsed().unwrap());
@@ -311,15 +314,12 @@ async fn show<'a>(cred_uid: String, disc_uid: String, challenge: String, proof_s
             };
 
             // create the show proof
-            let show_proof =
-            if &client_state.credtype == "mdl" {
+            let format = credential_format(&client_state.credtype).map_err(|e| e.to_string())?;
+            if let Some(birth_date_claim) = format.birth_date_claim() {
                 let age = disc_uid_to_age(&disc_uid).map_err(|_| "Disclosure UID does not have associated age parameter".to_string())? as u64;
-                proof_spec.range_over_year = Some(std::collections::BTreeMap::from([("birth_date".to_string(), age)]));
-                create_show_proof_mdl(&mut client_state, &range_pk, &proof_spec, &io_locations, device_signature).map_err(|e| format!("Failed to create show proof. {:?}", e))?
+                proof_spec.range_over_year = Some(std::collections::BTreeMap::from([(birth_date_claim.to_string(), age)]));
             }
-            else {
-                create_show_proof(&mut client_state, &range_pk, &io_locations, &proof_spec, device_signature).map_err(|e| format!("Failed to create show proof. {:?}", e))?
-            };
+            let show_proof = create_show_proof(&mut client_state, &range_pk, &io_locations, &proof_spec, device_signature).map_err(|e| format!("Failed to create show proof. {:?}", e))?;
             
             // Return the show proof as a base64-url encoded string
             let show_proof_b64 = write_to_b64url(&show_proof);     
@@ -5,7 +5,8 @@
 
 use rocket::serde::{Serialize, Deserialize};
 use rocket::fs::NamedFile;
-use crescent::{CachePaths, CrescentPairing, ShowParams};
+use crescent::store::{keys, FsParamStore, ParamStore};
+use crescent::{CrescentPairing, ShowParams};
 use crescent::VerifierParams;
 use crescent::utils::write_to_b64url;
 use std::path::PathBuf;
@@ -31,20 +32,20 @@ struct TokenInfo {
 // have been run and CRESCENT_DATA_BASE_PATH points to the place where the generated
 // parameters are stored.
 
-fn check_for_stored_params(paths :&CachePaths) -> bool {
+fn check_for_stored_params(store :&FsParamStore) -> bool {
 
     let files = vec![
-        &paths.groth16_pvk,
-        &paths.groth16_vk,
-        &paths.range_vk,
-        &paths.io_locations,
-        &paths.prover_params,
-        &paths.range_pk
+        keys::GROTH16_PVK,
+        keys::GROTH16_VK,
+        keys::RANGE_VK,
+        keys::IO_LOCATIONS,
+        keys::PROVER_PARAMS,
+        keys::RANGE_PK
     ];
 
     for f in files {
-        if !Path::new(&f).exists() {
-            println!("Error: required file not found ({})", f);
+        if !store.contains(f) {
+            println!("Error: required file not found ({})", store.path(f).display());
             return false;
         }        
     }
@@ -56,8 +57,7 @@ fn check_for_stored_params(paths :&CachePaths) -> bool {
 // Since the params are so big, we just expose the binary file for download
 #[get("/prove_params/<file..>")]
 async fn files(file: PathBuf) -> Option<NamedFile> {
-    let paths = CachePaths::new_from_str(CRESCENT_DATA_BASE_PATH);
-    let path = Path::new(&paths._base).join(file);
+    let path = Path::new(CRESCENT_DATA_BASE_PATH).join(file);
     println!("Got request for file : {:?}", path);
     NamedFile::open(path).await.ok()
 }
@@ -65,8 +65,8 @@ async fn files(file: PathBuf) -> Option<NamedFile> {
 // Get the parameters required to generate presentation/show proofs // TODO: add schema_uid to the path (as documented)
 #[get("/show_params")]
 fn show_params() -> String {
-    let paths = CachePaths::new_from_str(CRESCENT_DATA_BASE_PATH);
-    let show_params = ShowParams::<CrescentPairing>::new(&paths).expect("Failed to create ShowParams instance");
+    let store = FsParamStore::new(CRESCENT_DATA_BASE_PATH);
+    let show_params = ShowParams::<CrescentPairing>::new(&store).expect("Failed to create ShowParams instance");
     
     
     write_to_b64url(&show_params)
@@ -75,8 +75,8 @@ fn show_params() -> String {
 // Get the parameters required to verify presentation proofs // TODO: add schema_uid to the path (as documented)
 #[get("/verifier_params")]
 fn verifier_params() -> String {
-    let paths = CachePaths::new_from_str(CRESCENT_DATA_BASE_PATH);
-    let verifier_params = VerifierParams::<CrescentPairing>::new(&paths).expect("Failed to create VerifierParams instance");
+    let store = FsParamStore::new(CRESCENT_DATA_BASE_PATH);
+    let verifier_params = VerifierParams::<CrescentPairing>::new(&store).expect("Failed to create VerifierParams instance");
     
     
     write_to_b64url(&verifier_params)
@@ -84,8 +84,8 @@ fn verifier_params() -> String {
 
 #[launch]
 fn rocket() -> _ {
-    let paths = CachePaths::new_from_str(CRESCENT_DATA_BASE_PATH);
-    if ! check_for_stored_params(&paths) {
+    let store = FsParamStore::new(CRESCENT_DATA_BASE_PATH);
+    if ! check_for_stored_params(&store) {
         println!("Error: parameters not present, not starting setup service");
         std::process::exit(-1);
     }
@@ -3,7 +3,8 @@
 
 #[macro_use] extern crate rocket;
 
-use crescent::{verify_show_mdl, ProofSpec};
+use crescent::{formats::credential_format, ProofSpec, VerifiedPresentation};
+use crescent::policy::{SystemClock, VerifierPolicy};
 use rocket::serde::{Serialize, Deserialize};
 use rocket::serde::json::Json;
 use rocket_dyn_templates::{context, Template};
@@ -13,13 +14,13 @@ use rocket::State;
 use rocket::fs::{FileServer, NamedFile};
 use rocket::http::Status;
 use std::collections::{HashMap, HashSet};
-use serde_json::{json, Value};
+use serde_json::Value;
 use jsonwebkey::JsonWebKey;
 use std::path::Path;
 use std::fs;
 use std::sync::Mutex;
 use uuid::Uuid;
-use crescent::{utils::read_from_b64url, CachePaths, CrescentPairing, ShowProof, VerifierParams, verify_show};
+use crescent::{store::FsParamStore, utils::read_from_b64url, CrescentPairing, ShowProof, VerifierParams, verify_show};
 use crescent_sample_setup_service::common::*;
 use sha2::{Digest, Sha256};
 
@@ -29,7 +30,7 @@ const CRESCENT_SHARED_DATA_SUFFIX : &str = "shared";
 
 #[derive(Clone)]
 struct ValidationResult {
-    disclosed_info: Option<String>,
+    presentation: VerifiedPresentation,
 }
 
 // verifer config from Rocket.toml
@@ -43,6 +44,7 @@ struct VerifierConfig {
     site1_verifier_domain: String,
     site1_disclosure_uid: String,
     site1_proof_spec: String,
+    site1_policy: VerifierPolicy,
 
     // site 2 (mDL verifier)
     site2_verify_url: String,
@@ -50,6 +52,7 @@ struct VerifierConfig {
     site2_verifier_domain: String,
     site2_disclosure_uid: String,
     site2_proof_spec: String,
+    site2_policy: VerifierPolicy,
 
     // holds active session IDs (in a real system, these would be removed
     // after a timeout period)
@@ -127,19 +130,10 @@ fn login_page(verifier_config: &State<VerifierConfig>) -> Template {
     Template::render("login", context)
 }
 
-fn get_email_domain(disclsosed_info : &Option<String>) -> String {
-
-    match disclsosed_info {
-        Some(info) => {
-            match serde_json::from_str::<Value>(&info) {
-                Ok(j) =>{
-                    j.get("email_value").unwrap_or(&json!("ERROR: email key not found")).as_str().unwrap_or("ERROR: email domain is not a string").to_string()
-                }
-                Err(_) => "ERROR: domain not found".to_string()
-            }
-            
-        }
-        None => "ERROR: domain not found".to_string()
+fn get_email_domain(presentation : &VerifiedPresentation) -> String {
+    match presentation.revealed.get("email") {
+        Some(value) => value.as_str().unwrap_or("ERROR: email domain is not a string").to_string(),
+        None => "ERROR: email key not found".to_string()
     }
 }
 
@@ -166,9 +160,9 @@ fn resource_page(session_id: String, verifier_config: &State<VerifierConfig>) ->
         if let Some(result) = validation_result {
             Template::render("resource", context! {
                 site1_verifier_name: verifier_config.site1_verifier_name.as_str(),               
-                email_domain: get_email_domain(&result.disclosed_info),
-                country: get_disclosed_claim("tenant_ctry_value", &result.disclosed_info),
-                preferred_language: "en", // eventually, we can: get_disclosed_claim("xms_tpl_value", &result.disclosed_info),
+                email_domain: get_email_domain(&result.presentation),
+                country: get_disclosed_claim("tenant_ctry", &result.presentation),
+                preferred_language: "en", // eventually, we can: get_disclosed_claim("xms_tpl", &result.presentation),
             })
         } else {
             Template::render("error", context! { error: "Invalid session ID" })
@@ -226,17 +220,9 @@ fn signup2_page(session_id: String, verifier_config: &State<VerifierConfig>) ->
     }
 }
 
-fn get_disclosed_claim(claim: &str, disclsosed_info : &Option<String>) -> String {
-    match disclsosed_info {
-        Some(info) => {
-            match serde_json::from_str::<Value>(&info) {
-                Ok(j) =>{
-                    j.get(claim).unwrap_or(&json!("ERROR: disclosed claims not found")).as_str().unwrap_or("ERROR: disclosed claims is not a string").to_string()
-                }
-                Err(_) => "ERROR: disclosed claims not found".to_string()
-            }
-            
-        }
+fn get_disclosed_claim(claim: &str, presentation : &VerifiedPresentation) -> String {
+    match presentation.revealed.get(claim) {
+        Some(value) => value.as_str().unwrap_or("ERROR: disclosed claims is not a string").to_string(),
         None => "ERROR: disclosed claims not found".to_string()
     }
 }
@@ -346,57 +332,52 @@ async fn verify(proof_info: Json<ProofInfo>, verifier_config: &State<VerifierCon
         }    
     }
 
-    let paths = CachePaths::new_from_str(&issuer_folder);
-    let vp = VerifierParams::<CrescentPairing>::new(&paths).unwrap();
+    let store = FsParamStore::new(&issuer_folder);
+    let vp = VerifierParams::<CrescentPairing>::new(&store).unwrap();
 
     let show_proof = match read_from_b64url::<ShowProof<CrescentPairing>>(&proof_info.proof) {
         Ok(show_proof) => show_proof, 
         Err(_) => error_template!("Invalid proof; deserialization error", verifier_config),
     };
 
-    let is_valid;
-    let disclosed_info;
-    let config_proof_spec = match cred_type {
-        "jwt" => verifier_config.site1_proof_spec.clone(),
-        "mdl" => verifier_config.site2_proof_spec.clone(),
+    let (config_proof_spec, policy) = match cred_type {
+        "jwt" => (verifier_config.site1_proof_spec.clone(), &verifier_config.site1_policy),
+        "mdl" => (verifier_config.site2_proof_spec.clone(), &verifier_config.site2_policy),
         _ => error_template!("Unsupported credential type", verifier_config),
     };
     let mut ps : ProofSpec = serde_json::from_str(&config_proof_spec).unwrap();
     // hash the challenge to use as the presentation message (we need to hash it because device (for device-bound creds) only support signing digests)   
     ps.presentation_message = Some(Sha256::digest(challenge).to_vec());       
-    if cred_type == "jwt" {
-        let (valid, info) = verify_show(&vp, &show_proof, &ps);
-        is_valid = valid;
-        disclosed_info = Some(info);
-    } else {
+    let format = match credential_format(cred_type) {
+        Ok(format) => format,
+        Err(_) => error_template!("Unsupported credential type", verifier_config),
+    };
+    if let Some(birth_date_claim) = format.birth_date_claim() {
         let age = disc_uid_to_age(&proof_info.disclosure_uid).unwrap() as u64; // disclosure UID validated, so unwrap should be safe
-        ps.range_over_year = Some(std::collections::BTreeMap::from([("birth_date".to_string(), age)]));
-        let (valid, info) = verify_show_mdl(&vp, &show_proof, &ps);
-        is_valid = valid;
-        disclosed_info = Some(info);
+        ps.range_over_year = Some(std::collections::BTreeMap::from([(birth_date_claim.to_string(), age)]));
     }
-    println!("Proof is valid: {}", is_valid);
-    println!("Disclosed info: {:?}", disclosed_info);
+    let verify_result = verify_show(&vp, &show_proof, &ps, policy, &SystemClock);
 
-    if is_valid {
-        // Store the validation result in the hashmap
-        let validation_result = ValidationResult {
-            disclosed_info: disclosed_info.clone(),
-        };
-        verifier_config.validation_results.lock().unwrap().insert(proof_info.session_id.clone(), validation_result);
+    let presentation = match verify_result {
+        Ok(presentation) => presentation,
+        Err(e) => error_template!(format!("Proof is invalid: {}", e), verifier_config),
+    };
+    println!("Proof is valid, disclosed info: {:?}", presentation.revealed);
 
-        // Redirect to the resource page or signup2 page with the session_id as a query parameter
-        let redirect_url = match cred_type {
-            "jwt" => uri!(resource_page(session_id = proof_info.session_id.clone())).to_string(),
-            "mdl" => uri!(signup2_page(session_id = proof_info.session_id.clone())).to_string(),
-            _ => error_template!("Unsupported credential type", verifier_config),
-        };
+    // Store the validation result in the hashmap
+    let validation_result = ValidationResult {
+        presentation,
+    };
+    verifier_config.validation_results.lock().unwrap().insert(proof_info.session_id.clone(), validation_result);
 
-        Ok(Custom(Status::SeeOther, Redirect::to(redirect_url)))
-    } else {
-        // return an error template if the proof is invalid
-        error_template!("Proof is invalid.", verifier_config);
-    }
+    // Redirect to the resource page or signup2 page with the session_id as a query parameter
+    let redirect_url = match cred_type {
+        "jwt" => uri!(resource_page(session_id = proof_info.session_id.clone())).to_string(),
+        "mdl" => uri!(signup2_page(session_id = proof_info.session_id.clone())).to_string(),
+        _ => error_template!("Unsupported credential type", verifier_config),
+    };
+
+    Ok(Custom(Status::SeeOther, Redirect::to(redirect_url)))
 }
 
 #[get("/site1-favicon.ico")]
@@ -409,6 +390,15 @@ async fn site2_favicon() -> Option<NamedFile> {
     NamedFile::open("static/img/site2-favicon.ico").await.ok()
 }
 
+// Read the freshness settings of a site's verifier policy, using the library defaults for missing entries
+fn load_policy(figment: &rocket::figment::Figment, site: &str) -> VerifierPolicy {
+    let default = VerifierPolicy::default();
+    VerifierPolicy {
+        max_proof_age_seconds: figment.extract_inner(&format!("{}_max_proof_age_seconds", site)).unwrap_or(default.max_proof_age_seconds),
+        max_future_skew_seconds: figment.extract_inner(&format!("{}_max_future_skew_seconds", site)).ok().or(default.max_future_skew_seconds),
+    }
+}
+
 #[launch]
 fn rocket() -> _ {
     // Load verifier configuration
@@ -420,12 +410,14 @@ fn rocket() -> _ {
     let site1_verify_url: String = format!("http://{}:{}/verify", site1_verifier_domain, port);
     let site1_disclosure_uid: String = figment.extract_inner("site1_disclosure_uid").unwrap_or_else(|_| "{}".to_string());
     let site1_proof_spec: String = figment.extract_inner("site1_proof_spec").unwrap_or_else(|_| "{}".to_string());
+    let site1_policy = load_policy(&figment, "site1");
     
     let site2_verifier_name: String = figment.extract_inner("site2_verifier_name").unwrap_or_else(|_| "Example Verifier".to_string());
     let site2_verifier_domain: String = figment.extract_inner("site2_verifier_domain").unwrap_or_else(|_| "example.com".to_string());
     let site2_verify_url: String = format!("http://{}:{}/verify", site2_verifier_domain, port);
     let site2_disclosure_uid: String = figment.extract_inner("site2_disclosure_uid").unwrap_or_else(|_| "{}".to_string());
     let site2_proof_spec: String = figment.extract_inner("site2_proof_spec").unwrap_or_else(|_| "{}".to_string());
+    let site2_policy = load_policy(&figment, "site2");
     
     let verifier_config = VerifierConfig {
         port,
@@ -434,11 +426,13 @@ fn rocket() -> _ {
         site1_verify_url,
         site1_disclosure_uid,
         site1_proof_spec,
+        site1_policy,
         site2_verifier_name,
         site2_verifier_domain,
         site2_verify_url,
         site2_disclosure_uid,
         site2_proof_spec,
+        site2_policy,
         active_session_ids: Mutex::new(HashSet::new()),
         validation_results: Mutex::new(HashMap::new()),
     };